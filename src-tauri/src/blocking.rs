@@ -3,8 +3,10 @@
 use serde::{Deserialize, Serialize};
 use std::fs::{self, OpenOptions};
 use std::io::{Read, Write};
-use std::path::PathBuf;
-use sysinfo::{System, Signal, ProcessesToUpdate};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use sysinfo::{System, Signal, ProcessesToUpdate, ProcessRefreshKind, UpdateKind};
 
 #[cfg(target_os = "windows")]
 const HOSTS_PATH: &str = "C:\\Windows\\System32\\drivers\\etc\\hosts";
@@ -17,6 +19,8 @@ const HOSTS_PATH: &str = "/etc/hosts";
 
 const BASTION_MARKER_START: &str = "# === BASTION BLOCK START ===";
 const BASTION_MARKER_END: &str = "# === BASTION BLOCK END ===";
+const SAFESEARCH_MARKER_START: &str = "# === BASTION SAFESEARCH START ===";
+const SAFESEARCH_MARKER_END: &str = "# === BASTION SAFESEARCH END ===";
 
 const SYSTEM_WHITELIST: &[&str] = &[
     "explorer.exe", "dwm.exe", "taskhostw.exe", "lsass.exe", "csrss.exe", 
@@ -25,15 +29,43 @@ const SYSTEM_WHITELIST: &[&str] = &[
     "searchhost.exe", "startmenuexperiencehost.exe"
 ];
 
+/// System tools that are additionally blocked during extreme hardcore sessions.
+/// These take precedence over `SYSTEM_WHITELIST` (e.g. `taskmgr.exe`) while extreme mode is active.
+pub const EXTREME_MODE_TOOLS: &[&str] = &[
+    // Windows
+    "taskmgr.exe", "regedit.exe", "regedt32.exe", "mmc.exe", "resmon.exe", "perfmon.exe",
+    "procexp.exe", "procexp64.exe", "processhacker.exe", "systeminformer.exe", "hostsman.exe",
+    // Linux
+    "gnome-system-monitor", "plasma-systemmonitor", "ksysguard",
+    // macOS
+    "Activity Monitor",
+];
+
+/// App stores and package managers blocked during hardcore sessions when "block_installers" is
+/// on, so a new browser can't be installed to get around the blocks. Overridable by the user.
+pub const DEFAULT_INSTALLER_TOOLS: &[&str] = &[
+    // Windows
+    "WinStore.App.exe", "winget.exe", "AppInstaller.exe", "msiexec.exe", "choco.exe", "scoop.exe",
+    // macOS
+    "App Store", "Installer", "brew",
+    // Linux
+    "gnome-software", "plasma-discover", "snap-store", "pamac-manager", "apt", "apt-get", "dnf", "pacman", "flatpak", "snap",
+];
+
+pub const INSTALLER_TOOLS_SETTING: &str = "installer_block_list";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BlockingError {
     pub message: String,
+    #[serde(default)]
+    pub permission_denied: bool, // hosts file (or policy) not writable without admin rights
 }
 
 impl From<std::io::Error> for BlockingError {
     fn from(err: std::io::Error) -> Self {
         BlockingError {
             message: err.to_string(),
+            permission_denied: err.kind() == std::io::ErrorKind::PermissionDenied,
         }
     }
 }
@@ -43,16 +75,21 @@ pub fn get_hosts_path() -> PathBuf {
     PathBuf::from(HOSTS_PATH)
 }
 
-/// Backup the hosts file before modification
-#[allow(dead_code)]
-pub fn backup_hosts(backup_dir: &PathBuf) -> Result<PathBuf, BlockingError> {
-    let hosts_path = get_hosts_path();
-    let backup_path = backup_dir.join("hosts.backup");
-    
-    fs::create_dir_all(backup_dir)?;
-    fs::copy(&hosts_path, &backup_path)?;
-    
-    Ok(backup_path)
+/// Copy of the hosts file as it was before Bastion changed it, kept in the data directory
+const HOSTS_BASELINE_FILE: &str = "hosts.baseline";
+
+/// Snapshot the pristine hosts file on first run. Bastion's own sections are left out in case
+/// an earlier version already wrote them. Returns whether a snapshot was taken.
+pub fn snapshot_hosts_baseline(data_dir: &Path) -> Result<bool, BlockingError> {
+    let path = data_dir.join(HOSTS_BASELINE_FILE);
+    if path.exists() {
+        return Ok(false);
+    }
+    let mut contents = read_hosts()?;
+    strip_bastion_sections(&mut contents);
+    fs::create_dir_all(data_dir)?;
+    fs::write(&path, contents)?;
+    Ok(true)
 }
 
 /// Read the current hosts file
@@ -67,6 +104,7 @@ fn read_hosts() -> Result<String, BlockingError> {
 fn write_hosts(contents: &str) -> Result<(), BlockingError> {
     let mut file = OpenOptions::new()
         .write(true)
+        .create(true)
         .truncate(true)
         .open(get_hosts_path())?;
     file.write_all(contents.as_bytes())?;
@@ -81,6 +119,184 @@ fn get_bastion_section(contents: &str) -> Option<(&str, usize, usize)> {
     Some((&contents[start_idx..end_idx], start_idx, end_idx))
 }
 
+/// Block sites through the hosts file (the whole domain stops resolving)
+pub const BLOCK_MODE_HOSTS: &str = "hosts";
+/// Soft block: the browser extension closes matching tabs; the rest of the domain keeps working
+pub const BLOCK_MODE_CLOSE_TAB: &str = "close_tab";
+/// Soft block: the browser extension redirects matching tabs (see `SOFT_BLOCK_REDIRECT_SETTING`)
+pub const BLOCK_MODE_REDIRECT: &str = "redirect";
+
+pub const SOFT_BLOCK_REDIRECT_SETTING: &str = "soft_block_redirect_url";
+
+/// Which backend enforces hosts-mode sites (JSON `BlockingBackend`)
+pub const BLOCKING_BACKEND_SETTING: &str = "blocking_mode";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockingBackend {
+    /// Entries in the hosts file (needs admin rights to write it)
+    #[default]
+    Hosts,
+    /// The local DNS sinkhole (see `dns`), for systems where the hosts file can't be written
+    Dns,
+    /// A proxy auto-config file set as the system proxy: blocked domains are sent to the block
+    /// server as their proxy, everything else goes direct. Needs no admin rights.
+    Pac,
+}
+
+/// Path the block server serves the PAC file on
+pub const PAC_PATH: &str = "/bastion.pac";
+
+const PAC_TEMPLATE: &str = r#"// Generated by Bastion
+var blocked = [{{domains}}];
+
+function FindProxyForURL(url, host) {
+    host = host.toLowerCase();
+    if (host.indexOf("www.") === 0) {
+        host = host.substring(4);
+    }
+    for (var i = 0; i < blocked.length; i++) {
+        if (host === blocked[i]) {
+            return "PROXY 127.0.0.1:{{port}}";
+        }
+    }
+    return "DIRECT";
+}
+"#;
+
+/// Proxy auto-config script routing `domains` (and their www. hosts, like the hosts file) to the
+/// block server on `127.0.0.1:proxy_port`.
+pub fn render_pac(domains: &[String], proxy_port: u16) -> String {
+    let mut hosts: Vec<String> = domains.iter().map(|d| normalize_domain(d)).filter(|d| !d.is_empty()).collect();
+    hosts.sort();
+    hosts.dedup();
+    let list = hosts.iter().map(|host| format!("\"{}\"", host)).collect::<Vec<_>>().join(", ");
+    PAC_TEMPLATE.replace("{{domains}}", &list).replace("{{port}}", &proxy_port.to_string())
+}
+
+/// Whether `url` is covered by a soft-block rule: a host, optionally followed by a path prefix
+/// ("news.google.com", "youtube.com/shorts"). The host matches like `is_domain_blocked`.
+pub fn matches_url_rule(url: &str, rule: &str) -> bool {
+    let (rule_host, rule_path) = match rule.trim().split_once('/') {
+        Some((host, path)) => (host, format!("/{}", path.trim_end_matches('/'))),
+        None => (rule.trim(), String::new()),
+    };
+    if !is_domain_blocked(url, &[rule_host.to_string()]) {
+        return false;
+    }
+    let rest = url.trim().split_once("://").map_or(url.trim(), |(_, rest)| rest);
+    let path = rest.find('/').map_or("/", |i| &rest[i..]);
+    let path = path.split(['?', '#']).next().unwrap_or("/").to_lowercase();
+    path.starts_with(&rule_path.to_lowercase())
+}
+
+/// Deny-by-default check for allowlist-only sessions: whether `url` (a URL or host) is one of
+/// the `allowed` sites or a subdomain of one. The hosts file can only list hosts to block, so
+/// this is enforced per page by the browser extension instead. Browser pages (about:,
+/// chrome://, file://), localhost and other single-label names aren't sites and stay allowed.
+pub fn is_allowed_by_allowlist(url: &str, allowed: &[String]) -> bool {
+    let url = url.trim();
+    if let Some((scheme, _)) = url.split_once("://") {
+        if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+            return true;
+        }
+    } else if url.to_lowercase().starts_with("about:") {
+        return true;
+    }
+    let host = normalize_domain(url);
+    if !host.contains('.') || host == "127.0.0.1" {
+        return true;
+    }
+    allowed.iter().any(|site| {
+        let site = normalize_domain(site);
+        !site.is_empty() && (host == site || host.ends_with(&format!(".{}", site)))
+    })
+}
+
+/// Domains and ports developers keep for their own local servers. Excluded domains are never
+/// written to the hosts file; excluded ports are left free by the block server.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DevExclusions {
+    pub domains: Vec<String>,
+    pub ports: Vec<u16>,
+}
+
+pub const DEV_EXCLUSIONS_SETTING: &str = "dev_exclusions";
+
+/// Remove the section between `start_marker` and `end_marker` from `contents` and return it.
+fn take_section(contents: &mut String, start_marker: &str, end_marker: &str) -> Option<String> {
+    let start = contents.find(start_marker)?;
+    let end = contents[start..].find(end_marker)? + start + end_marker.len();
+    let section = contents[start..end].to_string();
+    contents.replace_range(start..end, "");
+    Some(section)
+}
+
+/// Domains mapped by hosts lines outside the Bastion sections (the user's own entries).
+fn user_hosts_domains(contents: &str) -> Vec<String> {
+    let mut outside = match get_bastion_section(contents) {
+        Some((_, start, end)) => format!("{}{}", &contents[..start], &contents[end..]),
+        None => contents.to_string(),
+    };
+    take_section(&mut outside, SAFESEARCH_MARKER_START, SAFESEARCH_MARKER_END);
+    outside
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| line.split_whitespace().skip(1).map(|d| d.to_lowercase()).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Leave out domains the user maps themselves, so Bastion never overrides their entries.
+fn without_user_domains(domains: &[String], contents: &str) -> Vec<String> {
+    let user_domains = user_hosts_domains(contents);
+    domains
+        .iter()
+        .filter(|domain| {
+            let domain = domain.to_lowercase();
+            !user_domains.contains(&domain) && !user_domains.contains(&format!("www.{}", domain))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Subdomains covered by every site blocked with its subdomains (hosts entries can't use wildcards)
+const COMMON_SUBDOMAINS: &[&str] = &["m", "mobile", "app", "web"];
+
+/// Known subdomains of popular distracting sites, on top of `COMMON_SUBDOMAINS`
+const KNOWN_SUBDOMAINS: &[(&str, &[&str])] = &[
+    ("facebook.com", &["touch", "mbasic", "business", "l", "lm"]),
+    ("reddit.com", &["old", "new", "i", "np", "out", "gateway", "sh"]),
+    ("twitter.com", &["mobile", "api", "pbs", "abs"]),
+    ("x.com", &["api", "pro"]),
+    ("youtube.com", &["music", "gaming", "kids", "tv"]),
+    ("instagram.com", &["help", "about", "graph", "i"]),
+    ("tiktok.com", &["vm", "vt", "us", "t"]),
+    ("linkedin.com", &["touch", "lnkd"]),
+    ("twitch.tv", &["clips", "player", "gql", "dashboard"]),
+    ("pinterest.com", &["in", "uk", "de", "fr", "br"]),
+    ("tumblr.com", &["assets", "api"]),
+    ("netflix.com", &["help", "assets"]),
+    ("discord.com", &["canary", "ptb", "cdn"]),
+];
+
+/// Concrete hosts for a site blocked with its subdomains: the domain itself, the common
+/// subdomains and any known ones, so e.g. "reddit.com" also covers "old.reddit.com".
+pub fn expand_subdomains(domain: &str) -> Vec<String> {
+    let domain = normalize_domain(domain);
+    let known = KNOWN_SUBDOMAINS
+        .iter()
+        .find(|(known, _)| *known == domain)
+        .map_or(&[][..], |(_, subdomains)| *subdomains);
+    let mut hosts = vec![domain.clone()];
+    for subdomain in COMMON_SUBDOMAINS.iter().chain(known) {
+        let host = format!("{}.{}", subdomain, domain);
+        if !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+    hosts
+}
+
 /// Generate hosts file entries for blocked domains
 fn generate_block_entries(domains: &[String]) -> String {
     let mut entries = String::new();
@@ -99,7 +315,123 @@ fn generate_block_entries(domains: &[String]) -> String {
     entries
 }
 
+/// Normalize a domain or URL to the bare host used in block list entries
+/// (e.g. "https://WWW.Reddit.com:443/r/rust" -> "www.reddit.com"). Internationalized names
+/// become punycode ("münchen.de" -> "xn--mnchen-3ya.de"), the form browsers resolve and send.
+pub fn normalize_domain(input: &str) -> String {
+    let host = input.trim();
+    let host = host.split_once("://").map_or(host, |(_, rest)| rest);
+    let host = host.split(['/', '?', '#']).next().unwrap_or("");
+    let host = host.rsplit_once('@').map_or(host, |(_, rest)| rest);
+    let host = host.split(':').next().unwrap_or("");
+    let host = host.trim_end_matches('.').to_lowercase();
+    idna::domain_to_ascii(&host).unwrap_or(host)
+}
+
+/// What a new block list entry covers
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DomainScope {
+    /// The registrable domain ("twitter.com" for "https://mobile.twitter.com/home")
+    #[default]
+    Registrable,
+    /// Exactly the host that was entered ("mobile.twitter.com")
+    Host,
+    /// Host plus path prefix, for soft-block rules ("youtube.com/shorts")
+    Rule,
+}
+
+/// A domain or pasted URL from the add-site flow, validated and normalized.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SiteInput {
+    pub host: String,        // without "www.", which the hosts entries cover anyway
+    pub registrable: String, // same as `host` when the host is the registrable domain
+    pub path: Option<String>,
+}
+
+impl SiteInput {
+    /// The block list entry for `scope`
+    pub fn rule(&self, scope: DomainScope) -> String {
+        match (scope, &self.path) {
+            (DomainScope::Registrable, _) => self.registrable.clone(),
+            (DomainScope::Host, _) | (DomainScope::Rule, None) => self.host.clone(),
+            (DomainScope::Rule, Some(path)) => format!("{}/{}", self.host, path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DomainError {
+    Empty,
+    /// Hosts entries can't block an IP address
+    IpAddress(String),
+    InvalidHost(String),
+    /// A public suffix such as "com" or "co.uk", which would block unrelated sites
+    PublicSuffix(String),
+}
+
+impl std::fmt::Display for DomainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DomainError::Empty => write!(f, "Enter a domain or URL to block"),
+            DomainError::IpAddress(ip) => write!(f, "'{}' is an IP address; enter a domain name", ip),
+            DomainError::InvalidHost(host) => write!(f, "'{}' is not a valid domain name", host),
+            DomainError::PublicSuffix(host) => write!(f, "'{}' is a public suffix shared by many sites", host),
+        }
+    }
+}
+
+/// Parse and validate a domain or URL ("https://www.twitter.com/home?x=1" -> host and
+/// registrable domain "twitter.com", path "home"). The registrable domain comes from the
+/// Public Suffix List, so "bbc.co.uk" is kept whole.
+pub fn parse_site_input(input: &str) -> Result<SiteInput, DomainError> {
+    let host = normalize_domain(input);
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+    if host.is_empty() {
+        return Err(DomainError::Empty);
+    }
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return Err(DomainError::IpAddress(host));
+    }
+    let valid_label = |label: &str| {
+        (1..=63).contains(&label.len())
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    if host.len() > 253 || !host.contains('.') || !host.split('.').all(valid_label) {
+        return Err(DomainError::InvalidHost(host));
+    }
+    let Some(registrable) = psl::domain_str(&host) else {
+        return Err(DomainError::PublicSuffix(host));
+    };
+
+    let rest = input.trim().split_once("://").map_or(input.trim(), |(_, rest)| rest);
+    let path = rest
+        .split(['?', '#'])
+        .next()
+        .and_then(|rest| rest.split_once('/'))
+        .map(|(_, path)| path.trim_end_matches('/').to_string())
+        .filter(|path| !path.is_empty());
+
+    Ok(SiteInput { registrable: registrable.to_string(), host, path })
+}
+
+/// Whether `domain` is covered by the hosts entries `generate_block_entries` writes for
+/// `blocked_domains` (each domain plus its `www.` variant).
+pub fn is_domain_blocked(domain: &str, blocked_domains: &[String]) -> bool {
+    let domain = normalize_domain(domain);
+    if domain.is_empty() {
+        return false;
+    }
+    blocked_domains.iter().any(|blocked| {
+        let blocked = normalize_domain(blocked);
+        domain == blocked || domain.strip_prefix("www.") == Some(blocked.as_str())
+    })
+}
+
 /// Update the hosts file with blocked domains
+#[tracing::instrument(skip(domains), fields(domains = domains.len()))]
 pub fn update_blocked_websites(domains: &[String]) -> Result<(), BlockingError> {
     let mut contents = read_hosts()?;
     
@@ -107,21 +439,31 @@ pub fn update_blocked_websites(domains: &[String]) -> Result<(), BlockingError>
     if let Some((_, start, end)) = get_bastion_section(&contents) {
         contents = format!("{}{}", &contents[..start], &contents[end..]);
     }
+    // Hosts lookups use the first match, so SafeSearch entries go after the blocks
+    let safesearch = take_section(&mut contents, SAFESEARCH_MARKER_START, SAFESEARCH_MARKER_END);
     
+    // Rows added before domains were normalized on insert may still be in Unicode form
+    let domains: Vec<String> = domains.iter().map(|domain| normalize_domain(domain)).filter(|d| !d.is_empty()).collect();
+    let domains = without_user_domains(&domains, &contents);
+
     // Add new block section if there are domains to block
     if !domains.is_empty() {
-        let block_section = generate_block_entries(domains);
+        let block_section = generate_block_entries(&domains);
         contents.push_str("\n\n");
         contents.push_str(&block_section);
         contents.push('\n');
     }
+    if let Some(section) = safesearch {
+        contents.push('\n');
+        contents.push_str(&section);
+        contents.push('\n');
+    }
     
     write_hosts(&contents)?;
     Ok(())
 }
 
 /// Remove all Bastion blocks from hosts file
-#[allow(dead_code)]
 pub fn clear_blocked_websites() -> Result<(), BlockingError> {
     let mut contents = read_hosts()?;
     
@@ -133,10 +475,56 @@ pub fn clear_blocked_websites() -> Result<(), BlockingError> {
     Ok(())
 }
 
-/// Restore hosts file from backup
-#[allow(dead_code)]
-pub fn restore_hosts(backup_path: &PathBuf) -> Result<(), BlockingError> {
-    fs::copy(backup_path, get_hosts_path())?;
+/// Remove Bastion's block and SafeSearch sections from `contents`.
+fn strip_bastion_sections(contents: &mut String) {
+    take_section(contents, BASTION_MARKER_START, BASTION_MARKER_END);
+    take_section(contents, SAFESEARCH_MARKER_START, SAFESEARCH_MARKER_END);
+}
+
+/// `current` without anything Bastion added: its sections, plus leftovers of sections another
+/// tool mangled (stray markers, entries blocking `bastion_domains`). Lines that are in the
+/// `baseline` are always kept, as is every other edit made since, including removed lines.
+/// An emptied hosts file gets the baseline back.
+fn merge_with_baseline(baseline: &str, current: &str, bastion_domains: &[String]) -> String {
+    let mut contents = current.to_string();
+    strip_bastion_sections(&mut contents);
+    if contents.trim().is_empty() {
+        return baseline.to_string();
+    }
+
+    let baseline_lines: HashSet<&str> = baseline.lines().map(str::trim).collect();
+    let markers = [BASTION_MARKER_START, BASTION_MARKER_END, SAFESEARCH_MARKER_START, SAFESEARCH_MARKER_END];
+    let is_leftover = |line: &str| {
+        let line = line.trim();
+        if baseline_lines.contains(line) {
+            return false;
+        }
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some("127.0.0.1" | "::1"), Some(host), None) => is_domain_blocked(host, bastion_domains),
+            _ => markers.contains(&line),
+        }
+    };
+
+    let newline = if current.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut merged = contents.lines().filter(|line| !is_leftover(line)).collect::<Vec<_>>().join(newline);
+    // Removing the sections leaves the blank lines that separated them
+    merged.truncate(merged.trim_end().len());
+    merged.push_str(newline);
+    merged
+}
+
+/// Emergency cleanup: take everything Bastion wrote out of the hosts file, leaving the baseline
+/// plus the user's own edits (see `merge_with_baseline`). `bastion_domains` are the domains
+/// Bastion may have blocked, to recognize entries left outside its sections.
+pub fn restore_hosts(data_dir: &Path, bastion_domains: &[String]) -> Result<(), BlockingError> {
+    let baseline = fs::read_to_string(data_dir.join(HOSTS_BASELINE_FILE)).unwrap_or_default();
+    // A deleted hosts file is restored from the baseline
+    let current = read_hosts().unwrap_or_default();
+    let restored = merge_with_baseline(&baseline, &current, bastion_domains);
+    if restored != current {
+        write_hosts(&restored)?;
+    }
     Ok(())
 }
 
@@ -150,50 +538,253 @@ pub fn is_admin() -> bool {
         .is_ok()
 }
 
-/// Disable DNS-over-HTTPS in Firefox via Enterprise Policies
-pub fn disable_firefox_doh() -> Result<(), BlockingError> {
+/// The Bastion block section currently in the hosts file, if any.
+pub fn hosts_section() -> Option<String> {
+    let contents = read_hosts().ok()?;
+    get_bastion_section(&contents).map(|(section, _, _)| section.to_string())
+}
+
+/// Start `exe` again with admin/root rights (UAC on Windows, pkexec on Linux, an AppleScript
+/// prompt on macOS), keeping the user's HOME so the same data directory is used. If the prompt
+/// is cancelled, `exe` is started without elevation instead so the app doesn't just disappear.
+/// The caller should exit right after; the launcher waits a moment so the single-instance
+/// check doesn't hand focus back to the old process.
+pub fn spawn_elevated(exe: &std::path::Path) -> Result<(), BlockingError> {
+    use std::process::Command;
+
+    let exe = exe.to_string_lossy().to_string();
+
     #[cfg(target_os = "windows")]
-    let policies_path = PathBuf::from("C:\\Program Files\\Mozilla Firefox\\distribution\\policies.json");
-    
-    #[cfg(not(target_os = "windows"))]
-    // Skip for non-Windows for now or implement as needed
-    return Ok(());
+    let result = {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        let quoted = format!("'{}'", exe.replace('\'', "''"));
+        Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                &format!(
+                    "Start-Sleep -Seconds 1; try {{ Start-Process -FilePath {0} -Verb RunAs -ErrorAction Stop }} catch {{ Start-Process -FilePath {0} }}",
+                    quoted
+                ),
+            ])
+            .creation_flags(CREATE_NO_WINDOW)
+            .spawn()
+    };
+
+    #[cfg(target_os = "linux")]
+    let result = Command::new("sh")
+        .args([
+            "-c",
+            r#"sleep 1; pkexec env HOME="$HOME" DISPLAY="$DISPLAY" XAUTHORITY="$XAUTHORITY" WAYLAND_DISPLAY="$WAYLAND_DISPLAY" XDG_RUNTIME_DIR="$XDG_RUNTIME_DIR" "$0" || "$0""#,
+            &exe,
+        ])
+        .spawn();
+
+    #[cfg(target_os = "macos")]
+    let result = Command::new("sh")
+        .args([
+            "-c",
+            r#"sleep 1; osascript -e 'on run argv' -e 'do shell script "HOME=" & (quoted form of item 2 of argv) & " " & (quoted form of item 1 of argv) & " > /dev/null 2>&1 &" with administrator privileges' -e 'end run' "$0" "$HOME" || "$0""#,
+            &exe,
+        ])
+        .spawn();
+
+    result?;
+    Ok(())
+}
+
+/// SHA-256 of a hosts section, used to detect edits by other tools. Line endings are
+/// normalized so editors that convert them don't count as drift.
+pub fn section_checksum(section: &str) -> String {
+    use sha2::{Digest, Sha256};
+    crate::security::to_hex(&Sha256::digest(section.replace("\r\n", "\n").as_bytes()))
+}
+
+/// Whether the hosts file currently contains the Bastion block section.
+pub fn hosts_section_present() -> bool {
+    hosts_section().is_some()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BrowserPolicyStatus {
+    pub browser: String,
+    pub doh_disabled: bool,
+}
+
+/// Whether the DoH policies written by `disable_firefox_doh` / `disable_chromium_doh` are in place.
+/// Empty on platforms where Bastion doesn't manage browser policies.
+pub fn doh_policy_status() -> Vec<BrowserPolicyStatus> {
+    #[allow(unused_mut)]
+    let mut statuses = Vec::new();
 
     #[cfg(target_os = "windows")]
     {
-        if !policies_path.parent().unwrap().exists() {
-            fs::create_dir_all(policies_path.parent().unwrap())?;
+        use std::process::Command;
+
+        let firefox = fs::read_to_string("C:\\Program Files\\Mozilla Firefox\\distribution\\policies.json")
+            .is_ok_and(|policies| policies.contains("\"DNSOverHTTPS\""));
+        statuses.push(BrowserPolicyStatus { browser: "Firefox".to_string(), doh_disabled: firefox });
+
+        for (browser, key) in [("Chrome", "HKLM\\SOFTWARE\\Policies\\Google\\Chrome"), ("Thorium", "HKLM\\SOFTWARE\\Policies\\Thorium")] {
+            let doh_disabled = Command::new("reg")
+                .args(&["query", key, "/v", "DnsOverHttpsMode"])
+                .output()
+                .is_ok_and(|output| output.status.success() && String::from_utf8_lossy(&output.stdout).contains("off"));
+            statuses.push(BrowserPolicyStatus { browser: browser.to_string(), doh_disabled });
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(path) = firefox_policies_path() {
+            let doh_disabled = fs::read_to_string(path).is_ok_and(|policies| policies.contains("\"DNSOverHTTPS\""));
+            statuses.push(BrowserPolicyStatus { browser: "Firefox".to_string(), doh_disabled });
+        }
+        for (browser, location, _id, _update_url) in chromium_policy_targets() {
+            if !is_chromium_installed(browser, location) {
+                continue;
+            }
+            let doh_disabled = fs::read_to_string(PathBuf::from(location).join(CHROMIUM_DOH_POLICY_FILE))
+                .is_ok_and(|policy| policy.contains("DnsOverHttpsMode"));
+            statuses.push(BrowserPolicyStatus { browser: browser.to_string(), doh_disabled });
         }
+    }
 
-        let policy_content = r#"{
-    "policies": {
-        "DNSOverHTTPS": {
-            "Enabled": false,
-            "Locked": true
+    #[cfg(target_os = "macos")]
+    {
+        if let Some(path) = firefox_policies_path() {
+            let doh_disabled = fs::read_to_string(path).is_ok_and(|policies| policies.contains("\"DNSOverHTTPS\""));
+            statuses.push(BrowserPolicyStatus { browser: "Firefox".to_string(), doh_disabled });
         }
+        statuses.extend(crate::macos::chromium_doh_status());
     }
-}"#;
 
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(policies_path)?;
-            
-        file.write_all(policy_content.as_bytes())?;
+    statuses
+}
+
+/// Whether the system resolver sends `domain` to localhost, i.e. the hosts entries are in effect.
+pub fn resolves_to_localhost(domain: &str) -> bool {
+    use std::net::ToSocketAddrs;
+
+    match (domain, 80).to_socket_addrs() {
+        Ok(addrs) => {
+            let addrs: Vec<_> = addrs.collect();
+            !addrs.is_empty() && addrs.iter().all(|addr| addr.ip().is_loopback() || addr.ip().is_unspecified())
+        }
+        Err(_) => false,
     }
-    
+}
+
+/// Disable DNS-over-HTTPS in Firefox via Enterprise Policies
+pub fn disable_firefox_doh() -> Result<(), BlockingError> {
+    update_firefox_policies(|policies| {
+        policies.insert("DNSOverHTTPS".to_string(), serde_json::json!({ "Enabled": false, "Locked": true }));
+    })
+}
+
+/// Have Firefox trust the CA certificate at `cert_path` (the HTTPS block page), or stop trusting it.
+pub fn set_firefox_certificate(cert_path: Option<&Path>) -> Result<(), BlockingError> {
+    update_firefox_policies(|policies| match cert_path {
+        Some(path) => {
+            policies.insert(
+                "Certificates".to_string(),
+                serde_json::json!({ "ImportEnterpriseRoots": true, "Install": [path.to_string_lossy()] }),
+            );
+        }
+        None => {
+            policies.remove("Certificates");
+        }
+    })
+}
+
+/// Firefox reads Enterprise Policies from `distribution/policies.json` next to its binary.
+fn firefox_policies_path() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    return Some(PathBuf::from("C:\\Program Files\\Mozilla Firefox\\distribution\\policies.json"));
+    #[cfg(target_os = "macos")]
+    return Some(PathBuf::from("/Applications/Firefox.app"))
+        .filter(|app| app.exists())
+        .map(|app| app.join("Contents/Resources/distribution/policies.json"));
+    #[cfg(target_os = "linux")]
+    {
+        // Distribution packages read policies next to the binary; snap and flatpak builds
+        // (which can't be written into) read the system-wide /etc/firefox/policies instead
+        if let Some(dir) = ["/usr/lib/firefox", "/usr/lib64/firefox", "/usr/lib/firefox-esr", "/opt/firefox"]
+            .iter()
+            .map(PathBuf::from)
+            .find(|dir| dir.exists())
+        {
+            return Some(dir.join("distribution").join("policies.json"));
+        }
+        let installed = ["/usr/bin/firefox", "/snap/bin/firefox", "/var/lib/flatpak/app/org.mozilla.firefox"]
+            .iter()
+            .any(|path| Path::new(path).exists());
+        installed.then(|| PathBuf::from("/etc/firefox/policies/policies.json"))
+    }
+}
+
+/// Edit the `policies` object of Firefox's policies.json, keeping policies Bastion doesn't own.
+fn update_firefox_policies(edit: impl FnOnce(&mut serde_json::Map<String, serde_json::Value>)) -> Result<(), BlockingError> {
+    let Some(policies_path) = firefox_policies_path() else {
+        return Ok(()); // Firefox isn't installed
+    };
+    let mut root: serde_json::Value = fs::read_to_string(&policies_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .filter(serde_json::Value::is_object)
+        .unwrap_or_else(|| serde_json::json!({}));
+    let policies = root
+        .as_object_mut()
+        .unwrap()
+        .entry("policies")
+        .or_insert_with(|| serde_json::json!({}));
+    if !policies.is_object() {
+        *policies = serde_json::json!({});
+    }
+    edit(policies.as_object_mut().unwrap());
+
+    if let Some(dir) = policies_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let content = serde_json::to_string_pretty(&root).map_err(|e| BlockingError { message: e.to_string(), permission_denied: false })?;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(policies_path)?;
+    file.write_all(content.as_bytes())?;
     Ok(())
 }
 
-/// Disables DNS-over-HTTPS (DoH) for Chromium-based browsers via Windows Registry.
+/// File Bastion owns inside a Chromium managed policy directory (Linux)
+#[cfg(target_os = "linux")]
+const CHROMIUM_DOH_POLICY_FILE: &str = "bastion-doh.json";
+
+/// Disables DNS-over-HTTPS (DoH) for Chromium-based browsers via Windows Registry, managed
+/// policy files on Linux, or preference domains on macOS.
 ///
 /// This is critical because modern browsers often bypass the system `hosts` file by using DoH.
 /// By enforcing the system DNS, we ensure that our `127.0.0.1` blocks in the `hosts` file are respected.
 ///
-/// Supports: Chrome, Thorium (and potentially others if they respect Global Policies).
+/// Supports: Chrome, Thorium (and potentially others if they respect Global Policies); Chrome,
+/// Chromium and Edge on Linux; Chrome, Edge, Brave and Chromium on macOS.
 /// Requires: Administrator privileges.
 pub fn disable_chromium_doh() -> Result<(), BlockingError> {
+    #[cfg(target_os = "linux")]
+    for (browser, location, _id, _update_url) in chromium_policy_targets() {
+        if !is_chromium_installed(browser, location) {
+            continue;
+        }
+        let dir = PathBuf::from(location);
+        fs::create_dir_all(&dir)?;
+        let policy = serde_json::json!({ "DnsOverHttpsMode": "off", "BuiltInDnsClientEnabled": false });
+        fs::write(dir.join(CHROMIUM_DOH_POLICY_FILE), policy.to_string())?;
+    }
+
+    #[cfg(target_os = "macos")]
+    crate::macos::disable_chromium_doh()?;
+
     #[cfg(target_os = "windows")]
     {
         use std::process::Command;
@@ -222,7 +813,286 @@ pub fn disable_chromium_doh() -> Result<(), BlockingError> {
     Ok(())
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionPolicyStatus {
+    pub browser: String,
+    pub force_installed: bool,
+}
+
+/// Chromium `ExtensionSettings` entry: install from the store, pin to the toolbar, no removal
+fn chromium_extension_settings(id: &str, update_url: &str) -> serde_json::Value {
+    serde_json::json!({
+        id: {
+            "installation_mode": "force_installed",
+            "update_url": update_url,
+            "toolbar_pin": "force_pinned",
+        }
+    })
+}
+
+/// Chromium browsers as (name, policy location, extension id, update URL). Locations are registry
+/// keys on Windows and managed policy directories on Linux; macOS only honours Chromium policies
+/// from configuration profiles, which Bastion doesn't install.
+#[allow(unused_imports)]
+fn chromium_policy_targets() -> Vec<(&'static str, &'static str, &'static str, &'static str)> {
+    use crate::extension::{CHROME_EXTENSION_ID, CHROME_UPDATE_URL, EDGE_EXTENSION_ID, EDGE_UPDATE_URL};
+
+    #[cfg(target_os = "windows")]
+    return vec![
+        ("Chrome", "HKLM\\SOFTWARE\\Policies\\Google\\Chrome", CHROME_EXTENSION_ID, CHROME_UPDATE_URL),
+        ("Thorium", "HKLM\\SOFTWARE\\Policies\\Thorium", CHROME_EXTENSION_ID, CHROME_UPDATE_URL),
+        ("Edge", "HKLM\\SOFTWARE\\Policies\\Microsoft\\Edge", EDGE_EXTENSION_ID, EDGE_UPDATE_URL),
+    ];
+    #[cfg(target_os = "linux")]
+    return vec![
+        ("Chrome", "/etc/opt/chrome/policies/managed", CHROME_EXTENSION_ID, CHROME_UPDATE_URL),
+        ("Chromium", "/etc/chromium/policies/managed", CHROME_EXTENSION_ID, CHROME_UPDATE_URL),
+        ("Edge", "/etc/opt/edge/policies/managed", EDGE_EXTENSION_ID, EDGE_UPDATE_URL),
+    ];
+    #[cfg(target_os = "macos")]
+    return Vec::new();
+}
+
+/// File Bastion owns inside a Chromium managed policy directory (Linux)
+#[cfg(not(target_os = "windows"))]
+const CHROMIUM_POLICY_FILE: &str = "bastion-extension.json";
+
+/// Whether a Chromium browser from `chromium_policy_targets` is installed (Linux): its binary is
+/// on the system, or its policy folder already exists.
+#[cfg(target_os = "linux")]
+fn is_chromium_installed(browser: &str, location: &str) -> bool {
+    let binaries: &[&str] = match browser {
+        "Chrome" => &["/opt/google/chrome/chrome", "/usr/bin/google-chrome", "/usr/bin/google-chrome-stable"],
+        "Chromium" => &["/usr/bin/chromium", "/usr/bin/chromium-browser", "/usr/lib/chromium/chromium"],
+        "Edge" => &["/opt/microsoft/msedge/msedge", "/usr/bin/microsoft-edge", "/usr/bin/microsoft-edge-stable"],
+        _ => &[],
+    };
+    binaries.iter().any(|path| Path::new(path).exists()) || Path::new(location).parent().is_some_and(Path::exists)
+}
+
+/// Force-install and pin the companion extension in Chrome/Edge/Firefox via Enterprise Policies,
+/// so it can't be disabled or removed while strict mode is on. Requires administrator privileges.
+pub fn force_install_extension() -> Result<(), BlockingError> {
+    for (_browser, location, id, update_url) in chromium_policy_targets() {
+        let settings = chromium_extension_settings(id, update_url);
+
+        #[cfg(target_os = "windows")]
+        {
+            use std::process::Command;
+
+            let output = Command::new("reg")
+                .args(&["add", location, "/v", "ExtensionSettings", "/t", "REG_SZ", "/d", &settings.to_string(), "/f"])
+                .output()?;
+            if !output.status.success() {
+                return Err(BlockingError {
+                    message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                    permission_denied: true,
+                });
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let dir = PathBuf::from(location);
+            if !is_chromium_installed(_browser, location) {
+                continue;
+            }
+            fs::create_dir_all(&dir)?;
+            let policy = serde_json::json!({ "ExtensionSettings": settings });
+            fs::write(dir.join(CHROMIUM_POLICY_FILE), policy.to_string())?;
+        }
+
+        #[cfg(target_os = "macos")]
+        let _ = (location, settings);
+    }
+
+    update_firefox_policies(|policies| {
+        let extensions = policies.entry("ExtensionSettings").or_insert_with(|| serde_json::json!({}));
+        if let Some(extensions) = extensions.as_object_mut() {
+            extensions.insert(
+                crate::extension::FIREFOX_EXTENSION_ID.to_string(),
+                serde_json::json!({
+                    "installation_mode": "force_installed",
+                    "install_url": crate::extension::FIREFOX_INSTALL_URL,
+                    "default_area": "navbar",
+                }),
+            );
+        }
+    })
+}
+
+/// Remove the policies written by `force_install_extension`. The extension stays installed
+/// but becomes removable again.
+pub fn remove_extension_policies() -> Result<(), BlockingError> {
+    for (_browser, location, _id, _update_url) in chromium_policy_targets() {
+        #[cfg(target_os = "windows")]
+        {
+            use std::process::Command;
+
+            // Fails harmlessly when the value isn't there
+            let _ = Command::new("reg").args(&["delete", location, "/v", "ExtensionSettings", "/f"]).output()?;
+        }
+
+        #[cfg(target_os = "linux")]
+        match fs::remove_file(PathBuf::from(location).join(CHROMIUM_POLICY_FILE)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+
+        #[cfg(target_os = "macos")]
+        let _ = location;
+    }
+
+    if firefox_policies_path().is_some_and(|path| path.exists()) {
+        update_firefox_policies(|policies| {
+            let now_empty = policies.get_mut("ExtensionSettings").and_then(|v| v.as_object_mut()).is_some_and(|extensions| {
+                extensions.remove(crate::extension::FIREFOX_EXTENSION_ID);
+                extensions.is_empty()
+            });
+            if now_empty {
+                policies.remove("ExtensionSettings");
+            }
+        })?;
+    }
+    Ok(())
+}
+
+/// Whether the force-install policies are in place, per browser Bastion manages.
+pub fn extension_policy_status() -> Vec<ExtensionPolicyStatus> {
+    let mut statuses: Vec<ExtensionPolicyStatus> = chromium_policy_targets()
+        .into_iter()
+        .map(|(browser, location, id, _update_url)| {
+            #[cfg(target_os = "windows")]
+            let force_installed = std::process::Command::new("reg")
+                .args(&["query", location, "/v", "ExtensionSettings"])
+                .output()
+                .is_ok_and(|output| output.status.success() && String::from_utf8_lossy(&output.stdout).contains(id));
+            #[cfg(not(target_os = "windows"))]
+            let force_installed = fs::read_to_string(PathBuf::from(location).join(CHROMIUM_POLICY_FILE))
+                .is_ok_and(|policy| policy.contains(id));
+            ExtensionPolicyStatus { browser: browser.to_string(), force_installed }
+        })
+        .collect();
+
+    if let Some(path) = firefox_policies_path() {
+        let force_installed = fs::read_to_string(path).is_ok_and(|policies| policies.contains(crate::extension::FIREFOX_EXTENSION_ID));
+        statuses.push(ExtensionPolicyStatus { browser: "Firefox".to_string(), force_installed });
+    }
+    statuses
+}
+
+pub const SAFESEARCH_SETTING: &str = "safesearch_enforced";
+
+/// (SafeSearch host, published address used when it can't be resolved, domains to point at it)
+const SAFESEARCH_TARGETS: &[(&str, Option<&str>, &[&str])] = &[
+    ("forcesafesearch.google.com", Some("216.239.38.120"), &["www.google.com", "google.com"]),
+    ("strict.bing.com", Some("204.79.197.220"), &["www.bing.com", "bing.com"]),
+    ("safe.duckduckgo.com", None, &["duckduckgo.com", "www.duckduckgo.com"]),
+    (
+        "restrict.youtube.com",
+        Some("216.239.38.120"),
+        &["www.youtube.com", "m.youtube.com", "youtubei.googleapis.com", "youtube.googleapis.com", "www.youtube-nocookie.com"],
+    ),
+];
+
+/// Hosts entries pointing the search engines at their SafeSearch hosts, the hosts-file
+/// equivalent of the CNAMEs the providers document for network-wide enforcement.
+fn generate_safesearch_entries(resolve: impl Fn(&str) -> Option<std::net::IpAddr>) -> String {
+    let mut entries = String::new();
+    entries.push_str(SAFESEARCH_MARKER_START);
+    entries.push('\n');
+
+    for (safe_host, fallback, domains) in SAFESEARCH_TARGETS {
+        let Some(ip) = resolve(safe_host).or_else(|| fallback.and_then(|ip| ip.parse().ok())) else {
+            continue;
+        };
+        for domain in *domains {
+            entries.push_str(&format!("{} {}\n", ip, domain));
+        }
+    }
+
+    entries.push_str(SAFESEARCH_MARKER_END);
+    entries
+}
+
+fn resolve_ipv4(host: &str) -> Option<std::net::IpAddr> {
+    use std::net::ToSocketAddrs;
+
+    (host, 443).to_socket_addrs().ok()?.map(|addr| addr.ip()).find(|ip| ip.is_ipv4())
+}
+
+/// Chromium policies enforcing SafeSearch and YouTube Restricted Mode (moderate)
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+fn chromium_safesearch_policies(browser: &str) -> Vec<(&'static str, u32)> {
+    let mut policies = vec![("ForceGoogleSafeSearch", 1), ("ForceYouTubeRestrict", 1)];
+    if browser == "Edge" {
+        policies.push(("ForceBingSafeSearch", 2)); // 2 = strict
+    }
+    policies
+}
+
+/// File Bastion owns inside a Chromium managed policy directory (Linux)
+#[cfg(target_os = "linux")]
+const CHROMIUM_SAFESEARCH_POLICY_FILE: &str = "bastion-safesearch.json";
+
+/// Enforce (or stop enforcing) SafeSearch for Google/Bing/DuckDuckGo and YouTube Restricted Mode:
+/// hosts entries for every browser, plus Chromium policies that also hold when the browser
+/// resolves names itself. Requires administrator privileges.
+pub fn set_safesearch_enforced(enabled: bool) -> Result<(), BlockingError> {
+    let mut contents = read_hosts()?;
+    take_section(&mut contents, SAFESEARCH_MARKER_START, SAFESEARCH_MARKER_END);
+    let mut contents = contents.trim_end().to_string();
+    contents.push('\n');
+    if enabled {
+        contents.push('\n');
+        contents.push_str(&generate_safesearch_entries(resolve_ipv4));
+        contents.push('\n');
+    }
+    write_hosts(&contents)?;
+
+    for (browser, location, _id, _update_url) in chromium_policy_targets() {
+        #[cfg(target_os = "windows")]
+        for (name, value) in chromium_safesearch_policies(browser) {
+            use std::process::Command;
+
+            let args: Vec<String> = if enabled {
+                ["add", location, "/v", name, "/t", "REG_DWORD", "/d", &value.to_string(), "/f"].iter().map(|a| a.to_string()).collect()
+            } else {
+                ["delete", location, "/v", name, "/f"].iter().map(|a| a.to_string()).collect()
+            };
+            let _ = Command::new("reg").args(&args).output()?;
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let path = PathBuf::from(location).join(CHROMIUM_SAFESEARCH_POLICY_FILE);
+            if enabled && path.parent().and_then(|dir| dir.parent()).is_some_and(|dir| dir.exists()) {
+                fs::create_dir_all(path.parent().unwrap())?;
+                let policy: serde_json::Map<String, serde_json::Value> =
+                    chromium_safesearch_policies(browser).into_iter().map(|(name, value)| (name.to_string(), value.into())).collect();
+                fs::write(&path, serde_json::Value::Object(policy).to_string())?;
+            } else if !enabled {
+                match fs::remove_file(&path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+        }
+
+        #[cfg(target_os = "macos")]
+        let _ = (browser, location);
+    }
+
+    flush_dns()
+}
+
+/// Whether the hosts file currently contains the SafeSearch section.
+pub fn safesearch_hosts_present() -> bool {
+    read_hosts().is_ok_and(|contents| contents.contains(SAFESEARCH_MARKER_START))
+}
+
 /// Purges system DNS cache.
+#[tracing::instrument]
 pub fn flush_dns() -> Result<(), BlockingError> {
     #[cfg(target_os = "windows")]
     {
@@ -243,16 +1113,69 @@ pub struct InstalledApp {
     pub name: String,
     #[serde(alias = "AppID")]
     pub id: String,
+    #[serde(default)]
+    pub running: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunningProcess {
     pub pid: u32,
     pub name: String,
+    pub exe: Option<String>, // full executable path; pass to `get_process_icon`
+    pub parent_pid: Option<u32>,
+    pub window_title: Option<String>, // main window title, where the platform exposes it
 }
 
-/// Get all installed applications using PowerShell
+/// Get all installed applications: Start menu entries on Windows, `.app` bundles on macOS,
+/// `.desktop` launchers on Linux. Slow (PowerShell on Windows), so callers go through `AppCatalog`.
 pub fn get_installed_applications() -> Vec<InstalledApp> {
+    #[cfg(target_os = "macos")]
+    {
+        let home = std::env::var("HOME").unwrap_or_default();
+        let mut apps: Vec<InstalledApp> = ["/Applications".to_string(), format!("{}/Applications", home)]
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "app" {
+                    return None;
+                }
+                let name = path.file_stem()?.to_string_lossy().to_string();
+                // The process is named after the bundle executable, which is usually the bundle name
+                Some(InstalledApp { name: name.clone(), id: name, running: false })
+            })
+            .collect();
+        if !apps.is_empty() {
+            apps.sort_by_key(|app| app.name.to_lowercase());
+            return apps;
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let home = std::env::var("HOME").unwrap_or_default();
+        let dirs = [
+            "/usr/share/applications".to_string(),
+            "/var/lib/flatpak/exports/share/applications".to_string(),
+            format!("{}/.local/share/applications", home),
+        ];
+        let mut apps: Vec<InstalledApp> = dirs
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "desktop"))
+            .filter_map(|entry| parse_desktop_entry(&fs::read_to_string(entry.path()).ok()?))
+            .collect();
+        if !apps.is_empty() {
+            apps.sort_by_key(|app| app.name.to_lowercase());
+            apps.dedup_by(|a, b| a.name == b.name);
+            return apps;
+        }
+    }
+
     #[cfg(target_os = "windows")]
     {
         use std::process::Command;
@@ -275,21 +1198,296 @@ pub fn get_installed_applications() -> Vec<InstalledApp> {
         }
     }
     
-    // Fallback/Common apps for manual discovery or if PowerShell fails
-    vec![
-        InstalledApp { name: "Discord".to_string(), id: "Discord.exe".to_string() },
-        InstalledApp { name: "Spotify".to_string(), id: "Spotify.exe".to_string() },
-        InstalledApp { name: "Firefox".to_string(), id: "firefox.exe".to_string() },
-        InstalledApp { name: "Chrome".to_string(), id: "chrome.exe".to_string() },
-        InstalledApp { name: "Steam".to_string(), id: "steam.exe".to_string() },
-        InstalledApp { name: "VS Code".to_string(), id: "Code.exe".to_string() },
+    // Fallback/Common apps for manual discovery or if the scan fails
+    [
+        ("Discord", "Discord.exe"),
+        ("Spotify", "Spotify.exe"),
+        ("Firefox", "firefox.exe"),
+        ("Chrome", "chrome.exe"),
+        ("Steam", "steam.exe"),
+        ("VS Code", "Code.exe"),
     ]
+    .into_iter()
+    .map(|(name, id)| InstalledApp { name: name.to_string(), id: id.to_string(), running: false })
+    .collect()
 }
 
-/// Get all running processes
-pub fn get_running_processes() -> Vec<RunningProcess> {
+/// Name and executable of a visible `.desktop` launcher
+#[cfg(target_os = "linux")]
+fn parse_desktop_entry(contents: &str) -> Option<InstalledApp> {
+    let mut name = None;
+    let mut exec = None;
+    let mut in_entry = false;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry {
+            continue;
+        }
+        match line.split_once('=') {
+            Some(("Name", value)) if name.is_none() => name = Some(value.to_string()),
+            // First word without arguments or %f-style field codes
+            Some(("Exec", value)) => exec = value.split_whitespace().find(|arg| *arg != "env" && !arg.contains('=')).map(str::to_string),
+            Some(("NoDisplay", "true")) | Some(("Hidden", "true")) => return None,
+            _ => {}
+        }
+    }
+    let exec = exec?;
+    let id = exec.trim_matches('"').rsplit('/').next()?.to_string();
+    Some(InstalledApp { name: name?, id, running: false })
+}
+
+/// Installed applications flagged with whether they're running, followed by running apps that
+/// aren't installed entries (anything with a window, or inside a macOS `.app` bundle).
+pub fn merge_running_apps(installed: Vec<InstalledApp>, running: &[RunningProcess]) -> Vec<InstalledApp> {
+    // Windows AppIDs are often full paths; compare the executable name
+    let process_name = |id: &str| id.rsplit(['\\', '/']).next().unwrap_or(id).to_lowercase();
+    let running_names: std::collections::HashSet<String> = running.iter().map(|p| p.name.to_lowercase()).collect();
+
+    let mut apps: Vec<InstalledApp> = installed
+        .into_iter()
+        .map(|app| InstalledApp { running: running_names.contains(&process_name(&app.id)), ..app })
+        .collect();
+    let known: std::collections::HashSet<String> = apps.iter().map(|app| process_name(&app.id)).collect();
+    for process in running {
+        let is_app = process.window_title.is_some() || process.exe.as_deref().is_some_and(|exe| exe.contains(".app/"));
+        if is_app && !known.contains(&process.name.to_lowercase()) {
+            apps.push(InstalledApp { name: process.name.clone(), id: process.name.clone(), running: true });
+        }
+    }
+    apps
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AppSource {
+    Blocked,
+    Installed,
+    Running,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppMatch {
+    pub name: String,
+    pub id: String, // process name to block
+    pub source: AppSource,
+    pub running: bool,
+    pub score: i64,
+}
+
+/// How well `query` matches `candidate`, case-insensitively: exact, prefix, word prefix,
+/// substring, then in-order characters ("dsc" -> "Discord"). `None` if it doesn't match.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let query = query.trim().to_lowercase();
+    let candidate = candidate.to_lowercase();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let extra = (candidate.chars().count() as i64 - query.chars().count() as i64).max(0);
+    if candidate == query {
+        return Some(1000);
+    }
+    if candidate.starts_with(&query) {
+        return Some(800 - extra.min(100));
+    }
+    if candidate
+        .match_indices(&query)
+        .any(|(at, _)| candidate[..at].ends_with([' ', '-', '_', '.']))
+    {
+        return Some(600 - extra.min(100));
+    }
+    if let Some(at) = candidate.find(&query) {
+        return Some(400 - (at as i64).min(100));
+    }
+
+    // Subsequence: reward consecutive runs, penalise gaps
+    let mut score = 200;
+    let mut chars = candidate.chars();
+    let mut consecutive = true;
+    for q in query.chars() {
+        let mut gap = 0;
+        loop {
+            match chars.next() {
+                Some(c) if c == q => break,
+                Some(_) => gap += 1,
+                None => return None,
+            }
+        }
+        if gap == 0 && consecutive {
+            score += 5;
+        }
+        consecutive = gap == 0;
+        score -= gap.min(20);
+    }
+    Some(score.max(1))
+}
+
+/// Rank blocked, installed and running apps against `query`, one entry per process name.
+/// Blocked apps are listed as such so the picker can mark them; running apps get a small boost.
+pub fn search_apps(
+    query: &str,
+    blocked: &[crate::storage::BlockedApp],
+    installed: Vec<InstalledApp>,
+    running: &[RunningProcess],
+    limit: usize,
+) -> Vec<AppMatch> {
+    let key = |id: &str| id.rsplit(['\\', '/']).next().unwrap_or(id).to_lowercase();
+    let running_names: std::collections::HashSet<String> = running.iter().map(|p| p.name.to_lowercase()).collect();
+
+    let candidates = blocked
+        .iter()
+        .map(|app| (app.name.clone(), app.process_name.clone(), AppSource::Blocked))
+        .chain(installed.into_iter().map(|app| (app.name, app.id, AppSource::Installed)))
+        .chain(running.iter().map(|p| (p.window_title.clone().unwrap_or_else(|| p.name.clone()), p.name.clone(), AppSource::Running)));
+
+    let mut seen = std::collections::HashSet::new();
+    let mut matches: Vec<AppMatch> = candidates
+        .filter(|(_, id, _)| seen.insert(key(id)))
+        .filter_map(|(name, id, source)| {
+            let score = fuzzy_score(query, &name).max(fuzzy_score(query, &id))?;
+            let running = running_names.contains(&key(&id));
+            Some(AppMatch { score: score + if running { 10 } else { 0 }, name, id, source, running })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+    matches.truncate(limit);
+    matches
+}
+
+/// Cached result of `get_installed_applications`, so the app picker opens instantly.
+/// Warmed at startup; entries older than `APP_CATALOG_TTL_SECS` are rescanned.
+#[derive(Default)]
+pub struct AppCatalog {
+    installed: Mutex<Option<(i64, Vec<InstalledApp>)>>,
+}
+
+pub const APP_CATALOG_TTL_SECS: i64 = 600;
+
+impl AppCatalog {
+    pub fn installed(&self, refresh: bool) -> Vec<InstalledApp> {
+        let now = chrono::Utc::now().timestamp();
+        if !refresh {
+            if let Some((scanned_at, apps)) = self.installed.lock().unwrap().as_ref() {
+                if now - scanned_at < APP_CATALOG_TTL_SECS {
+                    return apps.clone();
+                }
+            }
+        }
+        // Scan without holding the lock; concurrent callers at worst scan twice
+        let apps = get_installed_applications();
+        *self.installed.lock().unwrap() = Some((now, apps.clone()));
+        apps
+    }
+}
+
+/// The process listening on TCP `port`, as "name (pid N)", if it can be determined.
+pub fn port_holder(port: u16) -> Option<String> {
+    use std::process::Command;
+
+    #[cfg(target_os = "windows")]
+    let pid: u32 = {
+        let output = Command::new("netstat").args(&["-ano", "-p", "TCP"]).output().ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let suffix = format!(":{}", port);
+        stdout.lines().find_map(|line| {
+            let cols: Vec<&str> = line.split_whitespace().collect();
+            match cols.as_slice() {
+                [_, local, _, "LISTENING", pid] if local.ends_with(&suffix) => pid.parse().ok(),
+                _ => None,
+            }
+        })?
+    };
+
+    #[cfg(not(target_os = "windows"))]
+    let pid: u32 = {
+        let output = Command::new("lsof")
+            .args(["-nP", &format!("-iTCP:{}", port), "-sTCP:LISTEN", "-t"])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).lines().next()?.trim().parse().ok()?
+    };
+
     let mut system = System::new();
     system.refresh_processes(ProcessesToUpdate::All, true);
+    let name = system
+        .process(sysinfo::Pid::from_u32(pid))
+        .map_or_else(|| "unknown".to_string(), |p| p.name().to_string_lossy().to_string());
+    Some(format!("{} (pid {})", name, pid))
+}
+
+/// Comment that marks the firewall rules Bastion adds, so they can be removed reliably
+#[cfg(target_os = "linux")]
+const REDIRECT_RULE_COMMENT: &str = "bastion-block-server";
+
+/// Redirect local TCP connections to `addr:from` to `addr:to` (Linux only, via iptables).
+/// Used when another program holds port 80/443 and the block server runs on an alternate port.
+pub fn add_port_redirect(addr: &str, from: u16, to: u16) -> Result<(), BlockingError> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::process::Command;
+
+        let iptables = if addr.contains(':') { "ip6tables" } else { "iptables" };
+        let status = Command::new(iptables)
+            .args([
+                "-t", "nat", "-A", "OUTPUT", "-p", "tcp", "-d", addr, "--dport", &from.to_string(),
+                "-m", "comment", "--comment", REDIRECT_RULE_COMMENT,
+                "-j", "REDIRECT", "--to-ports", &to.to_string(),
+            ])
+            .status()?;
+        if !status.success() {
+            return Err(BlockingError {
+                message: format!("{} exited with {}", iptables, status),
+                permission_denied: false,
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (addr, from, to);
+        Err(BlockingError {
+            message: "Firewall redirects are only supported on Linux".to_string(),
+            permission_denied: false,
+        })
+    }
+}
+
+/// Remove every redirect added by `add_port_redirect`, including ones left by a crash.
+/// Returns how many rules were removed.
+pub fn remove_port_redirects() -> usize {
+    #[allow(unused_mut)]
+    let mut removed = 0;
+
+    #[cfg(target_os = "linux")]
+    {
+        use std::process::Command;
+
+        for iptables in ["iptables", "ip6tables"] {
+            let Ok(output) = Command::new(iptables).args(["-t", "nat", "-S", "OUTPUT"]).output() else {
+                continue;
+            };
+            for rule in String::from_utf8_lossy(&output.stdout).lines() {
+                if let Some(spec) = rule.strip_prefix("-A ").filter(|r| r.contains(REDIRECT_RULE_COMMENT)) {
+                    let mut args = vec!["-t", "nat", "-D"];
+                    args.extend(spec.split_whitespace().map(|arg| arg.trim_matches('"')));
+                    if Command::new(iptables).args(&args).status().is_ok_and(|status| status.success()) {
+                        removed += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    removed
+}
+
+/// Get all running processes
+pub fn get_running_processes(monitor: &ProcessMonitor) -> Vec<RunningProcess> {
+    let mut titles = window_titles();
+    let system = monitor.refreshed_detailed();
     
     let mut processes: Vec<RunningProcess> = system
         .processes()
@@ -297,21 +1495,164 @@ pub fn get_running_processes() -> Vec<RunningProcess> {
         .map(|(pid, process)| RunningProcess {
             pid: pid.as_u32(),
             name: process.name().to_string_lossy().to_string(),
+            exe: process.exe().map(|path| path.to_string_lossy().to_string()),
+            parent_pid: process.parent().map(|parent| parent.as_u32()),
+            window_title: titles.remove(&pid.as_u32()),
         })
         .collect();
 
-    // Sort by name and remove duplicates
-    processes.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    // Sort by name and remove duplicates, keeping the instance that has a window
+    processes.sort_by_key(|p| (p.name.to_lowercase(), p.window_title.is_none()));
     processes.dedup_by(|a, b| a.name.to_lowercase() == b.name.to_lowercase());
     
     processes
 }
 
+/// Main window titles by PID: `tasklist` on Windows, `wmctrl` on Linux (X11). Empty on macOS.
+fn window_titles() -> std::collections::HashMap<u32, String> {
+    #[cfg(target_os = "windows")]
+    {
+        let Ok(output) = std::process::Command::new("tasklist").args(["/v", "/fo", "csv", "/nh"]).output() else {
+            return Default::default();
+        };
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let cols: Vec<&str> = line.trim().trim_matches('"').split("\",\"").collect();
+                let pid = cols.get(1)?.parse().ok()?;
+                let title = cols.get(8)?.trim();
+                (!title.is_empty() && title != "N/A").then(|| (pid, title.to_string()))
+            })
+            .collect()
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let Ok(output) = std::process::Command::new("wmctrl").arg("-lp").output() else {
+            return Default::default();
+        };
+        // <window id> <desktop> <pid> <host> <title...>
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let cols: Vec<&str> = line.split_whitespace().collect();
+                let pid = cols.get(2)?.parse().ok()?;
+                let title = cols.get(4..)?.join(" ");
+                (!title.is_empty()).then_some((pid, title))
+            })
+            .collect()
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Default::default()
+    }
+}
+
+/// The icon of an executable as a PNG data URL, for the app picker. Windows extracts the
+/// associated icon; macOS converts the `.app` bundle's icon. `None` elsewhere.
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+pub fn get_process_icon(exe: &str) -> Option<String> {
+    use std::process::Command;
+
+    #[cfg(target_os = "windows")]
+    let base64 = {
+        let script = format!(
+            "Add-Type -AssemblyName System.Drawing; \
+             $icon = [System.Drawing.Icon]::ExtractAssociatedIcon('{}'); \
+             $stream = New-Object System.IO.MemoryStream; \
+             $icon.ToBitmap().Save($stream, [System.Drawing.Imaging.ImageFormat]::Png); \
+             [Convert]::ToBase64String($stream.ToArray())",
+            exe.replace('\'', "''")
+        );
+        let output = Command::new("powershell").args(["-NoProfile", "-Command", &script]).output().ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+
+    #[cfg(target_os = "macos")]
+    let base64 = {
+        // .../Foo.app/Contents/MacOS/Foo -> .../Foo.app/Contents/Resources/<CFBundleIconFile>
+        let contents = std::path::Path::new(exe).ancestors().find(|p| p.ends_with("Contents"))?;
+        let output = Command::new("defaults")
+            .args(["read", &contents.join("Info").to_string_lossy(), "CFBundleIconFile"])
+            .output()
+            .ok()?;
+        let mut icon_file = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !icon_file.ends_with(".icns") {
+            icon_file.push_str(".icns");
+        }
+        let png = std::env::temp_dir().join(format!("bastion-icon-{}.png", std::process::id()));
+        let converted = Command::new("sips")
+            .args(["-s", "format", "png", "-Z", "64"])
+            .arg(contents.join("Resources").join(icon_file))
+            .arg("--out")
+            .arg(&png)
+            .output()
+            .ok()?;
+        if !converted.status.success() {
+            return None;
+        }
+        let output = Command::new("base64").arg("-i").arg(&png).output().ok()?;
+        let _ = fs::remove_file(&png);
+        String::from_utf8_lossy(&output.stdout).split_whitespace().collect::<String>()
+    };
+
+    (!base64.is_empty()).then(|| format!("data:image/png;base64,{}", base64))
+}
+
+#[cfg(target_os = "linux")]
+pub fn get_process_icon(_exe: &str) -> Option<String> {
+    None
+}
+
+/// One `System` shared by all process queries. Refreshing it only reads processes that
+/// started or exited since the last refresh, instead of rescanning everything each call.
+#[derive(Default)]
+pub struct ProcessMonitor {
+    system: Mutex<System>,
+}
+
+impl ProcessMonitor {
+    /// Lightweight scan for the enforcement loop: only PIDs and names are refreshed,
+    /// memory/CPU/disk stats are skipped. The list stays locked for the borrow.
+    pub fn refreshed(&self) -> MutexGuard<'_, System> {
+        self.refresh(ProcessRefreshKind::new())
+    }
+
+    /// Like `refreshed`, plus the command line and executable path of processes that started
+    /// since the last scan.
+    pub fn refreshed_detailed(&self) -> MutexGuard<'_, System> {
+        self.refresh(
+            ProcessRefreshKind::new()
+                .with_cmd(UpdateKind::OnlyIfNotSet)
+                .with_exe(UpdateKind::OnlyIfNotSet),
+        )
+    }
+
+    fn refresh(&self, kind: ProcessRefreshKind) -> MutexGuard<'_, System> {
+        let mut system = self.system.lock().unwrap();
+        system.refresh_processes_specifics(ProcessesToUpdate::All, true, kind);
+        system
+    }
+}
+
+/// Names and command lines of running processes, for session triggers
+pub fn get_process_command_lines(monitor: &ProcessMonitor) -> Vec<crate::triggers::ProcessInfo> {
+    let system = monitor.refreshed_detailed();
+    system
+        .processes()
+        .values()
+        .map(|process| crate::triggers::ProcessInfo {
+            name: process.name().to_string_lossy().to_string(),
+            command_line: process.cmd().iter().map(|arg| arg.to_string_lossy()).collect::<Vec<_>>().join(" "),
+        })
+        .collect()
+}
+
 /// Check if a process is running by name
 #[allow(dead_code)]
-pub fn is_process_running(process_name: &str) -> bool {
-    let mut system = System::new();
-    system.refresh_processes(ProcessesToUpdate::All, true);
+pub fn is_process_running(monitor: &ProcessMonitor, process_name: &str) -> bool {
+    let system = monitor.refreshed();
     
     system
         .processes()
@@ -320,9 +1661,8 @@ pub fn is_process_running(process_name: &str) -> bool {
 }
 
 /// Kill a process by name (returns number of processes killed)
-pub fn kill_process_by_name(process_name: &str) -> Result<u32, BlockingError> {
-    let mut system = System::new();
-    system.refresh_processes(ProcessesToUpdate::All, true);
+pub fn kill_process_by_name(monitor: &ProcessMonitor, process_name: &str) -> Result<u32, BlockingError> {
+    let system = monitor.refreshed();
     
     let mut killed = 0u32;
     let process_name_lower = process_name.to_lowercase();
@@ -341,62 +1681,231 @@ pub fn kill_process_by_name(process_name: &str) -> Result<u32, BlockingError> {
     Ok(killed)
 }
 
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum KillMethod {
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    Quit,      // asked to quit through AppleScript (macOS apps)
+    Terminate, // SIGTERM; the app may exit gracefully
+    Force,     // SIGKILL / TerminateProcess
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KillFailure {
+    pub pid: u32,
+    pub error: String, // OS error, e.g. "Access is denied" for elevated processes
+}
+
+/// Outcome of enforcing one blocked app
+#[derive(Debug, Clone, Serialize)]
+pub struct AppEnforcement {
+    pub name: String,    // as configured in the block list
+    pub pids: Vec<u32>,  // matching processes
+    pub killed: Vec<u32>,
+    pub method: Option<KillMethod>, // strongest method that was needed
+    pub failures: Vec<KillFailure>,
+}
+
+/// How the background daemon enforces app blocks (JSON `AppEnforcementConfig`)
+pub const APP_ENFORCEMENT_SETTING: &str = "app_enforcement";
+pub const MIN_ENFORCEMENT_INTERVAL_SECS: u64 = 1;
+pub const MAX_ENFORCEMENT_INTERVAL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AppEnforcementConfig {
+    /// Whether blocked apps are closed outside sessions too; during sessions (and bedtime or
+    /// category schedules) the daemon always runs
+    pub enabled: bool,
+    pub interval_seconds: u64,
+}
+
+impl Default for AppEnforcementConfig {
+    fn default() -> Self {
+        AppEnforcementConfig { enabled: true, interval_seconds: 3 }
+    }
+}
+
+impl AppEnforcementConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_ENFORCEMENT_INTERVAL_SECS..=MAX_ENFORCEMENT_INTERVAL_SECS).contains(&self.interval_seconds) {
+            return Err(format!(
+                "The enforcement interval must be between {} and {} seconds",
+                MIN_ENFORCEMENT_INTERVAL_SECS, MAX_ENFORCEMENT_INTERVAL_SECS
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Terminate `process`, escalating to a force kill if SIGTERM can't be sent. On macOS, apps are
+/// asked to quit first (see `macos`).
+fn kill_process(process: &sysinfo::Process) -> Result<KillMethod, String> {
+    #[cfg(target_os = "macos")]
+    if process.exe().is_some_and(crate::macos::quit_app) && has_exited(process.pid()) {
+        return Ok(KillMethod::Quit);
+    }
+    if process.kill_with(Signal::Term) == Some(true) {
+        return Ok(KillMethod::Terminate);
+    }
+    if process.kill() {
+        Ok(KillMethod::Force)
+    } else {
+        // Read right after the failed call, before anything else can overwrite it
+        Err(std::io::Error::last_os_error().to_string())
+    }
+}
+
+/// Whether `pid` exits within a second.
+#[cfg(target_os = "macos")]
+fn has_exited(pid: sysinfo::Pid) -> bool {
+    let mut system = System::new();
+    for _ in 0..10 {
+        system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+        if system.process(pid).is_none() {
+            return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    false
+}
+
 /// Monitor and kill blocked apps (call this periodically).
 ///
 /// This function:
 /// 1. Getting a fresh snapshot of running processes.
 /// 2. Compares them against the `blocked_apps` list (case-insensitive).
 /// 3. Attempts to gracefully terminate (SIGTERM) first, then force kill (SIGKILL).
-/// 4. Returns a list of apps that were successfully killed.
+/// 4. Returns an `AppEnforcement` for every blocked app that was running, including failures.
 ///
 /// # Safety
 /// Includes a hardcoded `SYSTEM_WHITELIST` to prevent accidental killing of critical Windows processes
 /// (like `explorer.exe` or `svchost.exe`) even if the user accidentally adds them to the blocklist.
-pub fn enforce_app_blocks(blocked_apps: &[String]) -> Vec<String> {
+/// When `extreme` is set, the `EXTREME_MODE_TOOLS` are blocked as well and exempt from the whitelist.
+#[tracing::instrument(skip(monitor, blocked_apps), fields(apps = blocked_apps.len()))]
+pub fn enforce_app_blocks(monitor: &ProcessMonitor, blocked_apps: &[String], extreme: bool) -> Vec<AppEnforcement> {
+    let mut blocked_apps = blocked_apps.to_vec();
+    if extreme {
+        blocked_apps.extend(EXTREME_MODE_TOOLS.iter().map(|t| t.to_string()));
+    }
     if blocked_apps.is_empty() { return Vec::new(); }
     
-    let mut system = System::new();
-    // Incremental refresh for performance (don't re-scan static info)
-    system.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+    let is_protected = |name: &str| {
+        SYSTEM_WHITELIST.iter().any(|&w| w.to_lowercase() == name)
+            && !(extreme && EXTREME_MODE_TOOLS.iter().any(|&t| t.to_lowercase() == name))
+    };
     
-    let mut killed_apps = Vec::new();
+    let system = monitor.refreshed();
+    
+    let mut results: Vec<AppEnforcement> = Vec::new();
     let blocked_lower: Vec<String> = blocked_apps.iter()
         .map(|s| s.to_lowercase())
-        .filter(|s| !SYSTEM_WHITELIST.iter().any(|&w| w.to_lowercase() == *s))
+        .filter(|s| !is_protected(s.as_str()))
         .collect();
     
     if blocked_lower.is_empty() { return Vec::new(); }
 
-    for (_pid, process) in system.processes() {
+    for (pid, process) in system.processes() {
         let process_name = process.name().to_string_lossy().to_lowercase();
         
         // Safety check: never kill whitelisted processes even if added to block list
-        if SYSTEM_WHITELIST.iter().any(|&w| w.to_lowercase() == process_name) {
+        if is_protected(process_name.as_str()) {
             continue;
         }
 
         if blocked_lower.contains(&process_name) {
-            // Found a blocked process
-            if process.kill_with(Signal::Term).is_none() {
-                process.kill();
-            }
-            
             // Map back to the original name for the result
-            if let Some(original_name) = blocked_apps.iter().find(|&a| a.to_lowercase() == process_name) {
-                if !killed_apps.contains(original_name) {
-                    killed_apps.push(original_name.clone());
+            let Some(original_name) = blocked_apps.iter().find(|&a| a.to_lowercase() == process_name) else {
+                continue;
+            };
+            let index = match results.iter().position(|r| &r.name == original_name) {
+                Some(index) => index,
+                None => {
+                    results.push(AppEnforcement {
+                        name: original_name.clone(),
+                        pids: Vec::new(),
+                        killed: Vec::new(),
+                        method: None,
+                        failures: Vec::new(),
+                    });
+                    results.len() - 1
                 }
+            };
+            let result = &mut results[index];
+            result.pids.push(pid.as_u32());
+            match kill_process(process) {
+                Ok(method) => {
+                    result.killed.push(pid.as_u32());
+                    result.method = result.method.max(Some(method));
+                }
+                Err(error) => result.failures.push(KillFailure { pid: pid.as_u32(), error }),
             }
         }
     }
     
-    killed_apps
+    results
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_generate_safesearch_entries() {
+        // Unresolvable hosts fall back to the published address, or are skipped without one
+        let entries = generate_safesearch_entries(|_| None);
+        assert!(entries.starts_with(SAFESEARCH_MARKER_START));
+        assert!(entries.ends_with(SAFESEARCH_MARKER_END));
+        assert!(entries.contains("216.239.38.120 www.google.com\n"));
+        assert!(entries.contains("204.79.197.220 www.bing.com\n"));
+        assert!(entries.contains("216.239.38.120 www.youtube.com\n"));
+        assert!(!entries.contains("duckduckgo.com"));
+
+        let entries = generate_safesearch_entries(|_| "10.0.0.1".parse().ok());
+        assert!(entries.contains("10.0.0.1 duckduckgo.com\n"));
+    }
+
+    #[test]
+    fn test_safesearch_section_is_not_user_domains() {
+        let contents = format!("127.0.0.1 localhost\n{}\n1.2.3.4 www.youtube.com\n{}\n", SAFESEARCH_MARKER_START, SAFESEARCH_MARKER_END);
+        let domains = vec!["youtube.com".to_string()];
+        assert_eq!(without_user_domains(&domains, &contents), domains);
+    }
+
+    #[test]
+    fn test_fuzzy_score_ranking() {
+        let score = |candidate| fuzzy_score("disc", candidate);
+        assert!(score("Discord") > score("Disk Cleanup"));
+        assert!(score("Disk Cleanup").is_some());
+        assert!(score("discord") < score("disc"));
+        assert!(score("Steam").is_none());
+        assert!(fuzzy_score("dsc", "Discord").is_some());
+        assert!(fuzzy_score("code", "VS Code") > fuzzy_score("code", "Xcodeutil"));
+    }
+
+    #[test]
+    fn test_merge_running_apps() {
+        let installed = vec![
+            InstalledApp { name: "Discord".to_string(), id: "C:\\Apps\\Discord.exe".to_string(), running: false },
+            InstalledApp { name: "Steam".to_string(), id: "steam.exe".to_string(), running: false },
+        ];
+        let process = |name: &str, window_title: Option<&str>| RunningProcess {
+            pid: 1,
+            name: name.to_string(),
+            exe: None,
+            parent_pid: None,
+            window_title: window_title.map(str::to_string),
+        };
+        let running = vec![
+            process("discord.exe", Some("Discord")),
+            process("game.exe", Some("Game")),
+            process("svchost.exe", None),
+        ];
+
+        let apps = merge_running_apps(installed, &running);
+        let summary: Vec<(&str, bool)> = apps.iter().map(|a| (a.name.as_str(), a.running)).collect();
+        assert_eq!(summary, vec![("Discord", true), ("Steam", false), ("game.exe", true)]);
+    }
+
     #[test]
     fn test_generate_block_entries() {
         let domains = vec!["twitter.com".to_string(), "reddit.com".to_string()];
@@ -408,9 +1917,125 @@ mod tests {
         assert!(entries.contains("127.0.0.1 www.twitter.com"));
     }
 
+    #[test]
+    fn test_render_pac() {
+        let pac = render_pac(&["Reddit.com".to_string(), "twitter.com".to_string(), "reddit.com".to_string()], 8080);
+        assert!(pac.contains("var blocked = [\"reddit.com\", \"twitter.com\"];"));
+        assert!(pac.contains("return \"PROXY 127.0.0.1:8080\";"));
+        assert!(render_pac(&[], 80).contains("var blocked = [];"));
+    }
+
+    #[test]
+    fn test_expand_subdomains() {
+        let hosts = expand_subdomains("https://Reddit.com/r/rust");
+        assert_eq!(hosts[0], "reddit.com");
+        assert!(hosts.contains(&"m.reddit.com".to_string()));
+        assert!(hosts.contains(&"old.reddit.com".to_string()));
+        assert!(is_domain_blocked("old.reddit.com", &hosts));
+
+        // Unknown sites still get the common subdomains, without duplicates
+        assert_eq!(expand_subdomains("example.org").len(), 1 + COMMON_SUBDOMAINS.len());
+        assert_eq!(expand_subdomains("twitter.com").iter().filter(|h| *h == "mobile.twitter.com").count(), 1);
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let allowed = vec!["docs.rs".to_string(), "GitHub.com".to_string()];
+
+        assert!(is_allowed_by_allowlist("https://docs.rs/serde", &allowed));
+        assert!(is_allowed_by_allowlist("gist.github.com", &allowed));
+        assert!(!is_allowed_by_allowlist("https://reddit.com/", &allowed));
+        assert!(!is_allowed_by_allowlist("notgithub.com", &allowed));
+        assert!(!is_allowed_by_allowlist("reddit.com", &[]));
+
+        assert!(is_allowed_by_allowlist("about:blank", &[]));
+        assert!(is_allowed_by_allowlist("chrome://extensions", &[]));
+        assert!(is_allowed_by_allowlist("http://localhost:3000/", &[]));
+    }
+
+    #[test]
+    fn test_is_domain_blocked() {
+        let domains = vec!["reddit.com".to_string()];
+
+        assert!(is_domain_blocked("reddit.com", &domains));
+        assert!(is_domain_blocked("https://WWW.Reddit.com:443/r/rust", &domains));
+        assert!(!is_domain_blocked("old.reddit.com", &domains));
+        assert!(!is_domain_blocked("notreddit.com", &domains));
+
+        let domains = vec!["München.de.".to_string()];
+        assert!(is_domain_blocked("https://www.xn--mnchen-3ya.de/", &domains));
+        assert!(is_domain_blocked("münchen.de", &domains));
+    }
+
+    #[test]
+    fn test_normalize_domain_punycode() {
+        assert_eq!(normalize_domain("https://Bücher.example./x"), "xn--bcher-kva.example");
+        assert_eq!(normalize_domain("münchen.de"), "xn--mnchen-3ya.de");
+    }
+
+    #[test]
+    fn test_parse_site_input() {
+        let input = parse_site_input("https://mobile.twitter.com/home?x=1").unwrap();
+        assert_eq!(input.host, "mobile.twitter.com");
+        assert_eq!(input.registrable, "twitter.com");
+        assert_eq!(input.rule(DomainScope::Registrable), "twitter.com");
+        assert_eq!(input.rule(DomainScope::Host), "mobile.twitter.com");
+        assert_eq!(input.rule(DomainScope::Rule), "mobile.twitter.com/home");
+
+        assert_eq!(parse_site_input("HTTPS://www.YouTube.com/shorts/").unwrap().rule(DomainScope::Rule), "youtube.com/shorts");
+        assert_eq!(parse_site_input("news.bbc.co.uk").unwrap().registrable, "bbc.co.uk");
+        assert_eq!(parse_site_input("münchen.de").unwrap().host, "xn--mnchen-3ya.de");
+
+        assert_eq!(parse_site_input("  "), Err(DomainError::Empty));
+        assert!(matches!(parse_site_input("http://127.0.0.1/"), Err(DomainError::IpAddress(_))));
+        assert!(matches!(parse_site_input("not a domain"), Err(DomainError::InvalidHost(_))));
+        assert!(matches!(parse_site_input("localhost"), Err(DomainError::InvalidHost(_))));
+        assert!(matches!(parse_site_input("co.uk"), Err(DomainError::PublicSuffix(_))));
+    }
+
+    #[test]
+    fn test_matches_url_rule() {
+        assert!(matches_url_rule("https://www.youtube.com/shorts/abc", "youtube.com/shorts"));
+        assert!(!matches_url_rule("https://www.youtube.com/watch?v=abc", "youtube.com/shorts"));
+        assert!(matches_url_rule("https://news.google.com/home", "news.google.com"));
+        assert!(!matches_url_rule("https://www.google.com/search?q=rust", "news.google.com"));
+    }
+
+    #[test]
+    fn test_user_hosts_lines_untouched() {
+        let hosts = format!(
+            "127.0.0.1 localhost\n127.0.0.1 myapp.test www.reddit.com # dev\n\n{}\n",
+            generate_block_entries(&["twitter.com".to_string()])
+        );
+        let domains = vec!["reddit.com".to_string(), "twitter.com".to_string(), "myapp.test".to_string()];
+
+        assert_eq!(without_user_domains(&domains, &hosts), vec!["twitter.com".to_string()]);
+    }
+
+    #[test]
+    fn test_merge_with_baseline() {
+        let baseline = "127.0.0.1 localhost\n127.0.0.1 reddit.com\n";
+        let domains = vec!["reddit.com".to_string(), "twitter.com".to_string()];
+        let current = format!(
+            "127.0.0.1 localhost\n127.0.0.1 reddit.com\n10.0.0.5 nas.local\n\n{}\n127.0.0.1 twitter.com\n::1 www.twitter.com\n{}\n",
+            generate_block_entries(&domains),
+            BASTION_MARKER_END,
+        );
+
+        // The user's own entries stay, whether from the baseline or added later; stray blocks go
+        assert_eq!(
+            merge_with_baseline(baseline, &current, &domains),
+            "127.0.0.1 localhost\n127.0.0.1 reddit.com\n10.0.0.5 nas.local\n"
+        );
+        // Lines removed since the baseline stay removed
+        assert_eq!(merge_with_baseline(baseline, "127.0.0.1 localhost\n", &domains), "127.0.0.1 localhost\n");
+        assert_eq!(merge_with_baseline(baseline, "", &domains), baseline);
+        assert_eq!(merge_with_baseline(baseline, "127.0.0.1 localhost\r\n\r\n", &domains), "127.0.0.1 localhost\r\n");
+    }
+
     #[test]
     fn test_get_running_processes() {
-        let processes = get_running_processes();
+        let processes = get_running_processes(&ProcessMonitor::default());
         assert!(!processes.is_empty());
     }
 }
@@ -0,0 +1,278 @@
+//! Blocklists module - Built-in category presets
+//!
+//! Curated domain lists for common distractions. Applying a preset adds its domains to
+//! `blocked_sites` under the preset's category in one transaction, so they can be toggled,
+//! scheduled (see `session::CategorySchedule`) and blocked by profiles like hand-added sites.
+//! Domains already on the block list are left as they are.
+//!
+//! Block lists can also be exported and imported (to carry them across machines) as JSON, a
+//! `BlocklistExport`, or CSV with one site or app per row:
+//! `type,value,name,category,enabled,mode` where `type` is `site` or `app` and `value` is
+//! the domain or process name.
+
+use crate::storage::{AppEntry, BlockedApp, BlockedSite, SiteEntry};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlocklistPreset {
+    pub category: &'static str, // also the category the sites are added under
+    pub name: &'static str,
+    pub domains: &'static [&'static str],
+}
+
+pub const PRESETS: &[BlocklistPreset] = &[
+    BlocklistPreset {
+        category: "social",
+        name: "Social media",
+        domains: &[
+            "facebook.com", "instagram.com", "twitter.com", "x.com", "tiktok.com", "reddit.com",
+            "snapchat.com", "pinterest.com", "tumblr.com", "linkedin.com", "threads.net",
+            "bsky.app", "mastodon.social", "vk.com", "weibo.com",
+        ],
+    },
+    BlocklistPreset {
+        category: "entertainment",
+        name: "Video streaming",
+        domains: &[
+            "youtube.com", "netflix.com", "twitch.tv", "hulu.com", "disneyplus.com", "primevideo.com",
+            "max.com", "vimeo.com", "dailymotion.com", "crunchyroll.com", "peacocktv.com",
+            "paramountplus.com", "tv.apple.com",
+        ],
+    },
+    BlocklistPreset {
+        category: "news",
+        name: "News",
+        domains: &[
+            "cnn.com", "foxnews.com", "nytimes.com", "washingtonpost.com", "theguardian.com",
+            "bbc.com", "bbc.co.uk", "reuters.com", "apnews.com", "nbcnews.com", "huffpost.com",
+            "news.google.com", "news.ycombinator.com", "buzzfeed.com", "dailymail.co.uk",
+        ],
+    },
+    BlocklistPreset {
+        category: "games",
+        name: "Gaming",
+        domains: &[
+            "store.steampowered.com", "steamcommunity.com", "epicgames.com", "roblox.com",
+            "miniclip.com", "poki.com", "crazygames.com", "chess.com", "lichess.org", "ign.com",
+            "gamespot.com", "itch.io", "kongregate.com", "minecraft.net",
+        ],
+    },
+    BlocklistPreset {
+        category: "adult",
+        name: "Adult content",
+        domains: &[
+            "pornhub.com", "xvideos.com", "xnxx.com", "xhamster.com", "redtube.com", "youporn.com",
+            "onlyfans.com", "chaturbate.com", "stripchat.com", "spankbang.com", "eporner.com",
+            "tube8.com",
+        ],
+    },
+];
+
+pub fn find(category: &str) -> Option<&'static BlocklistPreset> {
+    PRESETS.iter().find(|preset| preset.category == category)
+}
+
+pub const BLOCKLIST_EXPORT_VERSION: u32 = 1;
+
+const CSV_HEADER: &str = "type,value,name,category,enabled,mode";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlocklistFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BlocklistExport {
+    pub bastion_blocklist: u32, // format version, identifies the file
+    #[serde(default)]
+    pub sites: Vec<SiteEntry>,
+    #[serde(default)]
+    pub apps: Vec<AppEntry>,
+}
+
+impl BlocklistExport {
+    pub fn new(sites: &[BlockedSite], apps: &[BlockedApp]) -> Self {
+        BlocklistExport {
+            bastion_blocklist: BLOCKLIST_EXPORT_VERSION,
+            sites: sites
+                .iter()
+                .map(|s| SiteEntry {
+                    domain: s.domain.clone(),
+                    category: s.category.clone(),
+                    enabled: s.enabled,
+                    mode: s.mode.clone(),
+                    include_subdomains: s.include_subdomains,
+                })
+                .collect(),
+            apps: apps
+                .iter()
+                .map(|a| AppEntry {
+                    name: a.name.clone(),
+                    process_name: a.process_name.clone(),
+                    category: a.category.clone(),
+                    enabled: a.enabled,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn render(&self, format: BlocklistFormat) -> Result<String, String> {
+        match format {
+            BlocklistFormat::Json => serde_json::to_string_pretty(self).map_err(|e| e.to_string()),
+            BlocklistFormat::Csv => {
+                let mut lines = vec![CSV_HEADER.to_string()];
+                for site in &self.sites {
+                    lines.push(csv_row(&["site", &site.domain, "", &site.category, &site.enabled.to_string(), &site.mode]));
+                }
+                for app in &self.apps {
+                    lines.push(csv_row(&["app", &app.process_name, &app.name, &app.category, &app.enabled.to_string(), ""]));
+                }
+                Ok(lines.join("\n") + "\n")
+            }
+        }
+    }
+
+    pub fn parse(contents: &str, format: BlocklistFormat) -> Result<Self, String> {
+        match format {
+            BlocklistFormat::Json => {
+                let export: BlocklistExport =
+                    serde_json::from_str(contents.trim()).map_err(|e| format!("Not a Bastion block list: {}", e))?;
+                if export.bastion_blocklist > BLOCKLIST_EXPORT_VERSION {
+                    return Err("This block list was made by a newer version of Bastion".to_string());
+                }
+                Ok(export)
+            }
+            BlocklistFormat::Csv => parse_csv(contents),
+        }
+    }
+}
+
+fn csv_row(fields: &[&str]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Split one CSV line, honouring double-quoted fields ("" is a literal quote).
+fn split_csv_line(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("Unterminated quote in '{}'", line));
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+fn parse_csv(contents: &str) -> Result<BlocklistExport, String> {
+    let mut export = BlocklistExport { bastion_blocklist: BLOCKLIST_EXPORT_VERSION, ..Default::default() };
+    let mut lines = contents.lines().map(str::trim).filter(|line| !line.is_empty());
+    if lines.next().map(|header| header.to_lowercase()) != Some(CSV_HEADER.to_string()) {
+        return Err(format!("The first line must be the header '{}'", CSV_HEADER));
+    }
+    for (number, line) in lines.enumerate() {
+        let fields = split_csv_line(line)?;
+        let field = |i: usize| fields.get(i).map_or("", |f| f.trim());
+        let category = match field(3) {
+            "" => "other".to_string(),
+            category => category.to_string(),
+        };
+        let enabled = !matches!(field(4).to_lowercase().as_str(), "false" | "0" | "no");
+        match field(0).to_lowercase().as_str() {
+            "site" => export.sites.push(SiteEntry {
+                domain: field(1).to_string(),
+                category,
+                enabled,
+                mode: match field(5) {
+                    "" => crate::blocking::BLOCK_MODE_HOSTS.to_string(),
+                    mode => mode.to_string(),
+                },
+                include_subdomains: false,
+            }),
+            "app" => export.apps.push(AppEntry {
+                name: match field(2) {
+                    "" => field(1).to_string(),
+                    name => name.to_string(),
+                },
+                process_name: field(1).to_string(),
+                category,
+                enabled,
+            }),
+            other => return Err(format!("Line {}: unknown type '{}' (expected 'site' or 'app')", number + 2, other)),
+        }
+    }
+    Ok(export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset_domains_are_normalized() {
+        let mut seen = std::collections::HashSet::new();
+        for preset in PRESETS {
+            for domain in preset.domains {
+                assert!(crate::blocking::parse_site_input(domain).is_ok(), "{} is not a valid site", domain);
+                assert_eq!(crate::blocking::normalize_domain(domain), *domain);
+                assert!(seen.insert(*domain), "{} is in two presets", domain);
+            }
+        }
+        assert_eq!(find("games").map(|p| p.name), Some("Gaming"));
+        assert!(find("unknown").is_none());
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let export = BlocklistExport {
+            bastion_blocklist: BLOCKLIST_EXPORT_VERSION,
+            sites: vec![SiteEntry {
+                domain: "youtube.com/shorts".to_string(),
+                category: "entertainment".to_string(),
+                enabled: false,
+                mode: crate::blocking::BLOCK_MODE_CLOSE_TAB.to_string(),
+                include_subdomains: false,
+            }],
+            apps: vec![AppEntry {
+                name: "Steam, \"the\" store".to_string(),
+                process_name: "steam.exe".to_string(),
+                category: "games".to_string(),
+                enabled: true,
+            }],
+        };
+        for format in [BlocklistFormat::Json, BlocklistFormat::Csv] {
+            let rendered = export.render(format).unwrap();
+            assert_eq!(BlocklistExport::parse(&rendered, format).unwrap(), export);
+        }
+
+        let csv = "type,value,name,category,enabled,mode\nsite,reddit.com\napp,Discord.exe,,social,no\n";
+        let parsed = BlocklistExport::parse(csv, BlocklistFormat::Csv).unwrap();
+        assert_eq!((parsed.sites[0].category.as_str(), parsed.sites[0].enabled), ("other", true));
+        assert_eq!((parsed.apps[0].name.as_str(), parsed.apps[0].enabled), ("Discord.exe", false));
+        assert!(BlocklistExport::parse("site,reddit.com", BlocklistFormat::Csv).is_err());
+    }
+}
@@ -0,0 +1,406 @@
+//! Companion module - Pairing and sync for the Bastion mobile companion app
+//!
+//! Pairing starts on the desktop: `Pairing::start` issues a single-use pairing token that is
+//! valid for five minutes and returns it, with the address the phone should connect to, as a
+//! JSON payload the frontend renders as a QR code. The phone scans it and answers with a
+//! `PairingConfirmation` (the pairing token plus its own device id and name). The desktop
+//! then remembers the device and hands back a long-lived device token for later requests.
+//! Only the SHA-256 hash of each device token is stored.
+//!
+//! The companion API is off unless `COMPANION_ENABLED_SETTING` is set, and even then it only
+//! listens (on `COMPANION_PORT`, all interfaces) while a pairing code is on screen or at least
+//! one device is paired:
+//! - `POST /pair` with a `PairingConfirmation`, answered with a `PairingResult`
+//! - `GET /blocklist` (device token) returning the enabled sites and apps to mirror
+//! - `POST /push` (device token) with `{"events": [...], "usage": [...]}` block events and
+//!   usage from the phone, merged into the desktop's tables so stats cover both
+
+use crate::error::{CommandError, ErrorCode};
+use crate::extension::{self, HttpRequest};
+use crate::storage::{BlockedApp, BlockedSite, Database, DeviceBlockEvent, DeviceUsage};
+use crate::{security, AppState};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Most events or usage rows accepted in one push
+const MAX_PUSH_ROWS: usize = 5000;
+
+/// Port the companion API listens on, on all interfaces so phones on the LAN can reach it
+pub const COMPANION_PORT: u16 = 47616;
+
+/// How long a pairing QR code stays valid
+pub const PAIRING_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How often the supervisor re-checks whether the API should be listening
+const SUPERVISOR_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Paired devices (JSON list of `StoredDevice`)
+pub const DEVICES_SETTING: &str = "companion_devices";
+
+/// "true" once the user has turned the mobile companion on
+pub const COMPANION_ENABLED_SETTING: &str = "companion_enabled";
+
+/// What the QR code encodes
+#[derive(Debug, Serialize)]
+struct PairingPayload {
+    v: u8, // payload format version
+    name: String, // this computer's name, shown on the phone
+    addresses: Vec<String>,
+    port: u16,
+    token: String,
+    expires_at: i64, // Unix timestamp
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PairingOffer {
+    pub payload: String, // JSON `PairingPayload`, rendered as a QR code by the frontend
+    pub expires_at: i64,
+}
+
+/// Sent by the phone after scanning the QR code
+#[derive(Debug, Clone, Deserialize)]
+pub struct PairingConfirmation {
+    pub token: String,
+    pub device_id: String,
+    pub device_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairedDevice {
+    pub id: String,
+    pub name: String,
+    pub paired_at: String,
+    #[serde(default)]
+    pub last_seen: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PairingResult {
+    pub device: PairedDevice,
+    pub device_token: String, // sent as `Authorization: Bearer <token>` from now on
+}
+
+/// What a paired phone mirrors
+#[derive(Debug, Clone, Serialize)]
+pub struct MobileBlocklist {
+    pub sites: Vec<BlockedSite>, // enabled only
+    pub apps: Vec<BlockedApp>,   // enabled only
+    pub active_session: Option<String>,
+    pub hardcore: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct PushRequest {
+    #[serde(default)]
+    events: Vec<DeviceBlockEvent>,
+    #[serde(default)]
+    usage: Vec<DeviceUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredDevice {
+    #[serde(flatten)]
+    device: PairedDevice,
+    token_hash: String,
+}
+
+/// The pairing token currently on screen, if any. Starting a new pairing replaces it.
+#[derive(Default)]
+pub struct Pairing {
+    current: Mutex<Option<(String, Instant)>>, // token and when it expires
+    wake: tokio::sync::Notify,
+}
+
+impl Pairing {
+    pub fn start(&self) -> PairingOffer {
+        let token = security::to_hex(&rand::random::<[u8; 16]>());
+        *self.current.lock().unwrap() = Some((token.clone(), Instant::now() + PAIRING_TTL));
+        self.wake.notify_one();
+
+        let expires_at = chrono::Local::now().timestamp() + PAIRING_TTL.as_secs() as i64;
+        let payload = PairingPayload {
+            v: 1,
+            name: sysinfo::System::host_name().unwrap_or_else(|| "Bastion".to_string()),
+            addresses: lan_addresses(),
+            port: COMPANION_PORT,
+            token,
+            expires_at,
+        };
+        PairingOffer {
+            payload: serde_json::to_string(&payload).unwrap_or_default(),
+            expires_at,
+        }
+    }
+
+    /// Consume the pairing token and remember the device. A device that pairs again
+    /// (same id) gets a new token and the old one stops working.
+    pub fn confirm(&self, db: &Database, confirmation: &PairingConfirmation) -> Result<PairingResult, CommandError> {
+        let device_id = confirmation.device_id.trim();
+        let device_name = confirmation.device_name.trim();
+        if device_id.is_empty() || device_name.is_empty() {
+            return Err(CommandError::new(ErrorCode::InvalidInput, "Device id and name are required"));
+        }
+
+        {
+            let mut current = self.current.lock().unwrap();
+            match current.as_ref() {
+                Some((token, expires_at))
+                    if Instant::now() < *expires_at
+                        && security::constant_time_eq(token.as_bytes(), confirmation.token.as_bytes()) =>
+                {
+                    *current = None;
+                }
+                _ => return Err(CommandError::new(ErrorCode::Unauthorized, "Pairing code is invalid or has expired")),
+            }
+        }
+
+        let device_token = security::to_hex(&rand::random::<[u8; 32]>());
+        let device = PairedDevice {
+            id: device_id.to_string(),
+            name: device_name.to_string(),
+            paired_at: chrono::Local::now().to_rfc3339(),
+            last_seen: None,
+        };
+        let mut devices = load_devices(db);
+        devices.retain(|d| d.device.id != device.id);
+        devices.push(StoredDevice { device: device.clone(), token_hash: hash_token(&device_token) });
+        save_devices(db, &devices)?;
+        self.wake.notify_one();
+
+        Ok(PairingResult { device, device_token })
+    }
+
+    pub fn cancel(&self) {
+        *self.current.lock().unwrap() = None;
+        self.wake.notify_one();
+    }
+
+    /// Whether a pairing code is on screen and hasn't expired.
+    pub fn is_open(&self) -> bool {
+        self.current.lock().unwrap().as_ref().is_some_and(|(_, expires_at)| Instant::now() < *expires_at)
+    }
+
+    /// Ask the supervisor to re-check whether the API should be listening.
+    pub fn state_changed(&self) {
+        self.wake.notify_one();
+    }
+}
+
+/// Confirm a pairing from the UI or the phone, and tell the UI about the new device.
+pub fn confirm_pairing(state: &AppState, confirmation: &PairingConfirmation) -> Result<PairingResult, CommandError> {
+    let result = state.pairing.confirm(&state.db, confirmation)?;
+    if let Some(app) = state.app_handle.lock().unwrap().as_ref() {
+        let _ = app.emit("companion-paired", &result.device);
+    }
+    Ok(result)
+}
+
+pub fn is_enabled(db: &Database) -> bool {
+    db.get_setting(COMPANION_ENABLED_SETTING).unwrap_or(None).as_deref() == Some("true")
+}
+
+fn hash_token(token: &str) -> String {
+    security::to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn load_devices(db: &Database) -> Vec<StoredDevice> {
+    db.get_setting(DEVICES_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_devices(db: &Database, devices: &[StoredDevice]) -> Result<(), CommandError> {
+    let json = serde_json::to_string(devices).map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))?;
+    db.set_setting(DEVICES_SETTING, &json)?;
+    Ok(())
+}
+
+pub fn paired_devices(db: &Database) -> Vec<PairedDevice> {
+    load_devices(db).into_iter().map(|d| d.device).collect()
+}
+
+/// Forget a device; its token stops working immediately. Returns false if it wasn't paired.
+pub fn unpair(db: &Database, device_id: &str) -> Result<bool, CommandError> {
+    let mut devices = load_devices(db);
+    let before = devices.len();
+    devices.retain(|d| d.device.id != device_id);
+    if devices.len() == before {
+        return Ok(false);
+    }
+    save_devices(db, &devices)?;
+    Ok(true)
+}
+
+/// The paired device a device token belongs to; records when it was last seen.
+fn authenticate(db: &Database, token: &str) -> Option<PairedDevice> {
+    let hash = hash_token(token);
+    let mut devices = load_devices(db);
+    let stored = devices
+        .iter_mut()
+        .find(|d| security::constant_time_eq(d.token_hash.as_bytes(), hash.as_bytes()))?;
+    stored.device.last_seen = Some(chrono::Local::now().to_rfc3339());
+    let device = stored.device.clone();
+    let _ = save_devices(db, &devices);
+    Some(device)
+}
+
+pub fn mobile_blocklist(state: &AppState) -> Result<MobileBlocklist, CommandError> {
+    Ok(MobileBlocklist {
+        sites: state.db.get_blocked_sites()?.into_iter().filter(|s| s.enabled).collect(),
+        apps: state.db.get_blocked_apps()?.into_iter().filter(|a| a.enabled).collect(),
+        active_session: state.session_manager.active_session().map(|s| s.name),
+        hardcore: state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst),
+    })
+}
+
+/// Keep the companion API listening on the LAN only while it's enabled and a phone is
+/// pairing or paired; the port is closed the rest of the time.
+pub async fn supervise_companion_server(state: Arc<AppState>) {
+    let mut running: Option<tokio::task::JoinHandle<()>> = None;
+    let mut bind_failed = false;
+    loop {
+        let wanted = is_enabled(&state.db) && (state.pairing.is_open() || !load_devices(&state.db).is_empty());
+        if !wanted {
+            if let Some(task) = running.take() {
+                task.abort();
+                println!("[Bastion] Companion API stopped");
+            }
+            bind_failed = false;
+        } else if running.is_none() {
+            match TcpListener::bind(("0.0.0.0", COMPANION_PORT)).await {
+                Ok(listener) => {
+                    println!("[Bastion] Companion API listening on 0.0.0.0:{}", COMPANION_PORT);
+                    running = Some(tokio::spawn(serve(listener, state.clone())));
+                    bind_failed = false;
+                }
+                // Retried on the next check; only the first failure is logged
+                Err(e) => {
+                    if !bind_failed {
+                        eprintln!("[Bastion] Companion API warning: Could not bind port {}: {}", COMPANION_PORT, e);
+                    }
+                    bind_failed = true;
+                }
+            }
+        }
+
+        tokio::select! {
+            _ = state.pairing.wake.notified() => {}
+            _ = tokio::time::sleep(SUPERVISOR_INTERVAL) => {}
+        }
+    }
+}
+
+async fn serve(listener: TcpListener, state: Arc<AppState>) {
+    loop {
+        let Ok((socket, _)) = listener.accept().await else {
+            continue;
+        };
+        let state_clone = state.clone();
+        tokio::spawn(async move {
+            handle_connection(socket, state_clone).await;
+        });
+    }
+}
+
+async fn handle_connection(mut socket: TcpStream, state: Arc<AppState>) {
+    if let Some(request) = extension::read_request(&mut socket).await {
+        let response = route(&state, &request);
+        let _ = socket.write_all(response.as_bytes()).await;
+    }
+}
+
+fn route(state: &AppState, request: &HttpRequest) -> String {
+    let path = request.path.split('?').next().unwrap_or_default();
+    if (request.method.as_str(), path) == ("POST", "/pair") {
+        return match serde_json::from_slice::<PairingConfirmation>(&request.body) {
+            Ok(confirmation) => json_response(confirm_pairing(state, &confirmation)),
+            Err(e) => bad_request(&e.to_string()),
+        };
+    }
+
+    let device = request
+        .headers
+        .get("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|token| authenticate(&state.db, token));
+    let Some(device) = device else {
+        return extension::http_response(401, r#"{"error":"unauthorized"}"#);
+    };
+
+    match (request.method.as_str(), path) {
+        ("GET", "/blocklist") => json_response(mobile_blocklist(state)),
+        ("POST", "/push") => match serde_json::from_slice::<PushRequest>(&request.body) {
+            Ok(push) if push.events.len() + push.usage.len() > MAX_PUSH_ROWS => bad_request("Too many rows in one push"),
+            Ok(push) => json_response(
+                state
+                    .db
+                    .import_device_data(&device.id, &push.events, &push.usage)
+                    .map(|imported| serde_json::json!({ "imported": imported }))
+                    .map_err(CommandError::from),
+            ),
+            Err(e) => bad_request(&e.to_string()),
+        },
+        _ => extension::http_response(404, r#"{"error":"not found"}"#),
+    }
+}
+
+fn json_response<T: Serialize>(result: Result<T, CommandError>) -> String {
+    match result {
+        Ok(data) => extension::http_response(200, &serde_json::to_string(&data).unwrap_or_default()),
+        Err(e) if e.code == ErrorCode::Unauthorized => {
+            extension::http_response(401, &serde_json::json!({ "error": e.message }).to_string())
+        }
+        Err(e) => bad_request(&e.message),
+    }
+}
+
+fn bad_request(message: &str) -> String {
+    extension::http_response(400, &serde_json::json!({ "error": message }).to_string())
+}
+
+/// IPv4 address of the interface that routes to the internet, for phones on the same network.
+/// Connecting a UDP socket only selects a route; nothing is sent.
+fn lan_addresses() -> Vec<String> {
+    std::net::UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect("8.8.8.8:80")?;
+            socket.local_addr()
+        })
+        .map(|addr| vec![addr.ip().to_string()])
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairing_token_is_single_use() {
+        let db = Database::demo().unwrap();
+        let pairing = Pairing::default();
+        let offer = pairing.start();
+        let payload: serde_json::Value = serde_json::from_str(&offer.payload).unwrap();
+
+        let confirmation = PairingConfirmation {
+            token: payload["token"].as_str().unwrap().to_string(),
+            device_id: "phone-1".to_string(),
+            device_name: "Pixel".to_string(),
+        };
+        let wrong = PairingConfirmation { token: "0".repeat(32), ..confirmation.clone() };
+        assert!(pairing.confirm(&db, &wrong).is_err());
+
+        let result = pairing.confirm(&db, &confirmation).unwrap();
+        assert_eq!(result.device.id, "phone-1");
+        assert!(pairing.confirm(&db, &confirmation).is_err());
+
+        assert_eq!(paired_devices(&db).len(), 1);
+        assert!(unpair(&db, "phone-1").unwrap());
+        assert!(paired_devices(&db).is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+//! Diagnostics module - Bug report bundle
+//!
+//! `create_bundle` writes a zip to the `diagnostics` data directory with everything needed
+//! to debug a blocking problem. Bastion logs to stdout, so the persistent log included is
+//! the recent block/tamper event history. Secrets in settings are redacted.
+
+use crate::{health, AppState};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const RECENT_EVENTS: i32 = 500;
+/// Settings whose key contains one of these are replaced with `REDACTED`
+const SECRET_KEY_PARTS: &[&str] = &["password", "token", "secret", "api_key"];
+
+fn is_secret(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SECRET_KEY_PARTS.iter().any(|part| key.contains(part))
+}
+
+/// Write the bundle and return its path.
+pub fn create_bundle(state: &AppState, data_dir: &Path) -> Result<PathBuf, String> {
+    let dir = data_dir.join("diagnostics");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(format!(
+        "bastion-diagnostics-{}.zip",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+
+    let settings: serde_json::Map<String, serde_json::Value> = state.db.get_all_settings()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|(key, value)| {
+            let value = if is_secret(&key) { "REDACTED".to_string() } else { value };
+            (key, serde_json::Value::String(value))
+        })
+        .collect();
+    let db_stats: serde_json::Map<String, serde_json::Value> = state.db.table_row_counts()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|(table, count)| (table, count.into()))
+        .collect();
+
+    let info = serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "created_at": chrono::Local::now().to_rfc3339(),
+        "active_sessions": state.session_manager.active_sessions(),
+        "hardcore_locked": state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst),
+        "bedtime": state.session_manager.is_bedtime_active(),
+        "plugins": state.plugins.list(),
+    });
+    let events = state.db.get_recent_blocks(RECENT_EVENTS).map_err(|e| e.to_string())?;
+    let hosts = crate::blocking::hosts_section().unwrap_or_else(|| "(no Bastion section)".to_string());
+
+    let files: Vec<(&str, Vec<u8>)> = vec![
+        ("info.json", to_json(&info)?),
+        ("protection_status.json", to_json(&health::check(state))?),
+        ("hosts_section.txt", hosts.into_bytes()),
+        ("settings.json", to_json(&settings)?),
+        ("db_stats.json", to_json(&db_stats)?),
+        ("block_events.json", to_json(&events)?),
+    ];
+
+    let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, contents) in files {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(&contents).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+
+    println!("[Bastion] Diagnostics bundle written to {}", path.display());
+    Ok(path)
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    serde_json::to_vec_pretty(value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_settings_redacted() {
+        assert!(is_secret("master_password_hash"));
+        assert!(is_secret("extension_token"));
+        assert!(is_secret("time_tracker_token"));
+        assert!(!is_secret("custom_warning_text"));
+        assert!(!is_secret("script_hooks"));
+    }
+}
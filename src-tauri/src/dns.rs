@@ -0,0 +1,330 @@
+//! DNS module - Local DNS sinkhole, an alternative to writing the hosts file
+//!
+//! With the `dns` blocking backend, `sync_blocked_websites` hands the blocked domains to a
+//! resolver on `127.0.0.1:port` instead of the hosts file. Queries for blocked domains are
+//! answered directly (NXDOMAIN, or localhost so the block server can show its page); the rest
+//! are forwarded to the upstream resolver and its answer relayed back unchanged.
+//!
+//! Bastion doesn't repoint the system resolver: the adapter DNS (or a router, dnsmasq, or the
+//! browser's DNS setting) has to use `127.0.0.1`. Port 53 usually needs admin rights, which is
+//! why the port is configurable.
+
+use crate::blocking;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+pub const DNS_SINKHOLE_SETTING: &str = "dns_sinkhole";
+
+/// How long to wait for the upstream resolver before giving up on a query
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(3);
+/// How often the supervisor re-checks the backend even without a `state_changed` notification
+const SUPERVISOR_INTERVAL: Duration = Duration::from_secs(5);
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const RCODE_NXDOMAIN: u8 = 3;
+
+/// The answer given for blocked domains
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkholeAnswer {
+    /// The domain doesn't exist; fails fast, but nothing reaches the block server
+    Nxdomain,
+    /// 127.0.0.1 / ::1, like the hosts file, so the block page is shown and the attempt counted
+    Localhost,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DnsSinkholeConfig {
+    pub port: u16,
+    pub upstream: String, // resolver for everything that isn't blocked, "ip" or "ip:port"
+    pub answer: SinkholeAnswer,
+}
+
+impl Default for DnsSinkholeConfig {
+    fn default() -> Self {
+        DnsSinkholeConfig { port: 53, upstream: "1.1.1.1".to_string(), answer: SinkholeAnswer::Localhost }
+    }
+}
+
+impl DnsSinkholeConfig {
+    pub fn upstream_addr(&self) -> Result<SocketAddr, String> {
+        let upstream = self.upstream.trim();
+        upstream
+            .parse::<SocketAddr>()
+            .or_else(|_| upstream.parse::<std::net::IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+            .map_err(|_| format!("'{}' is not an IP address (optionally with a port)", self.upstream))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.port == 0 {
+            return Err("The port must be between 1 and 65535".to_string());
+        }
+        let upstream = self.upstream_addr()?;
+        // Forwarding to ourselves would loop every query
+        if upstream.ip().is_loopback() && upstream.port() == self.port {
+            return Err("The upstream resolver can't be the sinkhole itself".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Sinkhole state shared with the supervisor: the domains to answer for and whether it runs.
+#[derive(Default)]
+pub struct DnsSinkhole {
+    domains: Mutex<Vec<String>>,
+    listening: Mutex<Option<u16>>,
+    error: Mutex<Option<String>>,
+    wake: tokio::sync::Notify,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DnsSinkholeStatus {
+    pub listening_port: Option<u16>,
+    pub domains: usize,
+    pub error: Option<String>,
+}
+
+impl DnsSinkhole {
+    /// Replace the blocked domains; picked up by the next query.
+    pub fn set_domains(&self, domains: Vec<String>) {
+        *self.domains.lock().unwrap() = domains;
+        self.wake.notify_one();
+    }
+
+    /// Ask the supervisor to re-check the backend and configuration.
+    pub fn state_changed(&self) {
+        self.wake.notify_one();
+    }
+
+    pub fn status(&self) -> DnsSinkholeStatus {
+        DnsSinkholeStatus {
+            listening_port: *self.listening.lock().unwrap(),
+            domains: self.domains.lock().unwrap().len(),
+            error: self.error.lock().unwrap().clone(),
+        }
+    }
+
+    fn is_blocked(&self, name: &str) -> bool {
+        blocking::is_domain_blocked(name, &self.domains.lock().unwrap())
+    }
+}
+
+pub fn load_config(state: &AppState) -> DnsSinkholeConfig {
+    state.db.get_setting(DNS_SINKHOLE_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Run the sinkhole while the `dns` backend is selected, restarting it when the config changes.
+pub async fn supervise_dns_sinkhole(state: Arc<AppState>) {
+    let mut running: Option<(DnsSinkholeConfig, tokio::task::JoinHandle<()>)> = None;
+    loop {
+        let wanted = (crate::load_blocking_backend(&state) == blocking::BlockingBackend::Dns).then(|| load_config(&state));
+        if running.as_ref().map(|(config, _)| config) != wanted.as_ref() {
+            if let Some((_, task)) = running.take() {
+                task.abort();
+                *state.dns_sinkhole.listening.lock().unwrap() = None;
+                println!("[Bastion] DNS sinkhole stopped");
+            }
+            if let Some(config) = wanted {
+                match UdpSocket::bind(("127.0.0.1", config.port)).await {
+                    Ok(socket) => {
+                        println!("[Bastion] DNS sinkhole listening on 127.0.0.1:{}", config.port);
+                        *state.dns_sinkhole.listening.lock().unwrap() = Some(config.port);
+                        *state.dns_sinkhole.error.lock().unwrap() = None;
+                        // Later changes arrive through `set_domains` from `sync_blocked_websites`
+                        *state.dns_sinkhole.domains.lock().unwrap() = crate::blocked_domains(&state).unwrap_or_default();
+                        let task = tokio::spawn(serve(Arc::new(socket), config.clone(), state.clone()));
+                        running = Some((config, task));
+                    }
+                    // Retried on the next check; only the first failure is logged
+                    Err(e) => {
+                        let mut error = state.dns_sinkhole.error.lock().unwrap();
+                        if error.as_deref() != Some(e.to_string().as_str()) {
+                            eprintln!("[Bastion] DNS sinkhole warning: Could not bind UDP 127.0.0.1:{}: {}", config.port, e);
+                        }
+                        *error = Some(e.to_string());
+                    }
+                }
+            }
+        }
+
+        tokio::select! {
+            _ = state.dns_sinkhole.wake.notified() => {}
+            _ = tokio::time::sleep(SUPERVISOR_INTERVAL) => {}
+        }
+    }
+}
+
+async fn serve(socket: Arc<UdpSocket>, config: DnsSinkholeConfig, state: Arc<AppState>) {
+    let Ok(upstream) = config.upstream_addr() else {
+        *state.dns_sinkhole.error.lock().unwrap() = Some(format!("Invalid upstream resolver '{}'", config.upstream));
+        return;
+    };
+    let mut buf = [0u8; 4096];
+    loop {
+        let Ok((n, client)) = socket.recv_from(&mut buf).await else {
+            continue;
+        };
+        let packet = buf[..n].to_vec();
+        let Some(query) = parse_query(&packet) else {
+            continue;
+        };
+
+        if state.dns_sinkhole.is_blocked(&query.name) {
+            let _ = socket.send_to(&blocked_response(&packet, &query, config.answer), client).await;
+            continue;
+        }
+        // Forward on a fresh socket per query so slow upstream answers don't hold up the rest
+        let socket = socket.clone();
+        tokio::spawn(async move {
+            if let Ok(response) = forward(&packet, upstream).await {
+                let _ = socket.send_to(&response, client).await;
+            }
+        });
+    }
+}
+
+async fn forward(packet: &[u8], upstream: SocketAddr) -> std::io::Result<Vec<u8>> {
+    let bind_addr: SocketAddr = if upstream.is_ipv6() { "[::]:0".parse().unwrap() } else { "0.0.0.0:0".parse().unwrap() };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.send_to(packet, upstream).await?;
+    let mut buf = [0u8; 4096];
+    let n = tokio::time::timeout(UPSTREAM_TIMEOUT, socket.recv(&mut buf))
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "upstream resolver timed out"))??;
+    Ok(buf[..n].to_vec())
+}
+
+/// The first question of a standard query
+#[derive(Debug, PartialEq)]
+struct Query {
+    name: String,
+    qtype: u16,
+    question_end: usize, // offset just past the question section's first entry
+}
+
+fn parse_query(packet: &[u8]) -> Option<Query> {
+    if packet.len() < 12 {
+        return None;
+    }
+    let is_response = packet[2] & 0x80 != 0;
+    let opcode = (packet[2] >> 3) & 0x0f;
+    let questions = u16::from_be_bytes([packet[4], packet[5]]);
+    if is_response || opcode != 0 || questions == 0 {
+        return None;
+    }
+
+    let mut labels = Vec::new();
+    let mut pos = 12;
+    loop {
+        let len = *packet.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        // Compression pointers don't appear in the question of a query
+        if len > 63 {
+            return None;
+        }
+        labels.push(String::from_utf8_lossy(packet.get(pos..pos + len)?).to_string());
+        pos += len;
+    }
+    let qtype = u16::from_be_bytes([*packet.get(pos)?, *packet.get(pos + 1)?]);
+    packet.get(pos + 3)?; // QCLASS
+    Some(Query { name: blocking::normalize_domain(&labels.join(".")), qtype, question_end: pos + 4 })
+}
+
+/// Answer `query` as blocked: NXDOMAIN, or a localhost record for A/AAAA questions (other
+/// types get an empty answer).
+fn blocked_response(packet: &[u8], query: &Query, answer: SinkholeAnswer) -> Vec<u8> {
+    let mut response = packet[..query.question_end].to_vec();
+    // QR + the client's opcode and RD flag; RA set
+    response[2] = 0x80 | (packet[2] & 0x79);
+    response[3] = 0x80;
+    // One question, no authority or additional records (drops the client's EDNS record)
+    response[4..6].copy_from_slice(&1u16.to_be_bytes());
+    response[8..12].fill(0);
+
+    let address: Option<Vec<u8>> = match (answer, query.qtype) {
+        (SinkholeAnswer::Nxdomain, _) => {
+            response[3] |= RCODE_NXDOMAIN;
+            None
+        }
+        (SinkholeAnswer::Localhost, TYPE_A) => Some(vec![127, 0, 0, 1]),
+        (SinkholeAnswer::Localhost, TYPE_AAAA) => Some(std::net::Ipv6Addr::LOCALHOST.octets().to_vec()),
+        (SinkholeAnswer::Localhost, _) => None,
+    };
+    let answers: u16 = if address.is_some() { 1 } else { 0 };
+    response[6..8].copy_from_slice(&answers.to_be_bytes());
+    if let Some(address) = address {
+        response.extend_from_slice(&[0xc0, 0x0c]); // name: pointer to the question
+        response.extend_from_slice(&query.qtype.to_be_bytes());
+        response.extend_from_slice(&1u16.to_be_bytes()); // class IN
+        response.extend_from_slice(&60u32.to_be_bytes()); // short TTL, so unblocking takes effect soon
+        response.extend_from_slice(&(address.len() as u16).to_be_bytes());
+        response.extend_from_slice(&address);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A recursive query for `name` with id 0x1234.
+    fn query_packet(name: &str, qtype: u16) -> Vec<u8> {
+        let mut packet = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        for label in name.split('.') {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&1u16.to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn test_blocked_answers() {
+        let packet = query_packet("WWW.Reddit.com", TYPE_A);
+        let query = parse_query(&packet).unwrap();
+        assert_eq!((query.name.as_str(), query.qtype, query.question_end), ("www.reddit.com", TYPE_A, packet.len()));
+
+        let response = blocked_response(&packet, &query, SinkholeAnswer::Localhost);
+        assert_eq!(&response[..2], &[0x12, 0x34]);
+        assert_eq!((response[2], response[3]), (0x81, 0x80));
+        assert_eq!(&response[6..8], &[0, 1]);
+        assert_eq!(&response[response.len() - 4..], &[127, 0, 0, 1]);
+
+        let response = blocked_response(&packet, &query, SinkholeAnswer::Nxdomain);
+        assert_eq!(response[3] & 0x0f, RCODE_NXDOMAIN);
+        assert_eq!((&response[6..8], response.len()), (&[0u8, 0][..], packet.len()));
+
+        let packet = query_packet("reddit.com", 16); // TXT
+        let response = blocked_response(&packet, &parse_query(&packet).unwrap(), SinkholeAnswer::Localhost);
+        assert_eq!((response[3], &response[6..8]), (0x80, &[0u8, 0][..]));
+
+        let mut answer = query_packet("reddit.com", TYPE_A);
+        answer[2] |= 0x80;
+        assert!(parse_query(&answer).is_none());
+        assert!(parse_query(&packet[..packet.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let config = |port: u16, upstream: &str| DnsSinkholeConfig { port, upstream: upstream.to_string(), ..Default::default() };
+        assert_eq!(config(53, "9.9.9.9").upstream_addr(), Ok("9.9.9.9:53".parse().unwrap()));
+        assert_eq!(config(53, "[2606:4700::1111]:5353").upstream_addr(), Ok("[2606:4700::1111]:5353".parse().unwrap()));
+        assert!(config(53, "dns.google").validate().is_err());
+        assert!(config(5353, "127.0.0.1:5353").validate().is_err());
+        assert!(config(5353, "127.0.0.1:53").validate().is_ok());
+        assert!(config(0, "1.1.1.1").validate().is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! Error module - Structured errors returned by Tauri commands
+//!
+//! Commands return `CommandError`, which serializes as `{ "code": "HARDCORE_LOCKED", "message": "..." }`.
+//! Codes are stable, so the frontend can branch on them and localize messages;
+//! `message` is English and meant for logs.
+
+use crate::blocking::{BlockingError, DomainError};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// A hardcore session forbids this action until it ends
+    HardcoreLocked,
+    /// Bedtime is in effect and this would weaken it
+    BedtimeLocked,
+    /// Strict break mode forbids skipping or dismissing the break
+    BreakLocked,
+    PasswordRequired,
+    InvalidPassword,
+    /// The unlock token is missing, expired or revoked; authenticate again
+    Unauthorized,
+    /// The hosts file couldn't be written (Bastion needs admin/root)
+    HostsPermissionDenied,
+    /// The database is locked by another connection; retrying usually works
+    DbBusy,
+    Database,
+    /// The temptation delay is on: request an unlock and wait it out first
+    UnlockDelay,
+    /// The action doesn't apply right now (e.g. no micro-break is running)
+    InvalidState,
+    InvalidInput,
+    /// Not something the hosts file can block: empty, an IP address, malformed or a public suffix
+    InvalidDomain,
+    /// Demo mode: only read-only commands are available
+    DemoMode,
+    Internal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommandError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl CommandError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        CommandError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<rusqlite::Error> for CommandError {
+    fn from(err: rusqlite::Error) -> Self {
+        let code = match err.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::DatabaseBusy) | Some(rusqlite::ErrorCode::DatabaseLocked) => ErrorCode::DbBusy,
+            _ => ErrorCode::Database,
+        };
+        CommandError::new(code, err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for CommandError {
+    fn from(err: serde_json::Error) -> Self {
+        CommandError::new(ErrorCode::InvalidInput, err.to_string())
+    }
+}
+
+impl From<BlockingError> for CommandError {
+    fn from(err: BlockingError) -> Self {
+        let code = if err.permission_denied {
+            ErrorCode::HostsPermissionDenied
+        } else {
+            ErrorCode::Internal
+        };
+        CommandError::new(code, err.message)
+    }
+}
+
+impl From<DomainError> for CommandError {
+    fn from(err: DomainError) -> Self {
+        CommandError::new(ErrorCode::InvalidDomain, err.to_string())
+    }
+}
+
+/// Errors from modules that still report plain strings
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::new(ErrorCode::Internal, message)
+    }
+}
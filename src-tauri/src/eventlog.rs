@@ -0,0 +1,89 @@
+//! Event log module - Mirror block and tamper events to the OS event log
+//!
+//! When the `os_event_log` setting is "true", new block events are forwarded to syslog
+//! (which journald also reads) on Linux/macOS and to the Windows Application event log,
+//! so deployments that centralize logs can see what Bastion blocked.
+
+use crate::storage::{BlockEvent, Database};
+use std::sync::Mutex;
+
+pub const EVENT_LOG_SETTING: &str = "os_event_log";
+
+const SOURCE: &str = "Bastion";
+
+pub struct EventLogExporter {
+    last_block_event_id: Mutex<i64>,
+}
+
+impl EventLogExporter {
+    /// Only events logged after this point are exported; history isn't replayed.
+    pub fn new(db: &Database) -> Self {
+        let latest = db.get_recent_blocks(1).unwrap_or_default();
+        EventLogExporter {
+            last_block_event_id: Mutex::new(latest.first().map_or(0, |e| e.id)),
+        }
+    }
+
+    /// Forward block events logged since the last call. Events logged while the option
+    /// was off are skipped.
+    pub fn export_new_events(&self, db: &Database) {
+        let enabled = db.get_setting(EVENT_LOG_SETTING).unwrap_or(None).as_deref() == Some("true");
+        let mut last_id = self.last_block_event_id.lock().unwrap();
+        let events = db.get_block_events_since(*last_id).unwrap_or_default();
+        if let Some(newest) = events.last() {
+            *last_id = newest.id;
+        }
+        if !enabled {
+            return;
+        }
+
+        for event in &events {
+            write_event(event);
+        }
+    }
+}
+
+fn format_event(event: &BlockEvent) -> String {
+    if event.target_type == "tamper" {
+        format!("Tamper attempt detected: {} (at {})", event.target, event.blocked_at)
+    } else if event.target_type == "repair" {
+        format!("Repaired {} modified outside Bastion (at {})", event.target, event.blocked_at)
+    } else {
+        format!("Blocked {} {} (at {})", event.target_type, event.target, event.blocked_at)
+    }
+}
+
+#[cfg(unix)]
+fn write_event(event: &BlockEvent) {
+    use std::os::unix::net::UnixDatagram;
+
+    // Facility "user" (1); severity warning (4) for tamper events, notice (5) otherwise
+    let priority = 8 + if event.target_type == "tamper" { 4 } else { 5 };
+    let message = format!("<{}>{}[{}]: {}", priority, SOURCE, std::process::id(), format_event(event));
+
+    let Ok(socket) = UnixDatagram::unbound() else {
+        return;
+    };
+    for path in ["/dev/log", "/var/run/syslog"] {
+        if socket.send_to(message.as_bytes(), path).is_ok() {
+            return;
+        }
+    }
+    eprintln!("[Bastion] Event log warning: No syslog socket available");
+}
+
+#[cfg(target_os = "windows")]
+fn write_event(event: &BlockEvent) {
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let (kind, id) = if event.target_type == "tamper" { ("WARNING", "200") } else { ("INFORMATION", "100") };
+    let result = Command::new("eventcreate")
+        .args(["/L", "APPLICATION", "/SO", SOURCE, "/T", kind, "/ID", id, "/D", &format_event(event)])
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn();
+    if let Err(e) = result {
+        eprintln!("[Bastion] Event log warning: Could not run eventcreate: {}", e);
+    }
+}
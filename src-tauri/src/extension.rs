@@ -0,0 +1,652 @@
+//! Extension module - Local API for the companion browser extension
+//!
+//! The extension reports visited pages to `POST /visit` on `127.0.0.1:EXTENSION_PORT`,
+//! authenticated with a bearer token the user copies from Settings. Each report is
+//! classified, added to `site_usage`, and answered with an allow/limit/block decision.
+//! Soft-block rules are answered with `close_tab` or `redirect`, so the extension acts on
+//! the offending tab only while the rest of the domain keeps working.
+//! YouTube rules work the same way for parts of YouTube (Shorts, home feed, comments), with
+//! `hide_sections` telling the extension which parts of an allowed page to hide.
+//! During allowlist-only sessions every page outside the allowlist is answered with `block`;
+//! the hosts file can't express "deny by default", so browsers are covered by the extension.
+//!
+//! The extension can also connect through native messaging (`NATIVE_HOST_NAME`), which needs no
+//! token: the browser starts Bastion as the host (see `is_native_host_launch`), and that process
+//! relays the extension's messages to the running app over `ipc`. The host pushes the block list
+//! (`ExtensionBlocklist`, including path-level rules) whenever it changes, so the extension can
+//! block navigations itself and report them with a `navigation` message.
+
+use crate::storage::Database;
+use crate::usage::{self, Productivity};
+use crate::{blocking, ipc, security, AppState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const EXTENSION_PORT: u16 = 47615;
+
+const TOKEN_SETTING: &str = "extension_token";
+/// Per-domain daily limits in minutes, stored as a JSON object
+pub const SITE_LIMITS_SETTING: &str = "site_time_limits";
+
+/// Whether strict mode (extension force-installed via Enterprise Policies) is on
+pub const STRICT_MODE_SETTING: &str = "extension_strict_mode";
+
+/// Store listings the force-install policies point browsers at
+pub const CHROME_EXTENSION_ID: &str = "lmhjbpgkkdgpedbfpcbjbcjgglmjdclf";
+#[cfg_attr(target_os = "macos", allow(dead_code))] // macOS gets no Chromium policies
+pub const CHROME_UPDATE_URL: &str = "https://clients2.google.com/service/update2/crx";
+pub const EDGE_EXTENSION_ID: &str = "hncgfoedmbkdbnlbfpadmadjgbpdjhkb";
+#[cfg_attr(target_os = "macos", allow(dead_code))] // macOS gets no Chromium policies
+pub const EDGE_UPDATE_URL: &str = "https://edge.microsoft.com/extensionwebstorebase/v1/crx";
+pub const FIREFOX_EXTENSION_ID: &str = "companion@bastion.app";
+pub const FIREFOX_INSTALL_URL: &str = "https://addons.mozilla.org/firefox/downloads/latest/bastion-companion/latest.xpi";
+
+/// Parts of YouTube blocked everywhere (JSON `YouTubeRules`); profiles can add more
+pub const YOUTUBE_RULES_SETTING: &str = "youtube_rules";
+
+/// Name browsers know the native messaging host by
+pub const NATIVE_HOST_NAME: &str = "app.bastion.companion";
+/// How often the native host checks the block list for changes to push
+const NATIVE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+/// Largest message a browser accepts from a native host
+const MAX_NATIVE_MESSAGE_BYTES: usize = 1024 * 1024;
+
+/// Longest stretch of time a single report may add to usage (seconds)
+const MAX_REPORT_SECS: i64 = 300;
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// A page visit reported by the extension. Extra fields (e.g. the page title) are ignored.
+#[derive(Debug, Deserialize)]
+pub struct VisitReport {
+    pub url: String,
+    #[serde(default)]
+    pub seconds: i64, // time spent on the page since the previous report
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Allow,
+    Limit, // allowed, but a daily limit applies (see `remaining_seconds`)
+    Block,
+    CloseTab,
+    Redirect, // navigate the tab to `redirect_url`
+}
+
+#[derive(Debug, Serialize)]
+pub struct VisitDecision {
+    pub domain: String,
+    pub decision: Decision,
+    pub classification: Productivity,
+    pub remaining_seconds: Option<i64>,
+    pub redirect_url: Option<String>,
+    pub hide_sections: Vec<String>, // page sections the extension hides, e.g. "comments"
+}
+
+/// Parts of YouTube to block while direct video links and subscriptions keep working.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct YouTubeRules {
+    pub shorts: bool,
+    pub home_feed: bool, // the home page, plus recommendations next to videos
+    pub comments: bool,
+}
+
+impl YouTubeRules {
+    pub fn union(self, other: YouTubeRules) -> YouTubeRules {
+        YouTubeRules {
+            shorts: self.shorts || other.shorts,
+            home_feed: self.home_feed || other.home_feed,
+            comments: self.comments || other.comments,
+        }
+    }
+}
+
+/// What `rules` mean for a YouTube page: where to send the tab if the whole page is blocked
+/// (Shorts open as a regular video, the home page becomes the subscriptions feed), and which
+/// sections to hide. `(None, [])` for other sites.
+pub fn youtube_action(url: &str, rules: &YouTubeRules) -> (Option<String>, Vec<String>) {
+    let host = blocking::normalize_domain(url);
+    if !matches!(host.as_str(), "youtube.com" | "www.youtube.com" | "m.youtube.com") {
+        return (None, Vec::new());
+    }
+    let after_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = after_scheme.find('/').map_or("/", |i| &after_scheme[i..]);
+    let path = path.split(['?', '#']).next().unwrap_or("/");
+
+    let redirect = if rules.shorts && path.starts_with("/shorts") {
+        match path.trim_start_matches("/shorts").trim_matches('/') {
+            "" => Some(format!("https://{}/feed/subscriptions", host)),
+            id => Some(format!("https://{}/watch?v={}", host, id)),
+        }
+    } else if rules.home_feed && (path == "/" || path.is_empty()) {
+        Some(format!("https://{}/feed/subscriptions", host))
+    } else {
+        None
+    };
+
+    let hide = [(rules.shorts, "shorts"), (rules.home_feed, "recommendations"), (rules.comments, "comments")]
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, section)| section.to_string())
+        .collect();
+    (redirect, hide)
+}
+
+pub fn get_youtube_rules(db: &Database) -> YouTubeRules {
+    db.get_setting(YOUTUBE_RULES_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Token the extension must send as `Authorization: Bearer <token>`, created on first use.
+pub fn get_or_create_token(db: &Database) -> Result<String, String> {
+    if let Some(token) = db.get_setting(TOKEN_SETTING).map_err(|e| e.to_string())? {
+        return Ok(token);
+    }
+    regenerate_token(db)
+}
+
+/// Replace the token, disconnecting any extension that uses the old one.
+pub fn regenerate_token(db: &Database) -> Result<String, String> {
+    let token = security::to_hex(&rand::random::<[u8; 32]>());
+    db.set_setting(TOKEN_SETTING, &token).map_err(|e| e.to_string())?;
+    Ok(token)
+}
+
+pub fn get_site_limits(db: &Database) -> HashMap<String, i64> {
+    db.get_setting(SITE_LIMITS_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Record a visit and decide whether the page may stay open.
+pub fn handle_visit(state: &AppState, report: &VisitReport) -> VisitDecision {
+    let host = blocking::normalize_domain(&report.url);
+    let domain = host.strip_prefix("www.").unwrap_or(&host).to_string();
+
+    let seconds = report.seconds.clamp(0, MAX_REPORT_SECS);
+    if seconds > 0 && !domain.is_empty() {
+        let _ = state.db.add_site_usage(&domain, seconds);
+    }
+
+    let overrides = state.db.get_process_classifications().unwrap_or_default();
+    let classification = usage::classify(&domain, &overrides);
+
+    let blocked = crate::blocked_domains(state)
+        .map(|domains| blocking::is_domain_blocked(&host, &domains))
+        .unwrap_or(false)
+        || crate::is_blocked_by_allowlist(state, &report.url);
+
+    let remaining_seconds = get_site_limits(&state.db).get(&domain).map(|limit_minutes| {
+        let used = state.db.get_site_usage(1)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|usage| usage.domain == domain)
+            .map_or(0, |usage| usage.seconds);
+        (limit_minutes * 60 - used).max(0)
+    });
+
+    let soft_rule = crate::soft_blocked_sites(state)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|site| blocking::matches_url_rule(&report.url, &site.domain));
+
+    let youtube_rules = state.session_manager.active_blocklist()
+        .map_or_else(YouTubeRules::default, |profile| profile.youtube)
+        .union(get_youtube_rules(&state.db));
+    let (youtube_redirect, hide_sections) = youtube_action(&report.url, &youtube_rules);
+
+    let decision = if blocked || remaining_seconds == Some(0) {
+        Decision::Block
+    } else if let Some(rule) = &soft_rule {
+        if rule.mode == blocking::BLOCK_MODE_REDIRECT { Decision::Redirect } else { Decision::CloseTab }
+    } else if youtube_redirect.is_some() {
+        crate::log_block_event(state, &host, "website");
+        Decision::Redirect
+    } else if remaining_seconds.is_some() {
+        Decision::Limit
+    } else {
+        Decision::Allow
+    };
+
+    match (&soft_rule, decision) {
+        (_, Decision::Block) => {
+            crate::log_block_event(state, &domain, "website");
+        }
+        (Some(rule), Decision::CloseTab | Decision::Redirect) => {
+            crate::log_block_event(state, &rule.domain, "website");
+        }
+        _ => {}
+    }
+
+    let redirect_url = (decision == Decision::Redirect).then(|| {
+        youtube_redirect.filter(|_| soft_rule.is_none()).unwrap_or_else(|| {
+            state.db.get_setting(blocking::SOFT_BLOCK_REDIRECT_SETTING)
+                .unwrap_or(None)
+                .unwrap_or_else(|| "about:blank".to_string())
+        })
+    });
+
+    VisitDecision {
+        domain,
+        decision,
+        classification,
+        remaining_seconds,
+        redirect_url,
+        hide_sections,
+    }
+}
+
+/// A soft-block rule: a host, optionally followed by a path prefix (see `blocking::matches_url_rule`)
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UrlRule {
+    pub rule: String,
+    pub mode: String, // `blocking::BLOCK_MODE_CLOSE_TAB` or `BLOCK_MODE_REDIRECT`
+    pub redirect_url: Option<String>,
+}
+
+/// What the extension needs to block navigations by itself
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ExtensionBlocklist {
+    pub domains: Vec<String>, // blocked outright, like the hosts file
+    pub rules: Vec<UrlRule>,
+    pub allowlist: Option<Vec<String>>, // set during allowlist-only sessions: nothing else is reachable
+}
+
+pub fn extension_blocklist(state: &AppState) -> ExtensionBlocklist {
+    let redirect_url = state.db.get_setting(blocking::SOFT_BLOCK_REDIRECT_SETTING)
+        .unwrap_or(None)
+        .unwrap_or_else(|| "about:blank".to_string());
+    let rules = crate::soft_blocked_sites(state)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|site| UrlRule {
+            redirect_url: (site.mode == blocking::BLOCK_MODE_REDIRECT).then(|| redirect_url.clone()),
+            rule: site.domain,
+            mode: site.mode,
+        })
+        .collect();
+    let allowlist = state.session_manager.is_allowlist_active().then(|| {
+        state.db.get_allowed_sites().unwrap_or_default().into_iter().map(|site| site.domain).collect()
+    });
+    ExtensionBlocklist { domains: crate::blocked_domains(state).unwrap_or_default(), rules, allowlist }
+}
+
+/// Serve the extension API on localhost.
+pub async fn start_extension_server(state: Arc<AppState>) {
+    let listener = match TcpListener::bind(("127.0.0.1", EXTENSION_PORT)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("[Bastion] Extension API warning: Could not bind port {}: {}", EXTENSION_PORT, e);
+            return;
+        }
+    };
+    println!("[Bastion] Extension API listening on 127.0.0.1:{}", EXTENSION_PORT);
+
+    loop {
+        let Ok((socket, _)) = listener.accept().await else {
+            continue;
+        };
+        let state_clone = state.clone();
+        tokio::spawn(async move {
+            handle_connection(socket, state_clone).await;
+        });
+    }
+}
+
+pub(crate) struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>, // lowercase names
+    pub body: Vec<u8>,
+}
+
+async fn handle_connection(mut socket: TcpStream, state: Arc<AppState>) {
+    if let Some(request) = read_request(&mut socket).await {
+        let response = route(&state, &request);
+        let _ = socket.write_all(response.as_bytes()).await;
+    }
+}
+
+pub(crate) async fn read_request(socket: &mut TcpStream) -> Option<HttpRequest> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+
+    loop {
+        let n = tokio::time::timeout(std::time::Duration::from_secs(2), socket.read(&mut buf))
+            .await
+            .ok()?
+            .ok()?;
+        if n == 0 || data.len() + n > MAX_REQUEST_BYTES {
+            return None;
+        }
+        data.extend_from_slice(&buf[..n]);
+
+        let Some(header_end) = data.windows(4).position(|w| w == b"\r\n\r\n") else {
+            continue;
+        };
+        let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+        let mut lines = head.lines();
+        let mut request_line = lines.next()?.split_whitespace();
+        let method = request_line.next()?.to_string();
+        let path = request_line.next()?.to_string();
+        let headers: HashMap<String, String> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+
+        let content_length = headers
+            .get("content-length")
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(0);
+        let body_start = header_end + 4;
+        if data.len() >= body_start + content_length {
+            let body = data[body_start..body_start + content_length].to_vec();
+            return Some(HttpRequest { method, path, headers, body });
+        }
+    }
+}
+
+fn route(state: &AppState, request: &HttpRequest) -> String {
+    // CORS preflight from the extension
+    if request.method == "OPTIONS" {
+        return http_response(204, "");
+    }
+    if !is_authorized(state, request) {
+        return http_response(401, r#"{"error":"unauthorized"}"#);
+    }
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/visit") => match serde_json::from_slice::<VisitReport>(&request.body) {
+            Ok(report) => {
+                let decision = handle_visit(state, &report);
+                http_response(200, &serde_json::to_string(&decision).unwrap_or_default())
+            }
+            Err(e) => http_response(400, &serde_json::json!({ "error": e.to_string() }).to_string()),
+        },
+        _ => http_response(404, r#"{"error":"not found"}"#),
+    }
+}
+
+fn is_authorized(state: &AppState, request: &HttpRequest) -> bool {
+    let Ok(Some(expected)) = state.db.get_setting(TOKEN_SETTING) else {
+        return false;
+    };
+    request
+        .headers
+        .get("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| security::constant_time_eq(token.as_bytes(), expected.as_bytes()))
+}
+
+pub(crate) fn http_response(status: u16, body: &str) -> String {
+    let reason = match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        _ => "Not Found",
+    };
+    format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Headers: Authorization, Content-Type\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )
+}
+
+/// Messages the extension sends over native messaging
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum NativeRequest {
+    GetBlocklist,
+    /// Same as `POST /visit`; answered with a `decision` message
+    Visit {
+        url: String,
+        #[serde(default)]
+        seconds: i64,
+    },
+    /// A navigation the extension blocked from the pushed block list, logged as a block
+    Navigation { url: String },
+}
+
+/// Whether the browser started this process as the native messaging host: Chromium passes the
+/// extension's origin (`chrome-extension://<id>/`), Firefox the manifest path and the add-on id.
+pub fn is_native_host_launch(args: &[String]) -> bool {
+    args.iter().skip(1).any(|arg| arg.starts_with("chrome-extension://") || arg == FIREFOX_EXTENSION_ID)
+}
+
+/// Run as the native messaging host until the extension disconnects (the browser closes stdin).
+pub fn run_native_host() {
+    let stdout = Arc::new(Mutex::new(std::io::stdout()));
+
+    let push_out = stdout.clone();
+    std::thread::spawn(move || {
+        let mut last = None;
+        loop {
+            let message = native_response(NativeRequest::GetBlocklist);
+            if last.as_ref() != Some(&message) {
+                if write_native_message(&mut *push_out.lock().unwrap(), &message).is_err() {
+                    return;
+                }
+                last = Some(message);
+            }
+            std::thread::sleep(NATIVE_POLL_INTERVAL);
+        }
+    });
+
+    let mut stdin = std::io::stdin().lock();
+    while let Ok(Some(message)) = read_native_message(&mut stdin) {
+        let response = match serde_json::from_slice::<NativeRequest>(&message) {
+            Ok(request) => native_response(request),
+            Err(e) => serde_json::json!({ "type": "error", "message": e.to_string() }),
+        };
+        if write_native_message(&mut *stdout.lock().unwrap(), &response).is_err() {
+            break;
+        }
+    }
+}
+
+/// Forward `request` to the running app; the answer is tagged with its message type.
+fn native_response(request: NativeRequest) -> serde_json::Value {
+    let (kind, result) = match request {
+        NativeRequest::GetBlocklist => ("blocklist", ipc::send_request(&serde_json::json!({ "command": "get_extension_blocklist" }))),
+        NativeRequest::Visit { url, seconds } => (
+            "decision",
+            ipc::send_request(&serde_json::json!({ "command": "report_visit", "url": url, "seconds": seconds })),
+        ),
+        NativeRequest::Navigation { url } => (
+            "decision",
+            ipc::send_request(&serde_json::json!({ "command": "report_visit", "url": url, "seconds": 0 })),
+        ),
+    };
+    match result {
+        Ok(mut data) => {
+            if let Some(object) = data.as_object_mut() {
+                object.insert("type".to_string(), kind.into());
+            }
+            data
+        }
+        Err(message) => serde_json::json!({ "type": "error", "message": message }),
+    }
+}
+
+/// Read one message: a 32-bit length in native byte order, then that much JSON.
+/// `None` once the browser has closed the stream.
+fn read_native_message(reader: &mut impl Read) -> std::io::Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 4];
+    match reader.read_exact(&mut length) {
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    let length = u32::from_ne_bytes(length) as usize;
+    if length > MAX_REQUEST_BYTES {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "message too large"));
+    }
+    let mut message = vec![0u8; length];
+    reader.read_exact(&mut message)?;
+    Ok(Some(message))
+}
+
+fn write_native_message(writer: &mut impl Write, message: &serde_json::Value) -> std::io::Result<()> {
+    let bytes = serde_json::to_vec(message)?;
+    if bytes.len() > MAX_NATIVE_MESSAGE_BYTES {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "message too large"));
+    }
+    writer.write_all(&(bytes.len() as u32).to_ne_bytes())?;
+    writer.write_all(&bytes)?;
+    writer.flush()
+}
+
+/// The host manifest, pointing the browser at this executable.
+fn native_host_manifest(exe: &Path, firefox: bool) -> serde_json::Value {
+    let mut manifest = serde_json::json!({
+        "name": NATIVE_HOST_NAME,
+        "description": "Bastion focus blocker",
+        "path": exe,
+        "type": "stdio",
+    });
+    if firefox {
+        manifest["allowed_extensions"] = serde_json::json!([FIREFOX_EXTENSION_ID]);
+    } else {
+        manifest["allowed_origins"] = serde_json::json!([
+            format!("chrome-extension://{}/", CHROME_EXTENSION_ID),
+            format!("chrome-extension://{}/", EDGE_EXTENSION_ID),
+        ]);
+    }
+    manifest
+}
+
+/// Per-user manifest directories: (browser, directory, Firefox-style manifest). Browsers whose
+/// profile folder doesn't exist are skipped when installing.
+#[cfg(unix)]
+fn native_host_dirs() -> Vec<(&'static str, std::path::PathBuf, bool)> {
+    let home = std::path::PathBuf::from(std::env::var("HOME").unwrap_or_default());
+    #[cfg(target_os = "macos")]
+    let (base, firefox, chromium) = (
+        home.join("Library/Application Support"),
+        home.join("Library/Application Support/Mozilla/NativeMessagingHosts"),
+        [("Chrome", "Google/Chrome"), ("Chromium", "Chromium"), ("Edge", "Microsoft Edge"), ("Brave", "BraveSoftware/Brave-Browser")],
+    );
+    #[cfg(not(target_os = "macos"))]
+    let (base, firefox, chromium) = (
+        home.join(".config"),
+        home.join(".mozilla/native-messaging-hosts"),
+        [("Chrome", "google-chrome"), ("Chromium", "chromium"), ("Edge", "microsoft-edge"), ("Brave", "BraveSoftware/Brave-Browser")],
+    );
+
+    let mut dirs: Vec<_> = chromium
+        .iter()
+        .map(|(browser, dir)| (*browser, base.join(dir).join("NativeMessagingHosts"), false))
+        .collect();
+    dirs.push(("Firefox", firefox, true));
+    dirs
+}
+
+/// Register the native messaging host with the installed browsers, for the current user (no
+/// admin rights needed). Returns the browsers it was registered with.
+pub fn install_native_host(data_dir: &Path) -> Result<Vec<String>, String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let mut installed = Vec::new();
+
+    #[cfg(unix)]
+    {
+        let _ = data_dir;
+        for (browser, dir, firefox) in native_host_dirs() {
+            if !dir.parent().is_some_and(Path::is_dir) {
+                continue;
+            }
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            let manifest = serde_json::to_string_pretty(&native_host_manifest(&exe, firefox)).map_err(|e| e.to_string())?;
+            std::fs::write(dir.join(format!("{}.json", NATIVE_HOST_NAME)), manifest).map_err(|e| e.to_string())?;
+            installed.push(browser.to_string());
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // Windows finds the manifests through the registry, so they can live in the data dir
+        let dir = data_dir.join("native-messaging");
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let keys = [
+            ("Chrome", r"HKCU\Software\Google\Chrome\NativeMessagingHosts", false),
+            ("Edge", r"HKCU\Software\Microsoft\Edge\NativeMessagingHosts", false),
+            ("Firefox", r"HKCU\Software\Mozilla\NativeMessagingHosts", true),
+        ];
+        for (browser, key, firefox) in keys {
+            let path = dir.join(if firefox { "firefox.json" } else { "chromium.json" });
+            let manifest = serde_json::to_string_pretty(&native_host_manifest(&exe, firefox)).map_err(|e| e.to_string())?;
+            std::fs::write(&path, manifest).map_err(|e| e.to_string())?;
+            let key = format!(r"{}\{}", key, NATIVE_HOST_NAME);
+            let output = std::process::Command::new("reg")
+                .args(["add", &key, "/ve", "/t", "REG_SZ", "/d", &path.to_string_lossy(), "/f"])
+                .output()
+                .map_err(|e| e.to_string())?;
+            if output.status.success() {
+                installed.push(browser.to_string());
+            }
+        }
+    }
+
+    if installed.is_empty() {
+        return Err("No supported browser found".to_string());
+    }
+    Ok(installed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_youtube_action() {
+        let rules = YouTubeRules { shorts: true, home_feed: true, comments: false };
+
+        let (redirect, hide) = youtube_action("https://www.youtube.com/shorts/abc123?feature=share", &rules);
+        assert_eq!(redirect.as_deref(), Some("https://www.youtube.com/watch?v=abc123"));
+        assert_eq!(hide, vec!["shorts", "recommendations"]);
+
+        let (redirect, _) = youtube_action("https://www.youtube.com/", &rules);
+        assert_eq!(redirect.as_deref(), Some("https://www.youtube.com/feed/subscriptions"));
+
+        // Direct video links and subscriptions stay open
+        assert_eq!(youtube_action("https://www.youtube.com/watch?v=abc123", &rules).0, None);
+        assert_eq!(youtube_action("https://m.youtube.com/feed/subscriptions", &rules).0, None);
+
+        assert_eq!(youtube_action("https://example.com/shorts/abc", &rules), (None, Vec::new()));
+        assert_eq!(youtube_action("https://www.youtube.com/shorts/abc", &YouTubeRules::default()), (None, Vec::new()));
+    }
+
+    #[test]
+    fn test_native_messaging() {
+        let mut stream = Vec::new();
+        write_native_message(&mut stream, &serde_json::json!({ "type": "get_blocklist" })).unwrap();
+        assert_eq!(&stream[..4], &24u32.to_ne_bytes());
+
+        let mut reader = std::io::Cursor::new(stream);
+        let message = read_native_message(&mut reader).unwrap().unwrap();
+        assert_eq!(serde_json::from_slice::<NativeRequest>(&message).unwrap(), NativeRequest::GetBlocklist);
+        assert!(read_native_message(&mut reader).unwrap().is_none());
+
+        let visit: NativeRequest = serde_json::from_str(r#"{"type":"visit","url":"https://reddit.com/"}"#).unwrap();
+        assert_eq!(visit, NativeRequest::Visit { url: "https://reddit.com/".to_string(), seconds: 0 });
+
+        let args = |arg: &str| vec!["bastion".to_string(), arg.to_string()];
+        assert!(is_native_host_launch(&args(&format!("chrome-extension://{}/", CHROME_EXTENSION_ID))));
+        assert!(is_native_host_launch(&["bastion", "/path/manifest.json", FIREFOX_EXTENSION_ID].map(String::from)));
+        assert!(!is_native_host_launch(&args("--silent")));
+
+        let manifest = native_host_manifest(Path::new("/opt/bastion"), true);
+        assert_eq!(manifest["allowed_extensions"][0], FIREFOX_EXTENSION_ID);
+        assert!(manifest.get("allowed_origins").is_none());
+    }
+}
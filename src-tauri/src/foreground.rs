@@ -0,0 +1,65 @@
+//! Foreground module - The process that owns the focused window
+//!
+//! Sampled by the usage tracker (see `usage::run_usage_tracker`). Windows asks
+//! `GetForegroundWindow`, macOS asks System Events through `osascript`, and Linux reads
+//! `_NET_ACTIVE_WINDOW` with `xprop` (X11 only). `foreground_process` returns `None` where it
+//! isn't available or nothing has focus.
+
+/// Name of the process owning the focused window, as sysinfo reports it (e.g. "chrome.exe").
+pub fn foreground_process() -> Option<String> {
+    process_name(foreground_pid()?)
+}
+
+fn process_name(pid: u32) -> Option<String> {
+    let pid = sysinfo::Pid::from_u32(pid);
+    let mut system = sysinfo::System::new();
+    system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]), true);
+    system.process(pid).map(|process| process.name().to_string_lossy().to_string())
+}
+
+#[cfg(target_os = "windows")]
+fn foreground_pid() -> Option<u32> {
+    use std::ffi::c_void;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetForegroundWindow() -> *mut c_void;
+        fn GetWindowThreadProcessId(hwnd: *mut c_void, process_id: *mut u32) -> u32;
+    }
+
+    // SAFETY: both calls only read window manager state; `pid` outlives the call
+    let window = unsafe { GetForegroundWindow() };
+    if window.is_null() {
+        return None;
+    }
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(window, &mut pid) };
+    (pid != 0).then_some(pid)
+}
+
+#[cfg(target_os = "macos")]
+fn foreground_pid() -> Option<u32> {
+    let output = std::process::Command::new("osascript")
+        .args(["-e", "tell application \"System Events\" to get unix id of first process whose frontmost is true"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+#[cfg(target_os = "linux")]
+fn foreground_pid() -> Option<u32> {
+    let xprop = |args: &[&str]| -> Option<String> {
+        let output = std::process::Command::new("xprop").args(args).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+    };
+    // "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007", then "_NET_WM_PID(CARDINAL) = 4242"
+    let window = xprop(&["-root", "_NET_ACTIVE_WINDOW"])?
+        .split_whitespace()
+        .last()
+        .filter(|id| id.starts_with("0x") && *id != "0x0")?
+        .to_string();
+    xprop(&["-id", &window, "_NET_WM_PID"])?.split('=').nth(1)?.trim().parse().ok()
+}
@@ -0,0 +1,242 @@
+//! Health module - Protection status report
+//!
+//! `check` inspects every layer Bastion relies on to block distractions (hosts file, block
+//! server, browser DoH policies, admin rights, the background loop, DNS resolution) so the
+//! UI can show a single Healthy/Degraded indicator and explain what is wrong.
+//!
+//! `selftest` goes further and sends a real request for a blocked domain through the
+//! resolver to check that it ends up on Bastion's block server.
+
+use crate::blocking::{self, BrowserPolicyStatus};
+use crate::server::ListenerStatus;
+use crate::AppState;
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// The background loop ticks every second; allow for a few slow ticks
+const LOOP_STALE_SECS: i64 = 10;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProtectionLevel {
+    Healthy,
+    Degraded,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtectionStatus {
+    pub level: ProtectionLevel,
+    pub hosts_repaired: bool, // the hosts section had been modified and was rewritten by this check
+    pub is_admin: bool,
+    pub hosts_writable: bool,
+    pub hosts_section_present: bool,
+    pub hosts_sync_pending: bool, // a hosts update is queued until Bastion has admin rights
+    pub block_server: Vec<ListenerStatus>,
+    pub doh_policies: Vec<BrowserPolicyStatus>,
+    pub enforcement_loop_alive: bool,
+    pub dns_domain: Option<String>, // blocked domain used for the DNS check
+    pub dns_resolves_to_localhost: Option<bool>,
+    pub issues: Vec<String>,
+}
+
+pub fn check(state: &AppState) -> ProtectionStatus {
+    let hosts_repaired = crate::repair_hosts_drift(state);
+    let domains = crate::blocked_domains(state).unwrap_or_default();
+    let hosts_writable = blocking::is_admin();
+    let dns_domain = domains.first().cloned();
+
+    let last_tick = state.last_loop_tick.load(std::sync::atomic::Ordering::SeqCst);
+    let mut status = ProtectionStatus {
+        level: ProtectionLevel::Healthy,
+        hosts_repaired,
+        // Bastion's notion of admin is being able to write the hosts file
+        is_admin: hosts_writable,
+        hosts_writable,
+        hosts_section_present: blocking::hosts_section_present(),
+        hosts_sync_pending: crate::is_hosts_sync_pending(state),
+        block_server: state.block_server.list(),
+        doh_policies: blocking::doh_policy_status(),
+        enforcement_loop_alive: chrono::Local::now().timestamp() - last_tick <= LOOP_STALE_SECS,
+        dns_resolves_to_localhost: dns_domain.as_deref().map(blocking::resolves_to_localhost),
+        dns_domain,
+        issues: Vec::new(),
+    };
+
+    status.issues = find_issues(&status, !domains.is_empty());
+    if !status.issues.is_empty() {
+        status.level = ProtectionLevel::Degraded;
+    }
+    status
+}
+
+/// Human-readable problems; empty when protection is healthy.
+fn find_issues(status: &ProtectionStatus, has_blocked_sites: bool) -> Vec<String> {
+    let mut issues = Vec::new();
+    if !status.hosts_writable {
+        issues.push("Bastion can't write the hosts file (run it as administrator)".to_string());
+    }
+    if status.hosts_sync_pending {
+        issues.push("The hosts file is out of date; changes will apply once Bastion has admin rights".to_string());
+    }
+    // The section is removed when nothing is blocked
+    if has_blocked_sites && !status.hosts_section_present {
+        issues.push("The hosts file is missing Bastion's block section".to_string());
+    }
+    // IPv4 is what browsers use for the hosts entries in practice; ::1 often isn't available
+    for listener in status.block_server.iter().filter(|l| l.address == "127.0.0.1") {
+        match (listener.bound_port, &listener.holder) {
+            (Some(bound), _) if bound == listener.port || listener.redirected => {}
+            (Some(bound), _) => issues.push(format!(
+                "The block page server runs on port {} but nothing forwards port {} to it",
+                bound, listener.port
+            )),
+            (None, Some(holder)) => issues.push(format!("Port {} is in use by {}", listener.port, holder)),
+            (None, None) => issues.push(format!("The block page server isn't listening on port {}", listener.port)),
+        }
+    }
+    for policy in status.doh_policies.iter().filter(|p| !p.doh_disabled) {
+        issues.push(format!("DNS-over-HTTPS isn't disabled for {}", policy.browser));
+    }
+    if !status.enforcement_loop_alive {
+        issues.push("The enforcement loop has stopped".to_string());
+    }
+    if let (Some(domain), Some(false)) = (&status.dns_domain, status.dns_resolves_to_localhost) {
+        issues.push(format!("{} doesn't resolve to localhost (DNS cache or a VPN may bypass blocking)", domain));
+    }
+    issues
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestLayer {
+    BlockList,   // something must be blocked to test
+    Dns,         // hosts entries resolve the domain to localhost
+    Connect,     // something accepts connections on the resolved address
+    BlockServer, // that something is Bastion's block server
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestStep {
+    pub layer: SelfTestLayer,
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub domain: Option<String>,
+    pub passed: bool,
+    pub failed_layer: Option<SelfTestLayer>,
+    pub steps: Vec<SelfTestStep>,
+}
+
+impl SelfTestReport {
+    fn step(&mut self, layer: SelfTestLayer, ok: bool, detail: String) -> bool {
+        self.steps.push(SelfTestStep { layer, ok, detail });
+        if !ok {
+            self.failed_layer = Some(layer);
+        }
+        ok
+    }
+}
+
+/// Resolve a blocked domain and request it over HTTP. Stops at the first layer that fails.
+pub fn selftest(state: &AppState) -> SelfTestReport {
+    let domains = crate::blocked_domains(state).unwrap_or_default();
+    let mut report = SelfTestReport {
+        domain: domains.first().cloned(),
+        passed: false,
+        failed_layer: None,
+        steps: Vec::new(),
+    };
+    let Some(domain) = report.domain.clone() else {
+        report.step(SelfTestLayer::BlockList, false, "No websites are blocked".to_string());
+        return report;
+    };
+    report.step(SelfTestLayer::BlockList, true, format!("Testing {}", domain));
+
+    let addrs: Vec<SocketAddr> = (domain.as_str(), 80).to_socket_addrs().map(|a| a.collect()).unwrap_or_default();
+    let local = !addrs.is_empty() && addrs.iter().all(|addr| addr.ip().is_loopback() || addr.ip().is_unspecified());
+    let resolved = addrs.iter().map(|addr| addr.ip().to_string()).collect::<Vec<_>>().join(", ");
+    let detail = if addrs.is_empty() {
+        "Could not resolve the domain".to_string()
+    } else {
+        format!("Resolved to {}", resolved)
+    };
+    if !report.step(SelfTestLayer::Dns, local, detail) {
+        return report;
+    }
+
+    let mut stream = match TcpStream::connect_timeout(&addrs[0], Duration::from_secs(2)) {
+        Ok(stream) => stream,
+        Err(e) => {
+            report.step(SelfTestLayer::Connect, false, format!("Could not connect to {}: {}", addrs[0], e));
+            return report;
+        }
+    };
+    report.step(SelfTestLayer::Connect, true, format!("Connected to {}", addrs[0]));
+
+    let request = format!(
+        "GET / HTTP/1.1\r\nHost: {}\r\n{}: 1\r\nConnection: close\r\n\r\n",
+        domain,
+        crate::server::SELFTEST_HEADER
+    );
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+    let mut response = String::new();
+    let answered = stream.write_all(request.as_bytes()).is_ok() && stream.read_to_string(&mut response).is_ok();
+    let is_block_page = response.contains(crate::server::BLOCK_PAGE_MARKER);
+    let detail = if is_block_page {
+        "Bastion's block page was served".to_string()
+    } else if answered {
+        "Another program answered on this address".to_string()
+    } else {
+        "No response from the block server".to_string()
+    };
+    report.passed = report.step(SelfTestLayer::BlockServer, is_block_page, detail);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn healthy() -> ProtectionStatus {
+        ProtectionStatus {
+            level: ProtectionLevel::Healthy,
+            hosts_repaired: false,
+            is_admin: true,
+            hosts_writable: true,
+            hosts_section_present: false,
+            hosts_sync_pending: false,
+            block_server: vec![ListenerStatus {
+                port: 80,
+                address: "127.0.0.1".to_string(),
+                bound_port: Some(80),
+                redirected: false,
+                holder: None,
+                error: None,
+            }],
+            doh_policies: Vec::new(),
+            enforcement_loop_alive: true,
+            dns_domain: None,
+            dns_resolves_to_localhost: None,
+            issues: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_find_issues() {
+        // No section is expected when nothing is blocked
+        assert!(find_issues(&healthy(), false).is_empty());
+        assert_eq!(find_issues(&healthy(), true).len(), 1);
+
+        let mut status = healthy();
+        status.dns_domain = Some("reddit.com".to_string());
+        status.dns_resolves_to_localhost = Some(false);
+        status.block_server[0].bound_port = None;
+        status.block_server[0].holder = Some("nginx (pid 42)".to_string());
+        assert_eq!(find_issues(&status, false).len(), 2);
+    }
+}
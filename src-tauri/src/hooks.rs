@@ -0,0 +1,101 @@
+//! Hooks module - User scripts run on session lifecycle events
+//!
+//! Hooks are stored as JSON in the `script_hooks` setting. Each matching hook is started
+//! in the background with the event described in `BASTION_*` environment variables and,
+//! as JSON, on stdin. Hooks are fire-and-forget: their exit status is only logged.
+
+use crate::session::ActiveSession;
+use crate::storage::Database;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+pub const HOOKS_SETTING: &str = "script_hooks";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    SessionStart,
+    SessionEnd,
+    HardcoreLock,
+}
+
+impl HookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::SessionStart => "session_start",
+            HookEvent::SessionEnd => "session_end",
+            HookEvent::HardcoreLock => "hardcore_lock",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptHook {
+    pub event: HookEvent,
+    pub command: String, // path to a script or executable
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct HookPayload<'a> {
+    event: HookEvent,
+    session: &'a ActiveSession,
+}
+
+pub fn get_hooks(db: &Database) -> Vec<ScriptHook> {
+    db.get_setting(HOOKS_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Start every enabled hook registered for `event`.
+pub fn run_hooks(db: &Database, event: HookEvent, session: &ActiveSession) {
+    let hooks: Vec<ScriptHook> = get_hooks(db)
+        .into_iter()
+        .filter(|hook| hook.enabled && hook.event == event)
+        .collect();
+    if hooks.is_empty() {
+        return;
+    }
+
+    let payload = serde_json::to_vec(&HookPayload { event, session }).unwrap_or_default();
+    for hook in hooks {
+        let child = Command::new(&hook.command)
+            .args(&hook.args)
+            .env("BASTION_EVENT", event.as_str())
+            .env("BASTION_SESSION_NAME", &session.name)
+            .env("BASTION_SESSION_START", session.start_time.to_string())
+            .env("BASTION_SESSION_END", session.end_time.to_string())
+            .env("BASTION_HARDCORE", if session.hardcore { "1" } else { "0" })
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                eprintln!("[Bastion] Could not run {} hook '{}': {}", event.as_str(), hook.command, e);
+                continue;
+            }
+        };
+
+        // Feed stdin and reap the process without blocking the caller
+        let payload = payload.clone();
+        std::thread::spawn(move || {
+            if let Some(mut stdin) = child.stdin.take() {
+                let _ = stdin.write_all(&payload);
+            }
+            match child.wait() {
+                Ok(status) if !status.success() => {
+                    eprintln!("[Bastion] {} hook '{}' exited with {}", event.as_str(), hook.command, status);
+                }
+                _ => {}
+            }
+        });
+    }
+}
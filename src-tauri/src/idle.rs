@@ -0,0 +1,78 @@
+//! Idle module - Time since the last keyboard/mouse input
+//!
+//! Used to pause the pomodoro work timer while the user is away (see `IdlePauseConfig`).
+//! Windows asks `GetLastInputInfo`, macOS reads `HIDIdleTime` from `ioreg`, and Linux
+//! uses `xprintidle` (X11 only). `idle_seconds` returns `None` where it isn't available.
+
+use serde::{Deserialize, Serialize};
+
+pub const IDLE_PAUSE_SETTING: &str = "pomodoro_idle_pause";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdlePauseConfig {
+    pub enabled: bool,
+    pub idle_minutes: i64, // pause after this long without input
+}
+
+impl Default for IdlePauseConfig {
+    fn default() -> Self {
+        IdlePauseConfig {
+            enabled: false,
+            idle_minutes: 5,
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn idle_seconds() -> Option<u64> {
+    #[repr(C)]
+    struct LastInputInfo {
+        cb_size: u32,
+        dw_time: u32,
+    }
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetLastInputInfo(plii: *mut LastInputInfo) -> i32;
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetTickCount() -> u32;
+    }
+
+    let mut info = LastInputInfo {
+        cb_size: std::mem::size_of::<LastInputInfo>() as u32,
+        dw_time: 0,
+    };
+    // SAFETY: `info` is a valid LASTINPUTINFO with `cbSize` set
+    if unsafe { GetLastInputInfo(&mut info) } == 0 {
+        return None;
+    }
+    let now = unsafe { GetTickCount() };
+    Some(u64::from(now.wrapping_sub(info.dw_time)) / 1000)
+}
+
+#[cfg(target_os = "macos")]
+pub fn idle_seconds() -> Option<u64> {
+    let output = std::process::Command::new("ioreg")
+        .args(["-c", "IOHIDSystem", "-d", "4"])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let nanos: u64 = stdout
+        .lines()
+        .find(|line| line.contains("\"HIDIdleTime\""))
+        .and_then(|line| line.split('=').nth(1))
+        .and_then(|value| value.trim().parse().ok())?;
+    Some(nanos / 1_000_000_000)
+}
+
+#[cfg(target_os = "linux")]
+pub fn idle_seconds() -> Option<u64> {
+    let output = std::process::Command::new("xprintidle").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let millis: u64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+    Some(millis / 1000)
+}
@@ -0,0 +1,399 @@
+//! Integrations module - Pushes completed focus time to external services
+//!
+//! Outgoing requests are written to the `integration_queue` table first and sent by
+//! `flush_queue`, so sessions finished while offline are delivered once the network is back.
+//! Time trackers (Toggl, Clockify) get time entries; Beeminder gets one datapoint per session,
+//! which the goal sums per day; Habitica scores a task when a session completes or the daily
+//! goal is met. Time tracker, Beeminder and Habitica tokens live in the OS keyring, not the
+//! database.
+
+use crate::storage::Database;
+use serde::{Deserialize, Serialize};
+
+const TOGGL_API: &str = "https://api.track.toggl.com/api/v9";
+const CLOCKIFY_API: &str = "https://api.clockify.me/api/v1";
+const BEEMINDER_API: &str = "https://www.beeminder.com/api/v1";
+const HABITICA_API: &str = "https://habitica.com/api/v3";
+
+pub const BEEMINDER_SETTING: &str = "beeminder";
+const BEEMINDER_PROVIDER: &str = "beeminder";
+const KEYRING_SERVICE: &str = "bastion";
+const TIME_TRACKER_KEYRING_USER: &str = "time_tracker";
+/// Where the time tracker token was kept before it moved to the keyring
+pub const LEGACY_TIME_TRACKER_TOKEN_SETTING: &str = "time_tracker_token";
+const BEEMINDER_KEYRING_USER: &str = "beeminder";
+
+pub const HABITICA_SETTING: &str = "habitica";
+/// Local date the daily goal was last scored, so it's scored once per day
+pub const HABITICA_GOAL_SCORED_SETTING: &str = "habitica_goal_scored_date";
+const HABITICA_PROVIDER: &str = "habitica";
+const HABITICA_KEYRING_USER: &str = "habitica";
+
+/// Give up on a queued push after this many failed attempts
+const MAX_ATTEMPTS: i64 = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeEntry {
+    pub description: String,
+    pub start: i64,          // Unix timestamp
+    pub duration_secs: i64,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeTracker {
+    Toggl,
+    Clockify,
+}
+
+impl TimeTracker {
+    pub fn from_setting(value: &str) -> Option<Self> {
+        match value {
+            "toggl" => Some(TimeTracker::Toggl),
+            "clockify" => Some(TimeTracker::Clockify),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeTracker::Toggl => "toggl",
+            TimeTracker::Clockify => "clockify",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BeeminderMetric {
+    /// Focused minutes of every session, finished or ended early
+    #[default]
+    Minutes,
+    /// 1 per session that ran its full length
+    Sessions,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BeeminderConfig {
+    pub enabled: bool,
+    pub username: String,
+    pub goal: String, // goal slug
+    #[serde(default)]
+    pub metric: BeeminderMetric,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeeminderDatapoint {
+    pub value: f64,
+    pub timestamp: i64,
+    pub comment: String,
+    /// Lets Beeminder drop duplicates when a retried request did arrive the first time
+    pub requestid: String,
+}
+
+fn keyring_entry(user: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, user).map_err(|e| e.to_string())
+}
+
+/// Store a secret in the OS keyring, or remove it with `None`.
+fn store_secret(user: &str, secret: Option<&str>) -> Result<(), String> {
+    let entry = keyring_entry(user)?;
+    match secret {
+        Some(secret) => entry.set_password(secret).map_err(|e| e.to_string()),
+        None => match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        },
+    }
+}
+
+fn load_secret(user: &str) -> Option<String> {
+    keyring_entry(user).ok()?.get_password().ok()
+}
+
+pub fn set_time_tracker_token(token: Option<&str>) -> Result<(), String> {
+    store_secret(TIME_TRACKER_KEYRING_USER, token)
+}
+
+pub fn has_time_tracker_token() -> bool {
+    load_secret(TIME_TRACKER_KEYRING_USER).is_some()
+}
+
+/// Move a time tracker token saved by an older version from the settings table to the keyring.
+/// The setting is only removed once the keyring holds the token.
+pub fn migrate_time_tracker_token(db: &Database) -> Result<(), String> {
+    let Some(token) = db.get_setting(LEGACY_TIME_TRACKER_TOKEN_SETTING).map_err(|e| e.to_string())? else {
+        return Ok(());
+    };
+    if !token.trim().is_empty() {
+        store_secret(TIME_TRACKER_KEYRING_USER, Some(token.trim()))?;
+    }
+    db.delete_setting(LEGACY_TIME_TRACKER_TOKEN_SETTING).map_err(|e| e.to_string())
+}
+
+pub fn set_beeminder_token(token: Option<&str>) -> Result<(), String> {
+    store_secret(BEEMINDER_KEYRING_USER, token)
+}
+
+pub fn has_beeminder_token() -> bool {
+    load_secret(BEEMINDER_KEYRING_USER).is_some()
+}
+
+pub fn load_beeminder_config(db: &Database) -> BeeminderConfig {
+    db.get_setting(BEEMINDER_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Queue a Beeminder datapoint for a finished session if the integration is enabled.
+pub fn queue_beeminder_datapoint(db: &Database, session_id: &str, entry: &TimeEntry, completed: bool) -> Result<(), String> {
+    let config = load_beeminder_config(db);
+    if !config.enabled {
+        return Ok(());
+    }
+    let value = match config.metric {
+        BeeminderMetric::Minutes => entry.duration_secs as f64 / 60.0,
+        BeeminderMetric::Sessions if completed => 1.0,
+        BeeminderMetric::Sessions => return Ok(()),
+    };
+    if value <= 0.0 {
+        return Ok(());
+    }
+    let datapoint = BeeminderDatapoint {
+        value: (value * 10.0).round() / 10.0,
+        timestamp: entry.start + entry.duration_secs,
+        comment: format!("Bastion: {}", entry.description),
+        requestid: format!("bastion-{}", session_id),
+    };
+    let payload = serde_json::to_string(&datapoint).map_err(|e| e.to_string())?;
+    db.enqueue_integration(BEEMINDER_PROVIDER, &payload).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct HabiticaConfig {
+    pub enabled: bool,
+    pub user_id: String,
+    /// Scored each time a session runs its full length
+    #[serde(default)]
+    pub session_task_id: Option<String>,
+    /// Scored once a day when the daily focus goal is reached
+    #[serde(default)]
+    pub goal_task_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HabiticaEvent {
+    SessionCompleted,
+    DailyGoalMet,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HabiticaScore {
+    pub task_id: String,
+}
+
+pub fn set_habitica_api_token(token: Option<&str>) -> Result<(), String> {
+    store_secret(HABITICA_KEYRING_USER, token)
+}
+
+pub fn has_habitica_api_token() -> bool {
+    load_secret(HABITICA_KEYRING_USER).is_some()
+}
+
+pub fn load_habitica_config(db: &Database) -> HabiticaConfig {
+    db.get_setting(HABITICA_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Queue an "up" score for the task configured for `event`, if the integration is enabled.
+pub fn queue_habitica_score(db: &Database, event: HabiticaEvent) -> Result<(), String> {
+    let config = load_habitica_config(db);
+    if !config.enabled {
+        return Ok(());
+    }
+    let task_id = match event {
+        HabiticaEvent::SessionCompleted => config.session_task_id.as_ref(),
+        HabiticaEvent::DailyGoalMet => config.goal_task_id.as_ref(),
+    };
+    let Some(task_id) = task_id.filter(|id| !id.trim().is_empty()) else {
+        return Ok(());
+    };
+    let payload = serde_json::to_string(&HabiticaScore { task_id: task_id.trim().to_string() }).map_err(|e| e.to_string())?;
+    db.enqueue_integration(HABITICA_PROVIDER, &payload).map_err(|e| e.to_string())
+}
+
+/// Queue a finished session for export if a time tracker is configured.
+pub fn queue_time_entry(db: &Database, entry: &TimeEntry) -> Result<(), String> {
+    let provider = db.get_setting("time_tracker_provider").map_err(|e| e.to_string())?;
+    let Some(tracker) = provider.as_deref().and_then(TimeTracker::from_setting) else {
+        return Ok(());
+    };
+
+    let payload = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    db.enqueue_integration(tracker.as_str(), &payload).map_err(|e| e.to_string())
+}
+
+/// Try to deliver everything in the integration queue.
+/// Returns the number of items that were delivered.
+pub async fn flush_queue(db: &Database) -> usize {
+    let pending = match db.get_integration_queue() {
+        Ok(pending) => pending,
+        Err(_) => return 0,
+    };
+    if pending.is_empty() {
+        return 0;
+    }
+
+    let client = reqwest::Client::new();
+    let mut delivered = 0;
+
+    for item in pending {
+        let result = match TimeTracker::from_setting(&item.provider) {
+            Some(tracker) => push_queued_entry(&client, db, &tracker, &item.payload).await,
+            None if item.provider == BEEMINDER_PROVIDER => push_queued_datapoint(&client, db, &item.payload).await,
+            None if item.provider == HABITICA_PROVIDER => push_queued_score(&client, db, &item.payload).await,
+            None => Err(format!("Unknown integration provider: {}", item.provider)),
+        };
+
+        match result {
+            Ok(()) => {
+                let _ = db.remove_from_integration_queue(item.id);
+                delivered += 1;
+            }
+            Err(e) if item.attempts + 1 >= MAX_ATTEMPTS => {
+                eprintln!("[Bastion] Dropping {} export after {} attempts: {}", item.provider, MAX_ATTEMPTS, e);
+                let _ = db.remove_from_integration_queue(item.id);
+            }
+            Err(e) => {
+                let _ = db.record_integration_failure(item.id, &e);
+            }
+        }
+    }
+
+    delivered
+}
+
+async fn push_queued_entry(client: &reqwest::Client, db: &Database, tracker: &TimeTracker, payload: &str) -> Result<(), String> {
+    let entry: TimeEntry = serde_json::from_str(payload).map_err(|e| e.to_string())?;
+    let token = load_secret(TIME_TRACKER_KEYRING_USER).ok_or("No time tracker API token in the keyring")?;
+    let workspace = db.get_setting("time_tracker_workspace").map_err(|e| e.to_string())?
+        .ok_or("No time tracker workspace configured")?;
+
+    push_time_entry(client, tracker, &token, &workspace, &entry).await
+}
+
+async fn push_queued_datapoint(client: &reqwest::Client, db: &Database, payload: &str) -> Result<(), String> {
+    let datapoint: BeeminderDatapoint = serde_json::from_str(payload).map_err(|e| e.to_string())?;
+    let config = load_beeminder_config(db);
+    if config.username.is_empty() || config.goal.is_empty() {
+        return Err("No Beeminder user or goal configured".to_string());
+    }
+    let token = load_secret(BEEMINDER_KEYRING_USER).ok_or("No Beeminder auth token in the keyring")?;
+
+    push_beeminder_datapoint(client, &config, &token, &datapoint).await
+}
+
+/// Add a datapoint to the configured Beeminder goal.
+pub async fn push_beeminder_datapoint(
+    client: &reqwest::Client,
+    config: &BeeminderConfig,
+    token: &str,
+    datapoint: &BeeminderDatapoint,
+) -> Result<(), String> {
+    let response = client
+        .post(format!("{}/users/{}/goals/{}/datapoints.json", BEEMINDER_API, config.username, config.goal))
+        .form(&[
+            ("auth_token", token.to_string()),
+            ("value", datapoint.value.to_string()),
+            ("timestamp", datapoint.timestamp.to_string()),
+            ("comment", datapoint.comment.clone()),
+            ("requestid", datapoint.requestid.clone()),
+        ])
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("beeminder rejected the datapoint: HTTP {}", response.status()))
+    }
+}
+
+async fn push_queued_score(client: &reqwest::Client, db: &Database, payload: &str) -> Result<(), String> {
+    let score: HabiticaScore = serde_json::from_str(payload).map_err(|e| e.to_string())?;
+    let config = load_habitica_config(db);
+    if config.user_id.is_empty() {
+        return Err("No Habitica user id configured".to_string());
+    }
+    let token = load_secret(HABITICA_KEYRING_USER).ok_or("No Habitica API token in the keyring")?;
+
+    let response = client
+        .post(format!("{}/tasks/{}/score/up", HABITICA_API, score.task_id))
+        .header("x-api-user", &config.user_id)
+        .header("x-api-key", token)
+        // Habitica asks third-party tools to identify themselves as "<author user id>-<app name>"
+        .header("x-client", format!("{}-Bastion", config.user_id))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("habitica rejected the score: HTTP {}", response.status()))
+    }
+}
+
+/// Send a single time entry to Toggl or Clockify.
+pub async fn push_time_entry(
+    client: &reqwest::Client,
+    tracker: &TimeTracker,
+    token: &str,
+    workspace: &str,
+    entry: &TimeEntry,
+) -> Result<(), String> {
+    let start = chrono::DateTime::from_timestamp(entry.start, 0).ok_or("Invalid start time")?;
+    let end = chrono::DateTime::from_timestamp(entry.start + entry.duration_secs, 0).ok_or("Invalid end time")?;
+
+    let request = match tracker {
+        TimeTracker::Toggl => {
+            let workspace_id: i64 = workspace.parse().map_err(|_| "Toggl workspace id must be numeric")?;
+            client
+                .post(format!("{}/workspaces/{}/time_entries", TOGGL_API, workspace_id))
+                .basic_auth(token, Some("api_token"))
+                .json(&serde_json::json!({
+                    "description": entry.description,
+                    "start": start.to_rfc3339(),
+                    "duration": entry.duration_secs,
+                    "tags": entry.tags,
+                    "workspace_id": workspace_id,
+                    "created_with": "Bastion",
+                }))
+        }
+        TimeTracker::Clockify => {
+            // Clockify only accepts tag ids, so tags are folded into the description
+            let description = if entry.tags.is_empty() {
+                entry.description.clone()
+            } else {
+                format!("{} [{}]", entry.description, entry.tags.join(", "))
+            };
+            client
+                .post(format!("{}/workspaces/{}/time-entries", CLOCKIFY_API, workspace))
+                .header("X-Api-Key", token)
+                .json(&serde_json::json!({
+                    "description": description,
+                    "start": start.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                    "end": end.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                }))
+        }
+    };
+
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("{} rejected the time entry: HTTP {}", tracker.as_str(), response.status()))
+    }
+}
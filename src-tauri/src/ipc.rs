@@ -0,0 +1,254 @@
+//! IPC module - Local control channel for the CLI, watchdog and scripts
+//!
+//! Listens on a Unix domain socket (`bastion.sock` in the data directory) on Linux/macOS
+//! and on the named pipe `\\.\pipe\bastion` on Windows. The protocol is one JSON object per
+//! line in each direction: a request such as `{"command":"start_focus_session","name":"Deep work",
+//! "duration_minutes":50}` is answered with `{"ok":true,"data":...}` or
+//! `{"ok":false,"error":{"code":"HARDCORE_LOCKED","message":"..."}}`.
+//!
+//! Requests are dispatched to the same functions as the Tauri commands, so hardcore and
+//! bedtime rules apply exactly as they do in the UI.
+//!
+//! `send_request` is the client side, used by the browser's native messaging host (see
+//! `extension::run_native_host`), which runs as a separate Bastion process.
+
+use crate::error::{CommandError, ErrorCode};
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::Manager;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+#[cfg(target_os = "windows")]
+pub const PIPE_NAME: &str = r"\\.\pipe\bastion";
+
+/// Must match the `identifier` in tauri.conf.json, which names the app data directory
+#[cfg(unix)]
+const APP_IDENTIFIER: &str = "com.bastion.focus";
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum IpcRequest {
+    Status,
+    StartFocusSession {
+        name: String,
+        duration_minutes: i64,
+        #[serde(default)]
+        hardcore: bool,
+        #[serde(default)]
+        blocklist_id: Option<String>,
+        #[serde(default)]
+        override_policy: Option<crate::session::OverridePolicy>,
+        #[serde(default)]
+        allowlist_only: Option<bool>,
+    },
+    EndFocusSession {
+        #[serde(default)]
+        session_id: Option<String>,
+        #[serde(default)]
+        challenge_response: Option<String>,
+        #[serde(default)]
+        token: Option<String>,
+    },
+    RequestUnlockChallenge,
+    PomodoroGetState,
+    PomodoroStart,
+    PomodoroPause,
+    PomodoroSkipPhase,
+    IsDomainBlocked {
+        domain: String,
+    },
+    GetFocusStats {
+        days: i32,
+    },
+    GetRecentBlocks {
+        limit: i32,
+    },
+    GetExtensionBlocklist,
+    ReportVisit {
+        url: String,
+        #[serde(default)]
+        seconds: i64,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct IpcResponse {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<CommandError>,
+}
+
+/// Serve the control channel until the app exits.
+pub async fn start_ipc_server(state: Arc<AppState>, data_dir: PathBuf) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let socket_path = data_dir.join("bastion.sock");
+        // A socket left behind by a previous run would make bind fail
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = match tokio::net::UnixListener::bind(&socket_path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("[Bastion] IPC warning: Could not bind {}: {}", socket_path.display(), e);
+                return;
+            }
+        };
+        // Only the current user may control Bastion
+        let _ = std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600));
+        println!("[Bastion] IPC listening on {}", socket_path.display());
+
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let state_clone = state.clone();
+            tokio::spawn(async move {
+                serve(stream, state_clone).await;
+            });
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        let _ = data_dir;
+        let mut first_instance = true;
+        loop {
+            let server = match ServerOptions::new()
+                .first_pipe_instance(first_instance)
+                .reject_remote_clients(true)
+                .create(PIPE_NAME)
+            {
+                Ok(server) => server,
+                Err(e) => {
+                    eprintln!("[Bastion] IPC warning: Could not create {}: {}", PIPE_NAME, e);
+                    return;
+                }
+            };
+            first_instance = false;
+
+            if server.connect().await.is_err() {
+                continue;
+            }
+            let state_clone = state.clone();
+            tokio::spawn(async move {
+                serve(server, state_clone).await;
+            });
+        }
+    }
+}
+
+async fn serve<S: AsyncRead + AsyncWrite + Unpin>(stream: S, state: Arc<AppState>) {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = serde_json::from_str::<IpcRequest>(&line)
+            .map_err(CommandError::from)
+            .and_then(|request| dispatch(&state, request));
+        let response = match result {
+            Ok(data) => IpcResponse { ok: true, data: Some(data), error: None },
+            Err(error) => IpcResponse { ok: false, data: None, error: Some(error) },
+        };
+
+        let mut out = serde_json::to_string(&response).unwrap_or_default();
+        out.push('\n');
+        if writer.write_all(out.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+fn dispatch(state: &AppState, request: IpcRequest) -> Result<serde_json::Value, CommandError> {
+    // The guard is dropped at the end of this statement; commands lock app_handle to emit events
+    let handle = state.app_handle.lock().unwrap().clone()
+        .ok_or_else(|| CommandError::new(ErrorCode::InvalidState, "Bastion is still starting"))?;
+    let app_state = || handle.state::<Arc<AppState>>();
+
+    let data = match request {
+        IpcRequest::Status => serde_json::json!({
+            "active_session": state.session_manager.active_session(),
+            "active_sessions": state.session_manager.active_sessions(),
+            "time_remaining": state.session_manager.get_time_remaining(),
+            "hardcore_locked": state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst),
+            "bedtime": state.session_manager.is_bedtime_active(),
+            "pomodoro": state.session_manager.get_pomodoro_state(),
+        }),
+        IpcRequest::StartFocusSession { name, duration_minutes, hardcore, blocklist_id, override_policy, allowlist_only } => {
+            to_value(crate::start_focus_session(app_state(), name, duration_minutes, hardcore, blocklist_id, override_policy, allowlist_only)?)?
+        }
+        IpcRequest::EndFocusSession { session_id, challenge_response, token } => {
+            to_value(crate::end_focus_session(app_state(), session_id, challenge_response, token)?)?
+        }
+        IpcRequest::RequestUnlockChallenge => to_value(crate::request_unlock_challenge(app_state(), None)?)?,
+        IpcRequest::PomodoroGetState => to_value(crate::pomodoro_get_state(app_state()))?,
+        IpcRequest::PomodoroStart => to_value(crate::pomodoro_start(app_state())?)?,
+        IpcRequest::PomodoroPause => to_value(crate::pomodoro_pause(app_state())?)?,
+        IpcRequest::PomodoroSkipPhase => to_value(crate::pomodoro_skip_phase(app_state())?)?,
+        IpcRequest::IsDomainBlocked { domain } => to_value(crate::is_domain_blocked(app_state(), domain)?)?,
+        IpcRequest::GetFocusStats { days } => to_value(crate::get_focus_stats(app_state(), days)?)?,
+        IpcRequest::GetRecentBlocks { limit } => to_value(crate::get_recent_blocks(app_state(), limit)?)?,
+        IpcRequest::GetExtensionBlocklist => to_value(crate::extension::extension_blocklist(state))?,
+        IpcRequest::ReportVisit { url, seconds } => {
+            to_value(crate::extension::handle_visit(state, &crate::extension::VisitReport { url, seconds }))?
+        }
+    };
+    Ok(data)
+}
+
+/// The socket the running app listens on, found without a Tauri handle: Tauri puts the app data
+/// directory under the platform data directory, named after the app identifier.
+#[cfg(unix)]
+fn socket_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    #[cfg(target_os = "macos")]
+    let data_dir = home.map(|home| home.join("Library/Application Support"));
+    #[cfg(not(target_os = "macos"))]
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home.map(|home| home.join(".local/share")));
+    Some(data_dir?.join(APP_IDENTIFIER).join("bastion.sock"))
+}
+
+/// Send one request to the running app and return its `data`, or the error message.
+pub fn send_request(request: &serde_json::Value) -> Result<serde_json::Value, String> {
+    #[cfg(unix)]
+    let stream = {
+        let path = socket_path().ok_or("No home directory")?;
+        std::os::unix::net::UnixStream::connect(path).map_err(|_| "Bastion isn't running".to_string())?
+    };
+    #[cfg(target_os = "windows")]
+    let stream = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(PIPE_NAME)
+        .map_err(|_| "Bastion isn't running".to_string())?;
+
+    let mut line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    line.push('\n');
+    (&stream).write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+    let mut response = String::new();
+    std::io::BufReader::new(&stream).read_line(&mut response).map_err(|e| e.to_string())?;
+
+    let response: serde_json::Value = serde_json::from_str(&response).map_err(|e| e.to_string())?;
+    if response["ok"].as_bool() == Some(true) {
+        Ok(response["data"].clone())
+    } else {
+        Err(response["error"]["message"].as_str().unwrap_or("Request failed").to_string())
+    }
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<serde_json::Value, CommandError> {
+    Ok(serde_json::to_value(value)?)
+}
@@ -0,0 +1,106 @@
+//! Leaderboard module - Opt-in weekly focus totals shared with friends
+//!
+//! When enabled, Bastion publishes one number per week (focus minutes since Monday) under a
+//! random id and display name to the user's own endpoint, and fetches the totals of the
+//! friend ids they added. Nothing else (sites, apps, sessions) leaves the machine.
+//!
+//! Endpoint protocol:
+//! - `POST <endpoint>` with `{"id", "name", "week_start", "focus_minutes"}`
+//! - `GET <endpoint>?week_start=YYYY-MM-DD&ids=a,b` returning `[{"id", "name", "focus_minutes"}]`
+
+use crate::storage::Database;
+use serde::{Deserialize, Serialize};
+
+pub const LEADERBOARD_SETTING: &str = "leaderboard";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct LeaderboardConfig {
+    pub enabled: bool,
+    pub endpoint: String,
+    pub display_name: String,
+    /// Random id this install publishes under; share it with friends. Generated on first enable.
+    #[serde(default)]
+    pub user_id: String,
+    #[serde(default)]
+    pub friend_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub id: String,
+    pub name: String,
+    pub focus_minutes: i64,
+    #[serde(default)]
+    pub is_me: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub week_start: String,
+    pub entries: Vec<LeaderboardEntry>, // most focus first
+}
+
+pub fn load_config(db: &Database) -> LeaderboardConfig {
+    db.get_setting(LEADERBOARD_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn new_user_id() -> String {
+    crate::security::to_hex(&rand::random::<[u8; 8]>())
+}
+
+/// Publish this week's total, then fetch friends' totals for the same week.
+pub async fn sync(db: &Database) -> Result<Leaderboard, String> {
+    let config = load_config(db);
+    if !config.enabled {
+        return Err("Leaderboard sync is disabled".to_string());
+    }
+    let week = db.get_week_comparison().map_err(|e| e.to_string())?.current;
+    let me = LeaderboardEntry {
+        id: config.user_id.clone(),
+        name: config.display_name.clone(),
+        focus_minutes: week.focus_minutes,
+        is_me: true,
+    };
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(&config.endpoint)
+        .json(&serde_json::json!({
+            "id": me.id,
+            "name": me.name,
+            "week_start": week.week_start,
+            "focus_minutes": me.focus_minutes,
+        }))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Leaderboard rejected the weekly total: HTTP {}", response.status()));
+    }
+
+    let mut entries = vec![me];
+    if !config.friend_ids.is_empty() {
+        let response = client
+            .get(&config.endpoint)
+            .query(&[("week_start", week.week_start.clone()), ("ids", config.friend_ids.join(","))])
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("Could not fetch friends' totals: HTTP {}", response.status()));
+        }
+        let friends: Vec<LeaderboardEntry> = response.json().await.map_err(|e| e.to_string())?;
+        entries.extend(
+            friends
+                .into_iter()
+                .filter(|f| f.id != config.user_id && config.friend_ids.contains(&f.id))
+                .map(|f| LeaderboardEntry { is_me: false, ..f }),
+        );
+    }
+    entries.sort_by_key(|e| std::cmp::Reverse(e.focus_minutes));
+
+    Ok(Leaderboard { week_start: week.week_start, entries })
+}
@@ -1,11 +1,42 @@
 mod blocking;
+mod blocklists;
+mod companion;
+mod diagnostics;
+mod dns;
+mod error;
+mod eventlog;
+mod extension;
+mod foreground;
+mod health;
+mod hooks;
+mod idle;
+mod integrations;
+mod ipc;
+mod leaderboard;
+mod macos;
+mod microbreak;
+mod network;
+mod notes;
+mod plugins;
+mod recovery;
+mod security;
 mod session;
 mod storage;
+mod streaks;
 mod server;
+mod summary;
+mod telemetry;
+mod tls;
+mod triggers;
+mod usage;
 
 use blocking::{RunningProcess, InstalledApp};
-use session::{ActiveSession, PomodoroState, SessionManager};
-use storage::{BlockedApp, BlockedSite, BlockEvent, Database, FocusStats, Session};
+use error::{CommandError, ErrorCode};
+use microbreak::{MicroBreakConfig, MicroBreakEngine, MicroBreakEvent, MicroBreakState};
+use session::{ActiveSession, BlocklistProfile, MorningRoutine, OverridePolicy, PomodoroPhase, PomodoroState, SessionManager, SessionOptions};
+use summary::{DailySummary, DailySummaryConfig};
+use usage::{ProcessClassification, Productivity, UsageBucket};
+use storage::{AddSiteResult, BlockedApp, BlockedSite, BlockEvent, CategoryBreakdown, Database, DeletedItem, FocusStats, ImportSummary, MicroBreakStats, PomodoroDay, QueuedPush, Quote, Session, SessionBlockSplit, SessionExtension, SessionStats, SiteUsage, AppUsageTotal, StreakFreeze, Task, TrashKind, TrendPoint, WeekComparison};
 
 use std::sync::Arc;
 use tauri::{Manager, State, WebviewWindowBuilder, WebviewUrl};
@@ -23,336 +54,2879 @@ use argon2::{
 pub struct AppState {
     pub db: Database,
     pub session_manager: SessionManager,
+    pub micro_breaks: MicroBreakEngine,
+    pub plugins: plugins::PluginManager,
+    pub unlock_tokens: security::UnlockTokens,
+    pub pairing: companion::Pairing,
+    pub event_log: eventlog::EventLogExporter,
+    pub block_server: server::BlockServerStatus,
+    pub dns_sinkhole: dns::DnsSinkhole,
+    pub triggers: triggers::TriggerMonitor,
+    pub processes: blocking::ProcessMonitor,
+    pub apps: blocking::AppCatalog,
+    pub proxy_guard: network::SettingsGuard,
+    pub dns_guard: network::SettingsGuard,
+    pub last_loop_tick: std::sync::atomic::AtomicI64, // unix time of the background loop's last tick
+    pub demo: bool, // `--demo`: sample data in memory, read-only commands, no enforcement
     pub app_handle: std::sync::Mutex<Option<tauri::AppHandle>>,
 }
 
 // --- Security Commands ---
 
 #[tauri::command]
-fn is_onboarded(state: State<Arc<AppState>>) -> Result<bool, String> {
-    let onboarded = state.db.get_setting("onboarded").map_err(|e| e.to_string())?;
+fn is_onboarded(state: State<Arc<AppState>>) -> Result<bool, CommandError> {
+    let onboarded = state.db.get_setting("onboarded")?;
     Ok(onboarded.map(|v| v == "true").unwrap_or(false))
 }
 
 // ============= Blocked Sites Commands =============
 
 #[tauri::command]
-fn add_blocked_site(state: State<Arc<AppState>>, domain: String, category: String) -> Result<i64, String> {
+fn add_blocked_site(
+    state: State<Arc<AppState>>,
+    domain: String,
+    category: String,
+    scope: Option<blocking::DomainScope>,
+    reenable: Option<bool>,
+) -> Result<AddSiteResult, CommandError> {
     if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
-        return Err("Cannot modify block list during a hardcore session".to_string());
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify block list during a hardcore session"));
     }
-    let id = state.db.add_blocked_site(&domain, &category).map_err(|e| e.to_string())?;
+    let domain = blocking::parse_site_input(&domain)?.rule(scope.unwrap_or_default());
+    let result = state.db.add_blocked_site(&domain, &category, reenable.unwrap_or(false))?;
+    if !matches!(result, AddSiteResult::AlreadyBlocked { .. }) {
+        sync_blocked_websites(&state)?;
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+fn get_blocklist_presets() -> Vec<blocklists::BlocklistPreset> {
+    blocklists::PRESETS.to_vec()
+}
+
+/// Block every site of a built-in preset under its category. Returns how many sites were added.
+#[tauri::command]
+fn apply_blocklist_preset(state: State<Arc<AppState>>, category: String) -> Result<usize, CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify block list during a hardcore session"));
+    }
+    let preset = blocklists::find(&category)
+        .ok_or_else(|| CommandError::new(ErrorCode::InvalidInput, format!("Unknown preset '{}'", category)))?;
+    let added = state.db.add_blocked_sites(preset.domains, preset.category)?;
+    if added > 0 {
+        sync_blocked_websites(&state)?;
+    }
+    Ok(added)
+}
+
+/// The block list (sites and apps, with their categories and enabled flags) as JSON or CSV.
+#[tauri::command]
+fn export_blocklist(state: State<Arc<AppState>>, format: blocklists::BlocklistFormat) -> Result<String, CommandError> {
+    let export = blocklists::BlocklistExport::new(&state.db.get_blocked_sites()?, &state.db.get_blocked_apps()?);
+    export.render(format).map_err(|e| CommandError::new(ErrorCode::Internal, e))
+}
+
+/// Import a file from `export_blocklist`. Entries are merged into the block list, or replace
+/// it if `replace` is set; duplicates and entries that aren't valid sites or apps are skipped.
+#[tauri::command]
+fn import_blocklist(
+    state: State<Arc<AppState>>,
+    contents: String,
+    format: blocklists::BlocklistFormat,
+    replace: Option<bool>,
+) -> Result<ImportSummary, CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify block list during a hardcore session"));
+    }
+    let export = blocklists::BlocklistExport::parse(&contents, format).map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    let modes = [blocking::BLOCK_MODE_HOSTS, blocking::BLOCK_MODE_CLOSE_TAB, blocking::BLOCK_MODE_REDIRECT];
+    let total = export.sites.len() + export.apps.len();
+    let sites: Vec<storage::SiteEntry> = export
+        .sites
+        .into_iter()
+        .filter(|site| modes.contains(&site.mode.as_str()))
+        .filter_map(|site| {
+            let domain = blocking::parse_site_input(&site.domain).ok()?.rule(blocking::DomainScope::Rule);
+            Some(storage::SiteEntry { domain, ..site })
+        })
+        .collect();
+    let apps: Vec<storage::AppEntry> = export
+        .apps
+        .into_iter()
+        .filter(|app| !app.process_name.trim().is_empty())
+        .collect();
+    let invalid = total - sites.len() - apps.len();
+
+    let mut summary = state.db.import_blocklist(&sites, &apps, replace.unwrap_or(false))?;
+    summary.invalid += invalid;
     sync_blocked_websites(&state)?;
-    Ok(id)
+    Ok(summary)
+}
+
+/// Validate what the user typed or pasted into the add-site flow. When `host` and
+/// `registrable` differ, the UI can offer both as the `scope` for `add_blocked_site`.
+#[tauri::command]
+fn parse_site_input(input: String) -> Result<blocking::SiteInput, CommandError> {
+    Ok(blocking::parse_site_input(&input)?)
+}
+
+#[tauri::command]
+fn get_blocked_sites(state: State<Arc<AppState>>) -> Result<Vec<BlockedSite>, CommandError> {
+    state.db.get_blocked_sites().map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn toggle_blocked_site(state: State<Arc<AppState>>, id: i64, enabled: bool) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify block list during a hardcore session"));
+    }
+    check_bedtime_site_lock(&state, id)?;
+    let target = session::UnlockTarget::DisableSite { id };
+    if !enabled {
+        check_temptation_delay(&state, &target)?;
+    }
+    state.db.toggle_blocked_site(id, enabled)?;
+    if !enabled {
+        state.session_manager.take_unlock(&target);
+    }
+    sync_blocked_websites(&state)
+}
+
+#[tauri::command]
+fn delete_blocked_site(state: State<Arc<AppState>>, id: i64) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify block list during a hardcore session"));
+    }
+    check_bedtime_site_lock(&state, id)?;
+    let target = session::UnlockTarget::DisableSite { id };
+    check_temptation_delay(&state, &target)?;
+    state.db.delete_blocked_site(id)?;
+    state.session_manager.take_unlock(&target);
+    sync_blocked_websites(&state)
+}
+
+/// Categories blocked right now whether or not their sites/apps are enabled: entertainment
+/// during bedtime, categories whose schedule is in effect, plus the categories of the
+/// blocklist profiles of all active sessions.
+fn forced_categories(state: &AppState) -> Vec<String> {
+    let mut categories = state.session_manager.blocked_categories();
+    categories.extend(state.session_manager.scheduled_categories());
+    if state.session_manager.is_bedtime_active() {
+        categories.push(BEDTIME_CATEGORY.to_string());
+    }
+    categories
+}
+
+/// Domains that are currently blocked: enabled sites, plus sites in `forced_categories`.
+/// Sites blocked with their subdomains are expanded to concrete hosts.
+fn blocked_domains(state: &AppState) -> Result<Vec<String>, CommandError> {
+    let sites = state.db.get_blocked_sites()?;
+    let forced = forced_categories(state);
+    let exclusions = load_dev_exclusions(state);
+    Ok(sites
+        .into_iter()
+        .filter(|s| s.enabled || forced.contains(&s.category))
+        .filter(|s| s.mode == blocking::BLOCK_MODE_HOSTS)
+        .flat_map(|s| if s.include_subdomains { blocking::expand_subdomains(&s.domain) } else { vec![s.domain] })
+        .filter(|domain| !blocking::is_domain_blocked(domain, &exclusions.domains))
+        .collect())
+}
+
+/// Log a block event, attributed to the active session if there is one.
+fn log_block_event(state: &AppState, target: &str, target_type: &str) {
+    let session_id = state.session_manager.active_session_id();
+    let _ = state.db.log_block_event(target, target_type, session_id.as_deref());
+}
+
+/// Active soft-block rules, enforced per tab by the browser extension instead of the hosts file.
+fn soft_blocked_sites(state: &AppState) -> Result<Vec<BlockedSite>, CommandError> {
+    let sites = state.db.get_blocked_sites()?;
+    let forced = forced_categories(state);
+    Ok(sites
+        .into_iter()
+        .filter(|s| s.enabled || forced.contains(&s.category))
+        .filter(|s| s.mode != blocking::BLOCK_MODE_HOSTS)
+        .collect())
+}
+
+/// Switch a site between hosts-file blocking and soft blocking by the extension.
+#[tauri::command]
+fn set_blocked_site_mode(state: State<Arc<AppState>>, id: i64, mode: String) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify block list during a hardcore session"));
+    }
+    if ![blocking::BLOCK_MODE_HOSTS, blocking::BLOCK_MODE_CLOSE_TAB, blocking::BLOCK_MODE_REDIRECT].contains(&mode.as_str()) {
+        return Err(CommandError::new(ErrorCode::InvalidInput, format!("Unknown block mode '{}'", mode)));
+    }
+    check_bedtime_site_lock(&state, id)?;
+    // The other modes rely on the browser extension, which is easier to get around
+    let target = session::UnlockTarget::DisableSite { id };
+    let loosened = mode != blocking::BLOCK_MODE_HOSTS;
+    if loosened {
+        check_temptation_delay(&state, &target)?;
+    }
+    state.db.set_blocked_site_mode(id, &mode)?;
+    if loosened {
+        state.session_manager.take_unlock(&target);
+    }
+    sync_blocked_websites(&state)
+}
+
+/// Also block the site's known subdomains (m., old., ...), not just the domain and www.
+#[tauri::command]
+fn set_blocked_site_subdomains(state: State<Arc<AppState>>, id: i64, include_subdomains: bool) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify block list during a hardcore session"));
+    }
+    check_bedtime_site_lock(&state, id)?;
+    state.db.set_blocked_site_subdomains(id, include_subdomains)?;
+    sync_blocked_websites(&state)
+}
+
+fn load_dev_exclusions(state: &AppState) -> blocking::DevExclusions {
+    state.db.get_setting(blocking::DEV_EXCLUSIONS_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+// ============= Allowed Sites Commands =============
+
+#[tauri::command]
+fn get_allowed_sites(state: State<Arc<AppState>>) -> Result<Vec<storage::AllowedSite>, CommandError> {
+    state.db.get_allowed_sites().map_err(CommandError::from)
+}
+
+/// Allow a site (and its subdomains) during allowlist-only sessions. Locked during hardcore sessions.
+#[tauri::command]
+fn add_allowed_site(state: State<Arc<AppState>>, domain: String) -> Result<i64, CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify the allowlist during a hardcore session"));
+    }
+    let site = blocking::parse_site_input(&domain)?;
+    state.db.add_allowed_site(&site.host).map_err(CommandError::from)
+}
+
+/// Removing a site only narrows the allowlist, so it is allowed during hardcore sessions.
+#[tauri::command]
+fn delete_allowed_site(state: State<Arc<AppState>>, id: i64) -> Result<(), CommandError> {
+    state.db.delete_allowed_site(id).map_err(CommandError::from)
+}
+
+/// Whether an allowlist-only session is running and `url` isn't on the allowlist.
+fn is_blocked_by_allowlist(state: &AppState, url: &str) -> bool {
+    if !state.session_manager.is_allowlist_active() {
+        return false;
+    }
+    let allowed: Vec<String> = state.db.get_allowed_sites()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|site| site.domain)
+        .collect();
+    !blocking::is_allowed_by_allowlist(url, &allowed)
+}
+
+#[tauri::command]
+fn get_dev_exclusions(state: State<Arc<AppState>>) -> blocking::DevExclusions {
+    load_dev_exclusions(&state)
+}
+
+/// Domains that are never blocked and ports the block server leaves free for local servers.
+/// Port changes take effect the next time the block server starts.
+#[tauri::command]
+fn set_dev_exclusions(state: State<Arc<AppState>>, exclusions: blocking::DevExclusions) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify exclusions during a hardcore session"));
+    }
+    let exclusions = blocking::DevExclusions {
+        domains: exclusions.domains.iter().map(|d| blocking::normalize_domain(d)).filter(|d| !d.is_empty()).collect(),
+        ports: exclusions.ports,
+    };
+    let json = serde_json::to_string(&exclusions)?;
+    state.db.set_setting(blocking::DEV_EXCLUSIONS_SETTING, &json)?;
+    sync_blocked_websites(&state)
+}
+
+/// Check a domain (or URL) against the same rules that are written to the hosts file.
+#[tauri::command]
+fn is_domain_blocked(state: State<Arc<AppState>>, domain: String) -> Result<bool, CommandError> {
+    Ok(blocking::is_domain_blocked(&domain, &blocked_domains(&state)?) || is_blocked_by_allowlist(&state, &domain))
+}
+
+// ============= Browser Extension Commands =============
+
+/// Token to paste into the companion extension.
+#[tauri::command]
+fn get_extension_token(state: State<Arc<AppState>>) -> Result<String, CommandError> {
+    Ok(extension::get_or_create_token(&state.db)?)
+}
+
+#[tauri::command]
+fn regenerate_extension_token(state: State<Arc<AppState>>) -> Result<String, CommandError> {
+    Ok(extension::regenerate_token(&state.db)?)
+}
+
+/// Register Bastion as the extension's native messaging host with the installed browsers, so the
+/// extension can connect without a token. Returns the browsers it was registered with.
+#[tauri::command]
+fn install_native_messaging_host(app: tauri::AppHandle) -> Result<Vec<String>, CommandError> {
+    let data_dir = app.path().app_data_dir().map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))?;
+    extension::install_native_host(&data_dir).map_err(|e| CommandError::new(ErrorCode::Internal, e))
+}
+
+/// Whether the companion extension is force-installed through browser policies.
+#[tauri::command]
+fn get_extension_strict_mode(state: State<Arc<AppState>>) -> bool {
+    is_setting_enabled(&state, extension::STRICT_MODE_SETTING)
+}
+
+/// Turn strict mode on (write the force-install policies) or off (remove them).
+/// Strict mode can't be turned off during a hardcore session.
+#[tauri::command]
+fn set_extension_strict_mode(state: State<Arc<AppState>>, enabled: bool) -> Result<(), CommandError> {
+    if enabled {
+        blocking::force_install_extension()?;
+    } else {
+        if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot disable strict mode during a hardcore session"));
+        }
+        blocking::remove_extension_policies()?;
+    }
+    state.db.set_setting(extension::STRICT_MODE_SETTING, if enabled { "true" } else { "false" }).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_extension_policy_status() -> Vec<blocking::ExtensionPolicyStatus> {
+    blocking::extension_policy_status()
+}
+
+/// Daily limits in minutes per domain, enforced through the extension.
+#[tauri::command]
+fn get_site_time_limits(state: State<Arc<AppState>>) -> std::collections::HashMap<String, i64> {
+    extension::get_site_limits(&state.db)
+}
+
+/// Set (or with `None`, remove) the daily limit for a domain.
+#[tauri::command]
+fn set_site_time_limit(state: State<Arc<AppState>>, domain: String, minutes: Option<i64>) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify site limits during a hardcore session"));
+    }
+    let domain = blocking::normalize_domain(&domain);
+    let domain = domain.strip_prefix("www.").unwrap_or(&domain).to_string();
+
+    let mut limits = extension::get_site_limits(&state.db);
+    match minutes {
+        Some(minutes) => limits.insert(domain, minutes.max(0)),
+        None => limits.remove(&domain),
+    };
+    let json = serde_json::to_string(&limits)?;
+    state.db.set_setting(extension::SITE_LIMITS_SETTING, &json).map_err(CommandError::from)
+}
+
+/// YouTube sections blocked at all times; blocklist profiles can block more during a session.
+#[tauri::command]
+fn get_youtube_rules(state: State<Arc<AppState>>) -> extension::YouTubeRules {
+    extension::get_youtube_rules(&state.db)
+}
+
+#[tauri::command]
+fn set_youtube_rules(state: State<Arc<AppState>>, rules: extension::YouTubeRules) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify YouTube rules during a hardcore session"));
+    }
+    let json = serde_json::to_string(&rules)?;
+    state.db.set_setting(extension::YOUTUBE_RULES_SETTING, &json).map_err(CommandError::from)
+}
+
+/// Domains the hosts file should contain but couldn't be written for lack of privileges
+/// (JSON list). Cleared by the next successful sync.
+const PENDING_HOSTS_SYNC_SETTING: &str = "pending_hosts_sync";
+
+fn load_blocking_backend(state: &AppState) -> blocking::BlockingBackend {
+    state.db.get_setting(blocking::BLOCKING_BACKEND_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+#[tracing::instrument(skip(state))]
+fn sync_blocked_websites(state: &AppState) -> Result<(), CommandError> {
+    let enabled_domains = blocked_domains(state)?;
+    let backend = load_blocking_backend(state);
+    if backend != blocking::BlockingBackend::Hosts {
+        return sync_alternate_backend(state, backend, enabled_domains);
+    }
+    let drifted = has_hosts_drift(state);
+    
+    // Try to update hosts file, but don't fail if we don't have admin privileges
+    match blocking::update_blocked_websites(&enabled_domains) {
+        Ok(()) => {
+            println!("[Bastion] Hosts file updated with {} domains", enabled_domains.len());
+            if drifted {
+                eprintln!("[Bastion] Repaired hosts section modified outside Bastion");
+                log_block_event(state, "Hosts file", "repair");
+            }
+            match blocking::hosts_section() {
+                Some(section) => state.db.set_setting(HOSTS_CHECKSUM_SETTING, &blocking::section_checksum(&section))?,
+                None => state.db.delete_setting(HOSTS_CHECKSUM_SETTING)?,
+            }
+            state.block_server.state_changed();
+            let _ = state.db.delete_setting(PENDING_HOSTS_SYNC_SETTING);
+            // Keep browsers from resolving blocked sites over DoH (macOS; a no-op elsewhere)
+            if let Err(e) = macos::apply_pf_rules(!enabled_domains.is_empty()) {
+                eprintln!("[Bastion] Could not update pf rules: {}", e.message);
+            }
+            // Flush DNS to make changes immediate
+            let _ = blocking::flush_dns();
+        },
+        Err(e) => {
+            // Log the error but don't fail - database is still updated
+            eprintln!("[Bastion] Could not update hosts file (need admin?): {}", e.message);
+            // Queue the change; the background loop retries once we have the rights
+            if e.permission_denied {
+                let json = serde_json::to_string(&enabled_domains)?;
+                state.db.set_setting(PENDING_HOSTS_SYNC_SETTING, &json)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Hand the blocked domains to the DNS sinkhole or the PAC file, and take Bastion's entries out
+/// of the hosts file if it can be written (so switching backends doesn't leave both in place).
+fn sync_alternate_backend(state: &AppState, backend: blocking::BlockingBackend, domains: Vec<String>) -> Result<(), CommandError> {
+    println!("[Bastion] {:?} blocking updated with {} domains", backend, domains.len());
+    // The sinkhole is bypassed by DoH just like the hosts file; a PAC file isn't
+    let doh_rules = backend == blocking::BlockingBackend::Dns && !domains.is_empty();
+    if let Err(e) = macos::apply_pf_rules(doh_rules) {
+        eprintln!("[Bastion] Could not update pf rules: {}", e.message);
+    }
+    match backend {
+        blocking::BlockingBackend::Dns => state.dns_sinkhole.set_domains(domains),
+        _ => state.block_server.set_pac_domains(Some(domains)),
+    }
+    if blocking::hosts_section().is_some() && blocking::update_blocked_websites(&[]).is_ok() {
+        state.db.delete_setting(HOSTS_CHECKSUM_SETTING)?;
+    }
+    let _ = state.db.delete_setting(PENDING_HOSTS_SYNC_SETTING);
+    state.block_server.state_changed();
+    let _ = blocking::flush_dns();
+    Ok(())
+}
+
+/// Checksum of the hosts section Bastion last wrote; absent when it wrote none
+const HOSTS_CHECKSUM_SETTING: &str = "hosts_section_checksum";
+
+/// Whether the Bastion section was changed or removed since Bastion wrote it.
+fn has_hosts_drift(state: &AppState) -> bool {
+    let Some(expected) = state.db.get_setting(HOSTS_CHECKSUM_SETTING).unwrap_or(None) else {
+        return false;
+    };
+    blocking::hosts_section().map(|section| blocking::section_checksum(&section)) != Some(expected)
+}
+
+/// Rewrite the hosts section if another tool changed it. Returns true if it was repaired.
+fn repair_hosts_drift(state: &AppState) -> bool {
+    if !has_hosts_drift(state) {
+        return false;
+    }
+    let _ = sync_blocked_websites(state);
+    !has_hosts_drift(state)
+}
+
+fn is_hosts_sync_pending(state: &AppState) -> bool {
+    state.db.get_setting(PENDING_HOSTS_SYNC_SETTING).unwrap_or(None).is_some()
+}
+
+/// Apply a queued hosts update once the hosts file is writable (e.g. after relaunching elevated).
+/// The blocklist is re-read from the database, so later changes are included.
+fn retry_pending_hosts_sync(state: &AppState) {
+    if !is_hosts_sync_pending(state) || !blocking::is_admin() {
+        return;
+    }
+    let _ = sync_blocked_websites(state);
+    if !is_hosts_sync_pending(state) {
+        println!("[Bastion] Applied queued hosts file update");
+        if let Some(handle) = state.app_handle.lock().unwrap().as_ref() {
+            let _ = handle.emit("hosts-sync-retried", ());
+        }
+    }
+}
+
+/// Emergency cleanup: take everything Bastion wrote out of the hosts file, leaving the original
+/// entries plus the user's own edits since. The blocks come back with the next sync.
+#[tauri::command]
+fn restore_hosts_file(app: tauri::AppHandle, state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot restore the hosts file during a hardcore session"));
+    }
+    let target = session::UnlockTarget::RestoreHosts;
+    check_temptation_delay(&state, &target)?;
+    let data_dir = app.path().app_data_dir().map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))?;
+    let domains: Vec<String> = state.db.get_blocked_sites()?.into_iter().map(|s| s.domain).collect();
+    blocking::restore_hosts(&data_dir, &domains)?;
+    state.session_manager.take_unlock(&target);
+    state.db.delete_setting(HOSTS_CHECKSUM_SETTING)?;
+    state.db.delete_setting(PENDING_HOSTS_SYNC_SETTING)?;
+    let _ = blocking::flush_dns();
+    state.block_server.state_changed();
+    Ok(())
+}
+
+/// Category of sites/apps that bedtime schedules block, whether or not they are enabled.
+const BEDTIME_CATEGORY: &str = "entertainment";
+
+/// Bedtime entries can't be unblocked once the cut-off time has passed.
+fn check_bedtime_lock(state: &AppState, category: &str) -> Result<(), CommandError> {
+    if state.session_manager.is_bedtime_active() && category == BEDTIME_CATEGORY {
+        return Err(CommandError::new(ErrorCode::BedtimeLocked, "Cannot modify entertainment blocks during bedtime"));
+    }
+    Ok(())
+}
+
+fn check_bedtime_site_lock(state: &AppState, id: i64) -> Result<(), CommandError> {
+    let sites = state.db.get_blocked_sites()?;
+    match sites.into_iter().find(|s| s.id == id) {
+        Some(site) => check_bedtime_lock(state, &site.category),
+        None => Ok(()),
+    }
+}
+
+fn check_bedtime_app_lock(state: &AppState, id: i64) -> Result<(), CommandError> {
+    let apps = state.db.get_blocked_apps()?;
+    match apps.into_iter().find(|a| a.id == id) {
+        Some(app) => check_bedtime_lock(state, &app.category),
+        None => Ok(()),
+    }
+}
+
+// ============= Mobile Companion Commands =============
+
+/// Turning it off closes the companion port; paired devices are kept for when it's back on.
+#[tauri::command]
+fn set_companion_enabled(state: State<Arc<AppState>>, enabled: bool) -> Result<(), CommandError> {
+    state.db.set_setting(companion::COMPANION_ENABLED_SETTING, if enabled { "true" } else { "false" })?;
+    if !enabled {
+        state.pairing.cancel();
+    }
+    state.pairing.state_changed();
+    Ok(())
+}
+
+/// New single-use pairing code; the frontend renders `payload` as a QR code.
+#[tauri::command]
+fn start_companion_pairing(state: State<Arc<AppState>>) -> Result<companion::PairingOffer, CommandError> {
+    if !companion::is_enabled(&state.db) {
+        return Err(CommandError::new(ErrorCode::InvalidState, "Turn on the mobile companion before pairing"));
+    }
+    Ok(state.pairing.start())
+}
+
+#[tauri::command]
+fn cancel_companion_pairing(state: State<Arc<AppState>>) {
+    state.pairing.cancel();
+}
+
+/// Accept the confirmation a phone sends after scanning the QR code. Emits `companion-paired`.
+#[tauri::command]
+fn confirm_companion_pairing(
+    state: State<Arc<AppState>>,
+    confirmation: companion::PairingConfirmation,
+) -> Result<companion::PairingResult, CommandError> {
+    companion::confirm_pairing(&state, &confirmation)
+}
+
+#[tauri::command]
+fn get_companion_devices(state: State<Arc<AppState>>) -> Vec<companion::PairedDevice> {
+    companion::paired_devices(&state.db)
+}
+
+#[tauri::command]
+fn unpair_companion_device(state: State<Arc<AppState>>, device_id: String) -> Result<bool, CommandError> {
+    let removed = companion::unpair(&state.db, &device_id)?;
+    state.pairing.state_changed();
+    Ok(removed)
+}
+
+// ============= Blocked Apps Commands =============
+
+#[tauri::command]
+fn add_blocked_app(state: State<Arc<AppState>>, name: String, process_name: String, category: String) -> Result<i64, CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify block list during a hardcore session"));
+    }
+    state.db.add_blocked_app(&name, &process_name, &category).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_blocked_apps(state: State<Arc<AppState>>) -> Result<Vec<BlockedApp>, CommandError> {
+    state.db.get_blocked_apps().map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn toggle_blocked_app(state: State<Arc<AppState>>, id: i64, enabled: bool) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify block list during a hardcore session"));
+    }
+    check_bedtime_app_lock(&state, id)?;
+    state.db.toggle_blocked_app(id, enabled).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn delete_blocked_app(state: State<Arc<AppState>>, id: i64) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify block list during a hardcore session"));
+    }
+    check_bedtime_app_lock(&state, id)?;
+    state.db.delete_blocked_app(id).map_err(CommandError::from)
+}
+
+/// Installed applications merged with running ones. Served from the startup-warmed cache
+/// unless `refresh` is set.
+#[tauri::command]
+async fn get_installed_applications(state: State<'_, Arc<AppState>>, refresh: Option<bool>) -> Result<Vec<InstalledApp>, CommandError> {
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let installed = state.apps.installed(refresh.unwrap_or(false));
+        blocking::merge_running_apps(installed, &blocking::get_running_processes(&state.processes))
+    })
+    .await
+    .map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))
+}
+
+#[tauri::command]
+fn get_running_processes(state: State<Arc<AppState>>) -> Vec<RunningProcess> {
+    blocking::get_running_processes(&state.processes)
+}
+
+/// Fuzzy-search running, installed and already blocked apps for the app picker, best match first.
+#[tauri::command]
+async fn search_apps(state: State<'_, Arc<AppState>>, query: String, limit: Option<usize>) -> Result<Vec<blocking::AppMatch>, CommandError> {
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let blocked = state.db.get_blocked_apps()?;
+        let installed = state.apps.installed(false);
+        let running = blocking::get_running_processes(&state.processes);
+        Ok::<_, CommandError>(blocking::search_apps(&query, &blocked, installed, &running, limit.unwrap_or(50)))
+    })
+    .await
+    .map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))?
+}
+
+/// PNG data URL of an executable's icon for the app picker; `None` when it can't be extracted.
+#[tauri::command]
+async fn get_process_icon(exe_path: String) -> Result<Option<String>, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || blocking::get_process_icon(&exe_path))
+        .await
+        .map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))
+}
+
+#[tauri::command]
+fn enforce_app_blocks(state: State<Arc<AppState>>) -> Result<Vec<blocking::AppEnforcement>, CommandError> {
+    let apps = state.db.get_blocked_apps()?;
+    let mut blocked_process_names: Vec<String> = apps
+        .into_iter()
+        .filter(|a| a.enabled)
+        .map(|a| a.process_name)
+        .collect();
+    blocked_process_names.extend(blocked_installers(&state));
+    
+    let results = blocking::enforce_app_blocks(&state.processes, &blocked_process_names, state.session_manager.is_extreme_active());
+    
+    // Log block events
+    for result in results.iter().filter(|r| !r.killed.is_empty()) {
+        log_block_event(&state, &result.name, "app");
+    }
+    
+    Ok(results)
+}
+
+#[tauri::command]
+fn get_app_enforcement_config(state: State<Arc<AppState>>) -> blocking::AppEnforcementConfig {
+    load_app_enforcement_config(&state)
+}
+
+/// Close blocked apps outside sessions too.
+#[tauri::command]
+fn start_app_enforcement(state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    let config = blocking::AppEnforcementConfig { enabled: true, ..load_app_enforcement_config(&state) };
+    save_app_enforcement_config(&state, &config)?;
+    enforce_blocked_apps(&state);
+    Ok(())
+}
+
+/// Stop closing blocked apps outside sessions. Refused while blocking is required.
+#[tauri::command]
+fn stop_app_enforcement(state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    if is_app_enforcement_required(&state) {
+        return Err(CommandError::new(ErrorCode::InvalidState, "App blocking can't be stopped during a session"));
+    }
+    let config = blocking::AppEnforcementConfig { enabled: false, ..load_app_enforcement_config(&state) };
+    save_app_enforcement_config(&state, &config)
+}
+
+/// Seconds between two checks for blocked apps. Can't be lengthened during a hardcore session.
+#[tauri::command]
+fn set_app_enforcement_interval(state: State<Arc<AppState>>, seconds: u64) -> Result<(), CommandError> {
+    let current = load_app_enforcement_config(&state);
+    if seconds > current.interval_seconds && state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot slow down app blocking during a hardcore session"));
+    }
+    let config = blocking::AppEnforcementConfig { interval_seconds: seconds, ..current };
+    config.validate().map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    save_app_enforcement_config(&state, &config)
+}
+
+fn load_app_enforcement_config(state: &AppState) -> blocking::AppEnforcementConfig {
+    state.db.get_setting(blocking::APP_ENFORCEMENT_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_app_enforcement_config(state: &AppState, config: &blocking::AppEnforcementConfig) -> Result<(), CommandError> {
+    let json = serde_json::to_string(config)?;
+    state.db.set_setting(blocking::APP_ENFORCEMENT_SETTING, &json).map_err(CommandError::from)
+}
+
+/// Whether blocked apps must be closed whatever the config says: during sessions, bedtime
+/// and category schedules.
+fn is_app_enforcement_required(state: &AppState) -> bool {
+    state.session_manager.get_time_remaining().is_some() || !forced_categories(state).is_empty()
+}
+
+#[tauri::command]
+fn reset_all_blocks(state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify block list during a hardcore session"));
+    }
+    // Entertainment blocks can't be removed after the cut-off, so neither can everything
+    if state.session_manager.is_bedtime_active() {
+        return Err(CommandError::new(ErrorCode::BedtimeLocked, "Cannot reset blocks during bedtime"));
+    }
+    let target = session::UnlockTarget::ResetBlocks;
+    check_temptation_delay(&state, &target)?;
+    let apps = state.db.get_blocked_apps()?;
+    for app in apps {
+        state.db.delete_blocked_app(app.id)?;
+    }
+    let sites = state.db.get_blocked_sites()?;
+    for site in sites {
+        state.db.delete_blocked_site(site.id)?;
+    }
+    state.session_manager.take_unlock(&target);
+    // Through the active backend, so the sinkhole/PAC lists and the hosts checksum follow
+    sync_blocked_websites(&state)
+}
+
+// --- Session Commands ---
+
+#[tauri::command]
+fn add_session(state: State<Arc<AppState>>, session: Session) -> Result<i64, CommandError> {
+    state.db.add_session(&session).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_sessions(state: State<Arc<AppState>>) -> Result<Vec<Session>, CommandError> {
+    state.db.get_sessions().map_err(CommandError::from)
+}
+
+/// Edit or enable/disable a schedule. Hardcore schedules are locked shortly before they start.
+#[tauri::command]
+fn update_session(state: State<Arc<AppState>>, session: Session) -> Result<(), CommandError> {
+    check_schedule_lock(&state, session.id)?;
+    let sessions = state.db.get_sessions()?;
+    let stored = sessions.iter().find(|s| s.id == session.id);
+    // Checked against the stored schedule, since the update could change its kind
+    if state.session_manager.is_bedtime_active() && stored.is_some_and(|s| s.kind == session::SCHEDULE_KIND_BEDTIME) {
+        return Err(CommandError::new(ErrorCode::BedtimeLocked, "Cannot change a bedtime schedule after the cut-off time"));
+    }
+    // Otherwise the deletion delay could be skipped by un-hardcoring first
+    let was_hardcore = stored.is_some_and(|s| s.hardcore);
+    if was_hardcore && !session.hardcore {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "A hardcore schedule can't be made normal; delete it instead"));
+    }
+    state.db.update_session(&session).map_err(CommandError::from)
+}
+
+fn schedule_lock_minutes(state: &AppState) -> i64 {
+    state.db.get_setting(session::SCHEDULE_LOCK_SETTING)
+        .unwrap_or(None)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(session::DEFAULT_SCHEDULE_LOCK_MINUTES)
+        .clamp(0, session::MAX_SCHEDULE_LOCK_MINUTES)
+}
+
+/// Reject changes to a hardcore schedule that is running or starts within the lock window.
+fn check_schedule_lock(state: &AppState, id: i64) -> Result<(), CommandError> {
+    let now = chrono::Local::now().naive_local();
+    let window = schedule_lock_minutes(state);
+    let sessions = state.db.get_sessions()?;
+    if sessions.iter().any(|s| s.id == id && session::is_schedule_locked(s, now, window)) {
+        return Err(CommandError::new(
+            ErrorCode::HardcoreLocked,
+            format!("Hardcore schedules can't be changed within {} minutes of their start", window),
+        ));
+    }
+    Ok(())
+}
+
+fn schedule_deletion_delay_hours(state: &AppState) -> i64 {
+    state.db.get_setting(session::SCHEDULE_DELETION_DELAY_SETTING)
+        .unwrap_or(None)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(session::DEFAULT_SCHEDULE_DELETION_DELAY_HOURS)
+}
+
+/// Delete a schedule. Hardcore schedules are only marked for deletion and stay in effect until
+/// the deletion delay has passed; returns the Unix timestamp the deletion takes effect.
+#[tauri::command]
+fn delete_session(state: State<Arc<AppState>>, id: i64) -> Result<Option<i64>, CommandError> {
+    check_schedule_lock(&state, id)?;
+    let sessions = state.db.get_sessions()?;
+    let Some(schedule) = sessions.iter().find(|s| s.id == id) else {
+        return Ok(None);
+    };
+    if state.session_manager.is_bedtime_active() && schedule.kind == session::SCHEDULE_KIND_BEDTIME {
+        return Err(CommandError::new(ErrorCode::BedtimeLocked, "Cannot remove a bedtime schedule after the cut-off time"));
+    }
+    if schedule.hardcore {
+        if schedule.delete_at.is_some() {
+            return Ok(schedule.delete_at);
+        }
+        let delete_at = chrono::Local::now().timestamp() + schedule_deletion_delay_hours(&state) * 3600;
+        state.db.set_session_delete_at(id, Some(delete_at))?;
+        return Ok(Some(delete_at));
+    }
+    state.db.delete_session(id)?;
+    Ok(None)
+}
+
+/// Recently deleted sites, apps and schedules that `undo_delete` can still restore.
+#[tauri::command]
+fn get_deleted_items(state: State<Arc<AppState>>) -> Result<Vec<DeletedItem>, CommandError> {
+    state.db.get_deleted_items().map_err(CommandError::from)
+}
+
+/// Restore a deleted item, or the most recently deleted one when no `kind`/`id` is given.
+/// Restoring only ever adds blocks back, so it is allowed during hardcore sessions.
+#[tauri::command]
+fn undo_delete(state: State<Arc<AppState>>, kind: Option<TrashKind>, id: Option<i64>) -> Result<Option<DeletedItem>, CommandError> {
+    let items = state.db.get_deleted_items()?;
+    let item = match (kind, id) {
+        (Some(kind), Some(id)) => items.into_iter().find(|item| item.kind == kind && item.id == id),
+        _ => items.into_iter().next(),
+    };
+    let Some(item) = item else {
+        return Ok(None);
+    };
+    state.db.undo_delete(item.kind, item.id)?;
+    if item.kind == TrashKind::Site {
+        sync_blocked_websites(&state)?;
+    }
+    Ok(Some(item))
+}
+
+#[tauri::command]
+fn cancel_session_deletion(state: State<Arc<AppState>>, id: i64) -> Result<(), CommandError> {
+    state.db.set_session_delete_at(id, None).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn start_focus_session(
+    state: State<Arc<AppState>>,
+    name: String,
+    duration_minutes: i64,
+    hardcore: bool,
+    blocklist_id: Option<String>,
+    override_policy: Option<OverridePolicy>,
+    allowlist_only: Option<bool>,
+) -> Result<ActiveSession, CommandError> {
+    let override_policy = override_policy.unwrap_or_default();
+    override_policy.validate().map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    if override_policy.needs_password() && !has_master_password(&state)? {
+        return Err(CommandError::new(ErrorCode::InvalidInput, "Set a master password before choosing a password override"));
+    }
+    let allowlist_only = allowlist_only.unwrap_or(false);
+    if allowlist_only && state.db.get_allowed_sites()?.is_empty() {
+        return Err(CommandError::new(ErrorCode::InvalidInput, "Add at least one allowed site before starting an allowlist-only session"));
+    }
+    let blocklist = match blocklist_id {
+        Some(id) => Some(
+            load_blocklist_profiles(&state)
+                .into_iter()
+                .find(|profile| profile.id == id)
+                .ok_or_else(|| CommandError::new(ErrorCode::InvalidInput, format!("Unknown blocklist '{}'", id)))?,
+        ),
+        None => None,
+    };
+    let session = state.session_manager.start_session(name, duration_minutes, SessionOptions {
+        hardcore,
+        extreme: is_extreme_mode_enabled(&state),
+        blocklist,
+        override_policy,
+        allowlist_only,
+    });
+    on_session_started(&state, &session);
+    Ok(session)
+}
+
+#[tauri::command]
+fn get_blocklist_profiles(state: State<Arc<AppState>>) -> Vec<BlocklistProfile> {
+    load_blocklist_profiles(&state)
+}
+
+/// Running sessions keep the profile they started with.
+#[tauri::command]
+fn set_blocklist_profiles(state: State<Arc<AppState>>, profiles: Vec<BlocklistProfile>) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify blocklists during a hardcore session"));
+    }
+    validate_blocklist_profiles(&profiles).map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    let json = serde_json::to_string(&profiles)?;
+    state.db.set_setting(session::BLOCKLIST_PROFILES_SETTING, &json).map_err(CommandError::from)
+}
+
+fn load_blocklist_profiles(state: &AppState) -> Vec<BlocklistProfile> {
+    state.db.get_setting(session::BLOCKLIST_PROFILES_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Ids and names must be set and ids unique (sessions pick a profile by id), and no
+/// category may be blank.
+fn validate_blocklist_profiles(profiles: &[BlocklistProfile]) -> Result<(), String> {
+    let mut ids = std::collections::HashSet::new();
+    for profile in profiles {
+        if profile.id.trim().is_empty() || profile.name.trim().is_empty() {
+            return Err("Blocklists need an id and a name".to_string());
+        }
+        if !ids.insert(profile.id.as_str()) {
+            return Err(format!("Duplicate blocklist id '{}'", profile.id));
+        }
+        if profile.categories.iter().any(|category| category.trim().is_empty()) {
+            return Err(format!("Blocklist '{}' has a blank category", profile.name));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_category_schedules(state: State<Arc<AppState>>) -> Vec<session::CategorySchedule> {
+    load_category_schedules(&state)
+}
+
+/// Replace the category schedules and apply them right away. Locked during hardcore sessions.
+#[tauri::command]
+fn set_category_schedules(state: State<Arc<AppState>>, schedules: Vec<session::CategorySchedule>) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot change category schedules during a hardcore session"));
+    }
+    for schedule in &schedules {
+        schedule.validate().map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    }
+    let json = serde_json::to_string(&schedules)?;
+    state.db.set_setting(session::CATEGORY_SCHEDULES_SETTING, &json)?;
+    if state.session_manager.check_category_schedules(&schedules) {
+        sync_blocked_websites(&state)?;
+        enforce_blocked_apps(&state);
+    }
+    Ok(())
+}
+
+fn load_category_schedules(state: &AppState) -> Vec<session::CategorySchedule> {
+    state.db.get_setting(session::CATEGORY_SCHEDULES_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn load_applied_session_blocks(state: &AppState) -> Vec<session::AppliedSessionBlocks> {
+    state.db.get_setting(session::APPLIED_SESSION_BLOCKS_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_applied_session_blocks(state: &AppState, applied: &[session::AppliedSessionBlocks]) {
+    let result = if applied.is_empty() {
+        state.db.delete_setting(session::APPLIED_SESSION_BLOCKS_SETTING)
+    } else {
+        state.db.set_setting(session::APPLIED_SESSION_BLOCKS_SETTING, &serde_json::to_string(applied).unwrap_or_default())
+    };
+    if let Err(e) = result {
+        eprintln!("[Bastion] Could not record session blocks: {}", e);
+    }
+}
+
+/// Block the sites and apps of a starting session's blocklist profile right away: the hosts
+/// file is rewritten and running apps are closed without waiting for the enforcement loop.
+/// What was added is recorded so it can be reverted even after a crash.
+fn apply_session_blocks(state: &AppState, session: &ActiveSession) {
+    let Some(profile) = &session.blocklist else {
+        return;
+    };
+    let in_profile = |category: &String| profile.categories.contains(category);
+    let domains = state.db.get_blocked_sites()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|s| !s.enabled && in_profile(&s.category) && s.mode == blocking::BLOCK_MODE_HOSTS)
+        .map(|s| s.domain)
+        .collect();
+    let apps = state.db.get_blocked_apps()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|a| !a.enabled && in_profile(&a.category))
+        .map(|a| a.process_name)
+        .collect();
+
+    let mut applied = load_applied_session_blocks(state);
+    applied.retain(|a| a.session_id != session.id);
+    applied.push(session::AppliedSessionBlocks {
+        session_id: session.id.clone(),
+        session_name: session.name.clone(),
+        domains,
+        apps,
+    });
+    save_applied_session_blocks(state, &applied);
+
+    let _ = sync_blocked_websites(state);
+    enforce_blocked_apps(state);
+}
+
+/// Lift the blocks of a session that ended. The hosts file is rebuilt from the block list and
+/// the sessions still running, so sites another session or the always-on list blocks stay blocked.
+fn revert_session_blocks(state: &AppState, session: &ActiveSession) {
+    let mut applied = load_applied_session_blocks(state);
+    let before = applied.len();
+    applied.retain(|a| a.session_id != session.id);
+    if applied.len() != before {
+        save_applied_session_blocks(state, &applied);
+    }
+    if session.blocklist.is_some() || applied.len() != before {
+        let _ = sync_blocked_websites(state);
+    }
+}
+
+/// Persist the schedule windows ended early, so a restart doesn't start them again.
+fn save_ended_windows(state: &AppState) {
+    let json = serde_json::to_string(&state.session_manager.ended_windows()).unwrap_or_default();
+    if let Err(e) = state.db.set_setting(session::ENDED_WINDOWS_SETTING, &json) {
+        eprintln!("[Bastion] Could not record ended schedule windows: {}", e);
+    }
+}
+
+/// Forget and lift blocks recorded for sessions that aren't running anymore, e.g. because
+/// Bastion crashed or was restarted during them. Returns what was reverted.
+fn revert_stale_session_blocks(state: &AppState) -> Vec<session::AppliedSessionBlocks> {
+    let running: Vec<String> = state.session_manager.active_sessions().into_iter().map(|s| s.id).collect();
+    let (stale, kept): (Vec<_>, Vec<_>) = load_applied_session_blocks(state)
+        .into_iter()
+        .partition(|a| !running.contains(&a.session_id));
+    if !stale.is_empty() {
+        save_applied_session_blocks(state, &kept);
+        let _ = sync_blocked_websites(state);
+    }
+    stale
+}
+
+/// Lifecycle hooks for a session that just started (manually, scheduled or by the morning routine).
+fn on_session_started(state: &AppState, session: &ActiveSession) {
+    apply_session_blocks(state, session);
+    state.block_server.state_changed();
+    hooks::run_hooks(&state.db, hooks::HookEvent::SessionStart, session);
+    if session.hardcore {
+        hooks::run_hooks(&state.db, hooks::HookEvent::HardcoreLock, session);
+    }
+}
+
+#[tauri::command]
+fn get_script_hooks(state: State<Arc<AppState>>) -> Vec<hooks::ScriptHook> {
+    hooks::get_hooks(&state.db)
+}
+
+#[tauri::command]
+fn set_script_hooks(state: State<Arc<AppState>>, script_hooks: Vec<hooks::ScriptHook>) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify hooks during a hardcore session"));
+    }
+    let json = serde_json::to_string(&script_hooks)?;
+    state.db.set_setting(hooks::HOOKS_SETTING, &json).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_morning_routine(state: State<Arc<AppState>>) -> Result<MorningRoutine, CommandError> {
+    Ok(load_morning_routine(&state))
+}
+
+#[tauri::command]
+fn set_morning_routine(state: State<Arc<AppState>>, routine: MorningRoutine) -> Result<(), CommandError> {
+    routine.validate().map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    let json = serde_json::to_string(&routine)?;
+    state.db.set_setting("morning_routine", &json).map_err(CommandError::from)
+}
+
+fn load_morning_routine(state: &AppState) -> MorningRoutine {
+    state.db.get_setting("morning_routine")
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Seconds without a background tick after which we treat the machine as resumed/unlocked
+const RESUME_GAP_SECS: i64 = 60;
+
+/// Start the morning routine if it hasn't run yet today. Called when Bastion starts with the
+/// OS session (login) and when the machine resumes from sleep or a lock screen.
+fn maybe_start_morning_routine(state: &AppState) {
+    if state.session_manager.get_time_remaining().is_some() {
+        return;
+    }
+    let routine = load_morning_routine(state);
+    let now = chrono::Local::now().naive_local();
+    let last_run = state.db.get_setting("morning_routine_last_run").unwrap_or(None);
+    if !routine.is_due(now, last_run.as_deref()) {
+        return;
+    }
+
+    let _ = state.db.set_setting("morning_routine_last_run", &now.format("%Y-%m-%d").to_string());
+    let session = state.session_manager.start_session(routine.name, routine.duration_minutes, SessionOptions {
+        hardcore: routine.hardcore,
+        extreme: is_extreme_mode_enabled(state),
+        ..Default::default()
+    });
+    on_session_started(state, &session);
+    if let Some(handle) = state.app_handle.lock().unwrap().as_ref() {
+        let _ = handle.emit("morning-routine-started", session);
+    }
+}
+
+#[tauri::command]
+fn get_session_triggers(state: State<Arc<AppState>>) -> Vec<triggers::SessionTrigger> {
+    load_session_triggers(&state)
+}
+
+#[tauri::command]
+fn set_session_triggers(state: State<Arc<AppState>>, session_triggers: Vec<triggers::SessionTrigger>) -> Result<(), CommandError> {
+    let profiles = load_blocklist_profiles(&state);
+    for trigger in &session_triggers {
+        trigger.validate().map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+        if let Some(id) = trigger.blocklist_id.as_ref().filter(|id| !profiles.iter().any(|profile| &profile.id == *id)) {
+            return Err(CommandError::new(ErrorCode::InvalidInput, format!("Unknown blocklist '{}'", id)));
+        }
+    }
+    let json = serde_json::to_string(&session_triggers)?;
+    state.db.set_setting(triggers::TRIGGERS_SETTING, &json).map_err(CommandError::from)
+}
+
+fn load_session_triggers(state: &AppState) -> Vec<triggers::SessionTrigger> {
+    state.db.get_setting(triggers::TRIGGERS_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Start a session when a process with a trigger starts (see `triggers`).
+fn check_session_triggers(state: &AppState) {
+    let session_triggers = load_session_triggers(state);
+    if session_triggers.iter().all(|t| !t.enabled) {
+        return;
+    }
+    let processes = blocking::get_process_command_lines(&state.processes);
+    let can_start = state.session_manager.get_time_remaining().is_none();
+    let now = chrono::Local::now().timestamp();
+    let Some(trigger) = state.triggers.poll(&session_triggers, &processes, now, can_start) else {
+        return;
+    };
+
+    let blocklist = trigger.blocklist_id.as_ref().and_then(|id| {
+        load_blocklist_profiles(state).into_iter().find(|profile| &profile.id == id)
+    });
+    let session = state.session_manager.start_session(trigger.session_name.clone(), trigger.duration_minutes, SessionOptions {
+        hardcore: trigger.hardcore,
+        extreme: is_extreme_mode_enabled(state),
+        blocklist,
+        ..Default::default()
+    });
+    on_session_started(state, &session);
+    if let Some(handle) = state.app_handle.lock().unwrap().as_ref() {
+        let _ = handle.emit("trigger-session-started", session);
+    }
+}
+
+/// Close running blocked apps: enabled ones, those in `forced_categories` and installers
+/// during hardcore. Kills are logged and reported to the frontend.
+fn enforce_blocked_apps(state: &AppState) {
+    let Ok(apps) = state.db.get_blocked_apps() else {
+        return;
+    };
+    let forced = forced_categories(state);
+    let mut blocked_process_names: Vec<String> = apps
+        .into_iter()
+        .filter(|a| a.enabled || forced.contains(&a.category))
+        .map(|a| a.process_name)
+        .collect();
+    blocked_process_names.extend(blocked_installers(state));
+    
+    let results = blocking::enforce_app_blocks(
+        &state.processes,
+        &blocked_process_names,
+        state.session_manager.is_extreme_active(),
+    );
+    let killed: Vec<String> = results.iter()
+        .filter(|r| !r.killed.is_empty())
+        .map(|r| r.name.clone())
+        .collect();
+    let failed: Vec<&blocking::AppEnforcement> = results.iter()
+        .filter(|r| !r.failures.is_empty())
+        .collect();
+    if !failed.is_empty() {
+        if let Some(handle) = state.app_handle.lock().unwrap().as_ref() {
+            let _ = handle.emit("app-block-failed", &failed);
+        }
+    }
+    
+    // Log block events and notify frontend
+    if !killed.is_empty() {
+        for app in &killed {
+            log_block_event(state, app, "app");
+        }
+        // Emit event to all windows if any apps were killed
+        if let Some(handle) = state.app_handle.lock().unwrap().as_ref() {
+            let _ = handle.emit("blocked-apps", killed);
+        }
+    }
+}
+
+/// App stores and package managers to block right now: the configured list during hardcore
+/// sessions when "block_installers" is on, otherwise nothing.
+fn blocked_installers(state: &AppState) -> Vec<String> {
+    if !state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst)
+        || !is_setting_enabled(state, "block_installers")
+    {
+        return Vec::new();
+    }
+    load_installer_tools(state)
+}
+
+fn load_installer_tools(state: &AppState) -> Vec<String> {
+    state.db.get_setting(blocking::INSTALLER_TOOLS_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_else(|| blocking::DEFAULT_INSTALLER_TOOLS.iter().map(|t| t.to_string()).collect())
+}
+
+#[tauri::command]
+fn get_installer_block_list(state: State<Arc<AppState>>) -> Vec<String> {
+    load_installer_tools(&state)
+}
+
+/// Replace the process names blocked by "block_installers"; `None` restores the defaults.
+#[tauri::command]
+fn set_installer_block_list(state: State<Arc<AppState>>, processes: Option<Vec<String>>) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot modify the installer block list during a hardcore session"));
+    }
+    match processes {
+        Some(processes) => {
+            let json = serde_json::to_string(&processes)?;
+            state.db.set_setting(blocking::INSTALLER_TOOLS_SETTING, &json)?;
+        }
+        None => state.db.delete_setting(blocking::INSTALLER_TOOLS_SETTING)?,
+    }
+    Ok(())
+}
+
+/// Whether the user opted into extreme hardcore (system tools blocked during hardcore sessions).
+fn is_extreme_mode_enabled(state: &AppState) -> bool {
+    is_setting_enabled(state, "extreme_hardcore")
+}
+
+/// Read a boolean ("true"/"false") setting, defaulting to false.
+fn is_setting_enabled(state: &AppState, key: &str) -> bool {
+    state.db.get_setting(key)
+        .unwrap_or(None)
+        .map(|v| v == "true")
+        .unwrap_or(false)
+}
+
+/// Ending a session before its time is up is a bypass: it needs the current unlock friction's
+/// challenge (see `request_unlock_challenge`) and raises the friction for the rest of the week.
+/// Hardcore sessions can only be ended early as their `OverridePolicy` allows; `token` is an
+/// unlock token from `authenticate` for the password policies, used up by the override.
+/// `session_id` picks one of several running sessions; by default the primary one is ended.
+#[tauri::command]
+fn end_focus_session(
+    state: State<Arc<AppState>>,
+    session_id: Option<String>,
+    challenge_response: Option<String>,
+    token: Option<String>,
+) -> Result<(), CommandError> {
+    let Some((target, remaining)) = state.session_manager.get_session(session_id.as_deref()) else {
+        return Err(CommandError::new(ErrorCode::InvalidState, "No active session"));
+    };
+    // Hardcore sessions have their override policy instead
+    let unlock = session::UnlockTarget::EndSession { session_id: target.id.clone() };
+    if remaining > 0 && !target.hardcore {
+        check_temptation_delay(&state, &unlock)?;
+    }
+    let proof = session::OverrideProof {
+        friction: current_unlock_friction(&state)?,
+        password_verified: token.as_deref().is_some_and(|token| state.unlock_tokens.validate(token)),
+        challenge_response: challenge_response.as_deref(),
+        now: chrono::Local::now().timestamp(),
+    };
+    let code = if target.hardcore { ErrorCode::HardcoreLocked } else { ErrorCode::InvalidState };
+    let session = state.session_manager.end_session(Some(&target.id), &proof).map_err(|e| CommandError::new(code, e))?;
+    
+    if remaining > 0 {
+        if target.hardcore && target.override_policy.needs_password() {
+            // One override per authentication
+            state.unlock_tokens.revoke();
+        }
+        state.session_manager.take_unlock(&unlock);
+        // The session is already over; a failed log must not leave its blocks in place
+        if let Err(e) = state.db.log_unlock_event("end_early", Some(&session.id)) {
+            eprintln!("[Bastion] Could not log early end: {}", e);
+        }
+    }
+    on_session_finished(&state, &session);
+    Ok(())
+}
+
+/// Every running session (manual, scheduled, pomodoro), highest precedence first.
+#[tauri::command]
+fn get_active_sessions(state: State<Arc<AppState>>) -> Vec<ActiveSession> {
+    state.session_manager.active_sessions()
+}
+
+fn load_friction_policy(state: &AppState) -> session::FrictionPolicy {
+    state.db.get_setting(session::UNLOCK_FRICTION_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn current_unlock_friction(state: &AppState) -> Result<session::UnlockFriction, CommandError> {
+    let counts = state.db.get_unlock_counts()?;
+    Ok(load_friction_policy(state).friction_for(counts.this_week))
+}
+
+fn pass_unlock_challenge(state: &AppState, response: Option<&str>) -> Result<(), CommandError> {
+    let required = current_unlock_friction(state)?;
+    state.session_manager
+        .pass_unlock_challenge(&required, response, chrono::Local::now().timestamp())
+        .map_err(|e| CommandError::new(ErrorCode::InvalidState, e))
+}
+
+/// Friction the next bypass will face, given this week's bypasses.
+#[tauri::command]
+fn get_unlock_friction(state: State<Arc<AppState>>) -> Result<session::UnlockFriction, CommandError> {
+    current_unlock_friction(&state)
+}
+
+/// Start the wait for ending a session early or an emergency unlock. Asking again restarts it.
+/// With `session_id` (or the primary session) being hardcore, the challenge also covers what
+/// its override policy asks for.
+#[tauri::command]
+fn request_unlock_challenge(state: State<Arc<AppState>>, session_id: Option<String>) -> Result<session::UnlockChallenge, CommandError> {
+    let mut friction = current_unlock_friction(&state)?;
+    if let Some((session, _)) = state.session_manager.get_session(session_id.as_deref()).filter(|(s, _)| s.hardcore) {
+        friction = friction.stricter(&session.override_policy.friction());
+    }
+    Ok(state.session_manager.issue_unlock_challenge(friction, chrono::Local::now().timestamp()))
+}
+
+fn load_temptation_delay(state: &AppState) -> session::TemptationDelay {
+    state.db.get_setting(session::TEMPTATION_DELAY_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// With the temptation delay on, `target` needs an unlock (see `request_unlock`) that has
+/// waited out its delay. Callers use it up with `SessionManager::take_unlock` once they're done.
+fn check_temptation_delay(state: &AppState, target: &session::UnlockTarget) -> Result<(), CommandError> {
+    if !load_temptation_delay(state).enabled {
+        return Ok(());
+    }
+    state.session_manager
+        .check_unlock(target)
+        .map_err(|e| CommandError::new(ErrorCode::UnlockDelay, e))
+}
+
+#[tauri::command]
+fn get_temptation_delay(state: State<Arc<AppState>>) -> session::TemptationDelay {
+    load_temptation_delay(&state)
+}
+
+/// Lengthening the delay applies at once; turning it off or shortening it needs an unlock
+/// (`UnlockTarget::LoosenDelay`) of its own.
+#[tauri::command]
+fn set_temptation_delay(state: State<Arc<AppState>>, config: session::TemptationDelay) -> Result<(), CommandError> {
+    config.validate().map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    let loosened = load_temptation_delay(&state).is_loosened_by(&config);
+    if loosened {
+        check_temptation_delay(&state, &session::UnlockTarget::LoosenDelay)?;
+    }
+    let json = serde_json::to_string(&config)?;
+    state.db.set_setting(session::TEMPTATION_DELAY_SETTING, &json)?;
+    if loosened {
+        state.session_manager.take_unlock(&session::UnlockTarget::LoosenDelay);
+    }
+    Ok(())
+}
+
+/// Start the temptation-delay countdown for `target`; once `poll_unlock_status` reports it ready,
+/// the action can be retried. Asking again for the same target doesn't restart the countdown.
+#[tauri::command]
+fn request_unlock(state: State<Arc<AppState>>, target: session::UnlockTarget) -> Result<session::UnlockStatus, CommandError> {
+    let delay = load_temptation_delay(&state);
+    if !delay.enabled {
+        return Err(CommandError::new(ErrorCode::InvalidState, "The temptation delay is off"));
+    }
+    Ok(state.session_manager.request_unlock(target, delay.delay_seconds))
+}
+
+/// The pending temptation-delay unlock, if any. The countdown runs in the backend, so the UI
+/// only displays it.
+#[tauri::command]
+fn poll_unlock_status(state: State<Arc<AppState>>) -> Option<session::UnlockStatus> {
+    state.session_manager.unlock_status()
+}
+
+#[tauri::command]
+fn cancel_unlock(state: State<Arc<AppState>>) {
+    state.session_manager.cancel_unlock();
+}
+
+#[tauri::command]
+fn get_friction_policy(state: State<Arc<AppState>>) -> session::FrictionPolicy {
+    load_friction_policy(&state)
+}
+
+/// Tightening always applies; loosening or disabling needs a week without bypasses.
+#[tauri::command]
+fn set_friction_policy(state: State<Arc<AppState>>, policy: session::FrictionPolicy) -> Result<(), CommandError> {
+    if load_friction_policy(&state).is_loosened_by(&policy) {
+        require_clean_week(&state)?;
+    }
+    let json = serde_json::to_string(&policy)?;
+    state.db.set_setting(session::UNLOCK_FRICTION_SETTING, &json).map_err(CommandError::from)
+}
+
+/// Restore the default (disabled) policy. Needs a week without bypasses.
+#[tauri::command]
+fn reset_friction_policy(state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    require_clean_week(&state)?;
+    let json = serde_json::to_string(&session::FrictionPolicy::default())?;
+    state.db.set_setting(session::UNLOCK_FRICTION_SETTING, &json).map_err(CommandError::from)
+}
+
+fn require_clean_week(state: &AppState) -> Result<(), CommandError> {
+    let counts = state.db.get_unlock_counts()?;
+    if counts.last_seven_days > 0 {
+        return Err(CommandError::new(
+            ErrorCode::InvalidState,
+            format!("Unlock friction can only be relaxed after a clean week ({} bypasses in the last 7 days)", counts.last_seven_days),
+        ));
+    }
+    Ok(())
+}
+
+/// Add time to the running session. For hardcore sessions the extension can't be taken back.
+#[tauri::command]
+fn extend_session(state: State<Arc<AppState>>, minutes: i64) -> Result<ActiveSession, CommandError> {
+    if !(1..=session::MAX_EXTENSION_MINUTES).contains(&minutes) {
+        return Err(CommandError::new(ErrorCode::InvalidInput, format!("Extension must be between 1 and {} minutes", session::MAX_EXTENSION_MINUTES)));
+    }
+    let session = state.session_manager.extend_session(minutes)
+        .map_err(|e| CommandError::new(ErrorCode::InvalidState, e))?;
+    state.db.log_session_extension(&session.id, &session.name, minutes, session.hardcore)?;
+    if let Some(handle) = state.app_handle.lock().unwrap().as_ref() {
+        let _ = handle.emit("session-extended", &session);
+    }
+    Ok(session)
+}
+
+/// Upgrade the running session to hardcore (one-way).
+#[tauri::command]
+fn harden_session(state: State<Arc<AppState>>) -> Result<ActiveSession, CommandError> {
+    let session = state.session_manager.harden_session(is_extreme_mode_enabled(&state))
+        .map_err(|e| CommandError::new(ErrorCode::InvalidState, e))?;
+    hooks::run_hooks(&state.db, hooks::HookEvent::HardcoreLock, &session);
+    if let Some(handle) = state.app_handle.lock().unwrap().as_ref() {
+        let _ = handle.emit("session-hardened", &session);
+    }
+    Ok(session)
+}
+
+/// Block attempts, app kills and tamper attempts during one session.
+#[tauri::command]
+fn get_session_stats(state: State<Arc<AppState>>, session_id: String) -> Result<SessionStats, CommandError> {
+    state.db.get_session_stats(&session_id).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_session_extensions(state: State<Arc<AppState>>, limit: i32) -> Result<Vec<SessionExtension>, CommandError> {
+    state.db.get_session_extensions(limit).map_err(CommandError::from)
+}
+
+/// Add a session that ended at `ended_at` to the session history.
+fn record_session_history(state: &AppState, session: &ActiveSession, ended_at: i64) {
+    let record = storage::SessionRecord {
+        session_id: session.id.clone(),
+        name: session.name.clone(),
+        session_type: session.session_type.as_str().to_string(),
+        hardcore: session.hardcore,
+        started_at: session.start_time,
+        ended_at: ended_at.min(session.end_time),
+        completed: ended_at >= session.end_time,
+    };
+    if let Err(e) = state.db.record_session(&record) {
+        eprintln!("[Bastion] Could not record session history: {}", e);
+    }
+}
+
+/// Bookkeeping for a session that just ended: user hooks, history, protected time, task
+/// attribution and time tracker export.
+fn on_session_finished(state: &AppState, session: &ActiveSession) {
+    revert_session_blocks(state, session);
+    if session.schedule_id.is_some() {
+        save_ended_windows(state);
+    }
+    hooks::run_hooks(&state.db, hooks::HookEvent::SessionEnd, session);
+
+    let now = chrono::Local::now().timestamp();
+    record_session_history(state, session, now);
+    let duration_secs = (now.min(session.end_time) - session.start_time).max(0);
+    if let Err(e) = state.db.update_protected_time(duration_secs / 60) {
+        eprintln!("[Bastion] Could not record protected time: {}", e);
+    }
+    record_task_focus(state, duration_secs / 60, "session");
+    
+    let mut tags = vec!["bastion".to_string()];
+    if session.hardcore {
+        tags.push("hardcore".to_string());
+    }
+    let entry = integrations::TimeEntry {
+        description: session.name.clone(),
+        start: session.start_time,
+        duration_secs,
+        tags,
+    };
+    if let Err(e) = integrations::queue_time_entry(&state.db, &entry) {
+        eprintln!("[Bastion] Could not queue time tracker export: {}", e);
+    }
+    if let Err(e) = integrations::queue_beeminder_datapoint(&state.db, &session.id, &entry, now >= session.end_time) {
+        eprintln!("[Bastion] Could not queue Beeminder datapoint: {}", e);
+    }
+    if now >= session.end_time {
+        if let Err(e) = integrations::queue_habitica_score(&state.db, integrations::HabiticaEvent::SessionCompleted) {
+            eprintln!("[Bastion] Could not queue Habitica score: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+fn get_session_time_remaining(state: State<Arc<AppState>>) -> Option<i64> {
+    state.session_manager.get_time_remaining()
+}
+
+#[tauri::command]
+fn is_hardcore_locked(state: State<Arc<AppState>>) -> bool {
+    state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+// ============= Pomodoro Commands =============
+
+/// Push the current pomodoro state to the UI as a `pomodoro-tick` event,
+/// so the frontend can render the countdown without polling `pomodoro_get_state`.
+fn emit_pomodoro_state(state: &AppState) {
+    if let Some(handle) = state.app_handle.lock().unwrap().as_ref() {
+        let _ = handle.emit("pomodoro-tick", state.session_manager.get_pomodoro_state());
+    }
+}
+
+#[tauri::command]
+fn get_pomodoro_idle_config(state: State<Arc<AppState>>) -> idle::IdlePauseConfig {
+    load_idle_pause_config(&state)
+}
+
+#[tauri::command]
+fn set_pomodoro_idle_config(state: State<Arc<AppState>>, config: idle::IdlePauseConfig) -> Result<(), CommandError> {
+    let json = serde_json::to_string(&config)?;
+    state.db.set_setting(idle::IDLE_PAUSE_SETTING, &json).map_err(CommandError::from)
+}
+
+fn load_idle_pause_config(state: &AppState) -> idle::IdlePauseConfig {
+    state.db.get_setting(idle::IDLE_PAUSE_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Pause the pomodoro work timer after the configured idle time and resume it on input.
+fn check_pomodoro_idle(state: &AppState) {
+    let config = load_idle_pause_config(state);
+    let pomodoro = state.session_manager.get_pomodoro_state();
+    if !config.enabled || !(pomodoro.is_running || pomodoro.paused_for_idle) {
+        return;
+    }
+    let Some(idle_secs) = idle::idle_seconds() else {
+        return;
+    };
+    let idle_secs = idle_secs as i64;
+    let changed = if idle_secs >= config.idle_minutes * 60 {
+        state.session_manager.pomodoro_idle_pause(idle_secs)
+    } else {
+        state.session_manager.pomodoro_idle_resume()
+    };
+    if changed {
+        emit_pomodoro_state(state);
+    }
+}
+
+#[tauri::command]
+fn pomodoro_start(state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot control timer during a hardcore session"));
+    }
+    state.session_manager.pomodoro_start();
+    emit_pomodoro_state(&state);
+    Ok(())
+}
+
+#[tauri::command]
+fn pomodoro_pause(state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot control timer during a hardcore session"));
+    }
+    if is_break_overlay_locked(&state) {
+        return Err(CommandError::new(ErrorCode::BreakLocked, "Cannot pause a break in strict mode"));
+    }
+    state.session_manager.pomodoro_pause();
+    emit_pomodoro_state(&state);
+    Ok(())
+}
+
+#[tauri::command]
+fn pomodoro_reset(state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot control timer during a hardcore session"));
+    }
+    if is_break_overlay_locked(&state) {
+        return Err(CommandError::new(ErrorCode::BreakLocked, "Cannot reset a break in strict mode"));
+    }
+    state.session_manager.pomodoro_reset();
+    emit_pomodoro_state(&state);
+    Ok(())
+}
+
+#[tauri::command]
+fn pomodoro_skip_phase(state: State<Arc<AppState>>) -> Result<PomodoroPhase, CommandError> {
+    if is_break_overlay_locked(&state) {
+        return Err(CommandError::new(ErrorCode::BreakLocked, "Breaks can't be skipped in strict mode"));
+    }
+    // Hardcore rules are enforced by the SessionManager (breaks may always be skipped)
+    let phase = state.session_manager.pomodoro_skip_phase().map_err(|e| CommandError::new(ErrorCode::HardcoreLocked, e))?;
+    emit_pomodoro_state(&state);
+    sync_break_overlay(&state);
+    Ok(phase)
+}
+
+// ============= Break Overlay =============
+
+const BREAK_OVERLAY_LABEL: &str = "break-overlay";
+const BREAK_OVERLAY_STRICT_SETTING: &str = "break_overlay_strict";
+
+/// Strict mode: the overlay can't be dismissed (or the break skipped/paused) until the break is over.
+fn is_break_overlay_locked(state: &AppState) -> bool {
+    is_setting_enabled(state, BREAK_OVERLAY_STRICT_SETTING)
+        && state.session_manager.get_pomodoro_state().phase != PomodoroPhase::Work
+}
+
+/// Show the full-screen break overlay during pomodoro breaks (if enabled) and close it otherwise.
+fn sync_break_overlay(state: &AppState) {
+    let handle_guard = state.app_handle.lock().unwrap();
+    let Some(handle) = handle_guard.as_ref() else {
+        return;
+    };
+    
+    let on_break = state.session_manager.get_pomodoro_state().phase != PomodoroPhase::Work;
+    let existing = handle.get_webview_window(BREAK_OVERLAY_LABEL);
+    
+    if on_break && is_setting_enabled(state, "break_overlay") {
+        if existing.is_none() {
+            let _ = WebviewWindowBuilder::new(
+                handle,
+                BREAK_OVERLAY_LABEL,
+                WebviewUrl::App("index.html?overlay=break".into())
+            )
+            .title("Break")
+            .fullscreen(true)
+            .always_on_top(true)
+            .decorations(false)
+            .skip_taskbar(true)
+            .build();
+        }
+    } else if let Some(window) = existing {
+        // destroy() skips CloseRequested, which strict mode intercepts
+        let _ = window.destroy();
+    }
+}
+
+#[tauri::command]
+fn close_break_overlay(app: tauri::AppHandle, state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    if is_break_overlay_locked(&state) {
+        return Err(CommandError::new(ErrorCode::BreakLocked, "Breaks can't be dismissed early in strict mode"));
+    }
+    if let Some(window) = app.get_webview_window(BREAK_OVERLAY_LABEL) {
+        window.destroy().map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn pomodoro_get_state(state: State<Arc<AppState>>) -> PomodoroState {
+    state.session_manager.get_pomodoro_state()
+}
+
+#[tauri::command]
+fn pomodoro_configure(state: State<Arc<AppState>>, work: i64, short_break: i64, long_break: i64, sessions: i32) {
+    state.session_manager.pomodoro_configure(work, short_break, long_break, sessions);
+    emit_pomodoro_state(&state);
+}
+
+fn load_pomodoro_presets(state: &AppState) -> Vec<session::PomodoroPreset> {
+    state.db.get_setting(session::POMODORO_PRESETS_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_pomodoro_presets(state: &AppState, presets: &[session::PomodoroPreset]) -> Result<(), CommandError> {
+    let json = serde_json::to_string(presets)?;
+    state.db.set_setting(session::POMODORO_PRESETS_SETTING, &json).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_pomodoro_presets(state: State<Arc<AppState>>) -> Vec<session::PomodoroPreset> {
+    load_pomodoro_presets(&state)
+}
+
+#[tauri::command]
+fn set_pomodoro_presets(state: State<Arc<AppState>>, presets: Vec<session::PomodoroPreset>) -> Result<(), CommandError> {
+    for preset in &presets {
+        preset.validate().map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    }
+    save_pomodoro_presets(&state, &presets)
+}
+
+/// JSON snippet with the current timer setup and, optionally, the custom presets.
+#[tauri::command]
+fn export_pomodoro_config(state: State<Arc<AppState>>, include_presets: bool) -> Result<String, CommandError> {
+    let current = state.session_manager.get_pomodoro_state();
+    let export = session::PomodoroExport {
+        bastion_pomodoro: session::POMODORO_EXPORT_VERSION,
+        config: Some(session::PomodoroPreset::from_state("Shared timer", &current)),
+        presets: if include_presets { load_pomodoro_presets(&state) } else { Vec::new() },
+    };
+    Ok(serde_json::to_string_pretty(&export)?)
+}
+
+/// Import a snippet from `export_pomodoro_config`: its presets are added (replacing presets
+/// with the same name), and its timer setup is applied if `apply` is set.
+#[tauri::command]
+fn import_pomodoro_config(state: State<Arc<AppState>>, json: String, apply: bool) -> Result<session::PomodoroExport, CommandError> {
+    let export = session::PomodoroExport::parse(&json).map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    if !export.presets.is_empty() {
+        let mut presets = load_pomodoro_presets(&state);
+        session::merge_presets(&mut presets, export.presets.clone());
+        save_pomodoro_presets(&state, &presets)?;
+    }
+    if let (true, Some(config)) = (apply, &export.config) {
+        state.session_manager.pomodoro_configure(
+            config.work_seconds,
+            config.short_break_seconds,
+            config.long_break_seconds,
+            config.sessions_until_long_break,
+        );
+        emit_pomodoro_state(&state);
+    }
+    Ok(export)
+}
+
+// ============= Micro-break Commands =============
+
+fn emit_micro_break_state(state: &AppState) {
+    if let Some(handle) = state.app_handle.lock().unwrap().as_ref() {
+        let _ = handle.emit("micro-break", state.micro_breaks.get_state());
+    }
+}
+
+#[tauri::command]
+fn micro_break_get_state(state: State<Arc<AppState>>) -> MicroBreakState {
+    state.micro_breaks.get_state()
+}
+
+#[tauri::command]
+fn micro_break_configure(state: State<Arc<AppState>>, config: MicroBreakConfig) -> Result<(), CommandError> {
+    let json = serde_json::to_string(&config)?;
+    state.db.set_setting("micro_break_config", &json)?;
+    state.micro_breaks.configure(config);
+    emit_micro_break_state(&state);
+    Ok(())
+}
+
+#[tauri::command]
+fn micro_break_snooze(state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    state.micro_breaks.snooze().map_err(|e| CommandError::new(ErrorCode::InvalidState, e))?;
+    let _ = state.db.log_micro_break("snoozed");
+    emit_micro_break_state(&state);
+    Ok(())
+}
+
+#[tauri::command]
+fn micro_break_skip(state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    state.micro_breaks.skip().map_err(|e| CommandError::new(ErrorCode::InvalidState, e))?;
+    let _ = state.db.log_micro_break("skipped");
+    emit_micro_break_state(&state);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_micro_break_stats(state: State<Arc<AppState>>, days: i32) -> Result<Vec<MicroBreakStats>, CommandError> {
+    state.db.get_micro_break_stats(days).map_err(CommandError::from)
+}
+
+// ============= Quote Commands =============
+
+fn validate_quote(text: &str) -> Result<(), CommandError> {
+    if text.trim().is_empty() {
+        return Err(CommandError::new(ErrorCode::InvalidInput, "Quote text is empty"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_quotes(state: State<Arc<AppState>>) -> Result<Vec<Quote>, CommandError> {
+    state.db.get_quotes().map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn add_quote(state: State<Arc<AppState>>, text: String, author: Option<String>) -> Result<i64, CommandError> {
+    validate_quote(&text)?;
+    Ok(state.db.add_quote(text.trim(), author.as_deref().map(str::trim).filter(|a| !a.is_empty()))?)
+}
+
+#[tauri::command]
+fn update_quote(state: State<Arc<AppState>>, id: i64, text: String, author: Option<String>, enabled: bool) -> Result<(), CommandError> {
+    validate_quote(&text)?;
+    Ok(state.db.update_quote(id, text.trim(), author.as_deref().map(str::trim).filter(|a| !a.is_empty()), enabled)?)
+}
+
+#[tauri::command]
+fn delete_quote(state: State<Arc<AppState>>, id: i64) -> Result<(), CommandError> {
+    state.db.delete_quote(id).map_err(CommandError::from)
+}
+
+/// A random enabled quote for the block page and break overlay; None if the library is empty.
+#[tauri::command]
+fn get_random_quote(state: State<Arc<AppState>>) -> Result<Option<Quote>, CommandError> {
+    state.db.get_random_quote().map_err(CommandError::from)
+}
+
+/// Add a quote pack (JSON array or one quote per line, see `storage::parse_quote_pack`).
+/// Returns how many new quotes were added.
+#[tauri::command]
+fn import_quote_pack(state: State<Arc<AppState>>, pack: String) -> Result<usize, CommandError> {
+    let entries = storage::parse_quote_pack(&pack).map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    Ok(state.db.import_quotes(&entries)?)
+}
+
+// ============= Task Commands =============
+
+#[tauri::command]
+fn add_task(state: State<Arc<AppState>>, title: String) -> Result<i64, CommandError> {
+    state.db.add_task(&title).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_tasks(state: State<Arc<AppState>>) -> Result<Vec<Task>, CommandError> {
+    state.db.get_tasks().map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn update_task(state: State<Arc<AppState>>, id: i64, title: String, completed: bool) -> Result<(), CommandError> {
+    state.db.update_task(id, &title, completed).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn delete_task(state: State<Arc<AppState>>, id: i64) -> Result<(), CommandError> {
+    if load_active_task(&state)? == Some(id) {
+        save_active_task(&state, None)?;
+    }
+    state.db.delete_task(id).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_active_task(state: State<Arc<AppState>>) -> Result<Option<i64>, CommandError> {
+    load_active_task(&state)
+}
+
+#[tauri::command]
+fn set_active_task(state: State<Arc<AppState>>, id: Option<i64>) -> Result<(), CommandError> {
+    save_active_task(&state, id)
+}
+
+fn load_active_task(state: &AppState) -> Result<Option<i64>, CommandError> {
+    let value = state.db.get_setting("active_task_id")?;
+    Ok(value.and_then(|v| v.parse().ok()))
+}
+
+fn save_active_task(state: &AppState, id: Option<i64>) -> Result<(), CommandError> {
+    let result = match id {
+        Some(id) => state.db.set_setting("active_task_id", &id.to_string()),
+        None => state.db.delete_setting("active_task_id"),
+    };
+    result.map_err(CommandError::from)
+}
+
+/// Attribute focus time to the active task, if one is selected.
+fn record_task_focus(state: &AppState, minutes: i64, source: &str) {
+    if minutes <= 0 {
+        return;
+    }
+    if let Some(task_id) = load_active_task(state).unwrap_or(None) {
+        let _ = state.db.log_task_focus(task_id, minutes, source);
+    }
+}
+
+// ============= Usage Commands =============
+
+#[tauri::command]
+fn get_process_classifications(state: State<Arc<AppState>>) -> Result<Vec<ProcessClassification>, CommandError> {
+    let overrides = state.db.get_process_classifications()?;
+    Ok(usage::list_classifications(&overrides))
+}
+
+#[tauri::command]
+fn set_process_classification(state: State<Arc<AppState>>, process_name: String, classification: Productivity) -> Result<(), CommandError> {
+    let name = usage::normalize_process_name(&process_name);
+    state.db.set_process_classification(&name, classification.as_str()).map_err(CommandError::from)
+}
+
+/// Drop the user's override so the built-in classification applies again.
+#[tauri::command]
+fn reset_process_classification(state: State<Arc<AppState>>, process_name: String) -> Result<(), CommandError> {
+    let name = usage::normalize_process_name(&process_name);
+    state.db.delete_process_classification(&name).map_err(CommandError::from)
+}
+
+/// Time per productivity category, grouped by `"day"` or `"week"`.
+#[tauri::command]
+fn get_usage_breakdown(state: State<Arc<AppState>>, days: i32, group_by: String) -> Result<Vec<UsageBucket>, CommandError> {
+    let rows = state.db.get_app_usage_grouped(days, group_by == "week")?;
+    let overrides = state.db.get_process_classifications()?;
+    Ok(usage::breakdown(&rows, &overrides))
+}
+
+#[tauri::command]
+fn get_site_usage(state: State<Arc<AppState>>, days: i32) -> Result<Vec<SiteUsage>, CommandError> {
+    state.db.get_site_usage(days).map_err(CommandError::from)
+}
+
+/// Time each app spent in the foreground over the last `days` days, most used first.
+#[tauri::command]
+fn get_app_usage(state: State<Arc<AppState>>, days: i32) -> Result<Vec<AppUsageTotal>, CommandError> {
+    state.db.get_app_usage(days).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_usage_tracking_config(state: State<Arc<AppState>>) -> usage::UsageTrackingConfig {
+    usage::load_config(&state.db)
+}
+
+#[tauri::command]
+fn set_usage_tracking_config(state: State<Arc<AppState>>, config: usage::UsageTrackingConfig) -> Result<(), CommandError> {
+    config.validate().map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    let json = serde_json::to_string(&config)?;
+    state.db.set_setting(usage::USAGE_TRACKING_SETTING, &json).map_err(CommandError::from)
+}
+
+// ============= Integration Commands =============
+
+#[tauri::command]
+fn get_integration_queue(state: State<Arc<AppState>>) -> Result<Vec<QueuedPush>, CommandError> {
+    state.db.get_integration_queue().map_err(CommandError::from)
+}
+
+/// Retry queued exports right away instead of waiting for the background flush.
+#[tauri::command]
+async fn flush_integration_queue(state: State<'_, Arc<AppState>>) -> Result<usize, CommandError> {
+    Ok(integrations::flush_queue(&state.db).await)
+}
+
+#[tauri::command]
+fn get_beeminder_config(state: State<Arc<AppState>>) -> integrations::BeeminderConfig {
+    integrations::load_beeminder_config(&state.db)
+}
+
+#[tauri::command]
+fn set_beeminder_config(state: State<Arc<AppState>>, config: integrations::BeeminderConfig) -> Result<(), CommandError> {
+    if config.enabled && (config.username.trim().is_empty() || config.goal.trim().is_empty()) {
+        return Err(CommandError::new(ErrorCode::InvalidInput, "Beeminder user and goal are required"));
+    }
+    let json = serde_json::to_string(&config)?;
+    state.db.set_setting(integrations::BEEMINDER_SETTING, &json).map_err(CommandError::from)
+}
+
+/// Save the Toggl or Clockify API token to the OS keyring; `None` removes it.
+#[tauri::command]
+fn set_time_tracker_token(token: Option<String>) -> Result<(), CommandError> {
+    let token = token.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    integrations::set_time_tracker_token(token).map_err(|e| CommandError::new(ErrorCode::Internal, e))
+}
+
+#[tauri::command]
+fn has_time_tracker_token() -> bool {
+    integrations::has_time_tracker_token()
+}
+
+/// Save the Beeminder auth token to the OS keyring; `None` removes it.
+#[tauri::command]
+fn set_beeminder_token(token: Option<String>) -> Result<(), CommandError> {
+    let token = token.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    integrations::set_beeminder_token(token).map_err(|e| CommandError::new(ErrorCode::Internal, e))
+}
+
+#[tauri::command]
+fn has_beeminder_token() -> bool {
+    integrations::has_beeminder_token()
+}
+
+#[tauri::command]
+fn get_habitica_config(state: State<Arc<AppState>>) -> integrations::HabiticaConfig {
+    integrations::load_habitica_config(&state.db)
+}
+
+#[tauri::command]
+fn set_habitica_config(state: State<Arc<AppState>>, config: integrations::HabiticaConfig) -> Result<(), CommandError> {
+    if config.enabled && config.user_id.trim().is_empty() {
+        return Err(CommandError::new(ErrorCode::InvalidInput, "Habitica user id is required"));
+    }
+    let json = serde_json::to_string(&config)?;
+    state.db.set_setting(integrations::HABITICA_SETTING, &json).map_err(CommandError::from)
+}
+
+/// Save the Habitica API token to the OS keyring; `None` removes it.
+#[tauri::command]
+fn set_habitica_api_token(token: Option<String>) -> Result<(), CommandError> {
+    let token = token.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    integrations::set_habitica_api_token(token).map_err(|e| CommandError::new(ErrorCode::Internal, e))
+}
+
+#[tauri::command]
+fn has_habitica_api_token() -> bool {
+    integrations::has_habitica_api_token()
+}
+
+// ============= Leaderboard Commands =============
+
+#[tauri::command]
+fn get_leaderboard_config(state: State<Arc<AppState>>) -> leaderboard::LeaderboardConfig {
+    leaderboard::load_config(&state.db)
+}
+
+/// Enabling assigns the random id friends use to follow this install.
+#[tauri::command]
+fn set_leaderboard_config(state: State<Arc<AppState>>, mut config: leaderboard::LeaderboardConfig) -> Result<leaderboard::LeaderboardConfig, CommandError> {
+    if config.enabled {
+        if !config.endpoint.starts_with("https://") && !config.endpoint.starts_with("http://") {
+            return Err(CommandError::new(ErrorCode::InvalidInput, "Leaderboard endpoint must be an http(s) URL"));
+        }
+        if config.display_name.trim().is_empty() {
+            return Err(CommandError::new(ErrorCode::InvalidInput, "A display name is required"));
+        }
+    }
+    if config.user_id.is_empty() {
+        config.user_id = leaderboard::load_config(&state.db).user_id;
+    }
+    if config.user_id.is_empty() {
+        config.user_id = leaderboard::new_user_id();
+    }
+    config.friend_ids.retain(|id| !id.trim().is_empty());
+    let json = serde_json::to_string(&config)?;
+    state.db.set_setting(leaderboard::LEADERBOARD_SETTING, &json)?;
+    Ok(config)
+}
+
+/// Publish this week's focus minutes and return friends' totals, most focus first.
+#[tauri::command]
+async fn get_leaderboard(state: State<'_, Arc<AppState>>) -> Result<leaderboard::Leaderboard, CommandError> {
+    leaderboard::sync(&state.db).await.map_err(|e| CommandError::new(ErrorCode::Internal, e))
+}
+
+// ============= Plugin Commands =============
+
+#[tauri::command]
+fn get_plugins(state: State<Arc<AppState>>) -> Vec<plugins::PluginInfo> {
+    state.plugins.list()
+}
+
+// ============= Stats Commands =============
+
+#[tauri::command]
+fn get_recent_blocks(state: State<Arc<AppState>>, limit: i32) -> Result<Vec<BlockEvent>, CommandError> {
+    state.db.get_recent_blocks(limit).map_err(CommandError::from)
+}
+
+/// Everything the dashboard needs on load, in place of a dozen separate commands.
+#[derive(serde::Serialize)]
+struct DashboardSnapshot {
+    #[serde(flatten)]
+    data: storage::DashboardData,
+    active_session: Option<ActiveSession>, // primary session
+    active_sessions: Vec<ActiveSession>,
+    time_remaining: Option<i64>, // seconds
+    is_hardcore_locked: bool,
+    pomodoro: PomodoroState,
+}
+
+#[tauri::command]
+async fn get_dashboard_snapshot(state: State<'_, Arc<AppState>>, recent_blocks: Option<i32>, stats_days: Option<i32>) -> Result<DashboardSnapshot, CommandError> {
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let data = state.db.get_dashboard_data(recent_blocks.unwrap_or(8), stats_days.unwrap_or(7))?;
+        Ok::<_, CommandError>(DashboardSnapshot {
+            data,
+            active_session: state.session_manager.active_session(),
+            active_sessions: state.session_manager.active_sessions(),
+            time_remaining: state.session_manager.get_time_remaining(),
+            is_hardcore_locked: state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst),
+            pomodoro: state.session_manager.get_pomodoro_state(),
+        })
+    })
+    .await
+    .map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))?
+}
+
+#[tauri::command]
+fn get_focus_stats(state: State<Arc<AppState>>, days: i32) -> Result<Vec<FocusStats>, CommandError> {
+    state.db.get_stats(days).map_err(CommandError::from)
+}
+
+/// This week so far vs. the same days last week, for the dashboard's trend widget.
+#[tauri::command]
+fn get_week_comparison(state: State<Arc<AppState>>) -> Result<WeekComparison, CommandError> {
+    state.db.get_week_comparison().map_err(CommandError::from)
+}
+
+/// Daily block attempts for one site or app, to see whether blocking it changed the habit.
+#[tauri::command]
+fn get_target_trend(state: State<Arc<AppState>>, target: String, days: i32) -> Result<Vec<TrendPoint>, CommandError> {
+    // Sites are matched in their normalized form, so a pasted URL works too
+    let is_app = state.db.get_blocked_apps()?.iter().any(|app| app.process_name.eq_ignore_ascii_case(target.trim()));
+    let target = if is_app { target } else { blocking::normalize_domain(&target) };
+    state.db.get_target_trend(&target, days).map_err(CommandError::from)
+}
+
+/// Block attempts and time spent per category (social, games, news...) over the last `days` days.
+#[tauri::command]
+fn get_category_breakdown(state: State<Arc<AppState>>, days: i32) -> Result<Vec<CategoryBreakdown>, CommandError> {
+    state.db.get_category_breakdown(days).map_err(CommandError::from)
+}
+
+/// Current streak of clean days and streak freezes held. Settles any finished days first,
+/// which is when freezes are earned or spent.
+#[tauri::command]
+fn get_streak(state: State<Arc<AppState>>) -> Result<streaks::StreakStatus, CommandError> {
+    streaks::update(&state.db, chrono::Local::now().date_naive()).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_streak_freeze_history(state: State<Arc<AppState>>, limit: Option<i32>) -> Result<Vec<StreakFreeze>, CommandError> {
+    state.db.get_streak_freezes(limit.unwrap_or(50)).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_daily_summary(state: State<Arc<AppState>>) -> Result<DailySummary, CommandError> {
+    build_daily_summary(&state).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_daily_summary_config(state: State<Arc<AppState>>) -> DailySummaryConfig {
+    load_daily_summary_config(&state)
+}
+
+#[tauri::command]
+fn set_daily_summary_config(state: State<Arc<AppState>>, config: DailySummaryConfig) -> Result<(), CommandError> {
+    let json = serde_json::to_string(&config)?;
+    state.db.set_setting(summary::SUMMARY_SETTING, &json).map_err(CommandError::from)
+}
+
+fn load_daily_summary_config(state: &AppState) -> DailySummaryConfig {
+    state.db.get_setting(summary::SUMMARY_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn build_daily_summary(state: &AppState) -> rusqlite::Result<DailySummary> {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let stats = state.db.get_stats(1)?.into_iter().find(|s| s.date == today);
+    Ok(DailySummary::new(&today, stats.as_ref(), load_daily_summary_config(state).goal_minutes))
+}
+
+#[tauri::command]
+fn get_daily_note_config(state: State<Arc<AppState>>) -> notes::DailyNoteConfig {
+    load_daily_note_config(&state)
+}
+
+#[tauri::command]
+fn set_daily_note_config(state: State<Arc<AppState>>, config: notes::DailyNoteConfig) -> Result<(), CommandError> {
+    config.validate().map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    let json = serde_json::to_string(&config)?;
+    state.db.set_setting(notes::DAILY_NOTE_SETTING, &json).map_err(CommandError::from)
+}
+
+fn load_daily_note_config(state: &AppState) -> notes::DailyNoteConfig {
+    state.db.get_setting(notes::DAILY_NOTE_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Append today's focus summary to today's daily note now, whether or not the automatic
+/// export is on. Returns the note's path.
+#[tauri::command]
+fn export_daily_note(state: State<Arc<AppState>>) -> Result<String, CommandError> {
+    let path = write_daily_note(&state)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+fn write_daily_note(state: &AppState) -> Result<std::path::PathBuf, CommandError> {
+    let config = load_daily_note_config(state);
+    let daily_summary = build_daily_summary(state)?;
+    let midnight = chrono::Local::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|t| t.and_local_timezone(chrono::Local).earliest())
+        .map_or(0, |t| t.timestamp());
+    let sessions = state.db.get_session_history(midnight, midnight + 24 * 60 * 60)?;
+    let top_blocks = state.db.get_top_blocks(&daily_summary.date, 5)?;
+    let contents = notes::render(&config.template, &daily_summary, &sessions, &top_blocks);
+    notes::append_to_note(&config, &daily_summary, &contents).map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))
+}
+
+/// Append the day's summary to the daily note once the configured time has passed.
+fn maybe_export_daily_note(state: &AppState) {
+    let config = load_daily_note_config(state);
+    let now = chrono::Local::now().naive_local();
+    let last_export = state.db.get_setting(notes::DAILY_NOTE_LAST_EXPORT_SETTING).unwrap_or(None);
+    if !config.is_due(now, last_export.as_deref()) {
+        return;
+    }
+    // Marked done even on failure, so a missing folder doesn't retry every tick
+    let _ = state.db.set_setting(notes::DAILY_NOTE_LAST_EXPORT_SETTING, &now.format("%Y-%m-%d").to_string());
+    match write_daily_note(state) {
+        Ok(path) => println!("[Bastion] Exported the daily note to {}", path.display()),
+        Err(e) => {
+            eprintln!("[Bastion] Could not export the daily note: {}", e.message);
+            if let Some(handle) = state.app_handle.lock().unwrap().as_ref() {
+                let _ = handle.emit("daily-note-failed", e.message);
+            }
+        }
+    }
+}
+
+/// Score the Habitica daily-goal task the first time today's focus goal is reached.
+fn check_habitica_daily_goal(state: &AppState) {
+    if !integrations::load_habitica_config(&state.db).enabled {
+        return;
+    }
+    let Ok(summary) = build_daily_summary(state) else {
+        return;
+    };
+    if summary.goal_minutes <= 0 || summary.focus_minutes < summary.goal_minutes {
+        return;
+    }
+    let last_scored = state.db.get_setting(integrations::HABITICA_GOAL_SCORED_SETTING).unwrap_or(None);
+    if last_scored.as_deref() == Some(summary.date.as_str()) {
+        return;
+    }
+    if let Err(e) = integrations::queue_habitica_score(&state.db, integrations::HabiticaEvent::DailyGoalMet) {
+        eprintln!("[Bastion] Could not queue Habitica score: {}", e);
+        return;
+    }
+    let _ = state.db.set_setting(integrations::HABITICA_GOAL_SCORED_SETTING, &summary.date);
+}
+
+/// Send today's summary once the configured evening time has passed.
+fn maybe_send_daily_summary(state: &AppState) {
+    let config = load_daily_summary_config(state);
+    let now = chrono::Local::now().naive_local();
+    let last_sent = state.db.get_setting(summary::SUMMARY_LAST_SENT_SETTING).unwrap_or(None);
+    if !config.is_due(now, last_sent.as_deref()) {
+        return;
+    }
+    let Ok(daily_summary) = build_daily_summary(state) else {
+        return;
+    };
+
+    let _ = state.db.set_setting(summary::SUMMARY_LAST_SENT_SETTING, &daily_summary.date);
+    if let Some(handle) = state.app_handle.lock().unwrap().as_ref() {
+        use tauri_plugin_notification::NotificationExt;
+        let _ = handle.notification()
+            .builder()
+            .title("Today in Bastion")
+            .body(daily_summary.message())
+            .show();
+        let _ = handle.emit("daily-summary", daily_summary);
+    }
+}
+
+#[tauri::command]
+fn get_pomodoro_history(state: State<Arc<AppState>>, days: i32) -> Result<Vec<PomodoroDay>, CommandError> {
+    state.db.get_pomodoro_history(days).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn log_protected_time(state: State<Arc<AppState>>, minutes: i64) -> Result<(), CommandError> {
+    state.db.update_protected_time(minutes).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_block_counts(state: State<Arc<AppState>>) -> Result<std::collections::HashMap<String, i64>, CommandError> {
+    state.db.get_block_counts().map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_session_block_split(state: State<Arc<AppState>>, days: i32) -> Result<SessionBlockSplit, CommandError> {
+    state.db.get_session_block_split(days).map_err(CommandError::from)
+}
+
+// ============= Settings Commands =============
+
+#[tauri::command]
+fn get_setting(state: State<Arc<AppState>>, key: String) -> Result<Option<String>, CommandError> {
+    state.db.get_setting(&key).map_err(CommandError::from)
+}
+
+/// How `set_setting` treats a setting that can't just be overwritten
+enum SettingGuard {
+    /// Only through the named command, which validates and applies it
+    Command(&'static str),
+    /// Only through the named command, which checks the current credentials
+    Credential(&'static str),
+    /// Can't be changed during a hardcore session; says what the setting is for the error
+    Hardcore(&'static str),
+    /// Bookkeeping kept by Bastion itself
+    Internal,
+}
+
+/// Settings `set_setting` won't write as given. The schedule lock settings are checked by
+/// value in `set_setting` itself.
+const PROTECTED_SETTINGS: &[(&str, SettingGuard)] = &[
+    ("extreme_hardcore", SettingGuard::Hardcore("extreme mode")),
+    ("block_installers", SettingGuard::Hardcore("installer blocking")),
+    (hooks::HOOKS_SETTING, SettingGuard::Hardcore("hooks")),
+    (blocking::DEV_EXCLUSIONS_SETTING, SettingGuard::Hardcore("exclusions")),
+    (blocking::INSTALLER_TOOLS_SETTING, SettingGuard::Command("set_installer_block_list")),
+    ("master_password_hash", SettingGuard::Credential("set_master_password")),
+    (session::UNLOCK_FRICTION_SETTING, SettingGuard::Command("set_friction_policy")),
+    (session::CATEGORY_SCHEDULES_SETTING, SettingGuard::Command("set_category_schedules")),
+    (blocking::APP_ENFORCEMENT_SETTING, SettingGuard::Command("set_app_enforcement_interval")),
+    (extension::YOUTUBE_RULES_SETTING, SettingGuard::Command("set_youtube_rules")),
+    (extension::STRICT_MODE_SETTING, SettingGuard::Command("set_extension_strict_mode")),
+    (extension::SITE_LIMITS_SETTING, SettingGuard::Command("set_site_time_limit")),
+    (blocking::BLOCKING_BACKEND_SETTING, SettingGuard::Command("set_blocking_backend")),
+    (blocking::SAFESEARCH_SETTING, SettingGuard::Command("set_safesearch_enforced")),
+    (streaks::STREAK_STATE_SETTING, SettingGuard::Internal),
+    (session::ENDED_WINDOWS_SETTING, SettingGuard::Internal),
+    (session::TEMPTATION_DELAY_SETTING, SettingGuard::Command("set_temptation_delay")),
+    (dns::DNS_SINKHOLE_SETTING, SettingGuard::Command("set_dns_sinkhole_config")),
+    (server::BLOCK_SERVER_SETTING, SettingGuard::Command("set_block_server_config")),
+    (EXIT_BEHAVIOR_SETTING, SettingGuard::Command("set_exit_behavior")),
+    (companion::DEVICES_SETTING, SettingGuard::Internal),
+    (companion::COMPANION_ENABLED_SETTING, SettingGuard::Command("set_companion_enabled")),
+    (integrations::LEGACY_TIME_TRACKER_TOKEN_SETTING, SettingGuard::Command("set_time_tracker_token")),
+];
+
+#[tauri::command]
+fn set_setting(state: State<Arc<AppState>>, key: String, mut value: String) -> Result<(), CommandError> {
+    match PROTECTED_SETTINGS.iter().find(|(protected, _)| *protected == key).map(|(_, guard)| guard) {
+        Some(SettingGuard::Command(command)) => {
+            return Err(CommandError::new(ErrorCode::InvalidInput, format!("Use {} to change this setting", command)));
+        }
+        Some(SettingGuard::Credential(command)) => {
+            return Err(CommandError::new(ErrorCode::Unauthorized, format!("Use {} to change this setting", command)));
+        }
+        Some(SettingGuard::Internal) => {
+            return Err(CommandError::new(ErrorCode::InvalidInput, "This setting is kept by Bastion and can't be changed"));
+        }
+        Some(SettingGuard::Hardcore(what)) if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) => {
+            return Err(CommandError::new(ErrorCode::HardcoreLocked, format!("Cannot change {} during a hardcore session", what)));
+        }
+        _ => {}
+    }
+    if key == session::SCHEDULE_LOCK_SETTING {
+        let minutes = value.trim().parse::<i64>()
+            .map_err(|_| CommandError::new(ErrorCode::InvalidInput, "The lock window must be a number of minutes"))?;
+        value = minutes.clamp(0, session::MAX_SCHEDULE_LOCK_MINUTES).to_string();
+        // Shrinking the window must not unlock a schedule that is already locked
+        let now = chrono::Local::now().naive_local();
+        let window = schedule_lock_minutes(&state);
+        if state.db.get_sessions()?.iter().any(|s| session::is_schedule_locked(s, now, window)) {
+            return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot change the lock window while a hardcore schedule is locked"));
+        }
+    }
+    if key == BREAK_OVERLAY_STRICT_SETTING && is_break_overlay_locked(&state) {
+        return Err(CommandError::new(ErrorCode::BreakLocked, "Cannot change strict mode during a break"));
+    }
+    if key == session::SCHEDULE_DELETION_DELAY_SETTING {
+        let shorter = value.parse::<i64>().map_or(true, |hours| hours < schedule_deletion_delay_hours(&state));
+        if shorter && state.db.get_sessions()?.iter().any(|s| s.hardcore) {
+            return Err(CommandError::new(ErrorCode::HardcoreLocked, "The deletion delay can't be shortened while hardcore schedules exist"));
+        }
+    }
+    state.db.set_setting(&key, &value).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_telemetry_config(state: State<Arc<AppState>>) -> Result<telemetry::TelemetryConfig, CommandError> {
+    let config = state.db.get_setting(telemetry::TELEMETRY_SETTING)?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    Ok(config)
 }
 
+/// Takes effect the next time Bastion starts.
 #[tauri::command]
-fn get_blocked_sites(state: State<Arc<AppState>>) -> Result<Vec<BlockedSite>, String> {
-    state.db.get_blocked_sites().map_err(|e| e.to_string())
+fn set_telemetry_config(state: State<Arc<AppState>>, config: telemetry::TelemetryConfig) -> Result<(), CommandError> {
+    let json = serde_json::to_string(&config)?;
+    state.db.set_setting(telemetry::TELEMETRY_SETTING, &json).map_err(CommandError::from)
 }
 
 #[tauri::command]
-fn toggle_blocked_site(state: State<Arc<AppState>>, id: i64, enabled: bool) -> Result<(), String> {
-    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
-        return Err("Cannot modify block list during a hardcore session".to_string());
-    }
-    state.db.toggle_blocked_site(id, enabled).map_err(|e| e.to_string())?;
-    sync_blocked_websites(&state)
+fn factory_reset(state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    state.db.factory_reset().map_err(CommandError::from)
 }
 
+/// Changing an existing master password needs an unlock token; setting the first one doesn't.
 #[tauri::command]
-fn delete_blocked_site(state: State<Arc<AppState>>, id: i64) -> Result<(), String> {
-    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
-        return Err("Cannot modify block list during a hardcore session".to_string());
+fn set_master_password(state: State<Arc<AppState>>, password: String, token: Option<String>) -> Result<(), CommandError> {
+    if has_master_password(&state)? {
+        require_unlock_token(&state, token.as_deref())?;
     }
-    state.db.delete_blocked_site(id).map_err(|e| e.to_string())?;
-    sync_blocked_websites(&state)
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+    let password_hash = argon2.hash_password(password.as_bytes(), &salt)
+        .map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))?
+        .to_string();
+    
+    state.db.set_setting("master_password_hash", &password_hash).map_err(CommandError::from)
 }
 
-fn sync_blocked_websites(state: &State<Arc<AppState>>) -> Result<(), String> {
-    let sites = state.db.get_blocked_sites().map_err(|e| e.to_string())?;
-    let enabled_domains: Vec<String> = sites
-        .into_iter()
-        .filter(|s| s.enabled)
-        .map(|s| s.domain)
-        .collect();
-    
-    // Try to update hosts file, but don't fail if we don't have admin privileges
-    match blocking::update_blocked_websites(&enabled_domains) {
-        Ok(()) => {
-            println!("[Bastion] Hosts file updated with {} domains", enabled_domains.len());
-            // Flush DNS to make changes immediate
-            let _ = blocking::flush_dns();
-        },
-        Err(e) => {
-            // Log the error but don't fail - database is still updated
-            eprintln!("[Bastion] Could not update hosts file (need admin?): {}", e.message);
-        }
-    }
+#[tauri::command]
+fn clear_master_password(state: State<Arc<AppState>>, token: String) -> Result<(), CommandError> {
+    require_unlock_token(&state, Some(&token))?;
+    state.db.delete_setting("master_password_hash")?;
+    state.unlock_tokens.revoke();
     Ok(())
 }
 
-// ============= Blocked Apps Commands =============
+/// Older versions disabled the password by storing an empty hash.
+fn has_master_password(state: &AppState) -> Result<bool, CommandError> {
+    Ok(state.db.get_setting("master_password_hash")?.is_some_and(|hash| !hash.is_empty()))
+}
 
 #[tauri::command]
-fn add_blocked_app(state: State<Arc<AppState>>, name: String, process_name: String, category: String) -> Result<i64, String> {
-    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
-        return Err("Cannot modify block list during a hardcore session".to_string());
+fn verify_master_password(state: State<Arc<AppState>>, password: String) -> Result<bool, CommandError> {
+    let hash_opt = state.db.get_setting("master_password_hash")?.filter(|hash| !hash.is_empty());
+    
+    if let Some(hash_str) = hash_opt {
+        let parsed_hash = PasswordHash::new(&hash_str).map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))?;
+        Ok(Argon2::default().verify_password(password.as_bytes(), &parsed_hash).is_ok())
+    } else {
+        Ok(false)
     }
-    state.db.add_blocked_app(&name, &process_name, &category).map_err(|e| e.to_string())
 }
 
-#[tauri::command]
-fn get_blocked_apps(state: State<Arc<AppState>>) -> Result<Vec<BlockedApp>, String> {
-    state.db.get_blocked_apps().map_err(|e| e.to_string())
+#[derive(serde::Serialize)]
+struct UnlockToken {
+    token: String,
+    expires_in: u64, // seconds
 }
 
+/// Exchange the master password for a short-lived token accepted by privileged commands.
 #[tauri::command]
-fn toggle_blocked_app(state: State<Arc<AppState>>, id: i64, enabled: bool) -> Result<(), String> {
-    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
-        return Err("Cannot modify block list during a hardcore session".to_string());
+fn authenticate(state: State<Arc<AppState>>, password: String) -> Result<UnlockToken, CommandError> {
+    if !verify_master_password(state.clone(), password)? {
+        return Err(CommandError::new(ErrorCode::InvalidPassword, "Invalid master password"));
     }
-    state.db.toggle_blocked_app(id, enabled).map_err(|e| e.to_string())
+    Ok(UnlockToken {
+        token: state.unlock_tokens.issue(),
+        expires_in: security::UNLOCK_TOKEN_TTL.as_secs(),
+    })
 }
 
+/// Revoke the current unlock token.
 #[tauri::command]
-fn delete_blocked_app(state: State<Arc<AppState>>, id: i64) -> Result<(), String> {
-    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
-        return Err("Cannot modify block list during a hardcore session".to_string());
+fn lock(state: State<Arc<AppState>>) {
+    state.unlock_tokens.revoke();
+}
+
+fn require_unlock_token(state: &AppState, token: Option<&str>) -> Result<(), CommandError> {
+    match token {
+        Some(token) if state.unlock_tokens.validate(token) => Ok(()),
+        _ => Err(CommandError::new(ErrorCode::Unauthorized, "Unlock token is missing or expired")),
     }
-    state.db.delete_blocked_app(id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn get_installed_applications() -> Vec<InstalledApp> {
-    blocking::get_installed_applications()
+fn emergency_unlock(state: State<Arc<AppState>>, token: String, challenge_response: Option<String>) -> Result<(), CommandError> {
+    require_unlock_token(&state, Some(&token))?;
+    pass_unlock_challenge(&state, challenge_response.as_deref())?;
+    let unlocked = state.session_manager.force_end_all();
+    // One unlock per authentication
+    state.unlock_tokens.revoke();
+    for session in &unlocked {
+        // The sessions are already over; a failed log must not leave their blocks in place
+        if let Err(e) = state.db.log_unlock_event("emergency_unlock", Some(&session.id)) {
+            eprintln!("[Bastion] Could not log emergency unlock: {}", e);
+        }
+        on_session_finished(&state, session);
+    }
+    Ok(())
 }
 
+// ============= System Commands =============
+
 #[tauri::command]
-fn get_running_processes() -> Vec<RunningProcess> {
-    blocking::get_running_processes()
+fn is_app_admin() -> bool {
+    blocking::is_admin()
 }
 
 #[tauri::command]
-fn enforce_app_blocks(state: State<Arc<AppState>>) -> Result<Vec<String>, String> {
-    let apps = state.db.get_blocked_apps().map_err(|e| e.to_string())?;
-    let blocked_process_names: Vec<String> = apps
-        .into_iter()
-        .filter(|a| a.enabled)
-        .map(|a| a.process_name)
-        .collect();
-    
-    let killed = blocking::enforce_app_blocks(&blocked_process_names);
+fn kill_browsers(state: State<Arc<AppState>>) -> Result<u32, CommandError> {
+    let mut total_killed = 0;
+    let browser_processes = [
+        "chrome.exe", "google-chrome.exe", "thorium.exe", "thorium-browser.exe", 
+        "firefox.exe", "msedge.exe", "brave.exe", "opera.exe", "vivaldi.exe",
+        "chrome", "google-chrome", "thorium", "thorium-browser",
+        "firefox", "msedge", "brave", "opera", "vivaldi"
+    ];
     
-    // Log block events
-    for app in &killed {
-        let _ = state.db.log_block_event(app, "app");
+    for browser in &browser_processes {
+        if let Ok(count) = blocking::kill_process_by_name(&state.processes, browser) {
+            total_killed += count;
+        }
     }
-    
-    Ok(killed)
+
+    Ok(total_killed)
 }
 
+/// Quit Bastion for good. While a session is active this requires an unlock token;
+/// without it the app stays running in the tray. Cleanup happens in `shutdown`.
 #[tauri::command]
-fn reset_all_blocks(state: State<Arc<AppState>>) -> Result<(), String> {
-    let apps = state.db.get_blocked_apps().map_err(|e| e.to_string())?;
-    for app in apps {
-        state.db.delete_blocked_app(app.id).map_err(|e| e.to_string())?;
+fn quit_app(app: tauri::AppHandle, state: State<Arc<AppState>>, token: Option<String>) -> Result<(), CommandError> {
+    if state.session_manager.get_time_remaining().is_some() {
+        let token = token.ok_or_else(|| {
+            CommandError::new(ErrorCode::PasswordRequired, "A focus session is active. Enter your master password to quit")
+        })?;
+        require_unlock_token(&state, Some(&token))?;
     }
-    let sites = state.db.get_blocked_sites().map_err(|e| e.to_string())?;
-    for site in sites {
-        state.db.delete_blocked_site(site.id).map_err(|e| e.to_string())?;
-    }
-    // Restore hosts file to original state
-    let _ = blocking::update_blocked_websites(&[]);
+    app.exit(0);
     Ok(())
 }
 
-// --- Session Commands ---
-
-#[tauri::command]
-fn add_session(state: State<Arc<AppState>>, session: Session) -> Result<i64, String> {
-    state.db.add_session(&session).map_err(|e| e.to_string())
+/// What a clean exit does with the blocks: keep the hosts section (the default, blocks stay
+/// in force while Bastion isn't running) or remove it until the next start.
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ExitBehavior {
+    #[default]
+    KeepBlocks,
+    RemoveBlocks,
 }
 
-#[tauri::command]
-fn get_sessions(state: State<Arc<AppState>>) -> Result<Vec<Session>, String> {
-    state.db.get_sessions().map_err(|e| e.to_string())
-}
+const EXIT_BEHAVIOR_SETTING: &str = "exit_behavior";
 
-#[tauri::command]
-fn delete_session(state: State<Arc<AppState>>, id: i64) -> Result<(), String> {
-    state.db.delete_session(id).map_err(|e| e.to_string())
+fn load_exit_behavior(state: &AppState) -> ExitBehavior {
+    state.db.get_setting(EXIT_BEHAVIOR_SETTING)
+        .unwrap_or(None)
+        .and_then(|value| serde_json::from_value(serde_json::Value::String(value)).ok())
+        .unwrap_or_default()
 }
 
 #[tauri::command]
-fn start_focus_session(state: State<Arc<AppState>>, name: String, duration_minutes: i64, hardcore: bool) -> Result<ActiveSession, String> {
-    Ok(state.session_manager.start_session(name, duration_minutes, hardcore))
+fn get_exit_behavior(state: State<Arc<AppState>>) -> ExitBehavior {
+    load_exit_behavior(&state)
 }
 
 #[tauri::command]
-fn end_focus_session(state: State<Arc<AppState>>) -> Result<(), String> {
-    state.session_manager.end_session()
+fn set_exit_behavior(state: State<Arc<AppState>>, behavior: ExitBehavior) -> Result<(), CommandError> {
+    let value = match behavior {
+        ExitBehavior::KeepBlocks => "keep_blocks",
+        ExitBehavior::RemoveBlocks => "remove_blocks",
+    };
+    state.db.set_setting(EXIT_BEHAVIOR_SETTING, value).map_err(CommandError::from)
 }
 
-#[tauri::command]
-fn get_session_time_remaining(state: State<Arc<AppState>>) -> Option<i64> {
-    state.session_manager.get_time_remaining()
+/// Clean-exit handler, run once on `RunEvent::Exit` (tray Quit, `quit_app`).
+/// The listeners (block page, extension API, IPC) stop with the async runtime right after.
+fn shutdown(app: &tauri::AppHandle) {
+    let state = app.state::<Arc<AppState>>();
+    let state = state.inner().as_ref();
+    blocking::remove_port_redirects();
+    if !state.demo && load_exit_behavior(state) == ExitBehavior::RemoveBlocks {
+        match blocking::clear_blocked_websites() {
+            Ok(()) => {
+                let _ = blocking::flush_dns();
+            }
+            Err(e) => eprintln!("[Bastion] Could not remove the hosts section on exit: {}", e.message),
+        }
+    }
+    telemetry::shutdown();
+    if !state.demo {
+        if let Ok(data_dir) = app.path().app_data_dir() {
+            recovery::mark_stopped(&data_dir);
+        }
+    }
 }
 
+/// What startup repaired after the last unclean shutdown, if anything was needed.
 #[tauri::command]
-fn is_hardcore_locked(state: State<Arc<AppState>>) -> bool {
-    state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst)
+fn get_recovery_report(state: State<Arc<AppState>>) -> Option<recovery::RecoveryReport> {
+    state.db.get_setting(recovery::RECOVERY_REPORT_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
 }
 
-// ============= Pomodoro Commands =============
+/// UI state saved by `relaunch_elevated` for the next instance (opaque JSON from the frontend)
+const RESTORE_UI_STATE_SETTING: &str = "restore_ui_state";
 
+/// Restart Bastion with admin/root rights, e.g. after the health check reports that the
+/// hosts file isn't writable. `ui_state` is handed to the new instance via `take_restore_ui_state`.
 #[tauri::command]
-fn pomodoro_start(state: State<Arc<AppState>>) -> Result<(), String> {
-    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
-        return Err("Cannot control timer during a hardcore session".to_string());
+fn relaunch_elevated(app: tauri::AppHandle, state: State<Arc<AppState>>, ui_state: Option<String>) -> Result<(), CommandError> {
+    // Hardcore sessions are restored from the lock state file after a restart; others would be lost
+    let session_active = state.session_manager.get_time_remaining().is_some();
+    let hardcore = state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst);
+    if session_active && !hardcore {
+        return Err(CommandError::new(ErrorCode::InvalidState, "End the focus session before relaunching"));
     }
-    state.session_manager.pomodoro_start();
-    Ok(())
-}
 
-#[tauri::command]
-fn pomodoro_pause(state: State<Arc<AppState>>) -> Result<(), String> {
-    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
-        return Err("Cannot control timer during a hardcore session".to_string());
+    let exe = std::env::current_exe().map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))?;
+    match ui_state {
+        Some(ui_state) => state.db.set_setting(RESTORE_UI_STATE_SETTING, &ui_state)?,
+        None => state.db.delete_setting(RESTORE_UI_STATE_SETTING)?,
     }
-    state.session_manager.pomodoro_pause();
-    Ok(())
+    blocking::spawn_elevated(&exe)?;
+    blocking::remove_port_redirects();
+    if let Ok(data_dir) = app.path().app_data_dir() {
+        recovery::mark_stopped(&data_dir);
+    }
+    std::process::exit(0);
 }
 
+/// UI state saved before an elevated relaunch, returned once.
 #[tauri::command]
-fn pomodoro_reset(state: State<Arc<AppState>>) -> Result<(), String> {
-    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
-        return Err("Cannot control timer during a hardcore session".to_string());
+fn take_restore_ui_state(state: State<Arc<AppState>>) -> Result<Option<String>, CommandError> {
+    let ui_state = state.db.get_setting(RESTORE_UI_STATE_SETTING)?;
+    if ui_state.is_some() {
+        state.db.delete_setting(RESTORE_UI_STATE_SETTING)?;
     }
-    state.session_manager.pomodoro_reset();
-    Ok(())
+    Ok(ui_state)
 }
 
 #[tauri::command]
-fn pomodoro_get_state(state: State<Arc<AppState>>) -> PomodoroState {
-    state.session_manager.get_pomodoro_state()
+fn get_block_server_config(state: State<Arc<AppState>>) -> server::BlockServerConfig {
+    server::load_config(&state)
 }
 
+/// How to handle ports 80/443 being taken by another program. Takes effect after a restart.
 #[tauri::command]
-fn pomodoro_configure(state: State<Arc<AppState>>, work: i64, short_break: i64, long_break: i64, sessions: i32) {
-    state.session_manager.pomodoro_configure(work, short_break, long_break, sessions);
+fn set_block_server_config(state: State<Arc<AppState>>, config: server::BlockServerConfig) -> Result<(), CommandError> {
+    let json = serde_json::to_string(&config)?;
+    state.db.set_setting(server::BLOCK_SERVER_SETTING, &json).map_err(CommandError::from)
 }
 
-// ============= Stats Commands =============
-
 #[tauri::command]
-fn get_recent_blocks(state: State<Arc<AppState>>, limit: i32) -> Result<Vec<BlockEvent>, String> {
-    state.db.get_recent_blocks(limit).map_err(|e| e.to_string())
+fn get_blocking_backend(state: State<Arc<AppState>>) -> blocking::BlockingBackend {
+    load_blocking_backend(&state)
 }
 
+/// Switch between the hosts file, the DNS sinkhole and the PAC file and re-apply the block list.
+/// The PAC backend points the system proxy at the block server's PAC file; switching away
+/// removes it again.
 #[tauri::command]
-fn get_focus_stats(state: State<Arc<AppState>>, days: i32) -> Result<Vec<FocusStats>, String> {
-    state.db.get_stats(days).map_err(|e| e.to_string())
-}
+fn set_blocking_backend(state: State<Arc<AppState>>, backend: blocking::BlockingBackend) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot change the blocking backend during a hardcore session"));
+    }
+    let previous = load_blocking_backend(&state);
+    let pac_url = format!("http://127.0.0.1:{}{}", state.block_server.http_port(), blocking::PAC_PATH);
+    if backend == blocking::BlockingBackend::Pac {
+        network::set_pac_url(&pac_url, true)
+            .map_err(|e| CommandError::new(ErrorCode::Internal, format!("Could not set the system proxy: {}", e)))?;
+    } else if previous == blocking::BlockingBackend::Pac {
+        state.block_server.set_pac_domains(None);
+        if let Err(e) = network::set_pac_url(&pac_url, false) {
+            eprintln!("[Bastion] Could not remove the PAC file from the system proxy: {}", e);
+        }
+    }
+    // The proxy guard would otherwise restore the settings from before the switch
+    state.proxy_guard.rebaseline();
 
-#[tauri::command]
-fn log_protected_time(state: State<Arc<AppState>>, minutes: i64) -> Result<(), String> {
-    state.db.update_protected_time(minutes).map_err(|e| e.to_string())
+    let json = serde_json::to_string(&backend)?;
+    state.db.set_setting(blocking::BLOCKING_BACKEND_SETTING, &json)?;
+    sync_blocked_websites(&state)?;
+    state.dns_sinkhole.state_changed();
+    Ok(())
 }
 
 #[tauri::command]
-fn get_block_counts(state: State<Arc<AppState>>) -> Result<std::collections::HashMap<String, i64>, String> {
-    state.db.get_block_counts().map_err(|e| e.to_string())
+fn get_dns_sinkhole_config(state: State<Arc<AppState>>) -> dns::DnsSinkholeConfig {
+    dns::load_config(&state)
 }
 
-// ============= Settings Commands =============
-
+/// Port, upstream resolver and answer for blocked domains. A running sinkhole restarts with it.
 #[tauri::command]
-fn get_setting(state: State<Arc<AppState>>, key: String) -> Result<Option<String>, String> {
-    state.db.get_setting(&key).map_err(|e| e.to_string())
+fn set_dns_sinkhole_config(state: State<Arc<AppState>>, config: dns::DnsSinkholeConfig) -> Result<(), CommandError> {
+    if state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot change the DNS sinkhole during a hardcore session"));
+    }
+    config.validate().map_err(|e| CommandError::new(ErrorCode::InvalidInput, e))?;
+    let json = serde_json::to_string(&config)?;
+    state.db.set_setting(dns::DNS_SINKHOLE_SETTING, &json)?;
+    state.dns_sinkhole.state_changed();
+    Ok(())
 }
 
 #[tauri::command]
-fn set_setting(state: State<Arc<AppState>>, key: String, value: String) -> Result<(), String> {
-    state.db.set_setting(&key, &value).map_err(|e| e.to_string())
+fn get_dns_sinkhole_status(state: State<Arc<AppState>>) -> dns::DnsSinkholeStatus {
+    state.dns_sinkhole.status()
 }
 
 #[tauri::command]
-fn factory_reset(state: State<Arc<AppState>>) -> Result<(), String> {
-    state.db.factory_reset().map_err(|e| e.to_string())
+fn get_https_block_page_status(state: State<Arc<AppState>>) -> tls::HttpsBlockPageStatus {
+    let ca = state.block_server.tls();
+    tls::HttpsBlockPageStatus {
+        enabled: ca.is_some(),
+        certificate_path: ca.map(|ca| ca.cert_path().to_string_lossy().into_owned()),
+    }
 }
 
+/// Opt in to the HTTPS block page: generate the local CA (once) and trust it system-wide, so
+/// the block server can answer HTTPS requests for blocked sites. Needs admin rights.
 #[tauri::command]
-fn set_master_password(state: State<Arc<AppState>>, password: String) -> Result<(), String> {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let password_hash = argon2.hash_password(password.as_bytes(), &salt)
-        .map_err(|e| e.to_string())?
-        .to_string();
-    
-    state.db.set_setting("master_password_hash", &password_hash).map_err(|e| e.to_string())
+fn enable_https_block_page(app: tauri::AppHandle, state: State<Arc<AppState>>) -> Result<tls::HttpsBlockPageStatus, CommandError> {
+    let data_dir = app.path().app_data_dir().map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))?;
+    let ca = tls::BlockPageCa::load_or_create(&data_dir)?;
+    tls::install_ca(ca.cert_path())?;
+    state.db.set_setting(tls::HTTPS_BLOCK_PAGE_SETTING, "true")?;
+    state.block_server.set_tls(Some(Arc::new(ca)));
+    Ok(get_https_block_page_status(state))
 }
 
+/// Untrust and delete the local CA; HTTPS connections to blocked sites are closed again.
 #[tauri::command]
-fn verify_master_password(state: State<Arc<AppState>>, password: String) -> Result<bool, String> {
-    let hash_opt = state.db.get_setting("master_password_hash").map_err(|e| e.to_string())?;
-    
-    if let Some(hash_str) = hash_opt {
-        let parsed_hash = PasswordHash::new(&hash_str).map_err(|e| e.to_string())?;
-        Ok(Argon2::default().verify_password(password.as_bytes(), &parsed_hash).is_ok())
-    } else {
-        Ok(false)
-    }
+fn disable_https_block_page(app: tauri::AppHandle, state: State<Arc<AppState>>) -> Result<(), CommandError> {
+    let data_dir = app.path().app_data_dir().map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))?;
+    tls::uninstall_ca()?;
+    state.block_server.set_tls(None);
+    state.db.delete_setting(tls::HTTPS_BLOCK_PAGE_SETTING)?;
+    tls::remove_ca(&data_dir);
+    Ok(())
 }
 
+/// Health of every blocking layer, for the Healthy/Degraded indicator. Probes DNS and
+/// local ports, so it runs off the main thread.
 #[tauri::command]
-fn emergency_unlock(state: State<Arc<AppState>>, password: String) -> Result<(), String> {
-    let valid = verify_master_password(state.clone(), password)?;
-    if valid {
-        state.session_manager.force_end_session()
-    } else {
-        Err("Invalid master password".to_string())
-    }
+async fn get_protection_status(state: State<'_, Arc<AppState>>) -> Result<health::ProtectionStatus, CommandError> {
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || health::check(&state))
+        .await
+        .map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))
 }
 
-// ============= System Commands =============
-
+/// Request a blocked domain end to end and report which layer (DNS, connect, block server) failed.
 #[tauri::command]
-fn is_app_admin() -> bool {
-    blocking::is_admin()
+async fn run_blocking_selftest(state: State<'_, Arc<AppState>>) -> Result<health::SelfTestReport, CommandError> {
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || health::selftest(&state))
+        .await
+        .map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))
 }
 
+/// Zip logs, the protection status, settings (secrets redacted) and DB stats for a bug report.
+/// Returns the path of the bundle.
 #[tauri::command]
-fn kill_browsers() -> Result<u32, String> {
-    let mut total_killed = 0;
-    let browser_processes = [
-        "chrome.exe", "google-chrome.exe", "thorium.exe", "thorium-browser.exe", 
-        "firefox.exe", "msedge.exe", "brave.exe", "opera.exe", "vivaldi.exe",
-        "chrome", "google-chrome", "thorium", "thorium-browser",
-        "firefox", "msedge", "brave", "opera", "vivaldi"
-    ];
-    
-    for browser in &browser_processes {
-        if let Ok(count) = blocking::kill_process_by_name(browser) {
-            total_killed += count;
-        }
-    }
-
-    Ok(total_killed)
+async fn create_diagnostics_bundle(app: tauri::AppHandle, state: State<'_, Arc<AppState>>) -> Result<String, CommandError> {
+    let data_dir = app.path().app_data_dir().map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))?;
+    let state = state.inner().clone();
+    let path = tauri::async_runtime::spawn_blocking(move || diagnostics::create_bundle(&state, &data_dir))
+        .await
+        .map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))??;
+    Ok(path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-fn fix_browser_policies() -> Result<(), String> {
+fn fix_browser_policies() -> Result<(), CommandError> {
     let _ = blocking::disable_firefox_doh();
     let _ = blocking::disable_chromium_doh();
     let _ = blocking::flush_dns();
     Ok(())
 }
 
+#[tauri::command]
+fn get_safesearch_enforced(state: State<Arc<AppState>>) -> bool {
+    is_setting_enabled(&state, blocking::SAFESEARCH_SETTING)
+}
+
+/// Enforce SafeSearch and YouTube Restricted Mode through the hosts file and browser policies.
+#[tauri::command]
+fn set_safesearch_enforced(state: State<Arc<AppState>>, enabled: bool) -> Result<(), CommandError> {
+    if !enabled && state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(CommandError::new(ErrorCode::HardcoreLocked, "Cannot disable SafeSearch during a hardcore session"));
+    }
+    blocking::set_safesearch_enforced(enabled)?;
+    state.db.set_setting(blocking::SAFESEARCH_SETTING, if enabled { "true" } else { "false" }).map_err(CommandError::from)
+}
+
 // --- Application Setup ---
 
+/// Commands available in demo mode: reads only, nothing that changes the system or goes online.
+fn is_demo_command(command: &str) -> bool {
+    const ALLOWED: &[&str] = &["pomodoro_get_state", "micro_break_get_state", "export_pomodoro_config", "parse_site_input"];
+    const DENIED: &[&str] = &["get_leaderboard"];
+    ALLOWED.contains(&command)
+        || (["get_", "is_", "has_", "search_"].iter().any(|prefix| command.starts_with(prefix)) && !DENIED.contains(&command))
+}
+
+#[tauri::command]
+fn is_demo_mode(state: State<Arc<AppState>>) -> bool {
+    state.demo
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Started by a browser as the extension's native messaging host: relay to the running app
+    // (before the single-instance plugin, which would hand the launch to that app and exit)
+    if extension::is_native_host_launch(&std::env::args().collect::<Vec<_>>()) {
+        extension::run_native_host();
+        return;
+    }
+
+    // Demo / guest mode: runs against sample data and leaves the real setup untouched
+    let demo = std::env::args().any(|arg| arg == "--demo");
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_autostart::init(tauri_plugin_autostart::MacosLauncher::LaunchAgent, Some(vec!["--silent"])))
@@ -360,35 +2934,206 @@ pub fn run() {
         .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
             let _ = ensure_window(app);
         }))
-        .on_window_event(|_window, event| {
-            if let tauri::WindowEvent::CloseRequested { .. } = event {
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                if window.label() == BREAK_OVERLAY_LABEL && is_break_overlay_locked(&window.state::<Arc<AppState>>()) {
+                    api.prevent_close();
+                }
                 // By default in Tauri 2.0, CloseRequested will lead to window destruction.
                 // We let this happen to save resources (Ghost Mode).
                 // Our RunEvent::ExitRequested handler will prevent the app from quitting.
             }
         })
-        .setup(|app| {
+        .setup(move |app| {
             let data_dir = app.path().app_data_dir().expect("Failed to get app data dir");
-            let db = Database::new(data_dir).expect("Failed to initialize database");
-            let session_manager = SessionManager::new();
+            let db = if demo {
+                Database::demo().expect("Failed to create demo database")
+            } else {
+                Database::new(data_dir.clone()).expect("Failed to initialize database")
+            };
+            let micro_break_config: MicroBreakConfig = db.get_setting("micro_break_config")
+                .unwrap_or(None)
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default();
+            let session_manager = if demo {
+                SessionManager::new()
+            } else {
+                SessionManager::new()
+                    .with_lock_state_file(data_dir.join("session.lock"))
+                    .with_unlock_request_file(data_dir.join("unlock_request.json"))
+                    .with_protected_file(Database::file_path(&data_dir), true)
+            };
+            let event_log = eventlog::EventLogExporter::new(&db);
+
+            let telemetry_config: telemetry::TelemetryConfig = db.get_setting(telemetry::TELEMETRY_SETTING)
+                .unwrap_or(None)
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default();
+            // The batch exporter spawns onto the Tokio runtime
+            tauri::async_runtime::block_on(async { telemetry::init(&telemetry_config) });
+            
+            // Schedule windows ended early stay ended across a restart
+            let ended_windows = db.get_setting(session::ENDED_WINDOWS_SETTING)
+                .unwrap_or(None)
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default();
+            session_manager.restore_ended_windows(ended_windows);
+
+            // Re-apply a hardcore lock that was active before a restart (even if the db was wiped)
+            match session_manager.restore_persisted_session() {
+                Ok(Some(session)) => println!("[Bastion] Restored hardcore session '{}'", session.name),
+                Ok(None) => {}
+                Err(security::LockStateError::Corrupt) => {
+                    eprintln!("[Bastion] Hardcore lock state is unreadable; ignored");
+                }
+                Err(e) => {
+                    eprintln!("[Bastion] Hardcore lock state rejected ({:?}); lock reinstated", e);
+                    let _ = db.log_block_event("Hardcore lock state", "tamper", None);
+                }
+            }
+            // A temptation-delay wait in progress carries on where it was
+            if let Some(request) = session_manager.restore_unlock_request() {
+                println!("[Bastion] Restored unlock request for {:?}", request.target);
+            }
             
             let state = Arc::new(AppState { 
                 db, 
                 session_manager,
+                micro_breaks: MicroBreakEngine::new(micro_break_config),
+                plugins: plugins::PluginManager::load(&data_dir.join("plugins")),
+                unlock_tokens: security::UnlockTokens::default(),
+                pairing: companion::Pairing::default(),
+                event_log,
+                block_server: server::BlockServerStatus::default(),
+                dns_sinkhole: dns::DnsSinkhole::default(),
+                triggers: triggers::TriggerMonitor::default(),
+                processes: blocking::ProcessMonitor::default(),
+                apps: blocking::AppCatalog::default(),
+                proxy_guard: network::SettingsGuard::proxy(),
+                dns_guard: network::SettingsGuard::dns(),
+                last_loop_tick: std::sync::atomic::AtomicI64::new(chrono::Local::now().timestamp()),
+                demo,
                 app_handle: std::sync::Mutex::new(Some(app.handle().clone())),
             });
             app.manage(state.clone());
 
+            if demo {
+                // No plugins, enforcement, servers or sync: just the UI over the sample data
+                ensure_window(app.handle());
+                return Ok(());
+            }
+
+            // The pristine hosts file, for restores that keep the user's own entries
+            match blocking::snapshot_hosts_baseline(&data_dir) {
+                Ok(true) => println!("[Bastion] Saved the hosts file baseline"),
+                Ok(false) => {}
+                Err(e) => eprintln!("[Bastion] Could not snapshot the hosts file: {}", e.message),
+            }
+
+            // The PAC file is served from memory, so it needs the block list before the first request
+            if state.plugins.start(&state.db) || load_blocking_backend(&state) == blocking::BlockingBackend::Pac {
+                let _ = sync_blocked_websites(&state);
+            }
+
+            // A marker left by the previous run means it crashed: don't trust what it applied
+            if recovery::mark_running(&data_dir) {
+                let report = recovery::reconcile(&state);
+                println!("[Bastion] Recovered from an unclean shutdown: {:?}", report.repaired);
+                if !report.repaired.is_empty() {
+                    log_block_event(&state, "Unclean shutdown", "repair");
+                }
+                if let Ok(json) = serde_json::to_string(&report) {
+                    let _ = state.db.set_setting(recovery::RECOVERY_REPORT_SETTING, &json);
+                }
+                let _ = app.handle().emit("recovery-completed", &report);
+            } else {
+                // Sessions that weren't restored leave their blocks behind after a restart
+                revert_stale_session_blocks(&state);
+            }
+
+            // Bastion autostarts with the OS session, so startup counts as the first login
+            maybe_start_morning_routine(&state);
+
             // Handle Silent Startup
             let is_silent = std::env::args().any(|arg| arg == "--silent");
             if !is_silent {
                 ensure_window(app.handle());
             }
             
-            // Start blocking stats listener
+            // Scan installed applications ahead of the app picker
+            let catalog_state = state.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                catalog_state.apps.installed(false);
+            });
+
+            // The HTTPS block page, if the user opted in to the local CA
+            if state.db.get_setting(tls::HTTPS_BLOCK_PAGE_SETTING).unwrap_or(None).as_deref() == Some("true") {
+                match tls::BlockPageCa::load_or_create(&data_dir) {
+                    Ok(ca) => state.block_server.set_tls(Some(Arc::new(ca))),
+                    Err(e) => eprintln!("[Bastion] HTTPS block page unavailable: {}", e.message),
+                }
+            }
+
+            // Start blocking stats listener (only bound while something is blocked)
             let server_state = state.clone();
             tauri::async_runtime::spawn(async move {
-                server::start_block_server(server_state).await;
+                server::supervise_block_server(server_state).await;
+            });
+
+            // DNS sinkhole (only runs with the `dns` blocking backend)
+            let dns_state = state.clone();
+            tauri::async_runtime::spawn(async move {
+                dns::supervise_dns_sinkhole(dns_state).await;
+            });
+
+            // Companion browser extension API
+            let extension_state = state.clone();
+            tauri::async_runtime::spawn(async move {
+                extension::start_extension_server(extension_state).await;
+            });
+
+            // Mobile companion pairing and sync, reachable from the LAN once turned on
+            let companion_state = state.clone();
+            tauri::async_runtime::spawn(async move {
+                companion::supervise_companion_server(companion_state).await;
+            });
+
+            // Local control channel for the CLI, watchdog and scripts
+            let ipc_state = state.clone();
+            let ipc_data_dir = data_dir.clone();
+            tauri::async_runtime::spawn(async move {
+                ipc::start_ipc_server(ipc_state, ipc_data_dir).await;
+            });
+
+            // Foreground app time for the usage stats
+            let usage_state = state.clone();
+            tauri::async_runtime::spawn(async move {
+                usage::run_usage_tracker(usage_state).await;
+            });
+
+            // Deliver queued integration exports (retries while offline)
+            let integration_state = state.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = integrations::migrate_time_tracker_token(&integration_state.db) {
+                    eprintln!("[Bastion] Could not move the time tracker token to the keyring: {}", e);
+                }
+                let mut flush_interval = tokio::time::interval(std::time::Duration::from_secs(60));
+                loop {
+                    flush_interval.tick().await;
+                    integrations::flush_queue(&integration_state.db).await;
+                }
+            });
+
+            // Keep the published weekly total fresh for friends (opt-in)
+            let leaderboard_state = state.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut publish_interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+                loop {
+                    publish_interval.tick().await;
+                    if leaderboard::load_config(&leaderboard_state.db).enabled {
+                        let _ = leaderboard::sync(&leaderboard_state.db).await;
+                    }
+                }
             });
 
             // Core Background Loop
@@ -396,14 +3141,34 @@ pub fn run() {
             tauri::async_runtime::spawn(async move {
                 let mut timer_interval = tokio::time::interval(std::time::Duration::from_secs(1));
                 let mut enforcement_counter = 0;
+                let mut last_tick_at = chrono::Local::now().timestamp();
 
                 loop {
                     timer_interval.tick().await;
 
+                    // A long gap between ticks means the machine was asleep or locked
+                    let now = chrono::Local::now().timestamp();
+                    if now - last_tick_at > RESUME_GAP_SECS {
+                        maybe_start_morning_routine(&background_state);
+                    }
+                    last_tick_at = now;
+                    background_state.last_loop_tick.store(now, std::sync::atomic::Ordering::SeqCst);
+
                     // 1. Tick Pomodoro (every second)
-                    // This handles the countdown logic for Pomodoro sessions and sends notifications
-                    // when a phase (Work/Break) is completed.
-                    if let Some(completed_phase) = background_state.session_manager.pomodoro_tick() {
+                    // This handles the countdown logic for Pomodoro sessions, streams the remaining time
+                    // to the UI and sends notifications when a phase (Work/Break) is completed.
+                    let completed_phase = background_state.session_manager.pomodoro_tick();
+                    if completed_phase.is_some() || background_state.session_manager.get_pomodoro_state().is_running {
+                        emit_pomodoro_state(&background_state);
+                    }
+
+                    if let Some(completed_phase) = completed_phase {
+                        if completed_phase == session::PomodoroPhase::Work {
+                            let _ = background_state.db.log_pomodoro_completed();
+                            let work_minutes = background_state.session_manager.get_pomodoro_state().work_duration / 60;
+                            record_task_focus(&background_state, work_minutes, "pomodoro");
+                        }
+
                         // Phase changed, send notification
                         if let Some(handle) = background_state.app_handle.lock().unwrap().as_ref() {
                             use tauri_plugin_notification::NotificationExt;
@@ -419,60 +3184,129 @@ pub fn run() {
                                 .body(body)
                                 .show();
                         }
+                        
+                        sync_break_overlay(&background_state);
+                    }
+
+                    // Micro-break reminders (20-20-20)
+                    if let Some(event) = background_state.micro_breaks.tick() {
+                        if event == MicroBreakEvent::Started {
+                            if let Some(handle) = background_state.app_handle.lock().unwrap().as_ref() {
+                                use tauri_plugin_notification::NotificationExt;
+                                let _ = handle.notification()
+                                    .builder()
+                                    .title("Micro-break")
+                                    .body("Look at something 20 feet away for 20 seconds.")
+                                    .show();
+                            }
+                        } else {
+                            let _ = background_state.db.log_micro_break("taken");
+                        }
+                        emit_micro_break_state(&background_state);
                     }
 
-                    // 2. Enforce App Blocks (every 3 seconds)
-                    // We throttle this to save CPU resources. 3 seconds is frequent enough to prevent
-                    // meaningful usage of a blocked app, but infrequent enough to be negligible on CPU.
+                    // Detect system clock changes during a session (hardcore deadlines are monotonic)
+                    if background_state.session_manager.check_clock() {
+                        eprintln!("[Bastion] System clock change detected during an active session");
+                        log_block_event(&background_state, "System clock change", "tamper");
+                    }
+
+                    // 2. Enforcement tick (every 3 seconds)
+                    // Sessions, schedules, hosts and network guards. Blocked apps are closed by
+                    // their own daemon below, on its configurable interval.
                     enforcement_counter += 1;
                     if enforcement_counter >= 3 {
                         enforcement_counter = 0;
+                        let _span = tracing::info_span!("enforcement_tick").entered();
+
+                        // A system proxy set during a session would route around the hosts file
+                        let session_active = background_state.session_manager.get_time_remaining().is_some();
+                        let reset = background_state.proxy_guard.check(session_active);
+                        if !reset.is_empty() {
+                            eprintln!("[Bastion] Reset system proxy changes made during the session: {:?}", reset);
+                            log_block_event(&background_state, "System proxy", "tamper");
+                        }
+
+                        // Hardcore also pins adapter DNS, so a public resolver can't be swapped in
+                        let hardcore = background_state.session_manager.is_hardcore_locked.load(std::sync::atomic::Ordering::SeqCst);
+                        let reset = background_state.dns_guard.check(hardcore);
+                        if !reset.is_empty() {
+                            eprintln!("[Bastion] Reset DNS servers changed during hardcore mode: {:?}", reset);
+                            log_block_event(&background_state, "DNS servers", "tamper");
+                        }
                         
-                        // Check for Scheduled Sessions (every 30 seconds or if no manual session is active)
-                        // This allows Bastion to auto-start sessions based on the user's weekly schedule.
-                        let active_session = background_state.session_manager.active_session.lock().unwrap();
-                        let has_manual_session = active_session.is_some();
-                        drop(active_session);
-
-                        if !has_manual_session {
-                            if let Ok(sessions) = background_state.db.get_sessions() {
-                                if let Some(scheduled) = background_state.session_manager.check_scheduled_sessions(&sessions) {
-                                    // Start a scheduled session automatically
-                                    let _ = background_state.session_manager.start_session(
-                                        scheduled.name,
-                                        60, // Dummy duration, we check every tick
-                                        scheduled.hardcore
-                                    );
-                                }
+                        let sessions = background_state.db.get_sessions().unwrap_or_default();
+
+                        // Bedtime runs alongside focus sessions; resync the hosts file when it starts or ends
+                        let was_bedtime = background_state.session_manager.is_bedtime_active();
+                        let is_bedtime = background_state.session_manager.check_bedtime(&sessions).is_some();
+                        if was_bedtime != is_bedtime {
+                            let _ = sync_blocked_websites(&background_state);
+                            if let Some(handle) = background_state.app_handle.lock().unwrap().as_ref() {
+                                let _ = handle.emit("bedtime-changed", is_bedtime);
                             }
-                        } else {
-                            // Check if manual session expired
-                            if background_state.session_manager.is_session_expired() {
-                                let _ = background_state.session_manager.end_session();
+                        }
+
+                        // Category schedules block whole categories during their window
+                        if background_state.session_manager.check_category_schedules(&load_category_schedules(&background_state)) {
+                            let _ = sync_blocked_websites(&background_state);
+                            if let Some(handle) = background_state.app_handle.lock().unwrap().as_ref() {
+                                let _ = handle.emit("scheduled-categories-changed", background_state.session_manager.scheduled_categories());
                             }
                         }
 
-                        // App Blocking Enforcement
-                        if let Ok(apps) = background_state.db.get_blocked_apps() {
-                            let blocked_process_names: Vec<String> = apps
-                                .into_iter()
-                                .filter(|a| a.enabled)
-                                .map(|a| a.process_name)
-                                .collect();
-                            
-                            let killed = blocking::enforce_app_blocks(&blocked_process_names);
-                            
-                            // Log block events and notify frontend
-                            if !killed.is_empty() {
-                                for app in &killed {
-                                    let _ = background_state.db.log_block_event(app, "app");
-                                }
-                                // Emit event to all windows if any apps were killed
+                        // End sessions whose time is up; the rest keep running
+                        for session in background_state.session_manager.take_expired_sessions() {
+                            on_session_finished(&background_state, &session);
+                            if let Some(handle) = background_state.app_handle.lock().unwrap().as_ref() {
+                                let _ = handle.emit("session-completed", &session);
+                            }
+                        }
+
+                        // Scheduled sessions start alongside manual ones and run until the schedule ends;
+                        // a window ended early stays ended
+                        for (scheduled, ends_at) in background_state.session_manager.check_scheduled_sessions(&sessions) {
+                            if let Some(session) = background_state.session_manager.start_scheduled_session(
+                                &scheduled,
+                                ends_at,
+                                is_extreme_mode_enabled(&background_state),
+                            ) {
+                                on_session_started(&background_state, &session);
                                 if let Some(handle) = background_state.app_handle.lock().unwrap().as_ref() {
-                                    let _ = handle.emit("blocked-apps", killed);
+                                    let _ = handle.emit("scheduled-session-started", &session);
                                 }
                             }
                         }
+
+                        check_session_triggers(&background_state);
+                        check_pomodoro_idle(&background_state);
+                        maybe_send_daily_summary(&background_state);
+                        maybe_export_daily_note(&background_state);
+                        check_habitica_daily_goal(&background_state);
+                        let now = chrono::Local::now().timestamp();
+                        let _ = background_state.db.purge_due_session_deletions(now);
+                        let _ = background_state.db.purge_deleted(now - storage::DELETED_RETENTION_SECS);
+
+                        // Deliver new block events to plugins
+                        if background_state.plugins.dispatch_block_events(&background_state.db) {
+                            let _ = sync_blocked_websites(&background_state);
+                        }
+                        background_state.event_log.export_new_events(&background_state.db);
+                        retry_pending_hosts_sync(&background_state);
+                    }
+                }
+            });
+
+            // App blocking daemon: refreshes the process list and closes blocked apps every
+            // `interval_seconds`; it can only be stopped outside sessions
+            let enforcement_state = state.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    let config = load_app_enforcement_config(&enforcement_state);
+                    let interval = config.interval_seconds.clamp(blocking::MIN_ENFORCEMENT_INTERVAL_SECS, blocking::MAX_ENFORCEMENT_INTERVAL_SECS);
+                    tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+                    if config.enabled || is_app_enforcement_required(&enforcement_state) {
+                        enforce_blocked_apps(&enforcement_state);
                     }
                 }
             });
@@ -492,7 +3326,14 @@ pub fn run() {
                 .on_menu_event(|app, event| {
                     match event.id.as_ref() {
                         "quit" => {
-                            std::process::exit(0);
+                            let state = app.state::<Arc<AppState>>();
+                            if state.session_manager.get_time_remaining().is_some() {
+                                // Ask for the master password in the UI instead of quitting outright
+                                ensure_window(app);
+                                let _ = app.emit("quit-requested", ());
+                            } else {
+                                app.exit(0);
+                            }
                         }
                         "show" => {
                             ensure_window(app);
@@ -514,14 +3355,46 @@ pub fn run() {
 
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
+        .invoke_handler({
+            let handler: fn(tauri::ipc::Invoke) -> bool = tauri::generate_handler![
+            is_demo_mode,
             // Security
             is_onboarded,
             // Blocked Sites
             add_blocked_site,
+            parse_site_input,
             get_blocked_sites,
             toggle_blocked_site,
             delete_blocked_site,
+            is_domain_blocked,
+            set_blocked_site_mode,
+            set_blocked_site_subdomains,
+            get_blocklist_presets,
+            apply_blocklist_preset,
+            export_blocklist,
+            import_blocklist,
+            get_allowed_sites,
+            add_allowed_site,
+            delete_allowed_site,
+            get_dev_exclusions,
+            set_dev_exclusions,
+            // Browser Extension
+            get_extension_token,
+            regenerate_extension_token,
+            install_native_messaging_host,
+            set_companion_enabled,
+            start_companion_pairing,
+            cancel_companion_pairing,
+            confirm_companion_pairing,
+            get_companion_devices,
+            unpair_companion_device,
+            get_extension_strict_mode,
+            set_extension_strict_mode,
+            get_extension_policy_status,
+            get_youtube_rules,
+            set_youtube_rules,
+            get_site_time_limits,
+            set_site_time_limit,
             // Blocked Apps
             add_blocked_app,
             get_blocked_apps,
@@ -529,44 +3402,185 @@ pub fn run() {
             delete_blocked_app,
             get_installed_applications,
             get_running_processes,
+            get_process_icon,
+            search_apps,
             enforce_app_blocks,
+            get_app_enforcement_config,
+            start_app_enforcement,
+            stop_app_enforcement,
+            set_app_enforcement_interval,
+            get_installer_block_list,
+            set_installer_block_list,
             // Sessions
             add_session,
             get_sessions,
+            update_session,
             delete_session,
+            cancel_session_deletion,
+            get_deleted_items,
+            undo_delete,
+            get_script_hooks,
+            set_script_hooks,
+            get_morning_routine,
+            set_morning_routine,
+            get_session_triggers,
+            set_session_triggers,
+            get_blocklist_profiles,
+            set_blocklist_profiles,
+            get_category_schedules,
+            set_category_schedules,
             start_focus_session,
             end_focus_session,
+            get_active_sessions,
+            get_unlock_friction,
+            request_unlock_challenge,
+            get_temptation_delay,
+            set_temptation_delay,
+            request_unlock,
+            poll_unlock_status,
+            cancel_unlock,
+            get_friction_policy,
+            set_friction_policy,
+            reset_friction_policy,
+            extend_session,
+            harden_session,
+            get_session_extensions,
+            get_session_stats,
             get_session_time_remaining,
             is_hardcore_locked,
             // Pomodoro
             pomodoro_start,
+            get_pomodoro_idle_config,
+            set_pomodoro_idle_config,
             pomodoro_pause,
             pomodoro_reset,
+            pomodoro_skip_phase,
+            close_break_overlay,
             pomodoro_get_state,
             pomodoro_configure,
+            get_pomodoro_presets,
+            set_pomodoro_presets,
+            export_pomodoro_config,
+            import_pomodoro_config,
+            // Micro-breaks
+            micro_break_get_state,
+            micro_break_configure,
+            micro_break_snooze,
+            micro_break_skip,
+            get_micro_break_stats,
+            // Tasks
+            add_task,
+            get_tasks,
+            update_task,
+            delete_task,
+            get_active_task,
+            set_active_task,
+            // Usage
+            get_process_classifications,
+            set_process_classification,
+            reset_process_classification,
+            get_usage_breakdown,
+            get_site_usage,
+            get_app_usage,
+            get_usage_tracking_config,
+            set_usage_tracking_config,
+            // Integrations
+            get_integration_queue,
+            flush_integration_queue,
+            get_beeminder_config,
+            set_beeminder_config,
+            set_time_tracker_token,
+            has_time_tracker_token,
+            set_beeminder_token,
+            has_beeminder_token,
+            get_habitica_config,
+            set_habitica_config,
+            set_habitica_api_token,
+            has_habitica_api_token,
+            get_leaderboard_config,
+            set_leaderboard_config,
+            get_leaderboard,
+            // Plugins
+            get_plugins,
             // Stats
             get_recent_blocks,
+            get_dashboard_snapshot,
             get_focus_stats,
+            get_daily_summary,
+            get_daily_summary_config,
+            get_daily_note_config,
+            set_daily_note_config,
+            export_daily_note,
+            set_daily_summary_config,
+            get_pomodoro_history,
+            get_week_comparison,
+            get_quotes,
+            add_quote,
+            update_quote,
+            delete_quote,
+            get_random_quote,
+            import_quote_pack,
+            get_target_trend,
+            get_category_breakdown,
+            get_streak,
+            get_streak_freeze_history,
+            get_session_block_split,
             log_protected_time,
             // Settings
             get_setting,
             set_setting,
             get_block_counts,
             get_block_counts,
+            get_telemetry_config,
+            set_telemetry_config,
             factory_reset,
             set_master_password,
+            clear_master_password,
             verify_master_password,
+            authenticate,
+            lock,
             emergency_unlock,
             // System
             is_app_admin,
             kill_browsers,
+            quit_app,
+            get_exit_behavior,
+            get_recovery_report,
+            set_exit_behavior,
+            relaunch_elevated,
+            restore_hosts_file,
+            take_restore_ui_state,
             fix_browser_policies,
+            get_safesearch_enforced,
+            set_safesearch_enforced,
+            get_protection_status,
+            get_block_server_config,
+            set_block_server_config,
+            get_blocking_backend,
+            set_blocking_backend,
+            get_dns_sinkhole_config,
+            set_dns_sinkhole_config,
+            get_dns_sinkhole_status,
+            get_https_block_page_status,
+            enable_https_block_page,
+            disable_https_block_page,
+            run_blocking_selftest,
+            create_diagnostics_bundle,
             reset_all_blocks,
-        ])
+            ];
+            move |invoke| {
+                if demo && !is_demo_command(invoke.message.command()) {
+                    invoke.resolver.reject(CommandError::new(ErrorCode::DemoMode, "Not available in demo mode"));
+                    return true;
+                }
+                handler(invoke)
+            }
+        })
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
         .run(|app_handle, event| match event {
-            tauri::RunEvent::ExitRequested { api, .. } => {
+            // `code` is set for explicit exits (`AppHandle::exit`), which always go through
+            tauri::RunEvent::ExitRequested { api, code: None, .. } => {
                 let state = app_handle.state::<Arc<AppState>>();
                 let minimize_to_tray = state.db.get_setting("minimize_to_tray")
                     .unwrap_or(Some("true".to_string()))
@@ -574,10 +3588,15 @@ pub fn run() {
 
                 // In Ghost Mode, we always prevent exit if tray is enabled, 
                 // because the "main" window closing is normal behavior for backgrounding.
-                if minimize_to_tray {
+                // During a session, quitting is only possible through `quit_app` (password protected).
+                let session_active = state.session_manager.get_time_remaining().is_some();
+                if minimize_to_tray || session_active {
                     api.prevent_exit();
                 }
             }
+            tauri::RunEvent::Exit => {
+                shutdown(app_handle);
+            }
             _ => {}
         });
 }
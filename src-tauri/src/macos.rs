@@ -0,0 +1,185 @@
+//! macOS module - Platform pieces of blocking that the hosts file and signals don't cover
+//!
+//! - Network: a pf anchor under Apple's `com.apple/*` anchor (so `/etc/pf.conf` stays untouched)
+//!   drops traffic to well-known DNS-over-HTTPS/TLS resolvers while something is blocked, so
+//!   browsers fall back to the system resolver and the hosts entries apply. Blocked sites
+//!   themselves aren't put in pf: their names already resolve to localhost, and their real
+//!   addresses are often shared CDN addresses.
+//! - Apps: GUI apps are asked to quit through AppleScript before any signal is sent. An app
+//!   killed by SIGTERM counts as crashed, which launchd agents with `KeepAlive` restart at once.
+//! - Browsers: Chromium browsers read `DnsOverHttpsMode` from their preference domain in
+//!   `/Library/Preferences`. Safari has no DoH of its own and follows the system resolver.
+
+use crate::blocking::{BlockingError, BrowserPolicyStatus};
+use std::path::Path;
+
+/// Loaded below Apple's anchor, which the stock pf.conf already evaluates
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+const PF_ANCHOR: &str = "com.apple/250.Bastion";
+
+/// Public resolvers browsers use for DoH by default or as a suggested provider
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+const DOH_RESOLVERS: &[&str] = &[
+    "1.1.1.1", "1.0.0.1", "2606:4700:4700::1111", "2606:4700:4700::1001", // Cloudflare
+    "8.8.8.8", "8.8.4.4", "2001:4860:4860::8888", "2001:4860:4860::8844", // Google
+    "9.9.9.9", "149.112.112.112", "2620:fe::fe", "2620:fe::9",            // Quad9
+    "208.67.222.222", "208.67.220.220",                                   // OpenDNS
+    "94.140.14.14", "94.140.15.15",                                       // AdGuard
+    "45.90.28.0", "45.90.30.0",                                           // NextDNS
+];
+
+/// Chromium browsers as (name, preference domain)
+const CHROMIUM_PREFERENCE_DOMAINS: &[(&str, &str)] = &[
+    ("Chrome", "com.google.Chrome"),
+    ("Edge", "com.microsoft.Edge"),
+    ("Brave", "com.brave.Browser"),
+    ("Chromium", "org.chromium.Chromium"),
+];
+
+/// Rules for `PF_ANCHOR`: DoH (443) and DoT (853) to the known resolvers, and DoT anywhere.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn render_pf_rules() -> String {
+    format!(
+        "table <bastion_doh> const {{ {} }}\n\
+         block drop out quick proto {{ tcp, udp }} from any to <bastion_doh> port {{ 443, 853 }}\n\
+         block drop out quick proto {{ tcp, udp }} from any to any port 853\n",
+        DOH_RESOLVERS.join(", ")
+    )
+}
+
+/// Load the anchor rules while something is blocked and flush them otherwise. Needs root.
+pub fn apply_pf_rules(active: bool) -> Result<(), BlockingError> {
+    #[cfg(target_os = "macos")]
+    {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        if !active {
+            run_pfctl(Command::new("pfctl").args(["-a", PF_ANCHOR, "-F", "rules"]).output()?)?;
+            return Ok(());
+        }
+        let mut child = Command::new("pfctl")
+            .args(["-a", PF_ANCHOR, "-f", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(render_pf_rules().as_bytes())?;
+        }
+        run_pfctl(child.wait_with_output()?)?;
+        // pf is off by default; "-e" fails harmlessly when it is already on
+        let _ = Command::new("pfctl").arg("-e").output();
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = active;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn run_pfctl(output: std::process::Output) -> Result<(), BlockingError> {
+    if output.status.success() {
+        return Ok(());
+    }
+    let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(BlockingError { permission_denied: message.contains("Permission denied"), message })
+}
+
+/// The `.app` bundle an executable belongs to, e.g. `/Applications/Slack.app`.
+pub fn app_bundle(exe: &Path) -> Option<&Path> {
+    exe.ancestors().find(|dir| dir.extension().is_some_and(|ext| ext == "app"))
+}
+
+/// AppleScript asking the app at `bundle` to quit, giving up after a few seconds (an app with
+/// unsaved changes may be waiting on a dialog).
+pub fn quit_app_script(bundle: &Path) -> String {
+    let path = bundle.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\"");
+    format!("with timeout of 3 seconds\ntell application \"{}\" to quit\nend timeout", path)
+}
+
+/// Ask the GUI app `exe` belongs to to quit. Returns whether the request was delivered; the
+/// caller still checks that the process is gone.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn quit_app(exe: &Path) -> bool {
+    let Some(bundle) = app_bundle(exe) else {
+        return false;
+    };
+    std::process::Command::new("osascript")
+        .args(["-e", &quit_app_script(bundle)])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Turn off DoH in the Chromium browsers that are installed. Needs admin rights.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn disable_chromium_doh() -> Result<(), BlockingError> {
+    use std::process::Command;
+
+    for (_browser, domain) in CHROMIUM_PREFERENCE_DOMAINS.iter().filter(|(browser, _)| is_chromium_installed(browser)) {
+        let preferences = format!("/Library/Preferences/{}", domain);
+        let output = Command::new("defaults").args(["write", &preferences, "DnsOverHttpsMode", "-string", "off"]).output()?;
+        if !output.status.success() {
+            return Err(BlockingError {
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                permission_denied: true,
+            });
+        }
+        Command::new("defaults").args(["write", &preferences, "BuiltInDnsClientEnabled", "-bool", "false"]).output()?;
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn chromium_doh_status() -> Vec<BrowserPolicyStatus> {
+    CHROMIUM_PREFERENCE_DOMAINS
+        .iter()
+        .filter(|(browser, _)| is_chromium_installed(browser))
+        .map(|(browser, domain)| {
+            let doh_disabled = std::process::Command::new("defaults")
+                .args(["read", &format!("/Library/Preferences/{}", domain), "DnsOverHttpsMode"])
+                .output()
+                .is_ok_and(|output| output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "off");
+            BrowserPolicyStatus { browser: browser.to_string(), doh_disabled }
+        })
+        .collect()
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn is_chromium_installed(browser: &str) -> bool {
+    let app = match browser {
+        "Chrome" => "Google Chrome.app",
+        "Edge" => "Microsoft Edge.app",
+        "Brave" => "Brave Browser.app",
+        _ => "Chromium.app",
+    };
+    let home = std::env::var("HOME").unwrap_or_default();
+    Path::new("/Applications").join(app).exists() || Path::new(&home).join("Applications").join(app).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pf_rules() {
+        let rules = render_pf_rules();
+        assert!(rules.starts_with("table <bastion_doh> const { 1.1.1.1, 1.0.0.1, "));
+        assert!(rules.contains("to <bastion_doh> port { 443, 853 }\n"));
+        assert_eq!(rules.lines().count(), 3);
+    }
+
+    #[test]
+    fn test_quit_app() {
+        let exe = Path::new("/Applications/Slack.app/Contents/MacOS/Slack");
+        assert_eq!(app_bundle(exe), Some(Path::new("/Applications/Slack.app")));
+        assert_eq!(app_bundle(Path::new("/usr/bin/python3")), None);
+        assert_eq!(
+            quit_app_script(Path::new("/Applications/My \"App\".app")),
+            "with timeout of 3 seconds\ntell application \"/Applications/My \\\"App\\\".app\" to quit\nend timeout"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Micro-break module - Periodic eye-rest reminders (20-20-20 rule)
+//!
+//! Every `interval_secs` of screen time, a short break of `duration_secs` is started.
+//! A break can be snoozed a limited number of times or skipped; the outcome is recorded
+//! by the caller for adherence stats.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MicroBreakConfig {
+    pub enabled: bool,
+    pub interval_secs: i64,
+    pub duration_secs: i64,
+    pub snooze_secs: i64,
+    pub max_snoozes: i32, // Snoozes allowed per break
+}
+
+impl Default for MicroBreakConfig {
+    fn default() -> Self {
+        MicroBreakConfig {
+            enabled: false,
+            interval_secs: 20 * 60,
+            duration_secs: 20,
+            snooze_secs: 5 * 60,
+            max_snoozes: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MicroBreakState {
+    pub config: MicroBreakConfig,
+    pub next_break_in: i64,            // seconds
+    pub break_remaining: Option<i64>,  // seconds, while a break is running
+    pub snoozes_used: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MicroBreakEvent {
+    Started,
+    Finished,
+}
+
+pub struct MicroBreakEngine {
+    state: Mutex<MicroBreakState>,
+}
+
+impl MicroBreakEngine {
+    pub fn new(config: MicroBreakConfig) -> Self {
+        MicroBreakEngine {
+            state: Mutex::new(MicroBreakState {
+                next_break_in: config.interval_secs,
+                break_remaining: None,
+                snoozes_used: 0,
+                config,
+            }),
+        }
+    }
+
+    /// Apply a new configuration and restart the countdown.
+    pub fn configure(&self, config: MicroBreakConfig) {
+        let mut state = self.state.lock().unwrap();
+        state.next_break_in = config.interval_secs;
+        state.break_remaining = None;
+        state.snoozes_used = 0;
+        state.config = config;
+    }
+
+    pub fn get_state(&self) -> MicroBreakState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Advance the engine by one second (call every second).
+    pub fn tick(&self) -> Option<MicroBreakEvent> {
+        let mut state = self.state.lock().unwrap();
+        if !state.config.enabled {
+            return None;
+        }
+
+        if let Some(remaining) = state.break_remaining {
+            if remaining <= 1 {
+                state.break_remaining = None;
+                state.next_break_in = state.config.interval_secs;
+                state.snoozes_used = 0;
+                return Some(MicroBreakEvent::Finished);
+            }
+            state.break_remaining = Some(remaining - 1);
+            return None;
+        }
+
+        state.next_break_in -= 1;
+        if state.next_break_in <= 0 {
+            state.break_remaining = Some(state.config.duration_secs);
+            return Some(MicroBreakEvent::Started);
+        }
+        None
+    }
+
+    /// Postpone the running break by `snooze_secs`, up to `max_snoozes` times.
+    pub fn snooze(&self) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        if state.break_remaining.is_none() {
+            return Err("No micro-break is running".to_string());
+        }
+        if state.snoozes_used >= state.config.max_snoozes {
+            return Err("No snoozes left for this break".to_string());
+        }
+        state.break_remaining = None;
+        state.next_break_in = state.config.snooze_secs;
+        state.snoozes_used += 1;
+        Ok(())
+    }
+
+    /// Skip the running break and start counting towards the next one.
+    pub fn skip(&self) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        if state.break_remaining.is_none() {
+            return Err("No micro-break is running".to_string());
+        }
+        state.break_remaining = None;
+        state.next_break_in = state.config.interval_secs;
+        state.snoozes_used = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_micro_break_cycle_and_snooze_limit() {
+        let engine = MicroBreakEngine::new(MicroBreakConfig {
+            enabled: true,
+            interval_secs: 2,
+            duration_secs: 1,
+            snooze_secs: 1,
+            max_snoozes: 1,
+        });
+
+        assert_eq!(engine.tick(), None);
+        assert_eq!(engine.tick(), Some(MicroBreakEvent::Started));
+
+        // One snooze allowed, then the break has to be taken
+        assert!(engine.snooze().is_ok());
+        assert_eq!(engine.tick(), Some(MicroBreakEvent::Started));
+        assert!(engine.snooze().is_err());
+        assert_eq!(engine.tick(), Some(MicroBreakEvent::Finished));
+    }
+}
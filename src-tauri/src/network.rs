@@ -0,0 +1,388 @@
+//! Network module - Guards OS network settings that can route around hosts blocking
+//!
+//! Pointing the system proxy at an outside proxy, or the adapter DNS at a public resolver
+//! together with a DoH-capable client, bypasses the hosts file. A `SettingsGuard` records the
+//! settings when protection starts (any session for the proxy, hardcore sessions for DNS) and
+//! writes the recorded values back whenever `check` finds them changed.
+//!
+//! The same settings carry the `pac` blocking backend: `set_pac_url` points the system proxy at
+//! the PAC file served by the block server.
+//!
+//! Settings are read per platform as key/value pairs:
+//! - proxy: the WinINET registry values on Windows, `networksetup` per network service on
+//!   macOS and the `org.gnome.system.proxy` schema on Linux (GNOME only)
+//! - DNS: `netsh` per interface on Windows, `networksetup` per service on macOS and
+//!   NetworkManager connections on Linux
+
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::sync::Mutex;
+
+pub type Settings = BTreeMap<String, String>;
+
+pub struct SettingsGuard {
+    read: fn() -> Option<Settings>,
+    /// Restore one key; an empty value means the key wasn't there at the start
+    write: fn(&str, &str) -> Result<(), String>,
+    /// Settings recorded when protection started; `None` while inactive
+    baseline: Mutex<Option<Settings>>,
+}
+
+impl SettingsGuard {
+    pub fn proxy() -> Self {
+        SettingsGuard { read: read_proxy_settings, write: write_proxy_setting, baseline: Mutex::new(None) }
+    }
+
+    pub fn dns() -> Self {
+        SettingsGuard { read: read_dns_settings, write: write_dns_setting, baseline: Mutex::new(None) }
+    }
+
+    /// Record the baseline on the first check while `active`, then restore anything that changed.
+    /// Returns the keys that had to be reset.
+    pub fn check(&self, active: bool) -> Vec<String> {
+        let mut baseline = self.baseline.lock().unwrap();
+        if !active {
+            *baseline = None;
+            return Vec::new();
+        }
+        let Some(current) = (self.read)() else {
+            return Vec::new();
+        };
+        let Some(expected) = baseline.as_ref() else {
+            *baseline = Some(current);
+            return Vec::new();
+        };
+
+        let changed = changed_keys(expected, &current);
+        for key in &changed {
+            let value = expected.get(key).map(String::as_str).unwrap_or_default();
+            if let Err(e) = (self.write)(key, value) {
+                eprintln!("[Bastion] Could not reset network setting {}: {}", key, e);
+            }
+        }
+        changed
+    }
+
+    /// Forget the baseline, so the next check records the current settings. Used after Bastion
+    /// changed the settings itself (see `set_pac_url`).
+    pub fn rebaseline(&self) {
+        *self.baseline.lock().unwrap() = None;
+    }
+}
+
+/// Keys whose value differs between `expected` and `current`, including added or removed keys
+pub fn changed_keys(expected: &Settings, current: &Settings) -> Vec<String> {
+    let keys: std::collections::BTreeSet<&String> = expected.keys().chain(current.keys()).collect();
+    keys.into_iter()
+        .filter(|key| expected.get(*key) != current.get(*key))
+        .cloned()
+        .collect()
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program).args(args).output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(target_os = "windows")]
+const INTERNET_SETTINGS_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Internet Settings";
+
+#[cfg(target_os = "windows")]
+const PROXY_VALUES: &[&str] = &["ProxyEnable", "ProxyServer", "AutoConfigURL"];
+
+#[cfg(target_os = "windows")]
+fn read_proxy_settings() -> Option<Settings> {
+    let output = run("reg", &["query", INTERNET_SETTINGS_KEY]).ok()?;
+    // "    ProxyEnable    REG_DWORD    0x1"
+    let mut settings: Settings = PROXY_VALUES.iter().map(|v| (v.to_string(), String::new())).collect();
+    for line in output.lines() {
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        if let [name, _kind, value @ ..] = cols.as_slice() {
+            if let Some(entry) = settings.get_mut(*name) {
+                *entry = value.join(" ");
+            }
+        }
+    }
+    Some(settings)
+}
+
+/// Takes effect for new connections; running browsers may keep the old proxy until restarted.
+#[cfg(target_os = "windows")]
+fn write_proxy_setting(key: &str, value: &str) -> Result<(), String> {
+    if value.is_empty() {
+        return run("reg", &["delete", INTERNET_SETTINGS_KEY, "/v", key, "/f"]).map(|_| ());
+    }
+    let (kind, data) = match key {
+        "ProxyEnable" => ("REG_DWORD", value.trim_start_matches("0x").to_string()),
+        _ => ("REG_SZ", value.to_string()),
+    };
+    run("reg", &["add", INTERNET_SETTINGS_KEY, "/v", key, "/t", kind, "/d", &data, "/f"]).map(|_| ())
+}
+
+/// Use the PAC file at `url` as the system proxy configuration, or with `enabled` false, stop
+/// using it (left alone if the user has since pointed the setting elsewhere).
+#[cfg(target_os = "windows")]
+pub fn set_pac_url(url: &str, enabled: bool) -> Result<(), String> {
+    if enabled {
+        return write_proxy_setting("AutoConfigURL", url);
+    }
+    let current = read_proxy_settings().and_then(|settings| settings.get("AutoConfigURL").cloned());
+    if current.as_deref() == Some(url) {
+        write_proxy_setting("AutoConfigURL", "")?;
+    }
+    Ok(())
+}
+
+/// Enabled macOS network services ("Wi-Fi", "Ethernet", ...)
+#[cfg(target_os = "macos")]
+fn network_services() -> Option<Vec<String>> {
+    let services = run("networksetup", &["-listallnetworkservices"]).ok()?;
+    // The first line explains that '*' marks disabled services
+    Some(services.lines().skip(1).filter(|s| !s.starts_with('*')).map(str::to_string).collect())
+}
+
+#[cfg(target_os = "macos")]
+const PROXY_KINDS: &[&str] = &["webproxy", "securewebproxy", "socksfirewallproxy"];
+
+/// Keys are "<service>|<kind>", values "off" or "on <host> <port>" ("on <url>" for autoproxy)
+#[cfg(target_os = "macos")]
+fn read_proxy_settings() -> Option<Settings> {
+    let mut settings = Settings::new();
+    for service in network_services()? {
+        let service = service.as_str();
+        for kind in PROXY_KINDS {
+            let output = run("networksetup", &[&format!("-get{}", kind), service]).ok()?;
+            let field = |name: &str| {
+                output.lines().find_map(|line| line.strip_prefix(name)).map(str::trim).unwrap_or_default().to_string()
+            };
+            let value = if field("Enabled:") == "Yes" {
+                format!("on {} {}", field("Server:"), field("Port:"))
+            } else {
+                "off".to_string()
+            };
+            settings.insert(format!("{}|{}", service, kind), value);
+        }
+        let output = run("networksetup", &["-getautoproxyurl", service]).ok()?;
+        let url = output.lines().find_map(|line| line.strip_prefix("URL:")).map(str::trim).unwrap_or_default();
+        let enabled = output.lines().any(|line| line.trim() == "Enabled: Yes");
+        let value = if enabled { format!("on {}", url) } else { "off".to_string() };
+        settings.insert(format!("{}|autoproxy", service), value);
+    }
+    Some(settings)
+}
+
+#[cfg(target_os = "macos")]
+fn write_proxy_setting(key: &str, value: &str) -> Result<(), String> {
+    let Some((service, kind)) = key.rsplit_once('|') else {
+        return Err(format!("Unknown proxy setting {}", key));
+    };
+    let kind = if kind == "autoproxy" { "autoproxyurl" } else { kind };
+    let state_kind = if kind == "autoproxyurl" { "autoproxy" } else { kind };
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    match parts.as_slice() {
+        ["on", url] if kind == "autoproxyurl" => run("networksetup", &["-setautoproxyurl", service, url]),
+        ["on", host, port] => run("networksetup", &[&format!("-set{}", kind), service, host, port]),
+        // A service that appeared during the session has no recorded value: turn its proxy off
+        _ => run("networksetup", &[&format!("-set{}state", state_kind), service, "off"]),
+    }
+    .map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+pub fn set_pac_url(url: &str, enabled: bool) -> Result<(), String> {
+    let ours = format!("on {}", url);
+    let settings = read_proxy_settings().ok_or("Could not read the proxy settings")?;
+    for (key, value) in settings.iter().filter(|(key, _)| key.ends_with("|autoproxy")) {
+        match (enabled, *value == ours) {
+            (true, false) => write_proxy_setting(key, &ours)?,
+            (false, true) => write_proxy_setting(key, "off")?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+const GNOME_PROXY_KEYS: &[(&str, &str)] = &[
+    ("org.gnome.system.proxy", "mode"),
+    ("org.gnome.system.proxy", "autoconfig-url"),
+    ("org.gnome.system.proxy.http", "host"),
+    ("org.gnome.system.proxy.http", "port"),
+    ("org.gnome.system.proxy.https", "host"),
+    ("org.gnome.system.proxy.https", "port"),
+    ("org.gnome.system.proxy.socks", "host"),
+    ("org.gnome.system.proxy.socks", "port"),
+];
+
+/// Keys are "<schema> <key>", values as printed by `gsettings get` (GVariant text)
+#[cfg(target_os = "linux")]
+fn read_proxy_settings() -> Option<Settings> {
+    GNOME_PROXY_KEYS
+        .iter()
+        .map(|(schema, key)| {
+            let value = run("gsettings", &["get", schema, key]).ok()?;
+            Some((format!("{} {}", schema, key), value.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn write_proxy_setting(key: &str, value: &str) -> Result<(), String> {
+    let Some((schema, name)) = key.split_once(' ') else {
+        return Err(format!("Unknown proxy setting {}", key));
+    };
+    run("gsettings", &["set", schema, name, value]).map(|_| ())
+}
+
+#[cfg(target_os = "linux")]
+pub fn set_pac_url(url: &str, enabled: bool) -> Result<(), String> {
+    let ours = format!("'{}'", url);
+    if enabled {
+        write_proxy_setting("org.gnome.system.proxy autoconfig-url", &ours)?;
+        return write_proxy_setting("org.gnome.system.proxy mode", "'auto'");
+    }
+    let current = run("gsettings", &["get", "org.gnome.system.proxy", "autoconfig-url"])?;
+    if current.trim() == ours {
+        write_proxy_setting("org.gnome.system.proxy mode", "'none'")?;
+    }
+    Ok(())
+}
+
+/// Keys are interface names, values "dhcp" or "static <server> <server>..."
+#[cfg(target_os = "windows")]
+fn read_dns_settings() -> Option<Settings> {
+    run("netsh", &["interface", "ipv4", "show", "dnsservers"]).ok().map(|output| parse_netsh_dns(&output))
+}
+
+/// Parse `netsh interface ipv4 show dnsservers`: servers are the IP addresses listed under
+/// each "Configuration for interface" header, static unless the block mentions DHCP.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn parse_netsh_dns(output: &str) -> Settings {
+    let mut blocks: Vec<(String, bool, Vec<String>)> = Vec::new();
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("Configuration for interface ") {
+            blocks.push((name.trim_matches('"').to_string(), false, Vec::new()));
+        } else if let Some((_, dhcp, servers)) = blocks.last_mut() {
+            *dhcp |= line.contains("DHCP");
+            let value = line.rsplit(':').next().unwrap_or(line).trim();
+            if let Ok(ip) = value.parse::<std::net::IpAddr>() {
+                servers.push(ip.to_string());
+            }
+        }
+    }
+    blocks
+        .into_iter()
+        .map(|(name, dhcp, servers)| {
+            let value = if dhcp { "dhcp".to_string() } else { format!("static {}", servers.join(" ")).trim_end().to_string() };
+            (name, value)
+        })
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+fn write_dns_setting(interface: &str, value: &str) -> Result<(), String> {
+    let name = format!("name={}", interface);
+    let servers: Vec<&str> = value.strip_prefix("static").map(|s| s.split_whitespace().collect()).unwrap_or_default();
+    let Some((primary, others)) = servers.split_first() else {
+        return run("netsh", &["interface", "ipv4", "set", "dnsservers", &name, "source=dhcp"]).map(|_| ());
+    };
+    let address = format!("address={}", primary);
+    run("netsh", &["interface", "ipv4", "set", "dnsservers", &name, "source=static", &address, "register=primary", "validate=no"])?;
+    for (i, server) in others.iter().enumerate() {
+        let address = format!("address={}", server);
+        let index = format!("index={}", i + 2);
+        run("netsh", &["interface", "ipv4", "add", "dnsservers", &name, &address, &index, "validate=no"])?;
+    }
+    Ok(())
+}
+
+/// Keys are network services, values "auto" or the servers separated by spaces
+#[cfg(target_os = "macos")]
+fn read_dns_settings() -> Option<Settings> {
+    let mut settings = Settings::new();
+    for service in network_services()? {
+        let output = run("networksetup", &["-getdnsservers", &service]).ok()?;
+        let value = if output.contains("aren't any DNS Servers") {
+            "auto".to_string()
+        } else {
+            output.split_whitespace().collect::<Vec<_>>().join(" ")
+        };
+        settings.insert(service, value);
+    }
+    Some(settings)
+}
+
+#[cfg(target_os = "macos")]
+fn write_dns_setting(service: &str, value: &str) -> Result<(), String> {
+    let mut args = vec!["-setdnsservers", service];
+    match value {
+        "" | "auto" => args.push("Empty"),
+        servers => args.extend(servers.split_whitespace()),
+    }
+    run("networksetup", &args).map(|_| ())
+}
+
+/// Keys are active NetworkManager connection UUIDs, values "<ipv4.dns>|<ipv4.ignore-auto-dns>"
+#[cfg(target_os = "linux")]
+fn read_dns_settings() -> Option<Settings> {
+    let active = run("nmcli", &["-t", "-f", "UUID", "connection", "show", "--active"]).ok()?;
+    active
+        .lines()
+        .filter(|uuid| !uuid.is_empty())
+        .map(|uuid| {
+            let output = run("nmcli", &["-g", "ipv4.dns,ipv4.ignore-auto-dns", "connection", "show", uuid]).ok()?;
+            let mut lines = output.lines();
+            let dns = lines.next().unwrap_or_default().trim();
+            let ignore_auto = lines.next().unwrap_or("no").trim();
+            Some((uuid.to_string(), format!("{}|{}", dns, ignore_auto)))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn write_dns_setting(uuid: &str, value: &str) -> Result<(), String> {
+    let (dns, ignore_auto) = value.split_once('|').unwrap_or(("", "no"));
+    run("nmcli", &["connection", "modify", uuid, "ipv4.dns", dns, "ipv4.ignore-auto-dns", ignore_auto])?;
+    // Reapply so the restored servers are used without waiting for a reconnect
+    run("nmcli", &["connection", "up", uuid]).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_netsh_dns() {
+        let output = "\r
+Configuration for interface \"Ethernet\"\r
+    DNS servers configured through DHCP:  192.168.1.1\r
+    Register with which suffix:           Primary only\r
+\r
+Configuration for interface \"Wi-Fi\"\r
+    Statically Configured DNS Servers:    1.1.1.1\r
+                                          8.8.8.8\r
+    Register with which suffix:           Primary only\r
+";
+        let settings = parse_netsh_dns(output);
+        assert_eq!(settings.get("Ethernet").map(String::as_str), Some("dhcp"));
+        assert_eq!(settings.get("Wi-Fi").map(String::as_str), Some("static 1.1.1.1 8.8.8.8"));
+    }
+
+    #[test]
+    fn test_changed_keys() {
+        let expected: Settings = [("ProxyEnable", "0x0"), ("ProxyServer", "")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let mut current = expected.clone();
+        assert!(changed_keys(&expected, &current).is_empty());
+
+        current.insert("ProxyEnable".to_string(), "0x1".to_string());
+        current.insert("AutoConfigURL".to_string(), "http://evil/pac".to_string());
+        assert_eq!(changed_keys(&expected, &current), vec!["AutoConfigURL", "ProxyEnable"]);
+    }
+}
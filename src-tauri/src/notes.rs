@@ -0,0 +1,178 @@
+//! Notes module - Daily focus summary for Markdown / Obsidian daily notes
+//!
+//! At the end of the day (or on demand through `export_daily_note`), the day's focus time,
+//! sessions, pomodoros and most blocked sites are rendered through a user-editable template
+//! and appended to that day's note in a vault folder, creating the note if it doesn't exist.
+
+use crate::storage::{SessionRecord, TopBlock};
+use crate::summary::DailySummary;
+use chrono::{Local, NaiveDateTime, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+pub const DAILY_NOTE_SETTING: &str = "daily_note_export";
+/// Date (YYYY-MM-DD) the daily note was last exported automatically
+pub const DAILY_NOTE_LAST_EXPORT_SETTING: &str = "daily_note_last_export";
+
+/// Placeholders: {{date}}, {{focus_time}}, {{focus_minutes}}, {{goal_percent}}, {{pomodoros}},
+/// {{blocks}}, {{sessions}} and {{top_blocks}} (the last two are Markdown lists).
+pub const DEFAULT_TEMPLATE: &str = "## Focus (Bastion)
+
+- Focused: {{focus_time}} ({{goal_percent}}% of goal)
+- Pomodoros: {{pomodoros}}
+- Distractions blocked: {{blocks}}
+
+### Sessions
+{{sessions}}
+
+### Most blocked
+{{top_blocks}}
+";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DailyNoteConfig {
+    pub enabled: bool,
+    pub folder: String,    // vault folder, must exist
+    pub file_name: String, // relative to `folder`, same placeholders as `template`
+    pub time: String,      // HH:MM
+    pub template: String,
+}
+
+impl Default for DailyNoteConfig {
+    fn default() -> Self {
+        DailyNoteConfig {
+            enabled: false,
+            folder: String::new(),
+            file_name: "{{date}}.md".to_string(),
+            time: "23:30".to_string(),
+            template: DEFAULT_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl DailyNoteConfig {
+    /// Whether the note should be exported at `now`, given the date (YYYY-MM-DD) it was last exported.
+    pub fn is_due(&self, now: NaiveDateTime, last_export: Option<&str>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let today = now.format("%Y-%m-%d").to_string();
+        if last_export == Some(today.as_str()) {
+            return false;
+        }
+        NaiveTime::parse_from_str(&self.time, "%H:%M").is_ok_and(|time| now.time() >= time)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if NaiveTime::parse_from_str(&self.time, "%H:%M").is_err() {
+            return Err(format!("'{}' is not a time (HH:MM)", self.time));
+        }
+        if self.enabled && !Path::new(&self.folder).is_dir() {
+            return Err(format!("Folder '{}' doesn't exist", self.folder));
+        }
+        // The note has to stay inside the vault
+        let file_name = Path::new(&self.file_name);
+        if self.file_name.trim().is_empty() || !file_name.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(format!("'{}' is not a file name inside the folder", self.file_name));
+        }
+        Ok(())
+    }
+}
+
+/// Fill in the placeholders of `template`.
+pub fn render(template: &str, summary: &DailySummary, sessions: &[SessionRecord], top_blocks: &[TopBlock]) -> String {
+    let time_of_day = |timestamp: i64| {
+        Local.timestamp_opt(timestamp, 0).single().map_or_else(String::new, |t| t.format("%H:%M").to_string())
+    };
+    let session_lines: Vec<String> = sessions
+        .iter()
+        .map(|s| {
+            let mut details = vec![format!("{} min", (s.ended_at - s.started_at).max(0) / 60)];
+            if s.hardcore {
+                details.push("hardcore".to_string());
+            }
+            if !s.completed {
+                details.push("ended early".to_string());
+            }
+            format!("- {}–{} {} ({})", time_of_day(s.started_at), time_of_day(s.ended_at), s.name, details.join(", "))
+        })
+        .collect();
+    let block_lines: Vec<String> = top_blocks.iter().map(|b| format!("- {} ({})", b.target, b.attempts)).collect();
+    let list = |lines: Vec<String>| if lines.is_empty() { "- None".to_string() } else { lines.join("\n") };
+
+    template
+        .replace("{{date}}", &summary.date)
+        .replace("{{focus_time}}", &format!("{}h {}m", summary.focus_minutes / 60, summary.focus_minutes % 60))
+        .replace("{{focus_minutes}}", &summary.focus_minutes.to_string())
+        .replace("{{goal_percent}}", &summary.goal_percent.to_string())
+        .replace("{{pomodoros}}", &summary.pomodoros.to_string())
+        .replace("{{blocks}}", &summary.blocks.to_string())
+        .replace("{{sessions}}", &list(session_lines))
+        .replace("{{top_blocks}}", &list(block_lines))
+}
+
+/// Append `contents` to the note for `summary.date`, separated from what's already there by a
+/// blank line. Returns the note's path.
+pub fn append_to_note(config: &DailyNoteConfig, summary: &DailySummary, contents: &str) -> Result<PathBuf, String> {
+    config.validate()?;
+    if !Path::new(&config.folder).is_dir() {
+        return Err(format!("Folder '{}' doesn't exist", config.folder));
+    }
+    let path = Path::new(&config.folder).join(render(&config.file_name, summary, &[], &[]));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let existing = fs::read_to_string(&path).unwrap_or_default();
+    let separator = match existing.as_str() {
+        "" => "",
+        text if text.ends_with("\n\n") => "",
+        text if text.ends_with('\n') => "\n",
+        _ => "\n\n",
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(&path).map_err(|e| e.to_string())?;
+    file.write_all(format!("{}{}", separator, contents.trim_end()).as_bytes()).map_err(|e| e.to_string())?;
+    file.write_all(b"\n").map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let summary = DailySummary {
+            date: "2024-03-01".to_string(),
+            focus_minutes: 135,
+            blocks: 12,
+            pomodoros: 4,
+            goal_minutes: 480,
+            goal_percent: 28,
+        };
+        let top = vec![TopBlock { target: "reddit.com".to_string(), target_type: "website".to_string(), attempts: 9 }];
+        let note = render(DEFAULT_TEMPLATE, &summary, &[], &top);
+
+        assert!(note.contains("- Focused: 2h 15m (28% of goal)"));
+        assert!(note.contains("### Sessions\n- None\n"));
+        assert!(note.contains("- reddit.com (9)"));
+        assert_eq!(render("Daily/{{date}}.md", &summary, &[], &[]), "Daily/2024-03-01.md");
+    }
+
+    #[test]
+    fn test_note_stays_in_folder() {
+        let dir = std::env::temp_dir();
+        let config = |file_name: &str| DailyNoteConfig {
+            enabled: true,
+            folder: dir.to_string_lossy().to_string(),
+            file_name: file_name.to_string(),
+            ..DailyNoteConfig::default()
+        };
+        assert!(config("Daily/{{date}}.md").validate().is_ok());
+        assert!(config("../{{date}}.md").validate().is_err());
+        assert!(config("").validate().is_err());
+        assert!(DailyNoteConfig { folder: "/does/not/exist".to_string(), ..config("x.md") }.validate().is_err());
+    }
+}
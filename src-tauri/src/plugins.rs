@@ -0,0 +1,269 @@
+//! Plugins module - Sandboxed WASM extensions
+//!
+//! Every `*.wasm` file in the `plugins` data directory is loaded into its own wasmi
+//! instance. Plugins get no WASI or filesystem access; the only imports are the host
+//! functions in the `bastion` module below, and each call into a plugin is fuel-limited.
+//!
+//! Host API (module `bastion`):
+//! - `log(ptr: i32, len: i32)` - write a UTF-8 message to Bastion's log
+//! - `focus_minutes_today() -> i64`, `blocks_today() -> i64` - read today's stats
+//! - `add_block_rule(ptr: i32, len: i32) -> i32` - block a domain (0 on success, -1 on error)
+//!
+//! Optional plugin exports:
+//! - `on_load()` - called once after the plugin is instantiated
+//! - `alloc(len: i32) -> i32` and `on_block_event(ptr: i32, len: i32)` - receive each new
+//!   block event as JSON (see `storage::BlockEvent`)
+
+use crate::storage::Database;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use wasmi::{Caller, Config, Engine, Extern, Instance, Linker, Module, Store};
+
+/// Instruction budget for a single call into a plugin
+const FUEL_PER_CALL: u64 = 10_000_000;
+/// Longest string a plugin may pass to the host (bytes)
+const MAX_STRING_LEN: i32 = 4096;
+/// Category for block rules added by plugins
+const PLUGIN_CATEGORY: &str = "plugin";
+
+#[derive(Default)]
+struct HostState {
+    plugin: String,
+    focus_minutes_today: i64,
+    blocks_today: i64,
+    pending_rules: Vec<String>,
+}
+
+struct Plugin {
+    name: String,
+    store: Store<HostState>,
+    instance: Instance,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+    pub name: String,
+    pub loaded: bool,
+    pub error: Option<String>,
+}
+
+pub struct PluginManager {
+    plugins: Mutex<Vec<Plugin>>,
+    infos: Vec<PluginInfo>,
+    last_block_event_id: Mutex<i64>,
+}
+
+impl PluginManager {
+    /// Compile and instantiate every plugin in `dir`. Broken plugins are reported by `list`.
+    pub fn load(dir: &Path) -> Self {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+
+        let mut plugins = Vec::new();
+        let mut infos = Vec::new();
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+            .unwrap_or_default();
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "wasm"));
+        paths.sort();
+
+        for path in paths {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            match instantiate(&engine, &path, &name) {
+                Ok((store, instance)) => {
+                    println!("[Bastion] Loaded plugin '{}'", name);
+                    plugins.push(Plugin { name: name.clone(), store, instance });
+                    infos.push(PluginInfo { name, loaded: true, error: None });
+                }
+                Err(e) => {
+                    eprintln!("[Bastion] Could not load plugin '{}': {}", name, e);
+                    infos.push(PluginInfo { name, loaded: false, error: Some(e) });
+                }
+            }
+        }
+
+        PluginManager {
+            plugins: Mutex::new(plugins),
+            infos,
+            last_block_event_id: Mutex::new(0),
+        }
+    }
+
+    pub fn list(&self) -> Vec<PluginInfo> {
+        self.infos.clone()
+    }
+
+    /// Run each plugin's `on_load`. Returns true if plugins added block rules.
+    pub fn start(&self, db: &Database) -> bool {
+        // Only events from now on are delivered to plugins
+        let latest = db.get_recent_blocks(1).unwrap_or_default();
+        *self.last_block_event_id.lock().unwrap() = latest.first().map_or(0, |e| e.id);
+
+        let mut plugins = self.plugins.lock().unwrap();
+        let mut rules_added = false;
+        for plugin in plugins.iter_mut() {
+            refresh_stats(&mut plugin.store, db);
+            if let Ok(on_load) = plugin.instance.get_typed_func::<(), ()>(&plugin.store, "on_load") {
+                if plugin.store.add_fuel(FUEL_PER_CALL).is_ok() {
+                    if let Err(e) = on_load.call(&mut plugin.store, ()) {
+                        eprintln!("[Bastion] Plugin '{}' on_load failed: {}", plugin.name, e);
+                    }
+                }
+            }
+            rules_added |= apply_pending_rules(&mut plugin.store, db);
+        }
+        rules_added
+    }
+
+    /// Deliver block events logged since the last call. Returns true if plugins added block rules.
+    pub fn dispatch_block_events(&self, db: &Database) -> bool {
+        let mut last_id = self.last_block_event_id.lock().unwrap();
+        let events = match db.get_block_events_since(*last_id) {
+            Ok(events) if !events.is_empty() => events,
+            _ => return false,
+        };
+        *last_id = events.last().map_or(*last_id, |e| e.id);
+        drop(last_id);
+
+        let mut plugins = self.plugins.lock().unwrap();
+        let mut rules_added = false;
+        for plugin in plugins.iter_mut() {
+            let (Ok(alloc), Ok(on_block_event)) = (
+                plugin.instance.get_typed_func::<i32, i32>(&plugin.store, "alloc"),
+                plugin.instance.get_typed_func::<(i32, i32), ()>(&plugin.store, "on_block_event"),
+            ) else {
+                continue;
+            };
+            let Some(memory) = plugin.instance.get_memory(&plugin.store, "memory") else {
+                continue;
+            };
+
+            refresh_stats(&mut plugin.store, db);
+            for event in &events {
+                let payload = serde_json::to_vec(event).unwrap_or_default();
+                if plugin.store.add_fuel(FUEL_PER_CALL).is_err() {
+                    break;
+                }
+                let result = alloc
+                    .call(&mut plugin.store, payload.len() as i32)
+                    .map_err(|e| e.to_string())
+                    .and_then(|ptr| {
+                        memory
+                            .write(&mut plugin.store, ptr as u32 as usize, &payload)
+                            .map_err(|e| e.to_string())?;
+                        on_block_event
+                            .call(&mut plugin.store, (ptr, payload.len() as i32))
+                            .map_err(|e| e.to_string())
+                    });
+                if let Err(e) = result {
+                    eprintln!("[Bastion] Plugin '{}' on_block_event failed: {}", plugin.name, e);
+                    break;
+                }
+            }
+            rules_added |= apply_pending_rules(&mut plugin.store, db);
+        }
+        rules_added
+    }
+}
+
+fn instantiate(engine: &Engine, path: &Path, name: &str) -> Result<(Store<HostState>, Instance), String> {
+    let wasm = std::fs::read(path).map_err(|e| e.to_string())?;
+    let module = Module::new(engine, &wasm[..]).map_err(|e| e.to_string())?;
+
+    let mut store = Store::new(
+        engine,
+        HostState {
+            plugin: name.to_string(),
+            ..HostState::default()
+        },
+    );
+    // Start functions run under the same budget as any other call
+    store.add_fuel(FUEL_PER_CALL).map_err(|e| e.to_string())?;
+
+    let mut linker = Linker::<HostState>::new(engine);
+    define_host_api(&mut linker)?;
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .map_err(|e| e.to_string())?
+        .start(&mut store)
+        .map_err(|e| e.to_string())?;
+    Ok((store, instance))
+}
+
+fn define_host_api(linker: &mut Linker<HostState>) -> Result<(), String> {
+    linker
+        .func_wrap("bastion", "log", |caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+            if let Some(message) = read_string(&caller, ptr, len) {
+                println!("[Plugin {}] {}", caller.data().plugin, message);
+            }
+        })
+        .map_err(|e| e.to_string())?;
+    linker
+        .func_wrap("bastion", "focus_minutes_today", |caller: Caller<'_, HostState>| -> i64 {
+            caller.data().focus_minutes_today
+        })
+        .map_err(|e| e.to_string())?;
+    linker
+        .func_wrap("bastion", "blocks_today", |caller: Caller<'_, HostState>| -> i64 {
+            caller.data().blocks_today
+        })
+        .map_err(|e| e.to_string())?;
+    linker
+        .func_wrap("bastion", "add_block_rule", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> i32 {
+            let domain = read_string(&caller, ptr, len).map(|d| crate::blocking::normalize_domain(&d));
+            match domain {
+                Some(domain) if !domain.is_empty() => {
+                    caller.data_mut().pending_rules.push(domain);
+                    0
+                }
+                _ => -1,
+            }
+        })
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn read_string(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Option<String> {
+    if !(0..=MAX_STRING_LEN).contains(&len) {
+        return None;
+    }
+    let memory = caller.get_export("memory").and_then(Extern::into_memory)?;
+    let mut buf = vec![0u8; len as usize];
+    memory.read(caller, ptr as u32 as usize, &mut buf).ok()?;
+    String::from_utf8(buf).ok()
+}
+
+fn refresh_stats(store: &mut Store<HostState>, db: &Database) {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let stats = db.get_stats(1).unwrap_or_default().into_iter().find(|s| s.date == today);
+    let data = store.data_mut();
+    data.focus_minutes_today = stats.as_ref().map_or(0, |s| s.minutes_protected);
+    data.blocks_today = stats.as_ref().map_or(0, |s| s.blocks_count);
+}
+
+/// Add the domains a plugin asked to block. Returns true if any were new.
+fn apply_pending_rules(store: &mut Store<HostState>, db: &Database) -> bool {
+    let rules = std::mem::take(&mut store.data_mut().pending_rules);
+    if rules.is_empty() {
+        return false;
+    }
+    let mut existing: Vec<String> = db
+        .get_blocked_sites()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|s| s.domain)
+        .collect();
+
+    let mut added = false;
+    for domain in rules {
+        let result = (!existing.contains(&domain)).then(|| db.add_blocked_site(&domain, PLUGIN_CATEGORY, false));
+        if let Some(Ok(crate::storage::AddSiteResult::Added { .. })) = result {
+            println!("[Plugin {}] Blocked {}", store.data().plugin, domain);
+            existing.push(domain);
+            added = true;
+        }
+    }
+    added
+}
@@ -0,0 +1,80 @@
+//! Recovery module - Repairs half-applied blocking after an unclean shutdown
+//!
+//! A marker file exists in the data directory while Bastion runs and is removed by the
+//! shutdown handler. Finding it at startup means the last run crashed or was killed, so
+//! the hosts section, SafeSearch entries and firewall redirects are reconciled with what
+//! the database says they should be, and blocks applied for sessions that didn't survive the
+//! crash are lifted. The persisted hardcore session is resumed separately
+//! (`SessionManager::restore_persisted_session`) and only reported here.
+
+use crate::{blocking, AppState};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const RUNNING_MARKER: &str = "bastion.running";
+/// Last `RecoveryReport` (JSON), for a UI that wasn't listening when it was emitted
+pub const RECOVERY_REPORT_SETTING: &str = "last_recovery_report";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryReport {
+    pub recovered_at: String,
+    pub repaired: Vec<String>, // human-readable, one entry per repair
+    pub resumed_session: Option<String>,
+}
+
+fn marker_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(RUNNING_MARKER)
+}
+
+/// Create the marker for this run. Returns true if the previous run didn't shut down cleanly.
+pub fn mark_running(data_dir: &Path) -> bool {
+    let path = marker_path(data_dir);
+    let unclean = path.exists();
+    if let Err(e) = std::fs::write(&path, std::process::id().to_string()) {
+        eprintln!("[Bastion] Could not write {}: {}", path.display(), e);
+    }
+    unclean
+}
+
+/// Remove the marker; called by every clean exit path.
+pub fn mark_stopped(data_dir: &Path) {
+    let _ = std::fs::remove_file(marker_path(data_dir));
+}
+
+/// Bring the system back in line with the database after a crash.
+pub fn reconcile(state: &AppState) -> RecoveryReport {
+    let mut repaired = Vec::new();
+
+    for stale in crate::revert_stale_session_blocks(state) {
+        repaired.push(format!(
+            "Lifted {} site and {} app block(s) left by session '{}'",
+            stale.domains.len(),
+            stale.apps.len(),
+            stale.session_name
+        ));
+    }
+
+    let removed = blocking::remove_port_redirects();
+    if removed > 0 {
+        repaired.push(format!("Removed {} stale firewall redirect(s)", removed));
+    }
+
+    let before = blocking::hosts_section();
+    if crate::sync_blocked_websites(state).is_ok() && blocking::hosts_section() != before {
+        repaired.push("Rewrote the hosts block section".to_string());
+    }
+
+    let safesearch = crate::is_setting_enabled(state, blocking::SAFESEARCH_SETTING);
+    if safesearch != blocking::safesearch_hosts_present() && blocking::set_safesearch_enforced(safesearch).is_ok() {
+        let action = if safesearch { "Restored" } else { "Removed" };
+        repaired.push(format!("{} the SafeSearch hosts entries", action));
+    }
+
+    let resumed_session = state.session_manager.active_session().map(|s| s.name);
+
+    RecoveryReport {
+        recovered_at: chrono::Local::now().to_rfc3339(),
+        repaired,
+        resumed_session,
+    }
+}
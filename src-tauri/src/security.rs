@@ -0,0 +1,341 @@
+//! Security module - Tamper resistance for the hardcore lock
+//!
+//! The lock state lives in its own file next to the database and is signed with an
+//! HMAC keyed by a machine-specific secret, so deleting `bastion.db` doesn't lift the
+//! lock and copying a state file from elsewhere (or editing it) is detected.
+//!
+//! While a session is active, the state file (and on Windows the database) are also
+//! protected against deletion.
+//!
+//! Privileged commands take an unlock token (see `UnlockTokens`) instead of the master password.
+
+use crate::session::{ActiveSession, UnlockRequest};
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Mixed into the machine id so the secret is specific to Bastion
+const SECRET_CONTEXT: &[u8] = b"bastion-hardcore-lock-v1";
+
+/// When a hardcore session was last seen running and how much of it was left then, so the
+/// time that passed while Bastion wasn't running can be checked on restart.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LockClock {
+    pub remaining: i64, // seconds
+    pub last_seen: i64, // Unix timestamp
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedLock {
+    session: ActiveSession,
+    /// Missing from lock states written before it existed, whose signature covers the session alone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    clock: Option<LockClock>,
+    signature: String, // hex HMAC-SHA256 of the serialized session and clock
+}
+
+fn lock_payload(session: &ActiveSession, clock: Option<&LockClock>) -> serde_json::Result<String> {
+    match clock {
+        Some(clock) => serde_json::to_string(&(session, clock)),
+        None => serde_json::to_string(session),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LockStateError {
+    Missing,
+    Corrupt,
+    Tampered,
+}
+
+/// Write the lock state for a hardcore session.
+pub fn save_lock_state(path: &Path, session: &ActiveSession, clock: &LockClock) -> std::io::Result<()> {
+    let payload = lock_payload(session, Some(clock))?;
+    let persisted = PersistedLock {
+        session: session.clone(),
+        clock: Some(*clock),
+        signature: sign(payload.as_bytes()),
+    };
+    write_atomically(path, &serde_json::to_vec(&persisted)?)
+}
+
+/// Read and verify the lock state.
+pub fn load_lock_state(path: &Path) -> Result<(ActiveSession, Option<LockClock>), LockStateError> {
+    let contents = fs::read(path).map_err(|_| LockStateError::Missing)?;
+    let persisted: PersistedLock = serde_json::from_slice(&contents).map_err(|_| LockStateError::Corrupt)?;
+    let payload = lock_payload(&persisted.session, persisted.clock.as_ref()).map_err(|_| LockStateError::Corrupt)?;
+
+    if verify(payload.as_bytes(), &persisted.signature) {
+        Ok((persisted.session, persisted.clock))
+    } else {
+        Err(LockStateError::Tampered)
+    }
+}
+
+/// End time claimed by a lock state that failed verification, if it can still be read.
+/// Only good for failing closed: nothing stops it from having been edited.
+pub fn unverified_lock_end(path: &Path) -> Option<i64> {
+    let persisted: PersistedLock = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+    Some(persisted.session.end_time)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedUnlockRequest {
+    request: UnlockRequest,
+    signature: String, // hex HMAC-SHA256 of the serialized request
+}
+
+/// Write a pending temptation-delay unlock, signed like the lock state so its wait can't be
+/// shortened by editing the file.
+pub fn save_unlock_request(path: &Path, request: &UnlockRequest) -> std::io::Result<()> {
+    let payload = serde_json::to_string(request)?;
+    let persisted = PersistedUnlockRequest {
+        request: request.clone(),
+        signature: sign(payload.as_bytes()),
+    };
+    write_atomically(path, &serde_json::to_vec(&persisted)?)
+}
+
+pub fn load_unlock_request(path: &Path) -> Result<UnlockRequest, LockStateError> {
+    let contents = fs::read(path).map_err(|_| LockStateError::Missing)?;
+    let persisted: PersistedUnlockRequest = serde_json::from_slice(&contents).map_err(|_| LockStateError::Corrupt)?;
+    let payload = serde_json::to_string(&persisted.request).map_err(|_| LockStateError::Corrupt)?;
+
+    if verify(payload.as_bytes(), &persisted.signature) {
+        Ok(persisted.request)
+    } else {
+        Err(LockStateError::Tampered)
+    }
+}
+
+/// Replace `path` with `contents` through a synced temporary file, so a crash or power loss
+/// mid-write leaves either the old file or the new one, never a truncated one.
+fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temp_path, path)?;
+    // Persist the rename itself
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let _ = fs::File::open(parent).and_then(|dir| dir.sync_all());
+    }
+    Ok(())
+}
+
+/// Remove a persisted lock state or unlock request.
+pub fn clear_lock_state(path: &Path) {
+    let _ = fs::remove_file(path);
+}
+
+/// Make `path` resistant to deletion while a session is active.
+///
+/// On Windows, a deny-delete ACE for Everyone is added. On Linux/macOS the immutable flag
+/// is set, which also blocks writes, so `writable` files (the database) are left alone there;
+/// the lock state file is enough to restore the lock if the database is removed.
+pub fn protect_file(path: &Path, writable: bool) {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = writable;
+        let _ = std::process::Command::new("icacls")
+            .arg(path)
+            .args(["/deny", "*S-1-1-0:(DE)"])
+            .output();
+    }
+
+    #[cfg(target_os = "linux")]
+    if !writable {
+        let _ = std::process::Command::new("chattr").arg("+i").arg(path).output();
+    }
+
+    #[cfg(target_os = "macos")]
+    if !writable {
+        let _ = std::process::Command::new("chflags").arg("uchg").arg(path).output();
+    }
+}
+
+/// Undo `protect_file`.
+pub fn unprotect_file(path: &Path, writable: bool) {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = writable;
+        let _ = std::process::Command::new("icacls")
+            .arg(path)
+            .args(["/remove:d", "*S-1-1-0"])
+            .output();
+    }
+
+    #[cfg(target_os = "linux")]
+    if !writable {
+        let _ = std::process::Command::new("chattr").arg("-i").arg(path).output();
+    }
+
+    #[cfg(target_os = "macos")]
+    if !writable {
+        let _ = std::process::Command::new("chflags").arg("nouchg").arg(path).output();
+    }
+}
+
+/// How long a token from `authenticate` stays valid
+pub const UNLOCK_TOKEN_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Short-lived proof that the master password was entered, so privileged commands
+/// don't need the raw password. Only the most recently issued token is valid.
+#[derive(Default)]
+pub struct UnlockTokens {
+    current: Mutex<Option<(String, Instant)>>, // token and when it expires
+}
+
+impl UnlockTokens {
+    pub fn issue(&self) -> String {
+        let token = to_hex(&rand::random::<[u8; 32]>());
+        *self.current.lock().unwrap() = Some((token.clone(), Instant::now() + UNLOCK_TOKEN_TTL));
+        token
+    }
+
+    pub fn validate(&self, token: &str) -> bool {
+        let mut current = self.current.lock().unwrap();
+        match current.as_ref() {
+            Some((_, expires_at)) if Instant::now() >= *expires_at => {
+                *current = None;
+                false
+            }
+            Some((valid, _)) => constant_time_eq(valid.as_bytes(), token.as_bytes()),
+            None => false,
+        }
+    }
+
+    pub fn revoke(&self) {
+        *self.current.lock().unwrap() = None;
+    }
+}
+
+/// Secret derived from the OS machine id; stable across reboots and reinstalls of Bastion.
+fn machine_secret() -> Vec<u8> {
+    let machine_id = read_machine_id().unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(SECRET_CONTEXT);
+    hasher.update(machine_id.trim().as_bytes());
+    hasher.finalize().to_vec()
+}
+
+#[cfg(target_os = "linux")]
+fn read_machine_id() -> Option<String> {
+    fs::read_to_string("/etc/machine-id")
+        .or_else(|_| fs::read_to_string("/var/lib/dbus/machine-id"))
+        .ok()
+}
+
+#[cfg(target_os = "windows")]
+fn read_machine_id() -> Option<String> {
+    use std::process::Command;
+
+    let output = Command::new("reg")
+        .args(["query", "HKLM\\SOFTWARE\\Microsoft\\Cryptography", "/v", "MachineGuid"])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .find(|line| line.contains("MachineGuid"))
+        .and_then(|line| line.split_whitespace().last())
+        .map(|guid| guid.to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn read_machine_id() -> Option<String> {
+    use std::process::Command;
+
+    let output = Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .find(|line| line.contains("IOPlatformUUID"))
+        .and_then(|line| line.split('"').nth(3))
+        .map(|uuid| uuid.to_string())
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// HMAC-SHA256 (RFC 2104)
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+/// Hex HMAC-SHA256 of `message`, keyed by the machine secret
+fn sign(message: &[u8]) -> String {
+    to_hex(&hmac_sha256(&machine_secret(), message))
+}
+
+/// Check a signature from `sign` (in constant time)
+fn verify(message: &[u8], signature: &str) -> bool {
+    let Some(signature) = from_hex(signature) else {
+        return false;
+    };
+    let mut mac = HmacSha256::new_from_slice(&machine_secret()).expect("HMAC takes keys of any length");
+    mac.update(message);
+    mac.verify_slice(&signature).is_ok()
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => Some((char::from(*high).to_digit(16)? * 16 + char::from(*low).to_digit(16)?) as u8),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlock_token_revoke() {
+        let tokens = UnlockTokens::default();
+        let token = tokens.issue();
+
+        assert!(tokens.validate(&token));
+        assert!(!tokens.validate("not-the-token"));
+        tokens.revoke();
+        assert!(!tokens.validate(&token));
+    }
+
+    #[test]
+    fn test_hmac_sha256_rfc4231() {
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(to_hex(&mac), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+
+        let signature = sign(b"lock state");
+        assert!(verify(b"lock state", &signature));
+        assert!(!verify(b"lock state!", &signature));
+        assert!(!verify(b"lock state", &signature[1..]));
+    }
+}
@@ -1,42 +1,208 @@
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
 // use tauri::State;
-use crate::AppState;
+use crate::{blocking, tls, AppState};
+
+/// Header sent by `health::selftest`; such requests aren't counted as blocks
+pub const SELFTEST_HEADER: &str = "x-bastion-selftest";
+/// Marker in the block page that identifies Bastion's block server
+pub const BLOCK_PAGE_MARKER: &str = "Blocked by Bastion";
+
+pub const BLOCK_SERVER_SETTING: &str = "block_server";
+
+/// What to do when another program (IIS, nginx, Skype...) already holds port 80 or 443
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PortConflictMode {
+    /// Leave the port to the other program; blocked sites still fail to load, but aren't counted
+    Skip,
+    /// Listen on the alternate port instead (only reachable if something forwards to it)
+    AlternatePort,
+    /// Listen on the alternate port and redirect local traffic for the port to it (Linux only).
+    /// This also redirects localhost traffic meant for the other program.
+    FirewallRedirect,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockServerConfig {
+    pub on_conflict: PortConflictMode,
+    pub http_alternate_port: u16,
+    pub https_alternate_port: u16,
+}
+
+impl Default for BlockServerConfig {
+    fn default() -> Self {
+        BlockServerConfig {
+            on_conflict: PortConflictMode::Skip,
+            http_alternate_port: 8080,
+            https_alternate_port: 8443,
+        }
+    }
+}
+
+impl BlockServerConfig {
+    fn alternate_port(&self, port: u16) -> u16 {
+        if port == 80 { self.http_alternate_port } else { self.https_alternate_port }
+    }
+}
+
+/// Outcome of binding one TCP listener, reported by the health check
+#[derive(Debug, Clone, Serialize)]
+pub struct ListenerStatus {
+    pub port: u16, // 80 or 443
+    pub address: String,
+    pub bound_port: Option<u16>, // port actually listened on, None if binding failed
+    pub redirected: bool,        // firewall redirect from `port` to `bound_port`
+    pub holder: Option<String>,  // program holding `port`, if it was taken
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct BlockServerStatus {
+    listeners: Mutex<Vec<ListenerStatus>>,
+    /// Domains for the PAC file served on `blocking::PAC_PATH` (`pac` blocking backend only)
+    pac_domains: Mutex<Option<Vec<String>>>,
+    /// CA for the HTTPS block page, while the user has it enabled (see `tls`)
+    tls: Mutex<Option<Arc<tls::BlockPageCa>>>,
+    wake: tokio::sync::Notify,
+}
+
+impl BlockServerStatus {
+    pub fn list(&self) -> Vec<ListenerStatus> {
+        self.listeners.lock().unwrap().clone()
+    }
+
+    /// Ask the supervisor to re-check whether the listeners are needed (blocklist or session changed).
+    pub fn state_changed(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Port the HTTP listener on 127.0.0.1 is bound to (an alternate port if 80 was taken).
+    pub fn http_port(&self) -> u16 {
+        self.listeners.lock().unwrap()
+            .iter()
+            .find(|l| l.port == 80 && l.address == "127.0.0.1")
+            .and_then(|l| l.bound_port)
+            .unwrap_or(80)
+    }
+
+    pub fn set_pac_domains(&self, domains: Option<Vec<String>>) {
+        *self.pac_domains.lock().unwrap() = domains;
+    }
+
+    pub fn set_tls(&self, ca: Option<Arc<tls::BlockPageCa>>) {
+        *self.tls.lock().unwrap() = ca;
+    }
+
+    pub fn tls(&self) -> Option<Arc<tls::BlockPageCa>> {
+        self.tls.lock().unwrap().clone()
+    }
+
+        fn record(&self, status: ListenerStatus) {
+        let mut listeners = self.listeners.lock().unwrap();
+        listeners.retain(|l| !(l.port == status.port && l.address == status.address));
+        listeners.push(status);
+    }
+}
+
+pub fn load_config(state: &AppState) -> BlockServerConfig {
+    state.db.get_setting(BLOCK_SERVER_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn socket_addr(addr_str: &str, port: u16) -> std::io::Result<SocketAddr> {
+    let full_addr = if addr_str.contains(':') {
+        format!("[{}]:{}", addr_str, port)
+    } else {
+        format!("{}:{}", addr_str, port)
+    };
+    full_addr.parse::<SocketAddr>().map_err(std::io::Error::other)
+}
+
+/// How often the supervisor re-checks even without a `state_changed` notification
+const SUPERVISOR_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The listeners only matter while something is blocked, so they don't hold ports 80/443
+/// around the clock (other local servers can use them the rest of the time).
+fn is_needed(state: &AppState) -> bool {
+    state.session_manager.get_time_remaining().is_some()
+        || crate::blocked_domains(state).is_ok_and(|domains| !domains.is_empty())
+}
+
+/// Run the block server whenever it is needed and shut it down when it isn't.
+pub async fn supervise_block_server(state: Arc<AppState>) {
+    let mut listeners: Vec<tokio::task::JoinHandle<()>> = Vec::new();
+    loop {
+        let needed = is_needed(&state);
+        if needed && listeners.is_empty() {
+            listeners = start_block_server(state.clone());
+        } else if !needed && !listeners.is_empty() {
+            // Aborting the tasks drops the sockets, which frees the ports
+            for listener in listeners.drain(..) {
+                listener.abort();
+            }
+            state.block_server.listeners.lock().unwrap().clear();
+            blocking::remove_port_redirects();
+            println!("[Bastion] Block server stopped (nothing to block)");
+        }
+
+        tokio::select! {
+            _ = state.block_server.wake.notified() => {}
+            _ = tokio::time::sleep(SUPERVISOR_INTERVAL) => {}
+        }
+    }
+}
 
 /// Start the block stats collector server
-pub async fn start_block_server(state: Arc<AppState>) {
+fn start_block_server(state: Arc<AppState>) -> Vec<tokio::task::JoinHandle<()>> {
+    let mut tasks = Vec::new();
     let ports = [80, 443];
     let addresses = [
         "127.0.0.1",
         "::1",
     ];
+    let config = load_config(&state);
+    let excluded_ports = crate::load_dev_exclusions(&state).ports;
+    // Rules left behind by a previous run that didn't exit cleanly
+    blocking::remove_port_redirects();
 
-    for &port in &ports {
+    // Excluded ports belong to the developer's own local servers
+    for &port in ports.iter().filter(|port| !excluded_ports.contains(port)) {
         for &addr_str in &addresses {
             let state_clone = state.clone();
-            tokio::spawn(async move {
-                let full_addr = if addr_str.contains(':') {
-                    format!("[{}]:{}", addr_str, port)
-                } else {
-                    format!("{}:{}", addr_str, port)
-                };
-                match listen_on_addr(&full_addr, port, state_clone).await {
-                    Ok(_) => println!("[Bastion] Block server listening on {}", full_addr),
-                    Err(e) => {
+            let config = config.clone();
+            tasks.push(tokio::spawn(async move {
+                let (listener, status) = bind_listener(addr_str, port, &config).await;
+                match (&listener, &status.error) {
+                    (Some(_), _) => println!(
+                        "[Bastion] Block server listening on {} port {}",
+                        addr_str,
+                        status.bound_port.unwrap_or(port)
+                    ),
+                    (None, Some(e)) => {
                         if port == 80 || addr_str == "127.0.0.1" {
-                            eprintln!("[Bastion] Block server warning: Could not bind TCP {}: {}. (Are you running with Admin privileges?)", full_addr, e);
+                            eprintln!("[Bastion] Block server warning: Could not bind TCP {} port {}: {}. (Are you running with Admin privileges?)", addr_str, port, e);
                         }
                     }
+                    (None, None) => {}
                 }
-            });
+                state_clone.block_server.record(status);
+
+                if let Some(listener) = listener {
+                    let _ = serve_tcp(listener, port, state_clone).await;
+                }
+            }));
 
             // For port 443, also listen on UDP to block QUIC
             if port == 443 {
                 let state_udp = state.clone();
                 let addr_udp = addr_str.to_string();
-                tokio::spawn(async move {
+                tasks.push(tokio::spawn(async move {
                     let full_addr = if addr_udp.contains(':') {
                         format!("[{}]:443", addr_udp)
                     } else {
@@ -50,8 +216,55 @@ pub async fn start_block_server(state: Arc<AppState>) {
                              }
                         }
                     }
-                });
+                }));
+            }
+        }
+    }
+    tasks
+}
+
+/// Bind `addr_str:port`, falling back according to `config` when another program holds the port.
+async fn bind_listener(addr_str: &str, port: u16, config: &BlockServerConfig) -> (Option<TcpListener>, ListenerStatus) {
+    let mut status = ListenerStatus {
+        port,
+        address: addr_str.to_string(),
+        bound_port: None,
+        redirected: false,
+        holder: None,
+        error: None,
+    };
+
+    let err = match bind_tcp(addr_str, port).await {
+        Ok(listener) => {
+            status.bound_port = Some(port);
+            return (Some(listener), status);
+        }
+        Err(e) => e,
+    };
+    status.error = Some(err.to_string());
+    if err.kind() != std::io::ErrorKind::AddrInUse {
+        return (None, status);
+    }
+    status.holder = blocking::port_holder(port);
+    if config.on_conflict == PortConflictMode::Skip {
+        return (None, status);
+    }
+
+    let alternate = config.alternate_port(port);
+    match bind_tcp(addr_str, alternate).await {
+        Ok(listener) => {
+            status.bound_port = Some(alternate);
+            if config.on_conflict == PortConflictMode::FirewallRedirect {
+                match blocking::add_port_redirect(addr_str, port, alternate) {
+                    Ok(()) => status.redirected = true,
+                    Err(e) => status.error = Some(e.message),
+                }
             }
+            (Some(listener), status)
+        }
+        Err(e) => {
+            status.error = Some(format!("{}; alternate port {}: {}", err, alternate, e));
+            (None, status)
         }
     }
 }
@@ -69,16 +282,18 @@ async fn listen_on_udp(addr_str: &str, _port: u16, state: Arc<AppState>) -> std:
             // without a full state machine. However, just hitting this listener means
             // the domain was resolved to localhost, and we are successfully blocking it.
             // We'll log a generic QUIC block.
-            let _ = state.db.log_block_event("QUIC/UDP Protocol", "website");
+            crate::log_block_event(&state, "QUIC/UDP Protocol", "website");
         }
     }
 }
 
-async fn listen_on_addr(addr_str: &str, port: u16, state: Arc<AppState>) -> std::io::Result<()> {
-    let addr = addr_str.parse::<SocketAddr>().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    let listener = TcpListener::bind(addr).await?;
-    // Print handled in caller
+async fn bind_tcp(addr_str: &str, port: u16) -> std::io::Result<TcpListener> {
+    TcpListener::bind(socket_addr(addr_str, port)?).await
+}
 
+/// Accept connections forever. `port` is the port browsers connect to (80/443), which decides
+/// how requests are parsed even when the listener runs on an alternate port.
+async fn serve_tcp(listener: TcpListener, port: u16, state: Arc<AppState>) -> std::io::Result<()> {
     loop {
         let (socket, _) = listener.accept().await?;
         let state_clone = state.clone();
@@ -88,37 +303,116 @@ async fn listen_on_addr(addr_str: &str, port: u16, state: Arc<AppState>) -> std:
     }
 }
 
+#[tracing::instrument(skip(socket, state), fields(domain = tracing::field::Empty))]
 async fn handle_connection(mut socket: TcpStream, port: u16, state: Arc<AppState>) {
     // Buffer to read initial packet
     let mut buf = [0u8; 4096];
     
+    // With the HTTPS block page the ClientHello is only peeked at: the TLS handshake still needs it
+    let ca = if port == 443 { state.block_server.tls() } else { None };
+
     // Set a short timeout for reading so we don't hang on idle connections
-    let read_result = tokio::time::timeout(
-        std::time::Duration::from_millis(500),
-        socket.read(&mut buf)
-    ).await;
+    let read_result = tokio::time::timeout(std::time::Duration::from_millis(500), async {
+        if ca.is_some() { socket.peek(&mut buf).await } else { socket.read(&mut buf).await }
+    }).await;
 
     match read_result {
         Ok(Ok(n)) if n > 0 => {
             let data = &buf[..n];
+            if port == 80 && is_pac_request(data) {
+                let domains = state.block_server.pac_domains.lock().unwrap().clone();
+                if let Some(domains) = domains {
+                    // Rendered per request, so the proxy port follows the listener actually bound
+                    let pac = blocking::render_pac(&domains, state.block_server.http_port());
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nContent-Type: application/x-ns-proxy-autoconfig\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n{}",
+                        pac.len(),
+                        pac
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    return;
+                }
+            }
             let domain = if port == 443 {
                 parse_sni(data)
             } else {
                 parse_host_header(data)
             };
 
-            if let Some(domain) = domain {
+            let is_selftest = port == 80 && has_header(data, SELFTEST_HEADER);
+            if let Some(domain) = domain.as_deref().filter(|_| !is_selftest) {
+                tracing::Span::current().record("domain", domain);
                 println!("Intercepted blocked request for: {}", domain);
-                let _ = state.db.log_block_event(&domain, "website");
+                crate::log_block_event(&state, domain, "website");
             }
 
-            // Send a basic response to close gracefully
-            let response = if port == 80 {
-                let warning_text = state.db.get_setting("custom_warning_text")
-                    .unwrap_or(None)
-                    .unwrap_or_else(|| "Is this really worth breaking your focus?".to_string());
+            if port == 80 {
+                let _ = socket.write_all(block_page_response(&state).as_bytes()).await;
+            } else if let (Some(ca), Some(domain)) = (ca, domain) {
+                serve_https_block_page(socket, &ca, &domain, &state).await;
+            }
+            // Otherwise (HTTPS without the local CA) there is no valid response to send, so just close
+        }
+        _ => {}
+    }
+}
 
-                let html = format!(r#"
+/// Finish the TLS handshake as `domain` and answer its request with the block page. Domains that
+/// aren't blocked get no certificate, so the CA can't be used to stand in for other sites.
+async fn serve_https_block_page(socket: TcpStream, ca: &tls::BlockPageCa, domain: &str, state: &AppState) {
+    if !crate::blocked_domains(state).is_ok_and(|domains| blocking::is_domain_blocked(domain, &domains)) {
+        return;
+    }
+    let config = match ca.server_config(domain) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("[Bastion] {}", e.message);
+            return;
+        }
+    };
+    let handshake = tokio_rustls::TlsAcceptor::from(config).accept(socket);
+    let Ok(Ok(mut stream)) = tokio::time::timeout(std::time::Duration::from_secs(5), handshake).await else {
+        return;
+    };
+    let mut buf = [0u8; 4096];
+    let request = tokio::time::timeout(std::time::Duration::from_secs(5), stream.read(&mut buf)).await;
+    if matches!(request, Ok(Ok(n)) if n > 0) {
+        let _ = stream.write_all(block_page_response(state).as_bytes()).await;
+        let _ = stream.shutdown().await;
+    }
+}
+
+/// The block page as a full HTTP response, with a countdown while a session is running.
+fn block_page_response(state: &AppState) -> String {
+    // A quote from the user's library if there is one, otherwise the custom warning
+    let warning_text = match state.db.get_random_quote().unwrap_or(None) {
+        Some(quote) => match quote.author {
+            Some(author) => format!("“{}”<br>— {}", escape_html(&quote.text), escape_html(&author)),
+            None => format!("“{}”", escape_html(&quote.text)),
+        },
+        None => state.db.get_setting("custom_warning_text")
+            .unwrap_or(None)
+            .unwrap_or_else(|| "Is this really worth breaking your focus?".to_string()),
+    };
+
+    let countdown = match state.session_manager.get_time_remaining().filter(|seconds| *seconds > 0) {
+        Some(seconds) => format!(r#"<div class="countdown" id="countdown"></div>
+        <script>
+            (function () {{
+                var left = {}, el = document.getElementById("countdown");
+                function show() {{
+                    var h = Math.floor(left / 3600), m = Math.floor(left / 60) % 60, s = left % 60;
+                    var time = (h ? h + ":" + String(m).padStart(2, "0") : m) + ":" + String(s).padStart(2, "0");
+                    el.textContent = time + " left in this session";
+                }}
+                show();
+                setInterval(function () {{ if (left > 0) {{ left--; show(); }} }}, 1000);
+            }})();
+        </script>"#, seconds),
+        None => String::new(),
+    };
+
+    let html = format!(r#"
 <!DOCTYPE html>
 <html>
 <head>
@@ -174,6 +468,12 @@ async fn handle_connection(mut socket: TcpStream, port: u16, state: Arc<AppState
             margin: 0;
             font-weight: 500;
         }}
+        .countdown {{
+            margin-top: 1.5rem;
+            font-size: 1.5rem;
+            font-weight: 900;
+            font-variant-numeric: tabular-nums;
+        }}
         .footer {{
             margin-top: 2rem;
             font-size: 0.75rem;
@@ -191,24 +491,18 @@ async fn handle_connection(mut socket: TcpStream, port: u16, state: Arc<AppState
         </div>
         <h1>Blocked</h1>
         <p>{}</p>
+        {}
         <div class="footer">Bastion Focus Defense System</div>
     </div>
 </body>
 </html>
-                "#, warning_text);
+                "#, warning_text, countdown);
 
-                format!("HTTP/1.1 403 Forbidden\r\nContent-Length: {}\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n{}", html.len(), html)
-            } else {
-                // For HTTPS, we can't send a valid response without certs, so just close
-                "".to_string()
-            };
+    format!("HTTP/1.1 403 Forbidden\r\nContent-Length: {}\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n{}", html.len(), html)
+}
 
-            if !response.is_empty() {
-                let _ = socket.write_all(response.as_bytes()).await;
-            }
-        }
-        _ => {}
-    }
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
 }
 
 /// Parse SNI from ClientHello to get the domain name
@@ -261,7 +555,7 @@ fn parse_sni(data: &[u8]) -> Option<String> {
                 let name_len = ((data[pos + 1] as usize) << 8) | (data[pos + 2] as usize);
                 pos += 3;
                 if pos + name_len <= data.len() {
-                    return Some(String::from_utf8_lossy(&data[pos..pos + name_len]).to_string());
+                    return Some(blocking::normalize_domain(&String::from_utf8_lossy(&data[pos..pos + name_len])));
                 }
             }
         }
@@ -272,12 +566,26 @@ fn parse_sni(data: &[u8]) -> Option<String> {
     None
 }
 
+/// A direct `GET /bastion.pac` (proxied requests carry an absolute URL instead of a path)
+fn is_pac_request(data: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(data);
+    let request_line = text.lines().next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    parts.next() == Some("GET") && parts.next().map(|path| path.split('?').next().unwrap_or(path)) == Some(blocking::PAC_PATH)
+}
+
 /// Parse Host header from HTTP request
+fn has_header(data: &[u8], name: &str) -> bool {
+    let text = String::from_utf8_lossy(data);
+    let prefix = format!("{}:", name);
+    text.lines().any(|line| line.to_lowercase().starts_with(&prefix))
+}
+
 fn parse_host_header(data: &[u8]) -> Option<String> {
     let text = String::from_utf8_lossy(data);
     for line in text.lines() {
         if line.to_lowercase().starts_with("host:") {
-            return Some(line[5..].trim().to_string());
+            return Some(blocking::normalize_domain(&line[5..]));
         }
     }
     None
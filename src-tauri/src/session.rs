@@ -1,9 +1,27 @@
 //! Session module - Focus session and Pomodoro timer management
 
-use chrono::{Local, NaiveTime, Weekday, Datelike};
+use chrono::{Local, NaiveDateTime, NaiveTime, Weekday, Datelike};
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::PathBuf;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Allowed disagreement between wall-clock and monotonic time (seconds) before we assume
+/// the system clock was changed. Sleep/resume can also trip this on platforms whose
+/// monotonic clock stops while suspended, which only ever makes sessions last longer.
+const CLOCK_JUMP_TOLERANCE_SECS: i64 = 120;
+
+/// A lock state that fails verification locks for at least this long (seconds)...
+const UNVERIFIED_LOCK_MIN_SECS: i64 = 24 * 60 * 60;
+/// ...and at most this long, whatever end time it claims
+const UNVERIFIED_LOCK_MAX_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Longest a single `extend_session` call can add (minutes)
+pub const MAX_EXTENSION_MINUTES: i64 = 24 * 60;
+
+/// How often the remaining time of the binding hardcore session is written to the lock state
+const LOCK_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActiveSession {
@@ -13,6 +31,176 @@ pub struct ActiveSession {
     pub end_time: i64,        // Unix timestamp
     pub hardcore: bool,
     pub session_type: SessionType,
+    /// Extreme hardcore: system tools (Task Manager, regedit, ...) are blocked too.
+    /// Fixed at session start and only possible for hardcore sessions.
+    #[serde(default)]
+    pub extreme: bool,
+    /// Blocklist profile enforced for this session on top of the always-on block list.
+    /// A copy is kept so editing the profile doesn't change a running session.
+    #[serde(default)]
+    pub blocklist: Option<BlocklistProfile>,
+    /// Schedule (`storage::Session` id) that started this session, for scheduled sessions.
+    /// Left out when unset so lock states signed before it existed still verify.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule_id: Option<i64>,
+    /// How a hardcore session can be ended early. Fixed at session start; part of the
+    /// signed lock state, so it can't be loosened by editing the file.
+    #[serde(default, skip_serializing_if = "OverridePolicy::is_never")]
+    pub override_policy: OverridePolicy,
+    /// Deny by default: everything but the allowed sites (`storage::AllowedSite`) is blocked.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allowlist_only: bool,
+}
+
+/// What it takes to end a hardcore session before its time is up (besides the emergency unlock).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OverridePolicy {
+    /// It can't be ended early
+    #[default]
+    Never,
+    /// The master password (an unlock token from `authenticate`)
+    Password,
+    /// An unlock challenge with a wait of `delay_seconds`, then the master password
+    DelayThenPassword { delay_seconds: i64 },
+    /// Typing back a random text of `length` characters
+    TypingChallenge { length: usize },
+}
+
+impl OverridePolicy {
+    pub fn is_never(&self) -> bool {
+        *self == OverridePolicy::Never
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            OverridePolicy::DelayThenPassword { delay_seconds } if !(60..=24 * 60 * 60).contains(delay_seconds) => {
+                Err("The override delay must be between 1 minute and 24 hours".to_string())
+            }
+            OverridePolicy::TypingChallenge { length } if !(10..=1000).contains(length) => {
+                Err("The override challenge must be between 10 and 1000 characters".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn needs_password(&self) -> bool {
+        matches!(self, OverridePolicy::Password | OverridePolicy::DelayThenPassword { .. })
+    }
+
+    /// Wait and typing challenge the policy asks for, on top of the weekly unlock friction
+    pub fn friction(&self) -> UnlockFriction {
+        match *self {
+            OverridePolicy::DelayThenPassword { delay_seconds } => UnlockFriction { delay_seconds, ..Default::default() },
+            OverridePolicy::TypingChallenge { length } => UnlockFriction { challenge_length: length, ..Default::default() },
+            OverridePolicy::Never | OverridePolicy::Password => UnlockFriction::default(),
+        }
+    }
+}
+
+/// What the caller established before asking to end a session early (see `SessionManager::end_session`)
+#[derive(Debug, Clone, Default)]
+pub struct OverrideProof<'a> {
+    pub friction: UnlockFriction, // this week's unlock friction, faced by every early end
+    pub password_verified: bool,
+    pub challenge_response: Option<&'a str>,
+    pub now: i64,
+}
+
+/// How a session started with `SessionManager::start_session` is enforced
+#[derive(Debug, Clone, Default)]
+pub struct SessionOptions {
+    /// The session can't be ended early until its time is up (unless the emergency override is used)
+    pub hardcore: bool,
+    /// Only applies to hardcore sessions
+    pub extreme: bool,
+    /// Adds the profile's categories to the block list until the session ends
+    pub blocklist: Option<BlocklistProfile>,
+    /// How a hardcore session can still be ended early
+    pub override_policy: OverridePolicy,
+    pub allowlist_only: bool,
+}
+
+impl ActiveSession {
+    /// Precedence when several sessions run at once: hardcore > scheduled > manual (and pomodoro).
+    /// The session with the highest precedence is the primary one shown and acted on by default.
+    pub fn precedence(&self) -> u8 {
+        let scheduled = self.session_type == SessionType::Scheduled;
+        (self.hardcore as u8) * 2 + scheduled as u8
+    }
+}
+
+pub const BLOCKLIST_PROFILES_SETTING: &str = "blocklist_profiles";
+/// Blocks applied for running sessions (JSON `Vec<AppliedSessionBlocks>`), so ones left
+/// behind by a crash or restart can be found and reverted
+pub const APPLIED_SESSION_BLOCKS_SETTING: &str = "applied_session_blocks";
+/// Schedule windows ended before their time (JSON `Vec<(schedule id, end time)>`), so they
+/// aren't started again after a restart
+pub const ENDED_WINDOWS_SETTING: &str = "ended_schedule_windows";
+
+/// What a session's blocklist profile blocked on top of the always-on block list
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AppliedSessionBlocks {
+    pub session_id: String,
+    pub session_name: String,
+    pub domains: Vec<String>,
+    pub apps: Vec<String>, // process names
+}
+
+/// A named set of site/app categories a session can block, e.g. "Writing" blocking
+/// "development" (IDEs) or "Coding" blocking "news".
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BlocklistProfile {
+    pub id: String,
+    pub name: String,
+    pub categories: Vec<String>,
+    /// YouTube sections blocked during the session, on top of the global `youtube_rules`
+    #[serde(default)]
+    pub youtube: crate::extension::YouTubeRules,
+}
+
+/// Category schedules (JSON `Vec<CategorySchedule>`)
+pub const CATEGORY_SCHEDULES_SETTING: &str = "category_schedules";
+
+/// Blocks every site and app in a category during a daily window, whether or not they are
+/// enabled, e.g. "social" 09:00-17:00 on weekdays. "Until 18:00" is 00:00-18:00; a window
+/// ending before it starts runs past midnight, like bedtime.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CategorySchedule {
+    pub category: String,
+    pub days: Vec<String>, // "Mon".."Sun", the days the window starts on
+    pub start_time: String, // HH:MM
+    pub end_time: String, // HH:MM
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl CategorySchedule {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.category.trim().is_empty() {
+            return Err("Category is required".to_string());
+        }
+        for time in [&self.start_time, &self.end_time] {
+            if NaiveTime::parse_from_str(time, "%H:%M").is_err() {
+                return Err(format!("'{}' is not a time (HH:MM)", time));
+            }
+        }
+        if self.start_time == self.end_time {
+            return Err("Start and end time must differ".to_string());
+        }
+        if let Some(day) = self.days.iter().find(|d| !WEEKDAYS.contains(&d.as_str())) {
+            return Err(format!("Unknown day '{}'", day));
+        }
+        Ok(())
+    }
+
+    pub fn is_active_at(&self, now: NaiveDateTime) -> bool {
+        self.enabled && is_window_at(&self.days, &self.start_time, &self.end_time, now)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -22,6 +210,16 @@ pub enum SessionType {
     Pomodoro,
 }
 
+impl SessionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionType::Manual => "manual",
+            SessionType::Scheduled => "scheduled",
+            SessionType::Pomodoro => "pomodoro",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PomodoroState {
     pub phase: PomodoroPhase,
@@ -32,6 +230,80 @@ pub struct PomodoroState {
     pub completed_sessions: i32,
     pub time_remaining: i64,     // seconds
     pub is_running: bool,
+    #[serde(default)]
+    pub paused_for_idle: bool, // paused automatically; resumes on the next input
+}
+
+/// Named timer setup, stored in the `pomodoro_presets` setting and shared as JSON snippets
+pub const POMODORO_PRESETS_SETTING: &str = "pomodoro_presets";
+/// Version of the export snippet format
+pub const POMODORO_EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PomodoroPreset {
+    pub name: String,
+    pub work_seconds: i64,
+    pub short_break_seconds: i64,
+    pub long_break_seconds: i64,
+    pub sessions_until_long_break: i32,
+}
+
+impl PomodoroPreset {
+    pub fn from_state(name: &str, state: &PomodoroState) -> Self {
+        PomodoroPreset {
+            name: name.to_string(),
+            work_seconds: state.work_duration,
+            short_break_seconds: state.break_duration,
+            long_break_seconds: state.long_break_duration,
+            sessions_until_long_break: state.sessions_until_long_break,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let in_range = |secs: i64| (60..=4 * 60 * 60).contains(&secs);
+        if self.name.trim().is_empty() {
+            return Err("Preset name can't be empty".to_string());
+        }
+        if !in_range(self.work_seconds) || !in_range(self.short_break_seconds) || !in_range(self.long_break_seconds) {
+            return Err(format!("'{}': durations must be between 1 minute and 4 hours", self.name));
+        }
+        if !(1..=12).contains(&self.sessions_until_long_break) {
+            return Err(format!("'{}': sessions until a long break must be between 1 and 12", self.name));
+        }
+        Ok(())
+    }
+}
+
+/// A shareable snippet: the current timer setup and/or custom presets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PomodoroExport {
+    pub bastion_pomodoro: u32, // format version, identifies the snippet
+    #[serde(default)]
+    pub config: Option<PomodoroPreset>,
+    #[serde(default)]
+    pub presets: Vec<PomodoroPreset>,
+}
+
+impl PomodoroExport {
+    /// Parse and validate a snippet
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let export: PomodoroExport = serde_json::from_str(json.trim()).map_err(|e| format!("Not a Bastion pomodoro snippet: {}", e))?;
+        if export.bastion_pomodoro > POMODORO_EXPORT_VERSION {
+            return Err("This snippet was made by a newer version of Bastion".to_string());
+        }
+        for preset in export.config.iter().chain(&export.presets) {
+            preset.validate()?;
+        }
+        Ok(export)
+    }
+}
+
+/// Add `imported` presets to `presets`, replacing any with the same name (case-insensitive).
+pub fn merge_presets(presets: &mut Vec<PomodoroPreset>, imported: Vec<PomodoroPreset>) {
+    for preset in imported {
+        presets.retain(|p| !p.name.trim().eq_ignore_ascii_case(preset.name.trim()));
+        presets.push(preset);
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -41,6 +313,54 @@ pub enum PomodoroPhase {
     LongBreak,
 }
 
+/// Monotonic view of a session's deadline, immune to changes of the system clock.
+/// Times are `boot_clock` readings.
+struct SessionClock {
+    deadline: Duration,
+    last_check_wall: i64,
+    last_check_mono: Duration,
+    jump_detected: bool,
+}
+
+impl SessionClock {
+    /// Compare wall-clock progress with `boot_clock` progress since the last reading.
+    /// Returns true the first time they disagree.
+    fn observe(&mut self, now_wall: i64, now_mono: Duration) -> bool {
+        let wall_delta = now_wall - self.last_check_wall;
+        let mono_delta = now_mono.saturating_sub(self.last_check_mono).as_secs() as i64;
+        self.last_check_wall = now_wall;
+        self.last_check_mono = now_mono;
+
+        if (wall_delta - mono_delta).abs() > CLOCK_JUMP_TOLERANCE_SECS && !self.jump_detected {
+            self.jump_detected = true;
+            return true;
+        }
+        false
+    }
+}
+
+/// Time since boot including time spent suspended, so deadlines keep running while the
+/// machine sleeps. `Instant` stops during sleep on Linux and macOS, which would stretch a
+/// session by the time the lid was closed and look like a clock change on resume.
+fn boot_clock() -> Duration {
+    #[cfg(unix)]
+    {
+        // CLOCK_MONOTONIC keeps counting during sleep on macOS; elsewhere that's CLOCK_BOOTTIME
+        #[cfg(target_vendor = "apple")]
+        const CLOCK: libc::clockid_t = libc::CLOCK_MONOTONIC;
+        #[cfg(not(target_vendor = "apple"))]
+        const CLOCK: libc::clockid_t = libc::CLOCK_BOOTTIME;
+        let mut now = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        // SAFETY: `now` is a valid timespec for clock_gettime to fill in
+        if unsafe { libc::clock_gettime(CLOCK, &mut now) } == 0 {
+            return Duration::new(now.tv_sec as u64, now.tv_nsec as u32);
+        }
+    }
+    // Windows' performance counter (behind `Instant`) keeps counting during sleep
+    static START: std::sync::OnceLock<Instant> = std::sync::OnceLock::new();
+    START.get_or_init(Instant::now).elapsed()
+}
+
 impl Default for PomodoroState {
     fn default() -> Self {
         PomodoroState {
@@ -52,19 +372,100 @@ impl Default for PomodoroState {
             completed_sessions: 0,
             time_remaining: 25 * 60,
             is_running: false,
+            paused_for_idle: false,
         }
     }
 }
 
 /// Manages the state of active focus sessions and Pomodoro timers.
 ///
+/// Several sessions can run at once (say, a scheduled block and a manual pomodoro session);
+/// each is a source of block rules and their blocklists are merged for enforcement. Where a
+/// single session is needed, the one with the highest `ActiveSession::precedence` is used.
+///
 /// This struct is thread-safe and shared across the application state.
 /// It uses `AtomicBool` for the critical `hardcore_locked` flag to ensure atomic access
 /// without needing a full mutex lock for read-heavy operations (like blocking checks).
 pub struct SessionManager {
-    pub active_session: Mutex<Option<ActiveSession>>,
+    /// Active sessions in the order they started
+    sessions: Mutex<Vec<RunningSession>>,
     pub pomodoro_state: Mutex<PomodoroState>,
+    /// Set while any active session is hardcore
     pub is_hardcore_locked: AtomicBool,
+    /// Set while a bedtime schedule is in effect (updated by the enforcement loop)
+    pub is_bedtime_active: AtomicBool,
+    /// Categories whose schedule is in effect (updated by the enforcement loop)
+    scheduled_categories: Mutex<Vec<String>>,
+    /// Where the signed hardcore lock is persisted (see `security`), if enabled
+    lock_state_path: Option<PathBuf>,
+    /// When the lock state was last written
+    lock_saved_at: Mutex<Instant>,
+    /// Files protected against deletion while a session is active, with whether they must stay writable
+    protected_files: Vec<(PathBuf, bool)>,
+    /// Typing challenge handed out by `issue_unlock_challenge`, awaiting its answer
+    pending_unlock: Mutex<Option<UnlockChallenge>>,
+    /// Temptation-delay unlock requested with `request_unlock`, waiting out its delay
+    unlock_request: Mutex<Option<PendingUnlock>>,
+    /// Where the pending temptation-delay unlock is persisted, if enabled
+    unlock_request_path: Option<PathBuf>,
+    /// Schedule windows (schedule id, end time) ended before their time, so the scheduler
+    /// doesn't start them again
+    ended_windows: Mutex<Vec<(i64, i64)>>,
+}
+
+struct RunningSession {
+    session: ActiveSession,
+    clock: SessionClock,
+}
+
+impl RunningSession {
+    fn new(session: ActiveSession) -> Self {
+        let now = Local::now().timestamp();
+        let mono_now = boot_clock();
+        RunningSession {
+            clock: SessionClock {
+                deadline: mono_now + Duration::from_secs((session.end_time - now).max(0) as u64),
+                last_check_wall: now,
+                last_check_mono: mono_now,
+                jump_detected: false,
+            },
+            session,
+        }
+    }
+
+    /// Remaining seconds, taking the later of the wall-clock and the monotonic deadline so
+    /// that moving the system clock forward can't shorten a session.
+    fn remaining(&self) -> i64 {
+        self.remaining_at(Local::now().timestamp(), boot_clock())
+    }
+
+    fn remaining_at(&self, now_wall: i64, now_mono: Duration) -> i64 {
+        let wall_remaining = self.session.end_time - now_wall;
+        let mono_remaining = self.clock.deadline.saturating_sub(now_mono).as_secs() as i64;
+        wall_remaining.max(mono_remaining).max(0)
+    }
+}
+
+/// Index of the session with the highest precedence; among equals, the one ending last.
+fn primary_index(sessions: &[RunningSession]) -> Option<usize> {
+    sessions
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, r)| (r.session.precedence(), r.session.end_time))
+        .map(|(i, _)| i)
+}
+
+/// The hardcore session ending last, which is the one the persisted lock binds to.
+fn binding_session(sessions: &[RunningSession]) -> Option<&RunningSession> {
+    sessions.iter().filter(|r| r.session.hardcore).max_by_key(|r| r.session.end_time)
+}
+
+/// Index of session `id`, or of the primary session if `id` is `None`.
+fn find_index(sessions: &[RunningSession], id: Option<&str>) -> Option<usize> {
+    match id {
+        Some(id) => sessions.iter().position(|r| r.session.id == id),
+        None => primary_index(sessions),
+    }
 }
 
 impl Default for SessionManager {
@@ -76,98 +477,539 @@ impl Default for SessionManager {
 impl SessionManager {
     pub fn new() -> Self {
         SessionManager {
-            active_session: Mutex::new(None),
+            sessions: Mutex::new(Vec::new()),
             pomodoro_state: Mutex::new(PomodoroState::default()),
             is_hardcore_locked: AtomicBool::new(false),
+            is_bedtime_active: AtomicBool::new(false),
+            scheduled_categories: Mutex::new(Vec::new()),
+            lock_state_path: None,
+            lock_saved_at: Mutex::new(Instant::now()),
+            protected_files: Vec::new(),
+            pending_unlock: Mutex::new(None),
+            unlock_request: Mutex::new(None),
+            unlock_request_path: None,
+            ended_windows: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Persist hardcore sessions to `path` so the lock survives restarts and database deletion.
+    pub fn with_lock_state_file(mut self, path: PathBuf) -> Self {
+        self.protected_files.push((path.clone(), false));
+        self.lock_state_path = Some(path);
+        self
+    }
+
+    /// Persist the pending temptation-delay unlock to `path`, so restarting doesn't skip the wait.
+    pub fn with_unlock_request_file(mut self, path: PathBuf) -> Self {
+        self.unlock_request_path = Some(path);
+        self
+    }
+
+    /// Protect `path` against deletion while a session is active.
+    pub fn with_protected_file(mut self, path: PathBuf, writable: bool) -> Self {
+        self.protected_files.push((path, writable));
+        self
+    }
+
+    fn protect_files(&self) {
+        for (path, writable) in &self.protected_files {
+            crate::security::protect_file(path, *writable);
+        }
+    }
+
+    fn unprotect_files(&self) {
+        for (path, writable) in &self.protected_files {
+            crate::security::unprotect_file(path, *writable);
+        }
+    }
+
+    /// Bring the hardcore flag, the persisted lock and file protection in line with `sessions`.
+    /// Only the hardcore session ending last is persisted, since it is the one that binds.
+    fn sessions_changed(&self, sessions: &[RunningSession]) {
+        let binding = binding_session(sessions);
+        self.is_hardcore_locked.store(binding.is_some(), Ordering::SeqCst);
+
+        // Lift file protection while the lock state is rewritten
+        self.unprotect_files();
+        self.save_lock_state(binding);
+        if !sessions.is_empty() {
+            self.protect_files();
+        }
+    }
+
+    /// Write the lock state for `binding` (with its remaining time as of now), or clear it.
+    fn save_lock_state(&self, binding: Option<&RunningSession>) {
+        let Some(ref path) = self.lock_state_path else {
+            return;
+        };
+        match binding {
+            Some(running) => {
+                let clock = crate::security::LockClock {
+                    remaining: running.remaining(),
+                    last_seen: Local::now().timestamp(),
+                };
+                if let Err(e) = crate::security::save_lock_state(path, &running.session, &clock) {
+                    eprintln!("[Bastion] Could not persist hardcore lock: {}", e);
+                }
+            }
+            None => crate::security::clear_lock_state(path),
         }
+        *self.lock_saved_at.lock().unwrap() = Instant::now();
     }
 
-    /// Start a manual focus session.
+    /// Restore a hardcore session persisted before the last shutdown.
     ///
-    /// If `hardcore` is true, the session cannot be ended early until the duration expires
-    /// (unless the emergency override is used).
-    pub fn start_session(&self, name: String, duration_minutes: i64, hardcore: bool) -> ActiveSession {
+    /// Returns the restored session, or `None` if there was nothing to restore or the session
+    /// has already expired. A state file whose signature doesn't match fails closed: a hardcore
+    /// lock is reinstated until the latest end it could plausibly have had, then the error is
+    /// returned. An unreadable one is only reported; it is written atomically, so it doesn't
+    /// come from an interrupted write.
+    ///
+    /// A hardcore session only counts down while Bastion runs: it resumes with the time it had
+    /// left when last seen, so moving the clock forward across a restart can't expire it.
+    pub fn restore_persisted_session(&self) -> Result<Option<ActiveSession>, crate::security::LockStateError> {
+        let Some(ref path) = self.lock_state_path else {
+            return Ok(None);
+        };
+        
+        let (mut session, clock) = match crate::security::load_lock_state(path) {
+            Ok(state) => state,
+            Err(crate::security::LockStateError::Missing) => return Ok(None),
+            Err(e @ crate::security::LockStateError::Corrupt) => return Err(e),
+            Err(e) => {
+                let now = Local::now().timestamp();
+                let claimed_end = crate::security::unverified_lock_end(path).unwrap_or(now);
+                // Replaces the state file with a valid lock through sessions_changed
+                self.add_session(ActiveSession {
+                    id: format!("session_{}", now),
+                    name: "Hardcore lock (unverified lock state)".to_string(),
+                    start_time: now,
+                    end_time: claimed_end.clamp(now + UNVERIFIED_LOCK_MIN_SECS, now + UNVERIFIED_LOCK_MAX_SECS),
+                    hardcore: true,
+                    session_type: SessionType::Manual,
+                    extreme: false,
+                    blocklist: None,
+                    schedule_id: None,
+                    override_policy: OverridePolicy::Never,
+                    allowlist_only: false,
+                });
+                return Err(e);
+            }
+        };
+        
         let now = Local::now().timestamp();
-        let session = ActiveSession {
+        if let Some(clock) = clock {
+            session.end_time = session.end_time.max(now + clock.remaining);
+        }
+        if session.end_time <= now {
+            crate::security::clear_lock_state(path);
+            return Ok(None);
+        }
+        
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|r| r.session.id != session.id);
+        sessions.push(RunningSession::new(session.clone()));
+        self.sessions_changed(&sessions);
+        Ok(Some(session))
+    }
+
+    /// Start a manual focus session alongside any sessions already running.
+    pub fn start_session(&self, name: String, duration_minutes: i64, options: SessionOptions) -> ActiveSession {
+        let now = Local::now().timestamp();
+        self.add_session(ActiveSession {
             id: format!("session_{}", now),
             name,
             start_time: now,
             end_time: now + (duration_minutes * 60),
-            hardcore,
+            hardcore: options.hardcore,
             session_type: SessionType::Manual,
-        };
-        
-        if hardcore {
-            self.is_hardcore_locked.store(true, Ordering::SeqCst);
+            extreme: options.hardcore && options.extreme,
+            blocklist: options.blocklist,
+            schedule_id: None,
+            override_policy: options.override_policy,
+            allowlist_only: options.allowlist_only,
+        })
+    }
+
+    /// Start the session for `schedule`, running until `end_time` (unix time), unless it is
+    /// already running or this window was ended early.
+    pub fn start_scheduled_session(
+        &self,
+        schedule: &crate::storage::Session,
+        end_time: i64,
+        extreme: bool,
+    ) -> Option<ActiveSession> {
+        if self.sessions.lock().unwrap().iter().any(|r| r.session.schedule_id == Some(schedule.id)) {
+            return None;
         }
-        
-        let mut active = self.active_session.lock().unwrap();
-        *active = Some(session.clone());
+        let now = Local::now().timestamp();
+        {
+            let mut ended_windows = self.ended_windows.lock().unwrap();
+            ended_windows.retain(|&(_, ends_at)| ends_at > now);
+            if ended_windows.contains(&(schedule.id, end_time)) {
+                return None;
+            }
+        }
+        Some(self.add_session(ActiveSession {
+            id: format!("schedule_{}_{}", schedule.id, now),
+            name: schedule.name.clone(),
+            start_time: now,
+            end_time,
+            hardcore: schedule.hardcore,
+            session_type: SessionType::Scheduled,
+            extreme: schedule.hardcore && extreme,
+            blocklist: None,
+            schedule_id: Some(schedule.id),
+            override_policy: OverridePolicy::Never,
+            allowlist_only: false,
+        }))
+    }
+
+    fn add_session(&self, mut session: ActiveSession) -> ActiveSession {
+        let mut sessions = self.sessions.lock().unwrap();
+        // Two sessions can start within the same second (e.g. a trigger and a schedule)
+        let base_id = session.id.clone();
+        let mut n = 1;
+        while sessions.iter().any(|r| r.session.id == session.id) {
+            n += 1;
+            session.id = format!("{}_{}", base_id, n);
+        }
+        sessions.push(RunningSession::new(session.clone()));
+        self.sessions_changed(&sessions);
         session
     }
 
-    /// End the current session
-    pub fn end_session(&self) -> Result<(), String> {
-        let active = self.active_session.lock().unwrap();
-        
-        if let Some(ref session) = *active {
-            if session.hardcore {
-                let now = Local::now().timestamp();
-                if now < session.end_time {
+    /// Push out the end of the primary session by `minutes`.
+    ///
+    /// Allowed for hardcore sessions too; since they can't be ended early, the extra time is binding.
+    pub fn extend_session(&self, minutes: i64) -> Result<ActiveSession, String> {
+        if !(1..=MAX_EXTENSION_MINUTES).contains(&minutes) {
+            return Err(format!("Extension must be between 1 and {} minutes", MAX_EXTENSION_MINUTES));
+        }
+        let mut sessions = self.sessions.lock().unwrap();
+        let Some(index) = primary_index(&sessions) else {
+            return Err("No active session".to_string());
+        };
+        let running = &mut sessions[index];
+        let seconds = minutes.checked_mul(60).ok_or("Extension is too long")?;
+        let end_time = running.session.end_time.checked_add(seconds).ok_or("Extension is too long")?;
+        let deadline = running.clock.deadline.checked_add(Duration::from_secs(seconds as u64)).ok_or("Extension is too long")?;
+        running.session.end_time = end_time;
+        running.clock.deadline = deadline;
+        let session = running.session.clone();
+
+        if session.hardcore {
+            self.sessions_changed(&sessions);
+        }
+        Ok(session)
+    }
+
+    /// Turn the primary session into a hardcore one. This is one-way: the session can't be
+    /// made normal again and can no longer be ended early.
+    pub fn harden_session(&self, extreme: bool) -> Result<ActiveSession, String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let Some(index) = primary_index(&sessions) else {
+            return Err("No active session".to_string());
+        };
+        let session = &mut sessions[index].session;
+        if session.hardcore {
+            return Err("Session is already hardcore".to_string());
+        }
+        session.hardcore = true;
+        session.extreme = extreme;
+        let session = session.clone();
+
+        self.sessions_changed(&sessions);
+        Ok(session)
+    }
+
+    /// End session `id` (the primary session if `None`) and return it.
+    ///
+    /// Ending a session before its time is up needs `proof` of this week's unlock friction.
+    /// Hardcore sessions also need what their `OverridePolicy` asks for, and can't be ended
+    /// early at all under `OverridePolicy::Never`.
+    pub fn end_session(&self, id: Option<&str>, proof: &OverrideProof) -> Result<ActiveSession, String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let Some(index) = find_index(&sessions, id) else {
+            return Err("No active session".to_string());
+        };
+        let running = &sessions[index];
+        if running.remaining() > 0 {
+            let policy = &running.session.override_policy;
+            let mut required = proof.friction.clone();
+            if running.session.hardcore {
+                if policy.is_never() {
+                    if running.clock.jump_detected {
+                        return Err("System clock change detected. Cannot end hardcore session before time expires".to_string());
+                    }
                     return Err("Cannot end hardcore session before time expires".to_string());
                 }
+                if policy.needs_password() && !proof.password_verified {
+                    return Err("This session can only be ended early with the master password".to_string());
+                }
+                required = required.stricter(&policy.friction());
             }
+            self.pass_unlock_challenge(&required, proof.challenge_response, proof.now)?;
         }
-        
-        drop(active);
-        self.force_end_session()
+
+        let ended = sessions.remove(index).session;
+        *self.pending_unlock.lock().unwrap() = None;
+        self.sessions_changed(&sessions);
+        self.remember_ended_windows(std::slice::from_ref(&ended));
+        Ok(ended)
+    }
+
+    /// Force end every session (bypassing hardcore checks) and return them.
+    pub fn force_end_all(&self) -> Vec<ActiveSession> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let ended: Vec<ActiveSession> = sessions.drain(..).map(|r| r.session).collect();
+        *self.pending_unlock.lock().unwrap() = None;
+        self.sessions_changed(&sessions);
+        self.remember_ended_windows(&ended);
+        ended
+    }
+
+    /// Schedule windows ended before their time that haven't run out yet, as (schedule id, end time)
+    pub fn ended_windows(&self) -> Vec<(i64, i64)> {
+        let now = Local::now().timestamp();
+        let mut ended_windows = self.ended_windows.lock().unwrap();
+        ended_windows.retain(|&(_, ends_at)| ends_at > now);
+        ended_windows.clone()
     }
 
-    /// Force end the current session (bypassing hardcore checks)
-    pub fn force_end_session(&self) -> Result<(), String> {
-        let mut active = self.active_session.lock().unwrap();
-        *active = None;
-        self.is_hardcore_locked.store(false, Ordering::SeqCst);
+    /// Restore the windows from `ended_windows` saved before a restart.
+    pub fn restore_ended_windows(&self, windows: Vec<(i64, i64)>) {
+        *self.ended_windows.lock().unwrap() = windows;
+    }
+
+    fn remember_ended_windows(&self, ended: &[ActiveSession]) {
+        let now = Local::now().timestamp();
+        let mut ended_windows = self.ended_windows.lock().unwrap();
+        for session in ended.iter().filter(|s| s.end_time > now) {
+            if let Some(schedule_id) = session.schedule_id {
+                ended_windows.push((schedule_id, session.end_time));
+            }
+        }
+    }
+
+    /// Remove and return the sessions whose time is up.
+    pub fn take_expired_sessions(&self) -> Vec<ActiveSession> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let (expired, running): (Vec<_>, Vec<_>) = sessions.drain(..).partition(|r| r.remaining() <= 0);
+        *sessions = running;
+        if !expired.is_empty() {
+            self.sessions_changed(&sessions);
+        }
+        expired.into_iter().map(|r| r.session).collect()
+    }
+
+    /// Start the wait and typing challenge for ending the session early. Replaces any
+    /// previous challenge, so asking again restarts the delay.
+    pub fn issue_unlock_challenge(&self, friction: UnlockFriction, now: i64) -> UnlockChallenge {
+        use rand::Rng;
+        // No look-alike characters, so the challenge can be typed from the screen
+        const CHARSET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+        let mut rng = rand::thread_rng();
+        let letters: Vec<char> = (0..friction.challenge_length)
+            .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+            .collect();
+        // Groups of five are easier to copy than one long run
+        let text = letters.chunks(5).map(|group| group.iter().collect::<String>()).collect::<Vec<_>>().join(" ");
+        let challenge = UnlockChallenge { text, ready_at: now + friction.delay_seconds, friction };
+        *self.pending_unlock.lock().unwrap() = Some(challenge.clone());
+        challenge
+    }
+
+    /// Check the answer to the pending challenge against the friction currently required.
+    /// The challenge is used up once passed.
+    pub fn pass_unlock_challenge(&self, required: &UnlockFriction, response: Option<&str>, now: i64) -> Result<(), String> {
+        if required.delay_seconds == 0 && required.challenge_length == 0 {
+            return Ok(());
+        }
+        let mut pending = self.pending_unlock.lock().unwrap();
+        let Some(challenge) = pending.as_ref() else {
+            return Err("Request an unlock challenge first".to_string());
+        };
+        if challenge.friction.delay_seconds < required.delay_seconds || challenge.friction.challenge_length < required.challenge_length {
+            return Err("Unlock friction increased, request a new challenge".to_string());
+        }
+        if now < challenge.ready_at {
+            return Err(format!("Wait {} more seconds before unlocking", challenge.ready_at - now));
+        }
+        if required.challenge_length > 0 && response.map(str::trim) != Some(challenge.text.as_str()) {
+            return Err("Challenge text doesn't match".to_string());
+        }
+        *pending = None;
         Ok(())
     }
 
-    /// Get time remaining in current session (seconds)
-    pub fn get_time_remaining(&self) -> Option<i64> {
-        let active = self.active_session.lock().unwrap();
-        if let Some(ref session) = *active {
-            let now = Local::now().timestamp();
-            let remaining = session.end_time - now;
-            Some(remaining.max(0))
-        } else {
-            None
+    /// Restore the temptation-delay unlock pending before the last shutdown. A tampered file is
+    /// discarded, which only means asking (and waiting) again.
+    pub fn restore_unlock_request(&self) -> Option<UnlockRequest> {
+        let path = self.unlock_request_path.as_ref()?;
+        match crate::security::load_unlock_request(path) {
+            Ok(request) => {
+                *self.unlock_request.lock().unwrap() = Some(PendingUnlock::new(request.clone()));
+                Some(request)
+            }
+            Err(_) => {
+                crate::security::clear_lock_state(path);
+                None
+            }
+        }
+    }
+
+    fn save_unlock_request(&self, request: Option<&UnlockRequest>) {
+        let Some(ref path) = self.unlock_request_path else {
+            return;
+        };
+        match request {
+            Some(request) => {
+                if let Err(e) = crate::security::save_unlock_request(path, request) {
+                    eprintln!("[Bastion] Could not persist unlock request: {}", e);
+                }
+            }
+            None => crate::security::clear_lock_state(path),
+        }
+    }
+
+    /// Start the temptation-delay wait for `target`. Asking again for the same target keeps the
+    /// time already waited; a different target replaces the request and starts over.
+    pub fn request_unlock(&self, target: UnlockTarget, delay_seconds: i64) -> UnlockStatus {
+        let mut pending = self.unlock_request.lock().unwrap();
+        if let Some(unlock) = pending.as_ref().filter(|unlock| unlock.request.target == target) {
+            return unlock.status();
+        }
+        let now = Local::now().timestamp();
+        let request = UnlockRequest { target, requested_at: now, ready_at: now + delay_seconds.max(0) };
+        self.save_unlock_request(Some(&request));
+        let unlock = PendingUnlock::new(request);
+        let status = unlock.status();
+        *pending = Some(unlock);
+        status
+    }
+
+    pub fn unlock_status(&self) -> Option<UnlockStatus> {
+        self.unlock_request.lock().unwrap().as_ref().map(PendingUnlock::status)
+    }
+
+    pub fn cancel_unlock(&self) {
+        if self.unlock_request.lock().unwrap().take().is_some() {
+            self.save_unlock_request(None);
+        }
+    }
+
+    /// Whether the pending unlock is for `target` and has waited out its delay. Use it up with
+    /// `take_unlock` once the unlocked action went through.
+    pub fn check_unlock(&self, target: &UnlockTarget) -> Result<(), String> {
+        let pending = self.unlock_request.lock().unwrap();
+        let Some(unlock) = pending.as_ref().filter(|unlock| unlock.request.target == *target) else {
+            return Err("Request an unlock and wait out the delay first".to_string());
+        };
+        match unlock.remaining() {
+            0 => Ok(()),
+            remaining => Err(format!("Wait {} more seconds before unlocking", remaining)),
         }
     }
 
-    /// Check if session has expired
-    pub fn is_session_expired(&self) -> bool {
-        if let Some(remaining) = self.get_time_remaining() {
-            remaining <= 0
-        } else {
-            true
+    pub fn take_unlock(&self, target: &UnlockTarget) {
+        let mut pending = self.unlock_request.lock().unwrap();
+        if pending.as_ref().is_some_and(|unlock| unlock.request.target == *target) {
+            *pending = None;
+            self.save_unlock_request(None);
         }
     }
 
-    /// Check if any scheduled session should be active now
-    pub fn check_scheduled_sessions(&self, sessions: &[crate::storage::Session]) -> Option<crate::storage::Session> {
+    /// Get time remaining in the primary session (seconds)
+    pub fn get_time_remaining(&self) -> Option<i64> {
+        let sessions = self.sessions.lock().unwrap();
+        primary_index(&sessions).map(|index| sessions[index].remaining())
+    }
+
+    /// Session `id` (the primary session if `None`) with its remaining seconds
+    pub fn get_session(&self, id: Option<&str>) -> Option<(ActiveSession, i64)> {
+        let sessions = self.sessions.lock().unwrap();
+        find_index(&sessions, id).map(|index| (sessions[index].session.clone(), sessions[index].remaining()))
+    }
+
+    /// The primary session: the active session with the highest precedence
+    pub fn active_session(&self) -> Option<ActiveSession> {
+        self.get_session(None).map(|(session, _)| session)
+    }
+
+    /// All active sessions, highest precedence first
+    pub fn active_sessions(&self) -> Vec<ActiveSession> {
+        let mut sessions: Vec<ActiveSession> = self.sessions.lock().unwrap().iter().map(|r| r.session.clone()).collect();
+        sessions.sort_by_key(|s| std::cmp::Reverse((s.precedence(), s.end_time)));
+        sessions
+    }
+
+    /// Compare wall-clock progress with monotonic progress since the last check (call every tick).
+    /// Returns true the first time a clock jump is detected during an active session.
+    pub fn check_clock(&self) -> bool {
+        let mut sessions = self.sessions.lock().unwrap();
+        let now_wall = Local::now().timestamp();
+        let now_mono = boot_clock();
+        let mut detected = false;
+        for clock in sessions.iter_mut().map(|r| &mut r.clock) {
+            detected |= clock.observe(now_wall, now_mono);
+        }
+
+        // Keep the remaining time in the lock state current for restore_persisted_session
+        let binding = binding_session(&sessions);
+        if binding.is_some() && self.lock_saved_at.lock().unwrap().elapsed() >= LOCK_REFRESH_INTERVAL {
+            self.unprotect_files();
+            self.save_lock_state(binding);
+            self.protect_files();
+        }
+        detected
+    }
+
+    pub fn active_session_id(&self) -> Option<String> {
+        self.active_session().map(|s| s.id)
+    }
+
+    /// Categories blocked by the blocklist profiles of all active sessions
+    pub fn blocked_categories(&self) -> Vec<String> {
+        self.active_blocklist().map(|profile| profile.categories).unwrap_or_default()
+    }
+
+    /// Blocklist profiles of all active sessions merged into one (named after the primary
+    /// session's profile), if any of them has one
+    pub fn active_blocklist(&self) -> Option<BlocklistProfile> {
+        self.active_sessions()
+            .into_iter()
+            .filter_map(|s| s.blocklist)
+            .reduce(|mut merged, profile| {
+                for category in profile.categories {
+                    if !merged.categories.contains(&category) {
+                        merged.categories.push(category);
+                    }
+                }
+                merged.youtube = merged.youtube.union(profile.youtube);
+                merged
+            })
+    }
+
+    /// Whether any active session blocks everything but the allowed sites
+    pub fn is_allowlist_active(&self) -> bool {
+        self.sessions.lock().unwrap().iter().any(|r| r.session.allowlist_only)
+    }
+
+    /// Whether any active session is running in extreme hardcore mode
+    pub fn is_extreme_active(&self) -> bool {
+        self.sessions.lock().unwrap().iter().any(|r| r.session.extreme)
+    }
+
+    /// Scheduled sessions that should be active now, with the unix time each one ends
+    pub fn check_scheduled_sessions(&self, sessions: &[crate::storage::Session]) -> Vec<(crate::storage::Session, i64)> {
         let now = Local::now();
         let current_time = now.time();
-        let current_day = match now.weekday() {
-            Weekday::Mon => "Mon",
-            Weekday::Tue => "Tue",
-            Weekday::Wed => "Wed",
-            Weekday::Thu => "Thu",
-            Weekday::Fri => "Fri",
-            Weekday::Sat => "Sat",
-            Weekday::Sun => "Sun",
-        };
+        let current_day = weekday_name(now.weekday());
 
+        let mut due = Vec::new();
         for session in sessions {
-            if !session.enabled {
+            if !session.enabled || session.kind == SCHEDULE_KIND_BEDTIME {
                 continue;
             }
 
@@ -182,13 +1024,54 @@ impl SessionManager {
                 NaiveTime::parse_from_str(&session.start_time, "%H:%M"),
                 NaiveTime::parse_from_str(&session.end_time, "%H:%M"),
             ) {
-                if current_time >= start && current_time <= end {
-                    return Some(session.clone());
+                if current_time >= start && current_time < end {
+                    let ends_at = now
+                        .date_naive()
+                        .and_time(end)
+                        .and_local_timezone(Local)
+                        .earliest()
+                        .map_or(now.timestamp(), |t| t.timestamp());
+                    due.push((session.clone(), ends_at));
                 }
             }
         }
 
-        None
+        due
+    }
+
+    /// Find the bedtime schedule in effect right now, if any, and update `is_bedtime_active`.
+    pub fn check_bedtime(&self, sessions: &[crate::storage::Session]) -> Option<crate::storage::Session> {
+        let now = Local::now().naive_local();
+        let bedtime = sessions
+            .iter()
+            .find(|s| s.enabled && s.kind == SCHEDULE_KIND_BEDTIME && is_bedtime_at(s, now))
+            .cloned();
+        self.is_bedtime_active.store(bedtime.is_some(), Ordering::SeqCst);
+        bedtime
+    }
+
+    pub fn is_bedtime_active(&self) -> bool {
+        self.is_bedtime_active.load(Ordering::SeqCst)
+    }
+
+    /// Update the categories blocked by their schedule right now. Returns whether they changed.
+    pub fn check_category_schedules(&self, schedules: &[CategorySchedule]) -> bool {
+        let now = Local::now().naive_local();
+        let mut categories: Vec<String> = schedules
+            .iter()
+            .filter(|s| s.is_active_at(now))
+            .map(|s| s.category.clone())
+            .collect();
+        categories.sort();
+        categories.dedup();
+        let mut current = self.scheduled_categories.lock().unwrap();
+        let changed = *current != categories;
+        *current = categories;
+        changed
+    }
+
+    pub fn scheduled_categories(&self) -> Vec<String> {
+        self.scheduled_categories.lock().unwrap().clone()
     }
 
     // Pomodoro methods
@@ -197,12 +1080,38 @@ impl SessionManager {
     pub fn pomodoro_start(&self) {
         let mut state = self.pomodoro_state.lock().unwrap();
         state.is_running = true;
+        state.paused_for_idle = false;
     }
 
     /// Pause pomodoro timer
     pub fn pomodoro_pause(&self) {
         let mut state = self.pomodoro_state.lock().unwrap();
         state.is_running = false;
+        state.paused_for_idle = false;
+    }
+
+    /// Pause a running work phase because the user has been idle for `idle_secs`.
+    /// The idle time already counted down is given back. Returns whether the timer was paused.
+    pub fn pomodoro_idle_pause(&self, idle_secs: i64) -> bool {
+        let mut state = self.pomodoro_state.lock().unwrap();
+        if !state.is_running || state.phase != PomodoroPhase::Work {
+            return false;
+        }
+        state.time_remaining = (state.time_remaining + idle_secs).min(state.work_duration);
+        state.is_running = false;
+        state.paused_for_idle = true;
+        true
+    }
+
+    /// Resume a timer paused by `pomodoro_idle_pause`. Returns whether it was resumed.
+    pub fn pomodoro_idle_resume(&self) -> bool {
+        let mut state = self.pomodoro_state.lock().unwrap();
+        if !state.paused_for_idle {
+            return false;
+        }
+        state.is_running = true;
+        state.paused_for_idle = false;
+        true
     }
 
     /// Reset pomodoro timer
@@ -214,6 +1123,7 @@ impl SessionManager {
             PomodoroPhase::LongBreak => state.long_break_duration,
         };
         state.is_running = false;
+        state.paused_for_idle = false;
     }
 
     /// Tick the pomodoro timer (call every second)
@@ -231,12 +1141,37 @@ impl SessionManager {
         
         // Timer expired, switch phase
         let completed_phase = state.phase.clone();
+        Self::advance_phase(&mut state, true);
         
+        Some(completed_phase)
+    }
+
+    /// Skip the rest of the current phase and move on to the next one.
+    ///
+    /// Breaks can always be skipped. Work phases can only be skipped outside of hardcore
+    /// mode, and a skipped work phase does not count towards the long break.
+    /// Returns the phase the timer moved to.
+    pub fn pomodoro_skip_phase(&self) -> Result<PomodoroPhase, String> {
+        let mut state = self.pomodoro_state.lock().unwrap();
+        
+        if state.phase == PomodoroPhase::Work && self.is_hardcore_locked.load(Ordering::SeqCst) {
+            return Err("Cannot skip a work phase during a hardcore session".to_string());
+        }
+        
+        Self::advance_phase(&mut state, false);
+        Ok(state.phase.clone())
+    }
+
+    /// Switch to the phase following the current one and reset the countdown.
+    /// `count_work` decides whether a finished work phase counts as a completed session.
+    fn advance_phase(state: &mut PomodoroState, count_work: bool) {
         match state.phase {
             PomodoroPhase::Work => {
-                state.completed_sessions += 1;
+                if count_work {
+                    state.completed_sessions += 1;
+                }
                 
-                if state.completed_sessions % state.sessions_until_long_break == 0 {
+                if count_work && state.completed_sessions % state.sessions_until_long_break == 0 {
                     state.phase = PomodoroPhase::LongBreak;
                     state.time_remaining = state.long_break_duration;
                 } else {
@@ -249,8 +1184,6 @@ impl SessionManager {
                 state.time_remaining = state.work_duration;
             }
         }
-        
-        Some(completed_phase)
     }
 
     /// Update pomodoro settings
@@ -274,21 +1207,618 @@ impl SessionManager {
     }
 }
 
+/// Focus session started automatically at the first login/unlock of the day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MorningRoutine {
+    pub enabled: bool,
+    pub name: String,
+    pub duration_minutes: i64,
+    pub hardcore: bool,
+    pub window_start: String, // HH:MM, earliest time the routine may start
+    pub window_end: String,   // HH:MM, latest time the routine may start
+}
+
+impl Default for MorningRoutine {
+    fn default() -> Self {
+        MorningRoutine {
+            enabled: false,
+            name: "Morning Routine".to_string(),
+            duration_minutes: 60,
+            hardcore: false,
+            window_start: "05:00".to_string(),
+            window_end: "11:00".to_string(),
+        }
+    }
+}
+
+impl MorningRoutine {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Routine name can't be empty".to_string());
+        }
+        if !(1..=24 * 60).contains(&self.duration_minutes) {
+            return Err("Routine duration must be between 1 minute and 24 hours".to_string());
+        }
+        let parse = |time: &str| NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| format!("'{}' is not a time (HH:MM)", time));
+        if parse(&self.window_start)? > parse(&self.window_end)? {
+            return Err("The start window must not end before it begins".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether the routine should start at `now`, given the date (YYYY-MM-DD) it last ran.
+    pub fn is_due(&self, now: NaiveDateTime, last_run: Option<&str>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let today = now.format("%Y-%m-%d").to_string();
+        if last_run == Some(today.as_str()) {
+            return false;
+        }
+        match (
+            NaiveTime::parse_from_str(&self.window_start, "%H:%M"),
+            NaiveTime::parse_from_str(&self.window_end, "%H:%M"),
+        ) {
+            (Ok(start), Ok(end)) => now.time() >= start && now.time() <= end,
+            _ => false,
+        }
+    }
+}
+
+/// Escalating friction for ending sessions early and emergency unlocks ("bypasses"): each
+/// bypass this week adds a wait and lengthens the typing challenge for the next one.
+pub const UNLOCK_FRICTION_SETTING: &str = "unlock_friction";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FrictionPolicy {
+    pub enabled: bool,
+    pub delay_step_seconds: i64, // wait added per bypass this week
+    pub max_delay_seconds: i64,
+    pub challenge_step_chars: usize, // typing challenge length added per bypass this week
+    pub max_challenge_chars: usize,
+}
+
+impl Default for FrictionPolicy {
+    fn default() -> Self {
+        FrictionPolicy {
+            enabled: false,
+            delay_step_seconds: 30,
+            max_delay_seconds: 600,
+            challenge_step_chars: 20,
+            max_challenge_chars: 400,
+        }
+    }
+}
+
+impl FrictionPolicy {
+    pub fn friction_for(&self, bypasses_this_week: i64) -> UnlockFriction {
+        let level = if self.enabled { bypasses_this_week.max(0) } else { 0 };
+        UnlockFriction {
+            bypasses_this_week,
+            delay_seconds: (self.delay_step_seconds.max(0) * level).min(self.max_delay_seconds.max(0)),
+            challenge_length: (self.challenge_step_chars * level as usize).min(self.max_challenge_chars),
+        }
+    }
+
+    /// Whether changing to `new` makes any bypass easier; only allowed after a clean week.
+    pub fn is_loosened_by(&self, new: &FrictionPolicy) -> bool {
+        self.enabled
+            && (!new.enabled
+                || new.delay_step_seconds < self.delay_step_seconds
+                || new.max_delay_seconds < self.max_delay_seconds
+                || new.challenge_step_chars < self.challenge_step_chars
+                || new.max_challenge_chars < self.max_challenge_chars)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct UnlockFriction {
+    pub bypasses_this_week: i64,
+    pub delay_seconds: i64,
+    pub challenge_length: usize,
+}
+
+impl UnlockFriction {
+    /// The stricter of two requirements, field by field
+    pub fn stricter(&self, other: &UnlockFriction) -> UnlockFriction {
+        UnlockFriction {
+            bypasses_this_week: self.bypasses_this_week.max(other.bypasses_this_week),
+            delay_seconds: self.delay_seconds.max(other.delay_seconds),
+            challenge_length: self.challenge_length.max(other.challenge_length),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlockChallenge {
+    pub text: String, // type this back to unlock
+    pub ready_at: i64, // unix time the unlock becomes possible
+    pub friction: UnlockFriction,
+}
+
+/// Temptation delay: a softer alternative to hardcore where ending a session early or disabling
+/// a blocked site is possible, but only after waiting out an unskippable countdown.
+pub const TEMPTATION_DELAY_SETTING: &str = "temptation_delay";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TemptationDelay {
+    pub enabled: bool,
+    pub delay_seconds: i64,
+}
+
+impl Default for TemptationDelay {
+    fn default() -> Self {
+        TemptationDelay { enabled: false, delay_seconds: 60 }
+    }
+}
+
+impl TemptationDelay {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(10..=60 * 60).contains(&self.delay_seconds) {
+            return Err("The temptation delay must be between 10 seconds and 1 hour".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether changing to `new` shortens the wait; that needs an unlock of its own.
+    pub fn is_loosened_by(&self, new: &TemptationDelay) -> bool {
+        self.enabled && (!new.enabled || new.delay_seconds < self.delay_seconds)
+    }
+}
+
+/// What a temptation-delay unlock is for
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UnlockTarget {
+    EndSession { session_id: String },
+    /// Disabling or deleting blocked site `id`, or moving it off the hosts file
+    DisableSite { id: i64 },
+    /// Turning the temptation delay off or shortening it
+    LoosenDelay,
+    /// Deleting every blocked app and site
+    ResetBlocks,
+    /// Restoring the hosts file from its backup
+    RestoreHosts,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlockRequest {
+    pub target: UnlockTarget,
+    pub requested_at: i64, // unix time
+    pub ready_at: i64,     // unix time the unlock becomes possible
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnlockStatus {
+    pub target: UnlockTarget,
+    pub remaining_seconds: i64,
+    pub ready: bool,
+}
+
+struct PendingUnlock {
+    request: UnlockRequest,
+    deadline: Duration, // `boot_clock` reading
+}
+
+impl PendingUnlock {
+    fn new(request: UnlockRequest) -> Self {
+        let wait = (request.ready_at - Local::now().timestamp()).max(0) as u64;
+        PendingUnlock { request, deadline: boot_clock() + Duration::from_secs(wait) }
+    }
+
+    /// Like `RunningSession::remaining`: moving the system clock forward can't cut the wait short.
+    fn remaining(&self) -> i64 {
+        let wall_remaining = self.request.ready_at - Local::now().timestamp();
+        let mono_remaining = self.deadline.saturating_sub(boot_clock()).as_secs() as i64;
+        wall_remaining.max(mono_remaining).max(0)
+    }
+
+    fn status(&self) -> UnlockStatus {
+        let remaining_seconds = self.remaining();
+        UnlockStatus { target: self.request.target.clone(), remaining_seconds, ready: remaining_seconds == 0 }
+    }
+}
+
+/// Schedule kind for nightly wind-down windows (see `check_bedtime`)
+pub const SCHEDULE_KIND_BEDTIME: &str = "bedtime";
+
+/// Minutes before a hardcore schedule starts during which it can't be edited, disabled or deleted
+pub const SCHEDULE_LOCK_SETTING: &str = "schedule_lock_minutes";
+pub const DEFAULT_SCHEDULE_LOCK_MINUTES: i64 = 120;
+/// Longest lock window accepted: a week, so every schedule is covered at least once
+pub const MAX_SCHEDULE_LOCK_MINUTES: i64 = 7 * 24 * 60;
+
+/// Hours between requesting deletion of a hardcore schedule and it being deleted
+pub const SCHEDULE_DELETION_DELAY_SETTING: &str = "schedule_deletion_delay_hours";
+pub const DEFAULT_SCHEDULE_DELETION_DELAY_HOURS: i64 = 24;
+
+/// Whether a hardcore focus schedule is running at `now` or starts within `window_minutes`.
+pub fn is_schedule_locked(session: &crate::storage::Session, now: NaiveDateTime, window_minutes: i64) -> bool {
+    if !session.hardcore || !session.enabled || session.kind == SCHEDULE_KIND_BEDTIME {
+        return false;
+    }
+    let days: Vec<String> = serde_json::from_str(&session.days).unwrap_or_default();
+    let (Ok(start), Ok(end)) = (
+        NaiveTime::parse_from_str(&session.start_time, "%H:%M"),
+        NaiveTime::parse_from_str(&session.end_time, "%H:%M"),
+    ) else {
+        return false;
+    };
+
+    let window_minutes = window_minutes.clamp(0, MAX_SCHEDULE_LOCK_MINUTES);
+    let lock_until = now + chrono::Duration::minutes(window_minutes);
+    for offset in 0..=(window_minutes / (24 * 60) + 1) {
+        let date = now.date() + chrono::Duration::days(offset);
+        if !days.iter().any(|d| d == weekday_name(date.weekday())) {
+            continue;
+        }
+        let (starts_at, ends_at) = (date.and_time(start), date.and_time(end));
+        if starts_at <= now && now <= ends_at {
+            return true;
+        }
+        if starts_at > now && starts_at <= lock_until {
+            return true;
+        }
+    }
+    false
+}
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Mon",
+        Weekday::Tue => "Tue",
+        Weekday::Wed => "Wed",
+        Weekday::Thu => "Thu",
+        Weekday::Fri => "Fri",
+        Weekday::Sat => "Sat",
+        Weekday::Sun => "Sun",
+    }
+}
+
+/// Whether a bedtime schedule covers `now`. Bedtime usually runs past midnight
+/// (e.g. 22:00-07:00), so `days` are the evenings the window starts on.
+fn is_bedtime_at(session: &crate::storage::Session, now: NaiveDateTime) -> bool {
+    let days: Vec<String> = serde_json::from_str(&session.days).unwrap_or_default();
+    is_window_at(&days, &session.start_time, &session.end_time, now)
+}
+
+/// Whether a daily `start`-`end` window covers `now`. A window ending before it starts runs
+/// past midnight, so `days` are the days it starts on.
+fn is_window_at(days: &[String], start: &str, end: &str, now: NaiveDateTime) -> bool {
+    let (Ok(start), Ok(end)) = (
+        NaiveTime::parse_from_str(start, "%H:%M"),
+        NaiveTime::parse_from_str(end, "%H:%M"),
+    ) else {
+        return false;
+    };
+
+    let time = now.time();
+    let starts_on = |day: Weekday| days.iter().any(|d| d == weekday_name(day));
+
+    if start <= end {
+        starts_on(now.weekday()) && time >= start && time < end
+    } else {
+        (starts_on(now.weekday()) && time >= start) || (starts_on(now.weekday().pred()) && time < end)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_pomodoro_export_round_trip() {
+        let preset = PomodoroPreset::from_state("Class", &PomodoroState::default());
+        let export = PomodoroExport { bastion_pomodoro: POMODORO_EXPORT_VERSION, config: Some(preset.clone()), presets: vec![preset.clone()] };
+        let parsed = PomodoroExport::parse(&serde_json::to_string(&export).unwrap()).unwrap();
+        assert_eq!(parsed.config, Some(preset.clone()));
+
+        let invalid = r#"{"bastion_pomodoro": 1, "presets": [{"name": "x", "work_seconds": 5, "short_break_seconds": 300, "long_break_seconds": 900, "sessions_until_long_break": 4}]}"#;
+        assert!(PomodoroExport::parse(invalid).is_err());
+        assert!(PomodoroExport::parse("{}").is_err());
+
+        let mut presets = vec![PomodoroPreset { name: "class".to_string(), work_seconds: 600, ..preset.clone() }];
+        merge_presets(&mut presets, vec![preset]);
+        assert_eq!(presets.len(), 1);
+        assert_eq!(presets[0].name, "Class");
+    }
+
+    #[test]
+    fn test_unlock_friction_escalates() {
+        let policy = FrictionPolicy { enabled: true, ..FrictionPolicy::default() };
+        assert_eq!(policy.friction_for(0).delay_seconds, 0);
+        assert_eq!(policy.friction_for(2).delay_seconds, 60);
+        assert_eq!(policy.friction_for(2).challenge_length, 40);
+        assert_eq!(policy.friction_for(100).delay_seconds, 600);
+        assert!(policy.is_loosened_by(&FrictionPolicy::default()));
+        assert!(!policy.is_loosened_by(&FrictionPolicy { delay_step_seconds: 60, ..policy.clone() }));
+
+        let manager = SessionManager::new();
+        let required = policy.friction_for(1);
+        assert!(manager.pass_unlock_challenge(&required, None, 0).is_err());
+        let challenge = manager.issue_unlock_challenge(required.clone(), 0);
+        assert_eq!(challenge.text.replace(' ', "").len(), 20);
+        assert!(manager.pass_unlock_challenge(&required, Some(&challenge.text), 10).is_err()); // still waiting
+        assert!(manager.pass_unlock_challenge(&required, Some("wrong"), 30).is_err());
+        assert!(manager.pass_unlock_challenge(&required, Some(&challenge.text), 30).is_ok());
+        // Used up
+        assert!(manager.pass_unlock_challenge(&required, Some(&challenge.text), 30).is_err());
+    }
+
+    #[test]
+    fn test_temptation_delay_unlock() {
+        let path = std::env::temp_dir().join(format!("bastion-unlock-test-{}.json", std::process::id()));
+        let manager = SessionManager::new().with_unlock_request_file(path.clone());
+        let site = UnlockTarget::DisableSite { id: 7 };
+        assert!(manager.check_unlock(&site).is_err());
+
+        let status = manager.request_unlock(site.clone(), 60);
+        assert!(!status.ready && status.remaining_seconds > 55);
+        assert!(manager.check_unlock(&site).unwrap_err().starts_with("Wait"));
+        // A restart resumes the wait instead of skipping it
+        let restarted = SessionManager::new().with_unlock_request_file(path.clone());
+        assert_eq!(restarted.restore_unlock_request().map(|r| r.target), Some(site.clone()));
+        assert!(restarted.check_unlock(&site).is_err());
+
+        // Another target starts over; a zero delay is ready at once and used up by `take_unlock`
+        assert!(manager.request_unlock(UnlockTarget::LoosenDelay, 0).ready);
+        assert!(manager.check_unlock(&site).is_err());
+        assert!(manager.check_unlock(&UnlockTarget::LoosenDelay).is_ok());
+        manager.take_unlock(&UnlockTarget::LoosenDelay);
+        assert!(manager.unlock_status().is_none());
+        assert!(!path.exists());
+
+        let delay = TemptationDelay { enabled: true, ..TemptationDelay::default() };
+        assert!(delay.is_loosened_by(&TemptationDelay { delay_seconds: 30, ..delay.clone() }));
+        assert!(!delay.is_loosened_by(&TemptationDelay { delay_seconds: 120, ..delay.clone() }));
+        assert!(TemptationDelay { delay_seconds: 5, ..delay }.validate().is_err());
+    }
+
+    #[test]
+    fn test_sleep_is_not_a_clock_change() {
+        let session = SessionManager::new().start_session("Deep work".to_string(), 120, SessionOptions { hardcore: true, ..Default::default() });
+        let mut running = RunningSession::new(session);
+        let (wall, mono) = (running.clock.last_check_wall, running.clock.last_check_mono);
+        let hour = Duration::from_secs(3600);
+
+        // An hour asleep: the wall clock and the boot clock both moved on by an hour
+        assert!(!running.clock.observe(wall + 3600, mono + hour));
+        assert!((3590..=3600).contains(&running.remaining_at(wall + 3600, mono + hour)));
+        // The wall clock moving on by itself is a clock change, and doesn't shorten the session
+        assert!(running.clock.observe(wall + 3 * 3600, mono + hour));
+        assert!((3590..=3600).contains(&running.remaining_at(wall + 3 * 3600, mono + hour)));
+    }
+
+    #[test]
+    fn test_restore_across_clock_jump() {
+        let path = std::env::temp_dir().join(format!("bastion-lock-test-{}.json", std::process::id()));
+        let mut session = SessionManager::new().start_session("Deep work".to_string(), 30, SessionOptions { hardcore: true, ..Default::default() });
+        // The clock was moved past the end of the session while Bastion wasn't running
+        let now = Local::now().timestamp();
+        session.end_time = now - 60;
+        let clock = crate::security::LockClock { remaining: 600, last_seen: now - 7200 };
+        crate::security::save_lock_state(&path, &session, &clock).unwrap();
+
+        let manager = SessionManager::new().with_lock_state_file(path.clone());
+        let restored = manager.restore_persisted_session().unwrap().unwrap();
+        assert!(restored.end_time >= now + 600);
+        assert!(manager.is_hardcore_locked.load(Ordering::SeqCst));
+        assert!(manager.get_time_remaining().unwrap() > 590);
+
+        manager.force_end_all();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_restore_fails_closed() {
+        let path = std::env::temp_dir().join(format!("bastion-lock-tampered-{}.json", std::process::id()));
+        // A truncated file is reported without locking
+        std::fs::write(&path, b"{not a lock state").unwrap();
+        let manager = SessionManager::new().with_lock_state_file(path.clone());
+        assert_eq!(manager.restore_persisted_session().unwrap_err(), crate::security::LockStateError::Corrupt);
+        assert!(!manager.is_hardcore_locked.load(Ordering::SeqCst));
+
+        // An edited one locks
+        let session = SessionManager::new().start_session("Deep work".to_string(), 25, SessionOptions { hardcore: true, ..Default::default() });
+        let clock = crate::security::LockClock { remaining: 1500, last_seen: session.start_time };
+        crate::security::save_lock_state(&path, &session, &clock).unwrap();
+        let mut edited: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        edited["session"]["end_time"] = serde_json::json!(session.start_time);
+        std::fs::write(&path, serde_json::to_vec(&edited).unwrap()).unwrap();
+
+        let manager = SessionManager::new().with_lock_state_file(path.clone());
+        assert_eq!(manager.restore_persisted_session().unwrap_err(), crate::security::LockStateError::Tampered);
+        assert!(manager.is_hardcore_locked.load(Ordering::SeqCst));
+        assert!(manager.get_time_remaining().unwrap() > 23 * 60 * 60);
+        assert!(manager.end_session(None, &OverrideProof::default()).is_err());
+        // The file now holds the reinstated lock
+        assert!(crate::security::load_lock_state(&path).unwrap().0.hardcore);
+
+        manager.force_end_all();
+        assert!(!path.exists());
+    }
+
     #[test]
     fn test_session_start_end() {
         let manager = SessionManager::new();
         
-        let session = manager.start_session("Test".to_string(), 30, false);
+        let session = manager.start_session("Test".to_string(), 30, SessionOptions::default());
         assert!(manager.get_time_remaining().is_some());
         
-        assert!(manager.end_session().is_ok());
+        assert_eq!(manager.end_session(None, &OverrideProof::default()).unwrap().id, session.id);
         assert!(manager.get_time_remaining().is_none());
     }
 
+    #[test]
+    fn test_hardcore_ignores_clock_change() {
+        let manager = SessionManager::new();
+        manager.start_session("Deep work".to_string(), 30, SessionOptions { hardcore: true, ..Default::default() });
+        
+        // Simulate the system clock being moved forward past the end time
+        manager.sessions.lock().unwrap()[0].session.end_time -= 3600;
+        
+        assert!(manager.get_time_remaining().unwrap() > 0);
+        assert!(manager.end_session(None, &OverrideProof::default()).is_err());
+    }
+
+    #[test]
+    fn test_override_policies() {
+        let manager = SessionManager::new();
+        let password = manager.start_session("A".to_string(), 30, SessionOptions { hardcore: true, override_policy: OverridePolicy::Password, ..Default::default() });
+        let proof = |password_verified| OverrideProof { password_verified, now: Local::now().timestamp(), ..Default::default() };
+        assert!(manager.end_session(Some(&password.id), &proof(false)).is_err());
+        assert!(manager.end_session(Some(&password.id), &proof(true)).is_ok());
+
+        let delayed = OverridePolicy::DelayThenPassword { delay_seconds: 60 };
+        let session = manager.start_session("B".to_string(), 30, SessionOptions { hardcore: true, override_policy: delayed.clone(), ..Default::default() });
+        let now = Local::now().timestamp();
+        manager.issue_unlock_challenge(delayed.friction(), now);
+        assert!(manager.end_session(None, &proof(true)).is_err()); // still waiting
+        let later = OverrideProof { password_verified: true, now: now + 60, ..Default::default() };
+        assert!(manager.end_session(None, &OverrideProof { password_verified: false, ..later.clone() }).is_err());
+        assert_eq!(manager.end_session(None, &later).unwrap().id, session.id);
+
+        let typing = OverridePolicy::TypingChallenge { length: 20 };
+        manager.start_session("C".to_string(), 30, SessionOptions { hardcore: true, override_policy: typing.clone(), ..Default::default() });
+        let challenge = manager.issue_unlock_challenge(typing.friction(), now);
+        let typed = OverrideProof { challenge_response: Some(&challenge.text), now, ..Default::default() };
+        assert!(manager.end_session(None, &OverrideProof { challenge_response: Some("wrong"), ..typed.clone() }).is_err());
+        assert!(manager.end_session(None, &typed).is_ok());
+        assert!(!manager.is_hardcore_locked.load(Ordering::SeqCst));
+
+        assert!(OverridePolicy::TypingChallenge { length: 5 }.validate().is_err());
+        assert!(OverridePolicy::DelayThenPassword { delay_seconds: 0 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_extend_session() {
+        let manager = SessionManager::new();
+        assert!(manager.extend_session(10).is_err());
+
+        let session = manager.start_session("Deep work".to_string(), 30, SessionOptions { hardcore: true, ..Default::default() });
+        let extended = manager.extend_session(15).unwrap();
+        assert_eq!(extended.end_time, session.end_time + 15 * 60);
+        assert!(manager.get_time_remaining().unwrap() > 30 * 60);
+        assert!(manager.extend_session(0).is_err());
+        assert!(manager.extend_session(MAX_EXTENSION_MINUTES + 1).is_err());
+        assert!(manager.extend_session(i64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_harden_session() {
+        let manager = SessionManager::new();
+        assert!(manager.harden_session(false).is_err());
+
+        manager.start_session("Writing".to_string(), 30, SessionOptions::default());
+        assert!(manager.harden_session(false).unwrap().hardcore);
+        assert!(manager.is_hardcore_locked.load(Ordering::SeqCst));
+        assert!(manager.harden_session(false).is_err());
+        assert!(manager.end_session(None, &OverrideProof::default()).is_err());
+    }
+
+    #[test]
+    fn test_session_blocklist_reverted() {
+        let manager = SessionManager::new();
+        let profile = BlocklistProfile {
+            id: "writing".to_string(),
+            name: "Writing".to_string(),
+            categories: vec!["development".to_string()],
+            youtube: Default::default(),
+        };
+        manager.start_session("Writing".to_string(), 30, SessionOptions { blocklist: Some(profile), ..Default::default() });
+        assert_eq!(manager.blocked_categories(), vec!["development".to_string()]);
+
+        assert!(manager.end_session(None, &OverrideProof::default()).is_ok());
+        assert!(manager.blocked_categories().is_empty());
+    }
+
+    #[test]
+    fn test_stacked_sessions() {
+        let manager = SessionManager::new();
+        let profile = |id: &str, category: &str| BlocklistProfile {
+            id: id.to_string(),
+            name: id.to_string(),
+            categories: vec![category.to_string()],
+            youtube: Default::default(),
+        };
+        let schedule = crate::storage::Session {
+            id: 7,
+            name: "Study".to_string(),
+            start_time: "00:00".to_string(),
+            end_time: "23:59".to_string(),
+            days: "[]".to_string(),
+            hardcore: false,
+            enabled: true,
+            kind: "focus".to_string(),
+            delete_at: None,
+        };
+        let now = Local::now().timestamp();
+
+        let manual = manager.start_session("Pomodoro".to_string(), 25, SessionOptions { blocklist: Some(profile("writing", "development")), ..Default::default() });
+        let scheduled = manager.start_scheduled_session(&schedule, now + 3600, false).unwrap();
+        assert!(manager.start_scheduled_session(&schedule, now + 3600, false).is_none());
+        let other = manager.start_session("Reading".to_string(), 10, SessionOptions { blocklist: Some(profile("reading", "news")), ..Default::default() });
+        assert_ne!(other.id, manual.id);
+
+        // Scheduled beats manual, and the blocklists of all sessions apply
+        assert_eq!(manager.active_session_id(), Some(scheduled.id.clone()));
+        assert_eq!(manager.blocked_categories(), vec!["development".to_string(), "news".to_string()]);
+
+        // Hardcore beats scheduled
+        let hardcore = manager.start_session("Deep work".to_string(), 5, SessionOptions { hardcore: true, ..Default::default() });
+        assert_eq!(manager.active_session_id(), Some(hardcore.id.clone()));
+        assert!(manager.end_session(None, &OverrideProof::default()).is_err());
+
+        // Other sessions can still be ended while the hardcore one runs
+        assert!(manager.end_session(Some(&manual.id), &OverrideProof::default()).is_ok());
+        assert_eq!(manager.blocked_categories(), vec!["news".to_string()]);
+        assert_eq!(manager.active_sessions().len(), 3);
+        assert!(manager.is_hardcore_locked.load(Ordering::SeqCst));
+
+        // Expired sessions are taken out one by one
+        {
+            let mut sessions = manager.sessions.lock().unwrap();
+            let running = sessions.iter_mut().find(|r| r.session.id == hardcore.id).unwrap();
+            running.session.end_time = now;
+            running.clock.deadline = boot_clock();
+        }
+        let expired = manager.take_expired_sessions();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, hardcore.id);
+        assert!(!manager.is_hardcore_locked.load(Ordering::SeqCst));
+        assert_eq!(manager.active_session_id(), Some(scheduled.id.clone()));
+
+        // A schedule window ended early isn't started again
+        assert!(manager.end_session(Some(&scheduled.id), &OverrideProof::default()).is_ok());
+        assert!(manager.start_scheduled_session(&schedule, now + 3600, false).is_none());
+        // ...after a restart either
+        let restarted = SessionManager::new();
+        restarted.restore_ended_windows(manager.ended_windows());
+        assert!(restarted.start_scheduled_session(&schedule, now + 3600, false).is_none());
+        assert!(manager.start_scheduled_session(&schedule, now + 7200, false).is_some());
+    }
+
+    #[test]
+    fn test_pomodoro_idle_pause_gives_time_back() {
+        let manager = SessionManager::new();
+        manager.pomodoro_configure(25 * 60, 5 * 60, 15 * 60, 4);
+        manager.pomodoro_start();
+        for _ in 0..600 {
+            manager.pomodoro_tick();
+        }
+
+        assert!(manager.pomodoro_idle_pause(300));
+        let state = manager.get_pomodoro_state();
+        assert!(!state.is_running);
+        assert_eq!(state.time_remaining, 20 * 60);
+
+        assert!(manager.pomodoro_idle_resume());
+        assert!(manager.get_pomodoro_state().is_running);
+        assert!(!manager.pomodoro_idle_resume());
+    }
+
     #[test]
     fn test_pomodoro_tick() {
         let manager = SessionManager::new();
@@ -302,4 +1832,110 @@ mod tests {
         let phase = manager.pomodoro_tick(); // 0 seconds, phase complete
         assert_eq!(phase, Some(PomodoroPhase::Work));
     }
+
+    #[test]
+    fn test_pomodoro_skip_phase_hardcore() {
+        let manager = SessionManager::new();
+        manager.start_session("Deep work".to_string(), 30, SessionOptions { hardcore: true, ..Default::default() });
+        
+        // Work cannot be skipped while hardcore is active
+        assert!(manager.pomodoro_skip_phase().is_err());
+        
+        // Breaks can always be skipped
+        manager.pomodoro_state.lock().unwrap().phase = PomodoroPhase::Break;
+        assert_eq!(manager.pomodoro_skip_phase(), Ok(PomodoroPhase::Work));
+        assert_eq!(manager.get_pomodoro_state().completed_sessions, 0);
+    }
+
+    #[test]
+    fn test_bedtime_crosses_midnight() {
+        let bedtime = crate::storage::Session {
+            id: 1,
+            name: "Bedtime".to_string(),
+            start_time: "22:00".to_string(),
+            end_time: "07:00".to_string(),
+            days: r#"["Fri"]"#.to_string(),
+            hardcore: false,
+            enabled: true,
+            kind: SCHEDULE_KIND_BEDTIME.to_string(),
+            delete_at: None,
+        };
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+
+        // 2024-03-01 is a Friday
+        assert!(!is_bedtime_at(&bedtime, at("2024-03-01 21:59")));
+        assert!(is_bedtime_at(&bedtime, at("2024-03-01 23:30")));
+        assert!(is_bedtime_at(&bedtime, at("2024-03-02 06:59")));
+        assert!(!is_bedtime_at(&bedtime, at("2024-03-02 07:00")));
+        assert!(!is_bedtime_at(&bedtime, at("2024-03-02 23:00")));
+    }
+
+    #[test]
+    fn test_category_schedule() {
+        let social = CategorySchedule {
+            category: "social".to_string(),
+            days: ["Mon", "Tue", "Wed", "Thu", "Fri"].iter().map(|d| d.to_string()).collect(),
+            start_time: "09:00".to_string(),
+            end_time: "17:00".to_string(),
+            enabled: true,
+        };
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+
+        // 2024-03-01 is a Friday
+        assert!(social.is_active_at(at("2024-03-01 09:00")));
+        assert!(!social.is_active_at(at("2024-03-01 17:00")));
+        assert!(!social.is_active_at(at("2024-03-02 12:00")));
+        assert!(!CategorySchedule { enabled: false, ..social.clone() }.is_active_at(at("2024-03-01 12:00")));
+
+        assert!(social.validate().is_ok());
+        assert!(CategorySchedule { days: vec!["Funday".to_string()], ..social.clone() }.validate().is_err());
+        assert!(CategorySchedule { end_time: "25:00".to_string(), ..social.clone() }.validate().is_err());
+        assert!(CategorySchedule { category: " ".to_string(), ..social }.validate().is_err());
+    }
+
+    #[test]
+    fn test_schedule_locked_before_start() {
+        let study = crate::storage::Session {
+            id: 1,
+            name: "Study".to_string(),
+            start_time: "19:00".to_string(),
+            end_time: "21:00".to_string(),
+            days: r#"["Fri"]"#.to_string(),
+            hardcore: true,
+            enabled: true,
+            kind: "focus".to_string(),
+            delete_at: None,
+        };
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+
+        // 2024-03-01 is a Friday
+        assert!(!is_schedule_locked(&study, at("2024-03-01 16:59"), 120));
+        assert!(is_schedule_locked(&study, at("2024-03-01 18:55"), 120));
+        assert!(is_schedule_locked(&study, at("2024-03-01 20:00"), 120));
+        assert!(!is_schedule_locked(&study, at("2024-03-01 21:01"), 120));
+        assert!(!is_schedule_locked(&crate::storage::Session { hardcore: false, ..study.clone() }, at("2024-03-01 18:55"), 120));
+        // An oversized window is capped at a week instead of overflowing
+        assert!(is_schedule_locked(&study, at("2024-03-01 21:01"), i64::MAX));
+    }
+
+    #[test]
+    fn test_morning_routine_once_per_day() {
+        let routine = MorningRoutine { enabled: true, ..MorningRoutine::default() };
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+
+        assert!(routine.is_due(at("2024-03-01 07:30"), None));
+        assert!(routine.is_due(at("2024-03-01 07:30"), Some("2024-02-29")));
+        assert!(!routine.is_due(at("2024-03-01 07:30"), Some("2024-03-01")));
+        assert!(!routine.is_due(at("2024-03-01 13:00"), None));
+    }
+
+    #[test]
+    fn test_morning_routine_validation() {
+        let routine = MorningRoutine::default();
+        assert!(routine.validate().is_ok());
+        assert!(MorningRoutine { duration_minutes: 0, ..routine.clone() }.validate().is_err());
+        assert!(MorningRoutine { name: " ".to_string(), ..routine.clone() }.validate().is_err());
+        assert!(MorningRoutine { window_start: "7am".to_string(), ..routine.clone() }.validate().is_err());
+        assert!(MorningRoutine { window_start: "12:00".to_string(), ..routine }.validate().is_err());
+    }
 }
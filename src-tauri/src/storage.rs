@@ -3,16 +3,90 @@
 
 use rusqlite::{Connection, Result as SqliteResult, params};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockedSite {
     pub id: i64,
-    pub domain: String,
+    pub domain: String, // for soft-block modes this may include a path, e.g. "youtube.com/shorts"
     pub category: String,
     pub enabled: bool,
     pub created_at: String,
+    #[serde(default = "default_block_mode")]
+    pub mode: String, // see `blocking::BLOCK_MODE_*`
+    #[serde(default)]
+    pub include_subdomains: bool, // also block known subdomains, see `blocking::expand_subdomains`
+}
+
+/// Outcome of `add_blocked_site`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AddSiteResult {
+    Added { id: i64 },
+    /// An equivalent entry exists (differing only in case, "www.", punycode or URL form)
+    AlreadyBlocked { site: BlockedSite },
+    /// The equivalent entry was disabled and has been switched back on
+    Reenabled { site: BlockedSite },
+}
+
+/// Key two block list entries share when they block the same thing
+fn site_key(domain: &str) -> String {
+    let host = crate::blocking::normalize_domain(domain);
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let rest = domain.trim().split_once("://").map_or(domain.trim(), |(_, rest)| rest);
+    let path = rest
+        .split(['?', '#'])
+        .next()
+        .and_then(|rest| rest.split_once('/'))
+        .map_or("", |(_, path)| path.trim_end_matches('/'));
+    if path.is_empty() { host.to_string() } else { format!("{}/{}", host, path.to_lowercase()) }
+}
+
+fn default_block_mode() -> String {
+    crate::blocking::BLOCK_MODE_HOSTS.to_string()
+}
+
+/// A blocked site in a block list export (see `blocklists::BlocklistExport`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SiteEntry {
+    pub domain: String,
+    #[serde(default = "default_category")]
+    pub category: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_block_mode")]
+    pub mode: String,
+    #[serde(default)]
+    pub include_subdomains: bool,
+}
+
+/// A blocked app in a block list export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppEntry {
+    pub name: String,
+    pub process_name: String,
+    #[serde(default = "default_category")]
+    pub category: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_category() -> String {
+    "other".to_string()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Outcome of `import_blocklist`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ImportSummary {
+    pub sites_added: usize,
+    pub apps_added: usize,
+    pub duplicates: usize, // already blocked, or listed twice in the import
+    pub invalid: usize,    // entries that aren't a valid site or app, left out
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +99,14 @@ pub struct BlockedApp {
     pub created_at: String,
 }
 
+/// A site that stays reachable during allowlist-only sessions, subdomains included
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllowedSite {
+    pub id: i64,
+    pub domain: String,
+    pub created_at: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
     pub id: i64,
@@ -34,14 +116,92 @@ pub struct Session {
     pub days: String, // JSON array of days
     pub hardcore: bool,
     pub enabled: bool,
+    #[serde(default = "default_schedule_kind")]
+    pub kind: String, // "focus" or "bedtime"
+    #[serde(default)]
+    pub delete_at: Option<i64>, // Unix timestamp a pending deletion takes effect (hardcore schedules)
+}
+
+fn default_schedule_kind() -> String {
+    "focus".to_string()
+}
+
+/// A block that happened on a paired phone
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceBlockEvent {
+    pub target: String,
+    pub target_type: String, // "website" or "app"
+    pub blocked_at: String, // UTC, "YYYY-MM-DD HH:MM:SS" like CURRENT_TIMESTAMP
+}
+
+/// Time spent on a site or app on a paired phone, added to the day's total
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceUsage {
+    pub date: String, // YYYY-MM-DD, local to the phone
+    pub target: String, // domain, or app package/process name
+    pub target_type: String, // "website" or "app"
+    pub seconds: i64,
+}
+
+/// How long soft-deleted sites, apps and schedules can be restored before they are purged
+pub const DELETED_RETENTION_SECS: i64 = 7 * 24 * 3600;
+
+/// Tables that use soft deletion (a `deleted_at` column) so deletes can be undone
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashKind {
+    Site,
+    App,
+    Schedule,
+}
+
+impl TrashKind {
+    fn table(self) -> &'static str {
+        match self {
+            TrashKind::Site => "blocked_sites",
+            TrashKind::App => "blocked_apps",
+            TrashKind::Schedule => "sessions",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletedItem {
+    pub kind: TrashKind,
+    pub id: i64,
+    pub name: String, // domain for sites
+    pub deleted_at: i64, // Unix timestamp
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockEvent {
     pub id: i64,
     pub target: String,
-    pub target_type: String, // "website" or "app"
+    pub target_type: String, // "website", "app", "tamper" or "repair"
     pub blocked_at: String,
+    #[serde(default)]
+    pub session_id: Option<String>, // focus session active when the event was logged
+    #[serde(default)]
+    pub device_id: Option<String>, // paired phone that reported it; None for this computer
+    #[serde(default)]
+    pub category: Option<String>, // of the blocked site/app when the event was logged
+}
+
+/// Blocks during focus sessions vs. outside them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionBlockSplit {
+    pub in_session: i64,
+    pub outside_session: i64,
+}
+
+/// Events attributed to one focus session (see `ActiveSession::id`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub session_id: String,
+    pub block_attempts: i64, // blocked website requests
+    pub app_kills: i64,
+    pub tamper_attempts: i64,
+    pub clean: bool, // nothing had to be blocked
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +209,187 @@ pub struct FocusStats {
     pub date: String,
     pub minutes_protected: i64,
     pub blocks_count: i64,
+    pub pomodoros_completed: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionExtension {
+    pub id: i64,
+    pub session_id: String,
+    pub session_name: String,
+    pub minutes: i64,
+    pub hardcore: bool,
+    pub extended_at: String,
+}
+
+/// A focus session that has ended (see `record_session`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub session_id: String,
+    pub name: String,
+    pub session_type: String, // manual, scheduled or pomodoro
+    pub hardcore: bool,
+    pub started_at: i64, // Unix timestamp
+    pub ended_at: i64,   // Unix timestamp
+    pub completed: bool, // ran until its end time instead of being ended early
+}
+
+/// Most blocked target of a day (see `get_top_blocks`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopBlock {
+    pub target: String,
+    pub target_type: String,
+    pub attempts: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quote {
+    pub id: i64,
+    pub text: String,
+    pub author: Option<String>,
+    pub enabled: bool,
+    pub created_at: String,
+}
+
+/// One entry of a quote pack: `{"text": "...", "author": "..."}`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct QuoteEntry {
+    pub text: String,
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+/// Parse a quote pack: a JSON array of `QuoteEntry`, or plain text with one quote per line
+/// and an optional " — Author" (or " - Author") suffix. Blank lines and `#` comments are skipped.
+pub fn parse_quote_pack(pack: &str) -> Result<Vec<QuoteEntry>, String> {
+    if pack.trim_start().starts_with('[') {
+        return serde_json::from_str(pack).map_err(|e| format!("Invalid quote pack: {}", e));
+    }
+    Ok(pack
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.rsplit_once(" — ").or_else(|| line.rsplit_once(" - ")) {
+            Some((text, author)) if !author.trim().is_empty() => QuoteEntry {
+                text: text.trim().to_string(),
+                author: Some(author.trim().to_string()),
+            },
+            _ => QuoteEntry { text: line.to_string(), author: None },
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreakFreeze {
+    pub id: i64,
+    pub kind: String, // "earned" or "used", see `streaks::FREEZE_*`
+    pub date: String, // day it was earned on or protected (YYYY-MM-DD)
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlockCounts {
+    pub this_week: i64,
+    pub last_seven_days: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeekTotals {
+    pub week_start: String, // Monday, YYYY-MM-DD
+    pub focus_minutes: i64,
+    pub blocks: i64,
+    pub pomodoros: i64,
+}
+
+/// This week so far against the same days of last week. Changes are percentages,
+/// `None` when last week's value is zero.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeekComparison {
+    pub current: WeekTotals,
+    pub previous: WeekTotals,
+    pub focus_minutes_change: Option<f64>,
+    pub blocks_change: Option<f64>,
+    pub pomodoros_change: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PomodoroDay {
+    pub date: String,
+    pub completed: i64,
+}
+
+/// Events and usage not attributed to any blocked site or app
+pub const UNCATEGORIZED: &str = "uncategorized";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryBreakdown {
+    pub category: String,
+    pub blocks: i64,
+    pub usage_seconds: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrendPoint {
+    pub date: String,
+    pub attempts: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: i64,
+    pub title: String,
+    pub completed: bool,
+    pub created_at: String,
+    pub focus_minutes: i64, // Total focus time attributed to this task
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppUsage {
+    pub date: String, // Day (or first day of the week when grouped weekly)
+    pub process_name: String,
+    pub seconds: i64,
+}
+
+/// Time in the foreground per app, summed over a range of days
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppUsageTotal {
+    pub process_name: String,
+    pub seconds: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteUsage {
+    pub domain: String,
+    pub seconds: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MicroBreakStats {
+    pub date: String,
+    pub taken: i64,
+    pub skipped: i64,
+    pub snoozed: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedPush {
+    pub id: i64,
+    pub provider: String,
+    pub payload: String, // JSON, provider-specific
+    pub attempts: i64,
+    pub last_error: Option<String>,
+}
+
+/// Everything the dashboard shows on load, read in a single transaction.
+#[derive(Debug, Clone, Serialize)]
+pub struct DashboardData {
+    pub blocked_sites: Vec<BlockedSite>,
+    pub blocked_apps: Vec<BlockedApp>,
+    pub sessions: Vec<Session>,
+    pub recent_blocks: Vec<BlockEvent>,
+    pub stats: Vec<FocusStats>,
+    pub settings: std::collections::HashMap<String, String>, // without the master password hash
+    pub has_master_password: bool,
 }
 
 pub struct Database {
@@ -56,8 +397,13 @@ pub struct Database {
 }
 
 impl Database {
+    /// Location of the database file inside the app data directory
+    pub fn file_path(data_dir: &Path) -> PathBuf {
+        data_dir.join("bastion.db")
+    }
+
     pub fn new(data_dir: PathBuf) -> SqliteResult<Self> {
-        let db_path = data_dir.join("bastion.db");
+        let db_path = Self::file_path(&data_dir);
         std::fs::create_dir_all(&data_dir).ok();
         
         let conn = Connection::open(db_path)?;
@@ -134,10 +480,217 @@ impl Database {
             [],
         )?;
 
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS tasks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT NOT NULL,
+                completed INTEGER DEFAULT 0,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS task_focus_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_id INTEGER NOT NULL,
+                minutes INTEGER NOT NULL,
+                source TEXT NOT NULL,
+                logged_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS integration_queue (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                provider TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                attempts INTEGER DEFAULT 0,
+                last_error TEXT,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS process_classifications (
+                process_name TEXT PRIMARY KEY,
+                classification TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS app_usage (
+                date TEXT NOT NULL,
+                process_name TEXT NOT NULL,
+                seconds INTEGER DEFAULT 0,
+                PRIMARY KEY (date, process_name)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS site_usage (
+                date TEXT NOT NULL,
+                domain TEXT NOT NULL,
+                seconds INTEGER DEFAULT 0,
+                PRIMARY KEY (date, domain)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS micro_break_stats (
+                date TEXT PRIMARY KEY,
+                taken INTEGER DEFAULT 0,
+                skipped INTEGER DEFAULT 0,
+                snoozed INTEGER DEFAULT 0
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS session_extensions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                session_name TEXT NOT NULL,
+                minutes INTEGER NOT NULL,
+                hardcore INTEGER DEFAULT 0,
+                extended_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS unlock_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                session_id TEXT,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS quotes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                text TEXT NOT NULL UNIQUE,
+                author TEXT,
+                enabled INTEGER DEFAULT 1,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS streak_freezes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                date TEXT NOT NULL,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS session_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL UNIQUE,
+                name TEXT NOT NULL,
+                session_type TEXT NOT NULL,
+                hardcore INTEGER DEFAULT 0,
+                started_at INTEGER NOT NULL,
+                ended_at INTEGER NOT NULL,
+                completed INTEGER DEFAULT 0
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS allowed_sites (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                domain TEXT NOT NULL UNIQUE,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        // Columns added after the initial release
+        Self::add_column_if_missing(&conn, "focus_stats", "pomodoros_completed", "INTEGER DEFAULT 0")?;
+        Self::add_column_if_missing(&conn, "sessions", "kind", "TEXT NOT NULL DEFAULT 'focus'")?;
+        Self::add_column_if_missing(&conn, "blocked_sites", "mode", "TEXT NOT NULL DEFAULT 'hosts'")?;
+        Self::add_column_if_missing(&conn, "blocked_sites", "include_subdomains", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::add_column_if_missing(&conn, "sessions", "delete_at", "INTEGER")?;
+        Self::add_column_if_missing(&conn, "block_events", "session_id", "TEXT")?;
+        Self::add_column_if_missing(&conn, "block_events", "device_id", "TEXT")?; // NULL for this computer
+        for table in ["blocked_sites", "blocked_apps", "sessions"] {
+            Self::add_column_if_missing(&conn, table, "deleted_at", "INTEGER")?;
+        }
+        if Self::add_column_if_missing(&conn, "block_events", "category", "TEXT")? {
+            // Older events were logged without one; attribute them to the current block list
+            let targets: Vec<(String, String)> = conn
+                .prepare("SELECT DISTINCT target, target_type FROM block_events WHERE target_type IN ('website', 'app')")?
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<SqliteResult<_>>()?;
+            for (target, target_type) in targets {
+                if let Some(category) = Self::category_for(&conn, &target, &target_type)? {
+                    conn.execute(
+                        "UPDATE block_events SET category = ?3 WHERE target = ?1 AND target_type = ?2",
+                        params![target, target_type, category],
+                    )?;
+                }
+            }
+        }
+
+        // The block server logs every intercepted connection, so block_events grows quickly
+        for index in [
+            "CREATE INDEX IF NOT EXISTS idx_block_events_blocked_at ON block_events (blocked_at)",
+            "CREATE INDEX IF NOT EXISTS idx_block_events_target ON block_events (target)",
+            "CREATE INDEX IF NOT EXISTS idx_block_events_type_time ON block_events (target_type, blocked_at)",
+            "CREATE INDEX IF NOT EXISTS idx_block_events_session ON block_events (session_id)",
+        ] {
+            conn.execute(index, [])?;
+        }
+
         Ok(())
     }
 
+    /// Add a column to an existing table, for databases created by older versions.
+    /// Returns true if the column was added.
+    fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> SqliteResult<bool> {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|name| name.ok())
+            .any(|name| name == column);
+        
+        if !exists {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+        }
+        Ok(!exists)
+    }
+
+    /// Category of the blocked site (most specific match, so "old.reddit.com" finds "reddit.com")
+    /// or app a block event is about.
+    fn category_for(conn: &Connection, target: &str, target_type: &str) -> SqliteResult<Option<String>> {
+        let sql = if target_type == "app" {
+            "SELECT category FROM blocked_apps WHERE deleted_at IS NULL AND lower(process_name) = lower(?1)"
+        } else {
+            "SELECT category FROM blocked_sites
+             WHERE deleted_at IS NULL
+               AND (domain = lower(?1)
+                    OR (length(?1) > length(domain) AND substr(lower(?1), -length(domain) - 1) = '.' || domain))
+             ORDER BY length(domain) DESC LIMIT 1"
+        };
+        let mut stmt = conn.prepare_cached(sql)?;
+        let mut rows = stmt.query_map(params![target], |row| row.get(0))?;
+        rows.next().transpose()
+    }
+
     // Settings
+    #[tracing::instrument(level = "debug", skip(self, value))]
     pub fn set_setting(&self, key: &str, value: &str) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
@@ -147,6 +700,7 @@ impl Database {
         Ok(())
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
     pub fn get_setting(&self, key: &str) -> SqliteResult<Option<String>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = ?1")?;
@@ -158,20 +712,71 @@ impl Database {
         }
     }
 
+    pub fn delete_setting(&self, key: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM settings WHERE key = ?1", params![key])?;
+        Ok(())
+    }
+
+    pub fn get_all_settings(&self) -> SqliteResult<Vec<(String, String)>> {
+        Self::query_all_settings(&self.conn.lock().unwrap())
+    }
+
+    fn query_all_settings(conn: &Connection) -> SqliteResult<Vec<(String, String)>> {
+        let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+        let settings = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        settings.collect()
+    }
+
     // Blocked Sites
-    pub fn add_blocked_site(&self, domain: &str, category: &str) -> SqliteResult<i64> {
+    /// Add a site unless an equivalent entry exists; `reenable` switches a disabled one back on.
+    pub fn add_blocked_site(&self, domain: &str, category: &str, reenable: bool) -> SqliteResult<AddSiteResult> {
         let conn = self.conn.lock().unwrap();
+        let key = site_key(domain);
+        if let Some(mut site) = Self::query_blocked_sites(&conn)?.into_iter().find(|site| site_key(&site.domain) == key) {
+            if reenable && !site.enabled {
+                conn.execute("UPDATE blocked_sites SET enabled = 1 WHERE id = ?1", params![site.id])?;
+                site.enabled = true;
+                return Ok(AddSiteResult::Reenabled { site });
+            }
+            return Ok(AddSiteResult::AlreadyBlocked { site });
+        }
+        // A soft-deleted row would still hold the UNIQUE domain
+        conn.execute("DELETE FROM blocked_sites WHERE domain = ?1 AND deleted_at IS NOT NULL", params![domain])?;
         conn.execute(
             "INSERT INTO blocked_sites (domain, category) VALUES (?1, ?2)",
             params![domain, category],
         )?;
-        Ok(conn.last_insert_rowid())
+        Ok(AddSiteResult::Added { id: conn.last_insert_rowid() })
     }
 
-    pub fn get_blocked_sites(&self) -> SqliteResult<Vec<BlockedSite>> {
+    /// Add many sites under `category` in one transaction, skipping ones already on the block
+    /// list (in any form). Returns how many were added.
+    pub fn add_blocked_sites(&self, domains: &[&str], category: &str) -> SqliteResult<usize> {
         let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        let mut existing: std::collections::HashSet<String> =
+            Self::query_blocked_sites(&tx)?.iter().map(|site| site_key(&site.domain)).collect();
+        let mut added = 0;
+        for domain in domains {
+            if !existing.insert(site_key(domain)) {
+                continue;
+            }
+            tx.execute("DELETE FROM blocked_sites WHERE domain = ?1 AND deleted_at IS NOT NULL", params![domain])?;
+            added += tx.execute("INSERT INTO blocked_sites (domain, category) VALUES (?1, ?2)", params![domain, category])?;
+        }
+        tx.commit()?;
+        Ok(added)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_blocked_sites(&self) -> SqliteResult<Vec<BlockedSite>> {
+        Self::query_blocked_sites(&self.conn.lock().unwrap())
+    }
+
+    fn query_blocked_sites(conn: &Connection) -> SqliteResult<Vec<BlockedSite>> {
         let mut stmt = conn.prepare(
-            "SELECT id, domain, category, enabled, created_at FROM blocked_sites"
+            "SELECT id, domain, category, enabled, created_at, mode, include_subdomains FROM blocked_sites WHERE deleted_at IS NULL"
         )?;
         let sites = stmt.query_map([], |row| {
             Ok(BlockedSite {
@@ -180,6 +785,8 @@ impl Database {
                 category: row.get(2)?,
                 enabled: row.get::<_, i32>(3)? == 1,
                 created_at: row.get(4)?,
+                mode: row.get(5)?,
+                include_subdomains: row.get::<_, i32>(6)? == 1,
             })
         })?;
         sites.collect()
@@ -194,15 +801,80 @@ impl Database {
         Ok(())
     }
 
-    pub fn delete_blocked_site(&self, id: i64) -> SqliteResult<()> {
+    pub fn set_blocked_site_mode(&self, id: i64, mode: &str) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM blocked_sites WHERE id = ?1", params![id])?;
+        conn.execute(
+            "UPDATE blocked_sites SET mode = ?2 WHERE id = ?1",
+            params![id, mode],
+        )?;
         Ok(())
     }
 
-    // Blocked Apps
+    pub fn set_blocked_site_subdomains(&self, id: i64, include_subdomains: bool) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE blocked_sites SET include_subdomains = ?2 WHERE id = ?1",
+            params![id, include_subdomains as i32],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_blocked_site(&self, id: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE blocked_sites SET deleted_at = strftime('%s', 'now') WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Import sites and apps in one transaction. `replace` moves the current lists to the trash
+    /// first; otherwise entries already blocked are kept as they are and counted as duplicates.
+    pub fn import_blocklist(&self, sites: &[SiteEntry], apps: &[AppEntry], replace: bool) -> SqliteResult<ImportSummary> {
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        if replace {
+            tx.execute("UPDATE blocked_sites SET deleted_at = strftime('%s', 'now') WHERE deleted_at IS NULL", [])?;
+            tx.execute("UPDATE blocked_apps SET deleted_at = strftime('%s', 'now') WHERE deleted_at IS NULL", [])?;
+        }
+        let mut summary = ImportSummary::default();
+
+        let mut site_keys: std::collections::HashSet<String> =
+            Self::query_blocked_sites(&tx)?.iter().map(|site| site_key(&site.domain)).collect();
+        for site in sites {
+            if !site_keys.insert(site_key(&site.domain)) {
+                summary.duplicates += 1;
+                continue;
+            }
+            // A soft-deleted row would still hold the UNIQUE domain
+            tx.execute("DELETE FROM blocked_sites WHERE domain = ?1 AND deleted_at IS NOT NULL", params![site.domain])?;
+            summary.sites_added += tx.execute(
+                "INSERT INTO blocked_sites (domain, category, enabled, mode, include_subdomains) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![site.domain, site.category, site.enabled as i32, site.mode, site.include_subdomains as i32],
+            )?;
+        }
+
+        let mut process_names: std::collections::HashSet<String> =
+            Self::query_blocked_apps(&tx)?.iter().map(|app| app.process_name.to_lowercase()).collect();
+        for app in apps {
+            if !process_names.insert(app.process_name.to_lowercase()) {
+                summary.duplicates += 1;
+                continue;
+            }
+            tx.execute(
+                "DELETE FROM blocked_apps WHERE process_name = ?1 COLLATE NOCASE AND deleted_at IS NOT NULL",
+                params![app.process_name],
+            )?;
+            summary.apps_added += tx.execute(
+                "INSERT INTO blocked_apps (name, process_name, category, enabled) VALUES (?1, ?2, ?3, ?4)",
+                params![app.name, app.process_name, app.category, app.enabled as i32],
+            )?;
+        }
+        tx.commit()?;
+        Ok(summary)
+    }
+
+    // Blocked Apps
     pub fn add_blocked_app(&self, name: &str, process_name: &str, category: &str) -> SqliteResult<i64> {
         let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM blocked_apps WHERE process_name = ?1 AND deleted_at IS NOT NULL", params![process_name])?;
         conn.execute(
             "INSERT INTO blocked_apps (name, process_name, category) VALUES (?1, ?2, ?3)",
             params![name, process_name, category],
@@ -210,10 +882,14 @@ impl Database {
         Ok(conn.last_insert_rowid())
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
     pub fn get_blocked_apps(&self) -> SqliteResult<Vec<BlockedApp>> {
-        let conn = self.conn.lock().unwrap();
+        Self::query_blocked_apps(&self.conn.lock().unwrap())
+    }
+
+    fn query_blocked_apps(conn: &Connection) -> SqliteResult<Vec<BlockedApp>> {
         let mut stmt = conn.prepare(
-            "SELECT id, name, process_name, category, enabled, created_at FROM blocked_apps"
+            "SELECT id, name, process_name, category, enabled, created_at FROM blocked_apps WHERE deleted_at IS NULL"
         )?;
         let apps = stmt.query_map([], |row| {
             Ok(BlockedApp {
@@ -239,7 +915,33 @@ impl Database {
 
     pub fn delete_blocked_app(&self, id: i64) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM blocked_apps WHERE id = ?1", params![id])?;
+        conn.execute("UPDATE blocked_apps SET deleted_at = strftime('%s', 'now') WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    // Allowed Sites (allowlist-only sessions)
+    pub fn add_allowed_site(&self, domain: &str) -> SqliteResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("INSERT OR IGNORE INTO allowed_sites (domain) VALUES (?1)", params![domain])?;
+        conn.query_row("SELECT id FROM allowed_sites WHERE domain = ?1", params![domain], |row| row.get(0))
+    }
+
+    pub fn get_allowed_sites(&self) -> SqliteResult<Vec<AllowedSite>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, domain, created_at FROM allowed_sites ORDER BY domain")?;
+        let sites = stmt.query_map([], |row| {
+            Ok(AllowedSite {
+                id: row.get(0)?,
+                domain: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })?;
+        sites.collect()
+    }
+
+    pub fn delete_allowed_site(&self, id: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM allowed_sites WHERE id = ?1", params![id])?;
         Ok(())
     }
 
@@ -247,24 +949,29 @@ impl Database {
     pub fn add_session(&self, session: &Session) -> SqliteResult<i64> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "INSERT INTO sessions (name, start_time, end_time, days, hardcore, enabled) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO sessions (name, start_time, end_time, days, hardcore, enabled, kind) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             params![
                 session.name,
                 session.start_time,
                 session.end_time,
                 session.days,
                 session.hardcore as i32,
-                session.enabled as i32
+                session.enabled as i32,
+                session.kind
             ],
         )?;
         Ok(conn.last_insert_rowid())
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
     pub fn get_sessions(&self) -> SqliteResult<Vec<Session>> {
-        let conn = self.conn.lock().unwrap();
+        Self::query_sessions(&self.conn.lock().unwrap())
+    }
+
+    fn query_sessions(conn: &Connection) -> SqliteResult<Vec<Session>> {
         let mut stmt = conn.prepare(
-            "SELECT id, name, start_time, end_time, days, hardcore, enabled FROM sessions"
+            "SELECT id, name, start_time, end_time, days, hardcore, enabled, kind, delete_at FROM sessions WHERE deleted_at IS NULL"
         )?;
         let sessions = stmt.query_map([], |row| {
             Ok(Session {
@@ -275,23 +982,114 @@ impl Database {
                 days: row.get(4)?,
                 hardcore: row.get::<_, i32>(5)? == 1,
                 enabled: row.get::<_, i32>(6)? == 1,
+                kind: row.get(7)?,
+                delete_at: row.get(8)?,
             })
         })?;
         sessions.collect()
     }
 
+    pub fn update_session(&self, session: &Session) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sessions SET name = ?2, start_time = ?3, end_time = ?4, days = ?5, hardcore = ?6, enabled = ?7, kind = ?8
+             WHERE id = ?1",
+            params![
+                session.id,
+                session.name,
+                session.start_time,
+                session.end_time,
+                session.days,
+                session.hardcore as i32,
+                session.enabled as i32,
+                session.kind
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Schedule (`Some`) or cancel (`None`) a delayed deletion.
+    pub fn set_session_delete_at(&self, id: i64, delete_at: Option<i64>) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE sessions SET delete_at = ?2 WHERE id = ?1", params![id, delete_at])?;
+        Ok(())
+    }
+
+    /// Delete schedules whose pending deletion is due. Returns how many were removed.
+    pub fn purge_due_session_deletions(&self, now: i64) -> SqliteResult<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sessions SET deleted_at = ?1, delete_at = NULL
+             WHERE delete_at IS NOT NULL AND delete_at <= ?1 AND deleted_at IS NULL",
+            params![now],
+        )
+    }
+
     pub fn delete_session(&self, id: i64) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM sessions WHERE id = ?1", params![id])?;
+        conn.execute("UPDATE sessions SET deleted_at = strftime('%s', 'now') WHERE id = ?1", params![id])?;
         Ok(())
     }
 
+    // Trash
+    /// Soft-deleted sites, apps and schedules, most recently deleted first.
+    pub fn get_deleted_items(&self) -> SqliteResult<Vec<DeletedItem>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT 'site', id, domain, deleted_at FROM blocked_sites WHERE deleted_at IS NOT NULL
+             UNION ALL SELECT 'app', id, name, deleted_at FROM blocked_apps WHERE deleted_at IS NOT NULL
+             UNION ALL SELECT 'schedule', id, name, deleted_at FROM sessions WHERE deleted_at IS NOT NULL
+             ORDER BY 4 DESC"
+        )?;
+        let items = stmt.query_map([], |row| {
+            let kind = match row.get::<_, String>(0)?.as_str() {
+                "site" => TrashKind::Site,
+                "app" => TrashKind::App,
+                _ => TrashKind::Schedule,
+            };
+            Ok(DeletedItem {
+                kind,
+                id: row.get(1)?,
+                name: row.get(2)?,
+                deleted_at: row.get(3)?,
+            })
+        })?;
+        items.collect()
+    }
+
+    /// Restore a soft-deleted row. Returns false if it was not in the trash.
+    pub fn undo_delete(&self, kind: TrashKind, id: i64) -> SqliteResult<bool> {
+        let conn = self.conn.lock().unwrap();
+        let restored = conn.execute(
+            &format!("UPDATE {} SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL", kind.table()),
+            params![id],
+        )?;
+        Ok(restored > 0)
+    }
+
+    /// Permanently remove rows soft-deleted before `before`. Returns how many were removed.
+    pub fn purge_deleted(&self, before: i64) -> SqliteResult<usize> {
+        let conn = self.conn.lock().unwrap();
+        let mut purged = 0;
+        for kind in [TrashKind::Site, TrashKind::App, TrashKind::Schedule] {
+            purged += conn.execute(
+                &format!("DELETE FROM {} WHERE deleted_at IS NOT NULL AND deleted_at < ?1", kind.table()),
+                params![before],
+            )?;
+        }
+        Ok(purged)
+    }
+
     // Block Events
-    pub fn log_block_event(&self, target: &str, target_type: &str) -> SqliteResult<()> {
+    #[tracing::instrument(level = "debug", skip(self))]
+    /// `session_id` attributes the event to the focus session running at the time.
+    pub fn log_block_event(&self, target: &str, target_type: &str, session_id: Option<&str>) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
+        // The category is recorded now, so stats stay right if the item is later recategorized or removed
+        let category = Self::category_for(&conn, target, target_type)?;
         conn.execute(
-            "INSERT INTO block_events (target, target_type) VALUES (?1, ?2)",
-            params![target, target_type],
+            "INSERT INTO block_events (target, target_type, session_id, category) VALUES (?1, ?2, ?3, ?4)",
+            params![target, target_type, session_id, category],
         )?;
         // Also update daily stats
         let today = chrono::Local::now().format("%Y-%m-%d").to_string();
@@ -303,11 +1101,58 @@ impl Database {
         Ok(())
     }
 
-    pub fn get_recent_blocks(&self, limit: i32) -> SqliteResult<Vec<BlockEvent>> {
+    pub fn get_session_stats(&self, session_id: &str) -> SqliteResult<SessionStats> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT
+                COALESCE(SUM(target_type = 'website'), 0),
+                COALESCE(SUM(target_type = 'app'), 0),
+                COALESCE(SUM(target_type = 'tamper'), 0)
+             FROM block_events WHERE session_id = ?1",
+            params![session_id],
+            |row| {
+                let (block_attempts, app_kills, tamper_attempts): (i64, i64, i64) = (row.get(0)?, row.get(1)?, row.get(2)?);
+                Ok(SessionStats {
+                    session_id: session_id.to_string(),
+                    block_attempts,
+                    app_kills,
+                    tamper_attempts,
+                    clean: block_attempts + app_kills + tamper_attempts == 0,
+                })
+            },
+        )
+    }
+
+    /// Block events logged after `id`, oldest first.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_block_events_since(&self, id: i64) -> SqliteResult<Vec<BlockEvent>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT id, target, target_type, blocked_at FROM block_events 
-             ORDER BY blocked_at DESC LIMIT ?1"
+            "SELECT id, target, target_type, blocked_at, session_id, device_id, category FROM block_events 
+             WHERE id > ?1 ORDER BY id ASC LIMIT 100"
+        )?;
+        let events = stmt.query_map(params![id], |row| {
+            Ok(BlockEvent {
+                id: row.get(0)?,
+                target: row.get(1)?,
+                target_type: row.get(2)?,
+                blocked_at: row.get(3)?,
+                session_id: row.get(4)?,
+                device_id: row.get(5)?,
+                category: row.get(6)?,
+            })
+        })?;
+        events.collect()
+    }
+
+    pub fn get_recent_blocks(&self, limit: i32) -> SqliteResult<Vec<BlockEvent>> {
+        Self::query_recent_blocks(&self.conn.lock().unwrap(), limit)
+    }
+
+    fn query_recent_blocks(conn: &Connection, limit: i32) -> SqliteResult<Vec<BlockEvent>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, target, target_type, blocked_at, session_id, device_id, category FROM block_events 
+             ORDER BY blocked_at DESC, id DESC LIMIT ?1"
         )?;
         let events = stmt.query_map(params![limit], |row| {
             Ok(BlockEvent {
@@ -315,6 +1160,9 @@ impl Database {
                 target: row.get(1)?,
                 target_type: row.get(2)?,
                 blocked_at: row.get(3)?,
+                session_id: row.get(4)?,
+                device_id: row.get(5)?,
+                category: row.get(6)?,
             })
         })?;
         events.collect()
@@ -337,6 +1185,26 @@ impl Database {
         Ok(counts)
     }
 
+    /// Website and app blocks over the last `days` days, split by whether a session was active.
+    pub fn get_session_block_split(&self, days: i32) -> SqliteResult<SessionBlockSplit> {
+        let conn = self.conn.lock().unwrap();
+        let offset = format!("-{} days", days.max(1) - 1);
+        conn.query_row(
+            "SELECT
+                COALESCE(SUM(session_id IS NOT NULL), 0),
+                COALESCE(SUM(session_id IS NULL), 0)
+             FROM block_events
+             WHERE target_type IN ('website', 'app') AND blocked_at >= date('now', ?1)",
+            params![offset],
+            |row| {
+                Ok(SessionBlockSplit {
+                    in_session: row.get(0)?,
+                    outside_session: row.get(1)?,
+                })
+            },
+        )
+    }
+
     // Stats
     pub fn update_protected_time(&self, minutes: i64) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
@@ -349,10 +1217,14 @@ impl Database {
         Ok(())
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
     pub fn get_stats(&self, days: i32) -> SqliteResult<Vec<FocusStats>> {
-        let conn = self.conn.lock().unwrap();
+        Self::query_stats(&self.conn.lock().unwrap(), days)
+    }
+
+    fn query_stats(conn: &Connection, days: i32) -> SqliteResult<Vec<FocusStats>> {
         let mut stmt = conn.prepare(
-            "SELECT date, minutes_protected, blocks_count FROM focus_stats 
+            "SELECT date, minutes_protected, blocks_count, pomodoros_completed FROM focus_stats 
              ORDER BY date DESC LIMIT ?1"
         )?;
         let stats = stmt.query_map(params![days], |row| {
@@ -360,12 +1232,730 @@ impl Database {
                 date: row.get(0)?,
                 minutes_protected: row.get(1)?,
                 blocks_count: row.get(2)?,
+                pomodoros_completed: row.get(3)?,
             })
         })?;
         stats.collect()
     }
 
+    /// Read the dashboard's data in one pass: one lock and one read transaction, so the
+    /// lists are consistent with each other and the disk is only hit once on cold start.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_dashboard_data(&self, recent_blocks: i32, stats_days: i32) -> SqliteResult<DashboardData> {
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        let mut settings: std::collections::HashMap<String, String> = Self::query_all_settings(&tx)?.into_iter().collect();
+        let has_master_password = settings.remove("master_password_hash").is_some();
+        let data = DashboardData {
+            blocked_sites: Self::query_blocked_sites(&tx)?,
+            blocked_apps: Self::query_blocked_apps(&tx)?,
+            sessions: Self::query_sessions(&tx)?,
+            recent_blocks: Self::query_recent_blocks(&tx, recent_blocks)?,
+            stats: Self::query_stats(&tx, stats_days)?,
+            settings,
+            has_master_password,
+        };
+        tx.commit()?;
+        Ok(data)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_week_comparison(&self) -> SqliteResult<WeekComparison> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "WITH bounds AS (
+                SELECT date('now', 'localtime', 'weekday 0', '-6 days') AS week_start,
+                       date('now', 'localtime') AS today
+             ),
+             totals AS (
+                SELECT
+                    COALESCE(SUM(CASE WHEN date BETWEEN week_start AND today THEN minutes_protected END), 0) AS cur_minutes,
+                    COALESCE(SUM(CASE WHEN date BETWEEN week_start AND today THEN blocks_count END), 0) AS cur_blocks,
+                    COALESCE(SUM(CASE WHEN date BETWEEN week_start AND today THEN pomodoros_completed END), 0) AS cur_pomodoros,
+                    COALESCE(SUM(CASE WHEN date BETWEEN date(week_start, '-7 days') AND date(today, '-7 days') THEN minutes_protected END), 0) AS prev_minutes,
+                    COALESCE(SUM(CASE WHEN date BETWEEN date(week_start, '-7 days') AND date(today, '-7 days') THEN blocks_count END), 0) AS prev_blocks,
+                    COALESCE(SUM(CASE WHEN date BETWEEN date(week_start, '-7 days') AND date(today, '-7 days') THEN pomodoros_completed END), 0) AS prev_pomodoros
+                FROM bounds LEFT JOIN focus_stats ON 1 = 1
+             )
+             SELECT week_start, date(week_start, '-7 days'),
+                    cur_minutes, cur_blocks, cur_pomodoros, prev_minutes, prev_blocks, prev_pomodoros,
+                    CASE WHEN prev_minutes = 0 THEN NULL ELSE ROUND((cur_minutes - prev_minutes) * 100.0 / prev_minutes, 1) END,
+                    CASE WHEN prev_blocks = 0 THEN NULL ELSE ROUND((cur_blocks - prev_blocks) * 100.0 / prev_blocks, 1) END,
+                    CASE WHEN prev_pomodoros = 0 THEN NULL ELSE ROUND((cur_pomodoros - prev_pomodoros) * 100.0 / prev_pomodoros, 1) END
+             FROM bounds, totals",
+            [],
+            |row| {
+                Ok(WeekComparison {
+                    current: WeekTotals {
+                        week_start: row.get(0)?,
+                        focus_minutes: row.get(2)?,
+                        blocks: row.get(3)?,
+                        pomodoros: row.get(4)?,
+                    },
+                    previous: WeekTotals {
+                        week_start: row.get(1)?,
+                        focus_minutes: row.get(5)?,
+                        blocks: row.get(6)?,
+                        pomodoros: row.get(7)?,
+                    },
+                    focus_minutes_change: row.get(8)?,
+                    blocks_change: row.get(9)?,
+                    pomodoros_change: row.get(10)?,
+                })
+            },
+        )
+    }
+
+    // Pomodoro History
+    pub fn log_pomodoro_completed(&self) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        conn.execute(
+            "INSERT INTO focus_stats (date, pomodoros_completed) VALUES (?1, 1)
+             ON CONFLICT(date) DO UPDATE SET pomodoros_completed = pomodoros_completed + 1",
+            params![today],
+        )?;
+        Ok(())
+    }
+
+    /// Block attempts per day for one site (including its subdomains) or app over the last
+    /// `days` days, oldest first. Every day is present, with 0 when nothing was blocked.
+    pub fn get_target_trend(&self, target: &str, days: i32) -> SqliteResult<Vec<TrendPoint>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "WITH RECURSIVE days(date) AS (
+                SELECT date('now', 'localtime', ?2)
+                UNION ALL SELECT date(date, '+1 day') FROM days WHERE date < date('now', 'localtime')
+             ),
+             attempts AS (
+                SELECT date(blocked_at, 'localtime') AS date, COUNT(*) AS count FROM block_events
+                WHERE target_type IN ('website', 'app')
+                  AND blocked_at >= datetime(date('now', 'localtime', ?2), 'utc')
+                  AND (lower(target) = ?1
+                       OR (length(target) > length(?1) AND substr(lower(target), -length(?1) - 1) = '.' || ?1))
+                GROUP BY 1
+             )
+             SELECT days.date, COALESCE(attempts.count, 0) FROM days
+             LEFT JOIN attempts ON attempts.date = days.date
+             ORDER BY days.date"
+        )?;
+        let offset = format!("-{} days", days.max(1) - 1);
+        let trend = stmt.query_map(params![target.trim().to_lowercase(), offset], |row| {
+            Ok(TrendPoint {
+                date: row.get(0)?,
+                attempts: row.get(1)?,
+            })
+        })?;
+        trend.collect()
+    }
+
+    /// Block attempts and time spent per category over the last `days` days, most blocked first.
+    /// Block events use the category recorded when they were logged; usage is matched to the
+    /// current block list.
+    pub fn get_category_breakdown(&self, days: i32) -> SqliteResult<Vec<CategoryBreakdown>> {
+        let conn = self.conn.lock().unwrap();
+        let offset = format!("-{} days", days.max(1) - 1);
+        let mut totals: std::collections::HashMap<String, (i64, i64)> = std::collections::HashMap::new();
+
+        let mut stmt = conn.prepare(
+            "SELECT category, COUNT(*) FROM block_events
+             WHERE target_type IN ('website', 'app') AND blocked_at >= datetime(date('now', 'localtime', ?1), 'utc')
+             GROUP BY category"
+        )?;
+        for row in stmt.query_map(params![offset], |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, i64>(1)?)))? {
+            let (category, blocks) = row?;
+            totals.entry(category.unwrap_or_else(|| UNCATEGORIZED.to_string())).or_default().0 += blocks;
+        }
+
+        for (table, column, target_type) in [("site_usage", "domain", "website"), ("app_usage", "process_name", "app")] {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {column}, SUM(seconds) FROM {table} WHERE date >= date('now', 'localtime', ?1) GROUP BY {column}"
+            ))?;
+            let usage: Vec<(String, i64)> =
+                stmt.query_map(params![offset], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<SqliteResult<_>>()?;
+            for (target, seconds) in usage {
+                let category = Self::category_for(&conn, &target, target_type)?.unwrap_or_else(|| UNCATEGORIZED.to_string());
+                totals.entry(category).or_default().1 += seconds;
+            }
+        }
+
+        let mut breakdown: Vec<CategoryBreakdown> = totals
+            .into_iter()
+            .map(|(category, (blocks, usage_seconds))| CategoryBreakdown { category, blocks, usage_seconds })
+            .collect();
+        breakdown.sort_by(|a, b| b.blocks.cmp(&a.blocks).then(b.usage_seconds.cmp(&a.usage_seconds)));
+        Ok(breakdown)
+    }
+
+    /// Pomodoros completed per day, most recent first. Days without a completed
+    /// pomodoro are omitted so the chart can decide how to render gaps.
+    pub fn get_pomodoro_history(&self, days: i32) -> SqliteResult<Vec<PomodoroDay>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT date, pomodoros_completed FROM focus_stats 
+             WHERE pomodoros_completed > 0 AND date >= date('now', 'localtime', ?1)
+             ORDER BY date DESC"
+        )?;
+        let offset = format!("-{} days", days.max(1) - 1);
+        let history = stmt.query_map(params![offset], |row| {
+            Ok(PomodoroDay {
+                date: row.get(0)?,
+                completed: row.get(1)?,
+            })
+        })?;
+        history.collect()
+    }
+
+    // Session Extensions
+    pub fn log_session_extension(&self, session_id: &str, session_name: &str, minutes: i64, hardcore: bool) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO session_extensions (session_id, session_name, minutes, hardcore) VALUES (?1, ?2, ?3, ?4)",
+            params![session_id, session_name, minutes, hardcore as i32],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_session_extensions(&self, limit: i32) -> SqliteResult<Vec<SessionExtension>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, session_name, minutes, hardcore, extended_at FROM session_extensions
+             ORDER BY id DESC LIMIT ?1"
+        )?;
+        let extensions = stmt.query_map(params![limit], |row| {
+            Ok(SessionExtension {
+                id: row.get(0)?,
+                session_id: row.get(1)?,
+                session_name: row.get(2)?,
+                minutes: row.get(3)?,
+                hardcore: row.get::<_, i32>(4)? == 1,
+                extended_at: row.get(5)?,
+            })
+        })?;
+        extensions.collect()
+    }
+
+    // Session history
+    /// Record a session that ended. Recording the same session twice keeps the first record.
+    pub fn record_session(&self, record: &SessionRecord) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR IGNORE INTO session_history (session_id, name, session_type, hardcore, started_at, ended_at, completed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                record.session_id,
+                record.name,
+                record.session_type,
+                record.hardcore as i32,
+                record.started_at,
+                record.ended_at,
+                record.completed as i32
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Sessions that started between `from` and `to` (Unix timestamps), oldest first.
+    pub fn get_session_history(&self, from: i64, to: i64) -> SqliteResult<Vec<SessionRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT session_id, name, session_type, hardcore, started_at, ended_at, completed FROM session_history
+             WHERE started_at >= ?1 AND started_at < ?2 ORDER BY started_at"
+        )?;
+        let history = stmt.query_map(params![from, to], |row| {
+            Ok(SessionRecord {
+                session_id: row.get(0)?,
+                name: row.get(1)?,
+                session_type: row.get(2)?,
+                hardcore: row.get::<_, i32>(3)? == 1,
+                started_at: row.get(4)?,
+                ended_at: row.get(5)?,
+                completed: row.get::<_, i32>(6)? == 1,
+            })
+        })?;
+        history.collect()
+    }
+
+    /// The `limit` most blocked sites and apps on `date` (YYYY-MM-DD, local time).
+    pub fn get_top_blocks(&self, date: &str, limit: i32) -> SqliteResult<Vec<TopBlock>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT target, target_type, COUNT(*) FROM block_events
+             WHERE target_type IN ('website', 'app') AND date(blocked_at, 'localtime') = ?1
+             GROUP BY target, target_type ORDER BY COUNT(*) DESC, target LIMIT ?2"
+        )?;
+        let blocks = stmt.query_map(params![date, limit], |row| {
+            Ok(TopBlock {
+                target: row.get(0)?,
+                target_type: row.get(1)?,
+                attempts: row.get(2)?,
+            })
+        })?;
+        blocks.collect()
+    }
+
+    // Unlock events (sessions ended early, emergency unlocks)
+    pub fn log_unlock_event(&self, kind: &str, session_id: Option<&str>) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO unlock_events (kind, session_id) VALUES (?1, ?2)",
+            params![kind, session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Unlock events since Monday (local time) and in the last seven days
+    pub fn get_unlock_counts(&self) -> SqliteResult<UnlockCounts> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT
+                COUNT(CASE WHEN created_at >= datetime(date('now', 'localtime', 'weekday 0', '-6 days'), 'utc') THEN 1 END),
+                COUNT(CASE WHEN created_at >= datetime('now', '-7 days') THEN 1 END)
+             FROM unlock_events",
+            [],
+            |row| Ok(UnlockCounts { this_week: row.get(0)?, last_seven_days: row.get(1)? }),
+        )
+    }
+
+    // Streaks
+    /// Earliest day with focus stats, where streak evaluation starts.
+    pub fn first_stats_date(&self) -> SqliteResult<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT MIN(date) FROM focus_stats", [], |row| row.get(0))
+    }
+
+    /// Days since `since` (YYYY-MM-DD, inclusive) with focus time and no unlock events.
+    pub fn get_clean_days(&self, since: &str) -> SqliteResult<std::collections::HashSet<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT date FROM focus_stats
+             WHERE date >= ?1 AND minutes_protected > 0
+               AND date NOT IN (SELECT date(created_at, 'localtime') FROM unlock_events)"
+        )?;
+        let days = stmt.query_map(params![since], |row| row.get(0))?;
+        days.collect()
+    }
+
+    pub fn add_streak_freeze(&self, kind: &str, date: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("INSERT INTO streak_freezes (kind, date) VALUES (?1, ?2)", params![kind, date])?;
+        Ok(())
+    }
+
+    /// Freezes earned minus freezes spent
+    pub fn get_streak_freeze_balance(&self) -> SqliteResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT COALESCE(SUM(CASE kind WHEN 'earned' THEN 1 WHEN 'used' THEN -1 ELSE 0 END), 0) FROM streak_freezes",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    /// Freezes earned and spent, most recent first.
+    pub fn get_streak_freezes(&self, limit: i32) -> SqliteResult<Vec<StreakFreeze>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, date, created_at FROM streak_freezes ORDER BY date DESC, id DESC LIMIT ?1"
+        )?;
+        let freezes = stmt.query_map(params![limit], |row| {
+            Ok(StreakFreeze {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                date: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })?;
+        freezes.collect()
+    }
+
+    // Quotes (shown on the block page and break overlay)
+    pub fn add_quote(&self, text: &str, author: Option<&str>) -> SqliteResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("INSERT INTO quotes (text, author) VALUES (?1, ?2)", params![text, author])?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn get_quotes(&self) -> SqliteResult<Vec<Quote>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, text, author, enabled, created_at FROM quotes ORDER BY id")?;
+        let quotes = stmt.query_map([], Self::quote_from_row)?;
+        quotes.collect()
+    }
+
+    pub fn update_quote(&self, id: i64, text: &str, author: Option<&str>, enabled: bool) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE quotes SET text = ?2, author = ?3, enabled = ?4 WHERE id = ?1",
+            params![id, text, author, enabled as i32],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_quote(&self, id: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM quotes WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// A random enabled quote, or None if the library is empty.
+    pub fn get_random_quote(&self) -> SqliteResult<Option<Quote>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, text, author, enabled, created_at FROM quotes WHERE enabled = 1 ORDER BY RANDOM() LIMIT 1"
+        )?;
+        let mut quotes = stmt.query_map([], Self::quote_from_row)?;
+        quotes.next().transpose()
+    }
+
+    /// Add a quote pack in one transaction, skipping quotes already in the library.
+    /// Returns how many were added.
+    pub fn import_quotes(&self, entries: &[QuoteEntry]) -> SqliteResult<usize> {
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        let mut added = 0;
+        for entry in entries.iter().filter(|e| !e.text.trim().is_empty()) {
+            added += tx.execute(
+                "INSERT OR IGNORE INTO quotes (text, author) VALUES (?1, ?2)",
+                params![entry.text.trim(), entry.author.as_deref().map(str::trim).filter(|a| !a.is_empty())],
+            )?;
+        }
+        tx.commit()?;
+        Ok(added)
+    }
+
+    fn quote_from_row(row: &rusqlite::Row) -> SqliteResult<Quote> {
+        Ok(Quote {
+            id: row.get(0)?,
+            text: row.get(1)?,
+            author: row.get(2)?,
+            enabled: row.get::<_, i32>(3)? == 1,
+            created_at: row.get(4)?,
+        })
+    }
+
+    // Tasks
+    pub fn add_task(&self, title: &str) -> SqliteResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("INSERT INTO tasks (title) VALUES (?1)", params![title])?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn get_tasks(&self) -> SqliteResult<Vec<Task>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT t.id, t.title, t.completed, t.created_at, COALESCE(SUM(l.minutes), 0)
+             FROM tasks t LEFT JOIN task_focus_log l ON l.task_id = t.id
+             GROUP BY t.id ORDER BY t.completed ASC, t.created_at DESC"
+        )?;
+        let tasks = stmt.query_map([], |row| {
+            Ok(Task {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                completed: row.get::<_, i32>(2)? == 1,
+                created_at: row.get(3)?,
+                focus_minutes: row.get(4)?,
+            })
+        })?;
+        tasks.collect()
+    }
+
+    pub fn update_task(&self, id: i64, title: &str, completed: bool) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE tasks SET title = ?2, completed = ?3 WHERE id = ?1",
+            params![id, title, completed as i32],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_task(&self, id: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM task_focus_log WHERE task_id = ?1", params![id])?;
+        conn.execute("DELETE FROM tasks WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Attribute focus time to a task. `source` is "session" or "pomodoro".
+    pub fn log_task_focus(&self, task_id: i64, minutes: i64, source: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO task_focus_log (task_id, minutes, source) VALUES (?1, ?2, ?3)",
+            params![task_id, minutes, source],
+        )?;
+        Ok(())
+    }
+
+    // Process Classifications (user overrides of the built-in productivity map)
+    pub fn set_process_classification(&self, process_name: &str, classification: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO process_classifications (process_name, classification) VALUES (?1, ?2)",
+            params![process_name, classification],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_process_classification(&self, process_name: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM process_classifications WHERE process_name = ?1", params![process_name])?;
+        Ok(())
+    }
+
+    pub fn get_process_classifications(&self) -> SqliteResult<std::collections::HashMap<String, String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT process_name, classification FROM process_classifications")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        rows.collect()
+    }
+
+    /// Merge block events and usage pushed by a paired phone into the same tables the
+    /// desktop writes, in one transaction. Returns how many rows were imported.
+    pub fn import_device_data(&self, device_id: &str, events: &[DeviceBlockEvent], usage: &[DeviceUsage]) -> SqliteResult<usize> {
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        for event in events {
+            let category = Self::category_for(&tx, &event.target, &event.target_type)?;
+            tx.execute(
+                "INSERT INTO block_events (target, target_type, blocked_at, device_id, category) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![event.target, event.target_type, event.blocked_at, device_id, category],
+            )?;
+            tx.execute(
+                "INSERT INTO focus_stats (date, blocks_count) VALUES (date(?1, 'localtime'), 1)
+                 ON CONFLICT(date) DO UPDATE SET blocks_count = blocks_count + 1",
+                params![event.blocked_at],
+            )?;
+        }
+        for entry in usage.iter().filter(|u| u.seconds > 0) {
+            let sql = if entry.target_type == "app" {
+                "INSERT INTO app_usage (date, process_name, seconds) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(date, process_name) DO UPDATE SET seconds = seconds + ?3"
+            } else {
+                "INSERT INTO site_usage (date, domain, seconds) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(date, domain) DO UPDATE SET seconds = seconds + ?3"
+            };
+            tx.execute(sql, params![entry.date, entry.target, entry.seconds])?;
+        }
+        tx.commit()?;
+        Ok(events.len() + usage.len())
+    }
+
+    // App Usage
+    pub fn add_app_usage(&self, process_name: &str, seconds: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        conn.execute(
+            "INSERT INTO app_usage (date, process_name, seconds) VALUES (?1, ?2, ?3)
+             ON CONFLICT(date, process_name) DO UPDATE SET seconds = seconds + ?3",
+            params![today, process_name, seconds],
+        )?;
+        Ok(())
+    }
+
+    /// Usage per process over the last `days` days, summed per day or per week
+    /// (weeks start on Monday and are labelled with that date).
+    pub fn get_app_usage_grouped(&self, days: i32, by_week: bool) -> SqliteResult<Vec<AppUsage>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT CASE WHEN ?2 THEN date(date, '-6 days', 'weekday 1') ELSE date END AS period,
+                    process_name, SUM(seconds)
+             FROM app_usage
+             WHERE date >= date('now', 'localtime', ?1)
+             GROUP BY period, process_name
+             ORDER BY period DESC"
+        )?;
+        let offset = format!("-{} days", days.max(1) - 1);
+        let usage = stmt.query_map(params![offset, by_week], |row| {
+            Ok(AppUsage {
+                date: row.get(0)?,
+                process_name: row.get(1)?,
+                seconds: row.get(2)?,
+            })
+        })?;
+        usage.collect()
+    }
+
+    /// Total foreground time per app over the last `days` days, most used first.
+    pub fn get_app_usage(&self, days: i32) -> SqliteResult<Vec<AppUsageTotal>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT process_name, SUM(seconds) AS total FROM app_usage
+             WHERE date >= date('now', 'localtime', ?1)
+             GROUP BY process_name ORDER BY total DESC"
+        )?;
+        let offset = format!("-{} days", days.max(1) - 1);
+        let usage = stmt.query_map(params![offset], |row| {
+            Ok(AppUsageTotal {
+                process_name: row.get(0)?,
+                seconds: row.get(1)?,
+            })
+        })?;
+        usage.collect()
+    }
+
+    // Site Usage (time on domain, reported by the browser companion)
+    pub fn add_site_usage(&self, domain: &str, seconds: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        conn.execute(
+            "INSERT INTO site_usage (date, domain, seconds) VALUES (?1, ?2, ?3)
+             ON CONFLICT(date, domain) DO UPDATE SET seconds = seconds + ?3",
+            params![today, domain, seconds],
+        )?;
+        Ok(())
+    }
+
+    /// Total time per domain over the last `days` days, most visited first.
+    pub fn get_site_usage(&self, days: i32) -> SqliteResult<Vec<SiteUsage>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT domain, SUM(seconds) AS total FROM site_usage
+             WHERE date >= date('now', 'localtime', ?1)
+             GROUP BY domain ORDER BY total DESC"
+        )?;
+        let offset = format!("-{} days", days.max(1) - 1);
+        let usage = stmt.query_map(params![offset], |row| {
+            Ok(SiteUsage {
+                domain: row.get(0)?,
+                seconds: row.get(1)?,
+            })
+        })?;
+        usage.collect()
+    }
+
+    // Micro-break adherence
+    /// Record the outcome of a micro-break: "taken", "skipped" or "snoozed".
+    pub fn log_micro_break(&self, outcome: &str) -> SqliteResult<()> {
+        let column = match outcome {
+            "taken" => "taken",
+            "skipped" => "skipped",
+            "snoozed" => "snoozed",
+            _ => return Err(rusqlite::Error::InvalidParameterName(outcome.to_string())),
+        };
+        let conn = self.conn.lock().unwrap();
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        conn.execute(
+            &format!(
+                "INSERT INTO micro_break_stats (date, {0}) VALUES (?1, 1)
+                 ON CONFLICT(date) DO UPDATE SET {0} = {0} + 1",
+                column
+            ),
+            params![today],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_micro_break_stats(&self, days: i32) -> SqliteResult<Vec<MicroBreakStats>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT date, taken, skipped, snoozed FROM micro_break_stats 
+             ORDER BY date DESC LIMIT ?1"
+        )?;
+        let stats = stmt.query_map(params![days], |row| {
+            Ok(MicroBreakStats {
+                date: row.get(0)?,
+                taken: row.get(1)?,
+                skipped: row.get(2)?,
+                snoozed: row.get(3)?,
+            })
+        })?;
+        stats.collect()
+    }
+
+    // Integration Queue
+    pub fn enqueue_integration(&self, provider: &str, payload: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO integration_queue (provider, payload) VALUES (?1, ?2)",
+            params![provider, payload],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_integration_queue(&self) -> SqliteResult<Vec<QueuedPush>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, provider, payload, attempts, last_error FROM integration_queue ORDER BY id ASC"
+        )?;
+        let items = stmt.query_map([], |row| {
+            Ok(QueuedPush {
+                id: row.get(0)?,
+                provider: row.get(1)?,
+                payload: row.get(2)?,
+                attempts: row.get(3)?,
+                last_error: row.get(4)?,
+            })
+        })?;
+        items.collect()
+    }
+
+    pub fn record_integration_failure(&self, id: i64, error: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE integration_queue SET attempts = attempts + 1, last_error = ?2 WHERE id = ?1",
+            params![id, error],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_from_integration_queue(&self, id: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM integration_queue WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
     /// Factory reset - clear all data from the database
+    /// Row count of every table, for diagnostics.
+    pub fn table_row_counts(&self) -> SqliteResult<Vec<(String, i64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")?;
+        let tables: Vec<String> = stmt.query_map([], |row| row.get(0))?.collect::<SqliteResult<_>>()?;
+        tables
+            .into_iter()
+            .map(|table| {
+                let count = conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| row.get(0))?;
+                Ok((table, count))
+            })
+            .collect()
+    }
+
+    fn in_memory() -> SqliteResult<Self> {
+        let db = Database {
+            conn: Mutex::new(Connection::open_in_memory()?),
+        };
+        db.init_tables()?;
+        Ok(db)
+    }
+
+    /// In-memory database filled with sample data, for demo / guest mode.
+    pub fn demo() -> SqliteResult<Self> {
+        let db = Self::in_memory()?;
+        db.conn.lock().unwrap().execute_batch(
+            "INSERT INTO settings (key, value) VALUES ('onboarded', 'true'), ('minimize_to_tray', 'false');
+             INSERT INTO blocked_sites (domain, category) VALUES
+                ('youtube.com', 'entertainment'), ('reddit.com', 'social'), ('twitter.com', 'social'),
+                ('instagram.com', 'social'), ('netflix.com', 'entertainment'), ('news.ycombinator.com', 'news');
+             INSERT INTO blocked_apps (name, process_name, category) VALUES
+                ('Discord', 'Discord.exe', 'social'), ('Steam', 'steam.exe', 'games'), ('Spotify', 'Spotify.exe', 'entertainment');
+             INSERT INTO sessions (name, start_time, end_time, days, hardcore) VALUES
+                ('Morning Deep Work', '09:00', '12:00', '[\"Mon\",\"Tue\",\"Wed\",\"Thu\",\"Fri\"]', 1),
+                ('Afternoon Focus', '14:00', '16:30', '[\"Mon\",\"Wed\",\"Fri\"]', 0);
+             INSERT INTO tasks (title, completed) VALUES
+                ('Write project proposal', 0), ('Review pull requests', 1), ('Prepare slides', 0);
+             WITH RECURSIVE days(n) AS (SELECT 0 UNION ALL SELECT n + 1 FROM days WHERE n < 13)
+             INSERT INTO focus_stats (date, minutes_protected, blocks_count, pomodoros_completed)
+                SELECT date('now', 'localtime', '-' || n || ' days'), 90 + (n * 37) % 150, 3 + (n * 7) % 19, 2 + n % 5 FROM days;
+             WITH RECURSIVE events(n) AS (SELECT 0 UNION ALL SELECT n + 1 FROM events WHERE n < 39)
+             INSERT INTO block_events (target, target_type, blocked_at)
+                SELECT CASE n % 4 WHEN 0 THEN 'youtube.com' WHEN 1 THEN 'reddit.com' WHEN 2 THEN 'twitter.com' ELSE 'Discord.exe' END,
+                       CASE n % 4 WHEN 3 THEN 'app' ELSE 'website' END,
+                       datetime('now', '-' || (n * 5) || ' hours') FROM events;",
+        )?;
+        Ok(db)
+    }
+
     pub fn factory_reset(&self) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute("DELETE FROM blocked_sites", [])?;
@@ -373,7 +1963,327 @@ impl Database {
         conn.execute("DELETE FROM sessions", [])?;
         conn.execute("DELETE FROM block_events", [])?;
         conn.execute("DELETE FROM focus_stats", [])?;
+        conn.execute("DELETE FROM tasks", [])?;
+        conn.execute("DELETE FROM task_focus_log", [])?;
+        conn.execute("DELETE FROM integration_queue", [])?;
+        conn.execute("DELETE FROM process_classifications", [])?;
+        conn.execute("DELETE FROM app_usage", [])?;
+        conn.execute("DELETE FROM site_usage", [])?;
+        conn.execute("DELETE FROM micro_break_stats", [])?;
+        conn.execute("DELETE FROM session_extensions", [])?;
+        conn.execute("DELETE FROM unlock_events", [])?;
+        conn.execute("DELETE FROM streak_freezes", [])?;
+        conn.execute("DELETE FROM session_history", [])?;
+        conn.execute("DELETE FROM allowed_sites", [])?;
+        conn.execute("DELETE FROM quotes", [])?;
         conn.execute("DELETE FROM settings", [])?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn query_plan(db: &Database, sql: &str) -> String {
+        let conn = db.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql)).unwrap();
+        let rows = stmt.query_map([], |row| row.get::<_, String>(3)).unwrap();
+        rows.map(|row| row.unwrap()).collect::<Vec<_>>().join("; ")
+    }
+
+    fn seed_block_events(db: &Database, count: usize) {
+        let mut conn = db.conn.lock().unwrap();
+        let tx = conn.transaction().unwrap();
+        for i in 0..count {
+            tx.execute(
+                "INSERT INTO block_events (target, target_type, blocked_at) VALUES (?1, ?2, datetime('now', ?3))",
+                params![format!("site{}.com", i % 200), if i % 10 == 0 { "app" } else { "website" }, format!("-{} minutes", i)],
+            )
+            .unwrap();
+        }
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_import_device_data() {
+        let db = Database::in_memory().unwrap();
+        let events = vec![DeviceBlockEvent {
+            target: "tiktok.com".to_string(),
+            target_type: "website".to_string(),
+            blocked_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        }];
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let usage = vec![
+            DeviceUsage { date: today.clone(), target: "reddit.com".to_string(), target_type: "website".to_string(), seconds: 120 },
+            DeviceUsage { date: today, target: "com.instagram.android".to_string(), target_type: "app".to_string(), seconds: 60 },
+        ];
+        db.add_site_usage("reddit.com", 30).unwrap();
+
+        assert_eq!(db.import_device_data("phone-1", &events, &usage).unwrap(), 3);
+        assert_eq!(db.get_recent_blocks(10).unwrap()[0].target, "tiktok.com");
+        assert_eq!(db.get_site_usage(1).unwrap()[0].seconds, 150);
+    }
+
+    #[test]
+    fn test_quote_pack_import() {
+        let pack = "# Stoics\nWaste no more time arguing what a good man should be. Be one. — Marcus Aurelius\n\nDo the work.\n";
+        let entries = parse_quote_pack(pack).unwrap();
+        assert_eq!(entries[0].author.as_deref(), Some("Marcus Aurelius"));
+        assert_eq!(entries[1], QuoteEntry { text: "Do the work.".to_string(), author: None });
+
+        let json = parse_quote_pack(r#"[{"text": "Do the work."}, {"text": "Focus.", "author": "Anon"}]"#).unwrap();
+        assert_eq!(json.len(), 2);
+
+        let db = Database::in_memory().unwrap();
+        assert!(db.get_random_quote().unwrap().is_none());
+        assert_eq!(db.import_quotes(&entries).unwrap(), 2);
+        // "Do the work." is already in the library
+        assert_eq!(db.import_quotes(&json).unwrap(), 1);
+        assert!(db.get_random_quote().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_app_usage_totals() {
+        let db = Database::in_memory().unwrap();
+        db.add_app_usage("Code.exe", 300).unwrap();
+        db.add_app_usage("Discord.exe", 60).unwrap();
+        db.add_app_usage("Code.exe", 120).unwrap();
+
+        let usage = db.get_app_usage(7).unwrap();
+        assert_eq!(usage.len(), 2);
+        assert_eq!((usage[0].process_name.as_str(), usage[0].seconds), ("Code.exe", 420));
+        assert_eq!(usage[1].seconds, 60);
+    }
+
+    #[test]
+    fn test_target_trend() {
+        let db = Database::in_memory().unwrap();
+        db.log_block_event("reddit.com", "website", None).unwrap();
+        db.log_block_event("old.reddit.com", "website", None).unwrap();
+        db.log_block_event("notreddit.com", "website", None).unwrap();
+
+        let trend = db.get_target_trend("Reddit.com", 7).unwrap();
+        assert_eq!(trend.len(), 7);
+        assert!(trend.windows(2).all(|w| w[0].date < w[1].date));
+        assert_eq!(trend.last().unwrap().attempts, 2);
+        assert_eq!(trend.iter().map(|p| p.attempts).sum::<i64>(), 2);
+    }
+
+    #[test]
+    fn test_category_breakdown() {
+        let db = Database::in_memory().unwrap();
+        db.add_blocked_site("reddit.com", "social", false).unwrap();
+        db.add_blocked_app("Steam", "steam.exe", "games").unwrap();
+        db.log_block_event("old.reddit.com", "website", None).unwrap();
+        db.log_block_event("Steam.exe", "app", None).unwrap();
+        db.log_block_event("Steam.exe", "app", None).unwrap();
+        db.log_block_event("example.org", "website", None).unwrap();
+        db.add_site_usage("www.reddit.com", 300).unwrap();
+
+        // Removing the app later doesn't rewrite history
+        db.delete_blocked_app(1).unwrap();
+
+        let breakdown = db.get_category_breakdown(7).unwrap();
+        let find = |category: &str| breakdown.iter().find(|c| c.category == category).unwrap();
+        assert_eq!(breakdown[0].category, "games");
+        assert_eq!(find("games").blocks, 2);
+        assert_eq!((find("social").blocks, find("social").usage_seconds), (1, 300));
+        assert_eq!(find(UNCATEGORIZED).blocks, 1);
+    }
+
+    #[test]
+    fn test_session_history_and_top_blocks() {
+        let db = Database::in_memory().unwrap();
+        let record = |id: &str, started_at: i64| SessionRecord {
+            session_id: id.to_string(),
+            name: "Deep work".to_string(),
+            session_type: "manual".to_string(),
+            hardcore: false,
+            started_at,
+            ended_at: started_at + 1500,
+            completed: true,
+        };
+        db.record_session(&record("a", 1_000)).unwrap();
+        db.record_session(&record("b", 5_000)).unwrap();
+        db.record_session(&SessionRecord { completed: false, ..record("a", 1_000) }).unwrap();
+
+        let history = db.get_session_history(0, 5_000).unwrap();
+        assert_eq!(history.len(), 1);
+        assert!(history[0].completed);
+
+        for target in ["reddit.com", "reddit.com", "steam.exe"] {
+            db.log_block_event(target, if target.ends_with(".exe") { "app" } else { "website" }, None).unwrap();
+        }
+        db.log_block_event("Hosts file", "tamper", None).unwrap();
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let top = db.get_top_blocks(&today, 5).unwrap();
+        assert_eq!(top.iter().map(|b| (b.target.as_str(), b.attempts)).collect::<Vec<_>>(), vec![("reddit.com", 2), ("steam.exe", 1)]);
+    }
+
+    #[test]
+    fn test_add_blocked_sites() {
+        let db = Database::in_memory().unwrap();
+        db.add_blocked_site("reddit.com", "other", false).unwrap();
+
+        assert_eq!(db.add_blocked_sites(&["www.reddit.com", "twitter.com", "x.com", "x.com"], "social").unwrap(), 2);
+        let sites = db.get_blocked_sites().unwrap();
+        assert_eq!(sites.len(), 3);
+        assert_eq!(sites.iter().find(|s| s.domain == "reddit.com").unwrap().category, "other");
+        assert_eq!(db.add_blocked_sites(&["twitter.com"], "social").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_import_blocklist() {
+        let db = Database::in_memory().unwrap();
+        db.add_blocked_site("reddit.com", "other", false).unwrap();
+        db.add_blocked_app("Steam", "steam.exe", "games").unwrap();
+        let site = |domain: &str| SiteEntry {
+            domain: domain.to_string(),
+            category: "social".to_string(),
+            enabled: false,
+            mode: crate::blocking::BLOCK_MODE_HOSTS.to_string(),
+            include_subdomains: false,
+        };
+        let app = AppEntry { name: "Steam".to_string(), process_name: "Steam.exe".to_string(), category: "games".to_string(), enabled: true };
+
+        let summary = db.import_blocklist(&[site("reddit.com"), site("x.com"), site("x.com")], std::slice::from_ref(&app), false).unwrap();
+        assert_eq!(summary, ImportSummary { sites_added: 1, apps_added: 0, duplicates: 3, invalid: 0 });
+        assert_eq!(db.get_blocked_sites().unwrap().len(), 2);
+
+        // Replacing trashes the current lists, so the same entries can come back
+        let summary = db.import_blocklist(&[site("reddit.com")], &[app], true).unwrap();
+        assert_eq!((summary.sites_added, summary.apps_added, summary.duplicates), (1, 1, 0));
+        let sites = db.get_blocked_sites().unwrap();
+        assert_eq!(sites.len(), 1);
+        assert!(!sites[0].enabled);
+        assert_eq!(db.get_blocked_apps().unwrap()[0].process_name, "Steam.exe");
+    }
+
+    #[test]
+    fn test_allowed_sites() {
+        let db = Database::in_memory().unwrap();
+        let id = db.add_allowed_site("docs.rs").unwrap();
+        assert_eq!(db.add_allowed_site("docs.rs").unwrap(), id);
+        db.add_allowed_site("github.com").unwrap();
+        assert_eq!(db.get_allowed_sites().unwrap().iter().map(|s| s.domain.as_str()).collect::<Vec<_>>(), vec!["docs.rs", "github.com"]);
+
+        db.delete_allowed_site(id).unwrap();
+        assert_eq!(db.get_allowed_sites().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_soft_delete_and_undo() {
+        let db = Database::in_memory().unwrap();
+        let AddSiteResult::Added { id } = db.add_blocked_site("reddit.com", "social", false).unwrap() else {
+            panic!("first add should insert");
+        };
+        let app = db.add_blocked_app("Steam", "steam.exe", "games").unwrap();
+
+        db.delete_blocked_site(id).unwrap();
+        db.delete_blocked_app(app).unwrap();
+        assert!(db.get_blocked_sites().unwrap().is_empty());
+        assert!(db.get_blocked_apps().unwrap().is_empty());
+        assert_eq!(db.get_deleted_items().unwrap().len(), 2);
+
+        assert!(db.undo_delete(TrashKind::Site, id).unwrap());
+        assert!(!db.undo_delete(TrashKind::Site, id).unwrap());
+        assert_eq!(db.get_blocked_sites().unwrap()[0].domain, "reddit.com");
+
+        // Re-adding a deleted app replaces the trashed row instead of hitting UNIQUE
+        assert!(db.add_blocked_app("Steam", "steam.exe", "games").is_ok());
+        assert!(db.get_deleted_items().unwrap().is_empty());
+
+        db.delete_blocked_site(id).unwrap();
+        assert_eq!(db.purge_deleted(chrono::Local::now().timestamp() + 1).unwrap(), 1);
+        assert!(db.get_deleted_items().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_add_blocked_site_duplicates() {
+        let db = Database::in_memory().unwrap();
+        let AddSiteResult::Added { id } = db.add_blocked_site("münchen.de", "other", false).unwrap() else {
+            panic!("first add should insert");
+        };
+
+        for variant in ["München.de", "www.xn--mnchen-3ya.de", "https://www.münchen.de/"] {
+            match db.add_blocked_site(variant, "other", false).unwrap() {
+                AddSiteResult::AlreadyBlocked { site } => assert_eq!(site.id, id),
+                other => panic!("{} was not detected as a duplicate: {:?}", variant, other),
+            }
+        }
+        // A path rule on the same host is a different entry
+        assert!(matches!(db.add_blocked_site("münchen.de/news", "other", false).unwrap(), AddSiteResult::Added { .. }));
+
+        db.toggle_blocked_site(id, false).unwrap();
+        match db.add_blocked_site("münchen.de", "other", true).unwrap() {
+            AddSiteResult::Reenabled { site } => assert!(site.enabled && site.id == id),
+            other => panic!("disabled duplicate was not re-enabled: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_dashboard_data() {
+        let db = Database::in_memory().unwrap();
+        db.add_blocked_site("reddit.com", "social", false).unwrap();
+        db.set_setting("master_password_hash", "hash").unwrap();
+        db.set_setting("theme", "dark").unwrap();
+
+        let data = db.get_dashboard_data(8, 1).unwrap();
+        assert_eq!(data.blocked_sites.len(), 1);
+        assert!(data.has_master_password);
+        assert!(!data.settings.contains_key("master_password_hash"));
+        assert_eq!(data.settings.get("theme").map(String::as_str), Some("dark"));
+    }
+
+    #[test]
+    fn test_demo_database_has_sample_data() {
+        let db = Database::demo().unwrap();
+        assert!(!db.get_blocked_sites().unwrap().is_empty());
+        assert!(!db.get_blocked_apps().unwrap().is_empty());
+        assert_eq!(db.get_stats(7).unwrap().len(), 7);
+        assert!(!db.get_recent_blocks(10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_block_event_queries_use_indexes() {
+        let db = Database::in_memory().unwrap();
+
+        let recent = query_plan(&db, "SELECT id FROM block_events ORDER BY blocked_at DESC, id DESC LIMIT 10");
+        assert!(recent.contains("idx_block_events_blocked_at"), "{}", recent);
+        let counts = query_plan(&db, "SELECT target, COUNT(*) FROM block_events GROUP BY target");
+        assert!(counts.contains("idx_block_events_target"), "{}", counts);
+        let split = query_plan(
+            &db,
+            "SELECT COUNT(*) FROM block_events WHERE target_type IN ('website', 'app') AND blocked_at >= date('now', '-6 days')",
+        );
+        assert!(split.contains("idx_block_events_type_time"), "{}", split);
+    }
+
+    /// `cargo test --release bench_block_event_queries -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_block_event_queries() {
+        let db = Database::in_memory().unwrap();
+        seed_block_events(&db, 200_000);
+
+        let start = Instant::now();
+        for _ in 0..100 {
+            db.get_recent_blocks(50).unwrap();
+        }
+        println!("get_recent_blocks(50): {:?} per call", start.elapsed() / 100);
+
+        let start = Instant::now();
+        for _ in 0..10 {
+            db.get_block_counts().unwrap();
+        }
+        println!("get_block_counts: {:?} per call", start.elapsed() / 10);
+
+        let start = Instant::now();
+        for _ in 0..100 {
+            db.get_session_block_split(7).unwrap();
+        }
+        println!("get_session_block_split(7): {:?} per call", start.elapsed() / 100);
+    }
+}
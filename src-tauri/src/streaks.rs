@@ -0,0 +1,155 @@
+//! Streaks module - Consecutive clean days and streak freeze tokens
+//!
+//! A clean day has some protected focus time and no sessions ended early or emergency
+//! unlocks. Every `DAYS_PER_FREEZE` clean days in a row earn a streak freeze (up to
+//! `MAX_FREEZES` held at once). When a day is missed, a held freeze is spent automatically
+//! and the streak carries on instead of resetting. Earned and spent freezes are kept in the
+//! `streak_freezes` table as history; the balance is earned minus spent.
+//!
+//! Days are settled once they are over, so today never costs a freeze; it only adds to the
+//! streak shown once it is already clean.
+
+use crate::storage::Database;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Streak progress (JSON `StreakState`)
+pub const STREAK_STATE_SETTING: &str = "streak_state";
+
+pub const DAYS_PER_FREEZE: i64 = 7;
+pub const MAX_FREEZES: i64 = 3;
+
+/// How far back the first evaluation looks
+const MAX_BACKFILL_DAYS: i64 = 365;
+
+pub const FREEZE_EARNED: &str = "earned";
+pub const FREEZE_USED: &str = "used";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct StreakState {
+    current: i64, // clean days in a row, through `settled_through`
+    longest: i64,
+    settled_through: Option<String>, // last day (YYYY-MM-DD) already counted
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreakStatus {
+    pub current: i64, // includes today once it is clean
+    pub longest: i64,
+    pub today_clean: bool,
+    pub freezes_available: i64,
+    pub days_to_next_freeze: i64,
+}
+
+/// What settling one day did
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DayOutcome {
+    Counted { earned_freeze: bool },
+    Frozen,
+    Broken,
+}
+
+/// Settle one finished day, given how many freezes are held.
+fn settle_day(state: &mut StreakState, clean: bool, freezes: i64) -> DayOutcome {
+    if clean {
+        state.current += 1;
+        state.longest = state.longest.max(state.current);
+        let earned_freeze = state.current % DAYS_PER_FREEZE == 0 && freezes < MAX_FREEZES;
+        DayOutcome::Counted { earned_freeze }
+    } else if state.current > 0 && freezes > 0 {
+        DayOutcome::Frozen
+    } else {
+        state.current = 0;
+        DayOutcome::Broken
+    }
+}
+
+fn load_state(db: &Database) -> StreakState {
+    db.get_setting(STREAK_STATE_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Settle every finished day since the last call, earning and spending freezes on the way.
+pub fn update(db: &Database, today: NaiveDate) -> rusqlite::Result<StreakStatus> {
+    let mut state = load_state(db);
+    let yesterday = today - Duration::days(1);
+    let first_day = match state.settled_through.as_deref().and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()) {
+        Some(settled) => settled + Duration::days(1),
+        None => db
+            .first_stats_date()?
+            .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok())
+            .unwrap_or(today)
+            .max(today - Duration::days(MAX_BACKFILL_DAYS)),
+    };
+
+    if first_day <= yesterday {
+        let clean_days = db.get_clean_days(&first_day.format("%Y-%m-%d").to_string())?;
+        let mut freezes = db.get_streak_freeze_balance()?;
+        let mut day = first_day;
+        while day <= yesterday {
+            let date = day.format("%Y-%m-%d").to_string();
+            match settle_day(&mut state, clean_days.contains(&date), freezes) {
+                DayOutcome::Counted { earned_freeze: true } => {
+                    db.add_streak_freeze(FREEZE_EARNED, &date)?;
+                    freezes += 1;
+                }
+                DayOutcome::Frozen => {
+                    db.add_streak_freeze(FREEZE_USED, &date)?;
+                    freezes -= 1;
+                }
+                DayOutcome::Counted { earned_freeze: false } | DayOutcome::Broken => {}
+            }
+            day += Duration::days(1);
+        }
+        state.settled_through = Some(yesterday.format("%Y-%m-%d").to_string());
+        db.set_setting(STREAK_STATE_SETTING, &serde_json::to_string(&state).unwrap_or_default())?;
+    }
+
+    let today = today.format("%Y-%m-%d").to_string();
+    let today_clean = db.get_clean_days(&today)?.contains(&today);
+    let current = state.current + today_clean as i64;
+    Ok(StreakStatus {
+        current,
+        longest: state.longest.max(current),
+        today_clean,
+        freezes_available: db.get_streak_freeze_balance()?,
+        days_to_next_freeze: DAYS_PER_FREEZE - current % DAYS_PER_FREEZE,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settle_day() {
+        let mut state = StreakState::default();
+        let mut freezes = 0;
+        for day in 1..=DAYS_PER_FREEZE {
+            let outcome = settle_day(&mut state, true, freezes);
+            assert_eq!(outcome, DayOutcome::Counted { earned_freeze: day == DAYS_PER_FREEZE });
+        }
+        freezes += 1;
+
+        // A missed day spends the freeze and keeps the streak
+        assert_eq!(settle_day(&mut state, false, freezes), DayOutcome::Frozen);
+        freezes -= 1;
+        assert_eq!(state.current, DAYS_PER_FREEZE);
+
+        // Without a freeze the streak resets, but the longest is kept
+        assert_eq!(settle_day(&mut state, false, freezes), DayOutcome::Broken);
+        assert_eq!(state.current, 0);
+        assert_eq!(state.longest, DAYS_PER_FREEZE);
+
+        // Nothing to protect yet: no freeze is spent
+        assert_eq!(settle_day(&mut state, false, 1), DayOutcome::Broken);
+    }
+
+    #[test]
+    fn test_no_freeze_earned_at_cap() {
+        let mut state = StreakState { current: DAYS_PER_FREEZE - 1, ..Default::default() };
+        assert_eq!(settle_day(&mut state, true, MAX_FREEZES), DayOutcome::Counted { earned_freeze: false });
+    }
+}
@@ -0,0 +1,112 @@
+//! Summary module - End-of-day focus summary
+//!
+//! Once a day, at the configured evening time, the background loop builds a `DailySummary`
+//! from today's focus stats and pushes it as a notification and a `daily-summary` event.
+
+use crate::storage::FocusStats;
+use chrono::{NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+pub const SUMMARY_SETTING: &str = "daily_summary";
+/// Date (YYYY-MM-DD) the summary was last sent
+pub const SUMMARY_LAST_SENT_SETTING: &str = "daily_summary_last_sent";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailySummaryConfig {
+    pub enabled: bool,
+    pub time: String,      // HH:MM
+    pub goal_minutes: i64, // daily focus goal
+}
+
+impl Default for DailySummaryConfig {
+    fn default() -> Self {
+        DailySummaryConfig {
+            enabled: true,
+            time: "21:00".to_string(),
+            goal_minutes: 8 * 60,
+        }
+    }
+}
+
+impl DailySummaryConfig {
+    /// Whether the summary should be sent at `now`, given the date (YYYY-MM-DD) it was last sent.
+    /// A summary missed while the machine was off is sent later the same evening, not the next day.
+    pub fn is_due(&self, now: NaiveDateTime, last_sent: Option<&str>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let today = now.format("%Y-%m-%d").to_string();
+        if last_sent == Some(today.as_str()) {
+            return false;
+        }
+        NaiveTime::parse_from_str(&self.time, "%H:%M").is_ok_and(|time| now.time() >= time)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DailySummary {
+    pub date: String,
+    pub focus_minutes: i64,
+    pub blocks: i64,
+    pub pomodoros: i64,
+    pub goal_minutes: i64,
+    pub goal_percent: i64, // capped at 100
+}
+
+impl DailySummary {
+    pub fn new(date: &str, stats: Option<&FocusStats>, goal_minutes: i64) -> Self {
+        let focus_minutes = stats.map_or(0, |s| s.minutes_protected);
+        let goal_percent = if goal_minutes > 0 {
+            (focus_minutes * 100 / goal_minutes).min(100)
+        } else {
+            100
+        };
+        DailySummary {
+            date: date.to_string(),
+            focus_minutes,
+            blocks: stats.map_or(0, |s| s.blocks_count),
+            pomodoros: stats.map_or(0, |s| s.pomodoros_completed),
+            goal_minutes,
+            goal_percent,
+        }
+    }
+
+    /// Notification body, e.g. "2h 15m focused (28% of goal), 12 distractions blocked"
+    pub fn message(&self) -> String {
+        format!(
+            "{}h {}m focused ({}% of goal), {} distractions blocked",
+            self.focus_minutes / 60,
+            self.focus_minutes % 60,
+            self.goal_percent,
+            self.blocks
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_due_once_after_time() {
+        let config = DailySummaryConfig::default();
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+
+        assert!(!config.is_due(at("2024-01-01 20:59"), None));
+        assert!(config.is_due(at("2024-01-01 21:00"), None));
+        assert!(config.is_due(at("2024-01-01 23:30"), Some("2023-12-31")));
+        assert!(!config.is_due(at("2024-01-01 23:30"), Some("2024-01-01")));
+    }
+
+    #[test]
+    fn test_summary_goal_percent() {
+        let stats = FocusStats {
+            date: "2024-01-01".to_string(),
+            minutes_protected: 600,
+            blocks_count: 3,
+            pomodoros_completed: 4,
+        };
+        assert_eq!(DailySummary::new("2024-01-01", Some(&stats), 480).goal_percent, 100);
+        assert_eq!(DailySummary::new("2024-01-01", None, 480).goal_percent, 0);
+    }
+}
@@ -0,0 +1,74 @@
+//! Telemetry module - Optional OpenTelemetry trace export for diagnostics
+//!
+//! The block server, enforcement loop, hosts-file sync and database layer are instrumented
+//! with `tracing` spans. Spans are discarded unless the `telemetry` setting enables export,
+//! in which case they are sent over OTLP/HTTP to the configured collector (e.g. a local
+//! Jaeger at `http://localhost:4318/v1/traces`). Changes take effect on the next start.
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{runtime, trace, Resource};
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+pub const TELEMETRY_SETTING: &str = "telemetry";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    pub endpoint: String, // OTLP/HTTP traces endpoint
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig {
+            enabled: false,
+            endpoint: "http://localhost:4318/v1/traces".to_string(),
+        }
+    }
+}
+
+/// Install the OTLP exporter if enabled. Must be called from within the Tokio runtime.
+pub fn init(config: &TelemetryConfig) {
+    if !config.enabled {
+        return;
+    }
+    let exporter = opentelemetry_otlp::new_exporter()
+        .http()
+        .with_endpoint(&config.endpoint);
+    let provider = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(
+            trace::Config::default().with_resource(Resource::new(vec![
+                KeyValue::new("service.name", "bastion"),
+                KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+            ])),
+        )
+        .install_batch(runtime::Tokio);
+
+    let provider = match provider {
+        Ok(provider) => provider,
+        Err(e) => {
+            eprintln!("[Bastion] Telemetry warning: Could not start OTLP exporter: {}", e);
+            return;
+        }
+    };
+    let tracer = provider.tracer("bastion");
+    opentelemetry::global::set_tracer_provider(provider);
+
+    let result = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init();
+    match result {
+        Ok(()) => println!("[Bastion] Exporting traces to {}", config.endpoint),
+        Err(e) => eprintln!("[Bastion] Telemetry warning: {}", e),
+    }
+}
+
+/// Flush spans that haven't been exported yet.
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
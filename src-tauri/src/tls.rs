@@ -0,0 +1,243 @@
+//! TLS module - Local certificate authority for the HTTPS block page
+//!
+//! Without it, the block server can only close HTTPS connections to blocked sites, which
+//! browsers report as a network error. When the user opts in (`enable_https_block_page`),
+//! Bastion generates a CA, adds it to the system trust store (and Firefox's, through its
+//! policies), and the block server completes the handshake on port 443 with a certificate for
+//! the requested domain so it can show the block page.
+//!
+//! Certificates are issued on first use and kept in memory, and only for domains on the block
+//! list. The CA key stays in the data directory (owner-only on Unix); disabling removes the CA
+//! from the trust stores and deletes it, so a stale CA is never left trusted.
+
+use crate::blocking::{self, BlockingError};
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio_rustls::rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_rustls::rustls::ServerConfig;
+
+pub const HTTPS_BLOCK_PAGE_SETTING: &str = "https_block_page";
+
+/// Common name of the CA, also how it is found again in the trust stores
+pub const CA_COMMON_NAME: &str = "Bastion Local Block Page CA";
+const CA_CERT_FILE: &str = "bastion-ca.pem";
+const CA_KEY_FILE: &str = "bastion-ca.key";
+/// Browsers reject leaf certificates valid for more than 398 days
+const LEAF_VALIDITY_DAYS: i64 = 390;
+const CA_VALIDITY_DAYS: i64 = 3650;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpsBlockPageStatus {
+    pub enabled: bool,
+    pub certificate_path: Option<String>, // the CA certificate, for trusting it by hand
+}
+
+/// The CA and the server configurations issued by it, one per blocked domain
+pub struct BlockPageCa {
+    cert_path: PathBuf,
+    cert: Certificate,
+    key: KeyPair,
+    configs: Mutex<HashMap<String, Arc<ServerConfig>>>,
+}
+
+impl BlockPageCa {
+    /// Load the CA from `data_dir`, generating it the first time.
+    pub fn load_or_create(data_dir: &Path) -> Result<Self, BlockingError> {
+        let cert_path = data_dir.join(CA_CERT_FILE);
+        let key_path = data_dir.join(CA_KEY_FILE);
+        if let (Ok(_), Ok(key_pem)) = (fs::metadata(&cert_path), fs::read_to_string(&key_path)) {
+            // Re-signing the same name with the same key gives an issuer equivalent to the stored one
+            let key = KeyPair::from_pem(&key_pem).map_err(cert_error)?;
+            let cert = ca_params().self_signed(&key).map_err(cert_error)?;
+            return Ok(BlockPageCa { cert_path, cert, key, configs: Mutex::new(HashMap::new()) });
+        }
+
+        let key = KeyPair::generate().map_err(cert_error)?;
+        let cert = ca_params().self_signed(&key).map_err(cert_error)?;
+        fs::create_dir_all(data_dir)?;
+        fs::write(&cert_path, cert.pem())?;
+        fs::write(&key_path, key.serialize_pem())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&key_path, fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(BlockPageCa { cert_path, cert, key, configs: Mutex::new(HashMap::new()) })
+    }
+
+    pub fn cert_path(&self) -> &Path {
+        &self.cert_path
+    }
+
+    /// TLS configuration presenting a certificate for `domain`, issued the first time it is asked for.
+    pub fn server_config(&self, domain: &str) -> Result<Arc<ServerConfig>, BlockingError> {
+        if let Some(config) = self.configs.lock().unwrap().get(domain) {
+            return Ok(config.clone());
+        }
+        let key = KeyPair::generate().map_err(cert_error)?;
+        let cert = leaf_params(domain)?.signed_by(&key, &self.cert, &self.key).map_err(cert_error)?;
+        let private_key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der()));
+        let provider = Arc::new(tokio_rustls::rustls::crypto::ring::default_provider());
+        let mut config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .and_then(|builder| builder.with_no_client_auth().with_single_cert(vec![cert.der().clone()], private_key))
+            .map_err(cert_error)?;
+        // The block server only speaks HTTP/1.1
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        let config = Arc::new(config);
+        self.configs.lock().unwrap().insert(domain.to_string(), config.clone());
+        Ok(config)
+    }
+}
+
+fn cert_error(err: impl std::fmt::Display) -> BlockingError {
+    BlockingError { message: format!("Could not create the block page certificate: {}", err), permission_denied: false }
+}
+
+/// Midnight UTC `days` from today
+fn date(days: i64) -> time::OffsetDateTime {
+    use chrono::Datelike;
+
+    let day = (chrono::Utc::now() + chrono::Duration::days(days)).date_naive();
+    rcgen::date_time_ymd(day.year(), day.month() as u8, day.day() as u8)
+}
+
+fn ca_params() -> CertificateParams {
+    let mut params = CertificateParams::default();
+    params.distinguished_name.push(DnType::CommonName, CA_COMMON_NAME);
+    params.distinguished_name.push(DnType::OrganizationName, "Bastion");
+    // Path length 0: the CA can only sign leaf certificates
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    params.not_before = date(-1);
+    params.not_after = date(CA_VALIDITY_DAYS);
+    params
+}
+
+fn leaf_params(domain: &str) -> Result<CertificateParams, BlockingError> {
+    let mut params = CertificateParams::new(vec![domain.to_string()]).map_err(cert_error)?;
+    params.distinguished_name.push(DnType::CommonName, domain);
+    params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    params.not_before = date(-1);
+    params.not_after = date(LEAF_VALIDITY_DAYS);
+    Ok(params)
+}
+
+/// Where the CA is copied for the system trust store on Linux, with the command that rebuilds it
+#[cfg(target_os = "linux")]
+fn linux_anchor() -> Option<(PathBuf, &'static [&'static str])> {
+    const ANCHORS: &[(&str, &str, &[&str])] = &[
+        ("/usr/local/share/ca-certificates", "bastion-ca.crt", &["update-ca-certificates"]), // Debian, Ubuntu
+        ("/etc/pki/ca-trust/source/anchors", "bastion-ca.pem", &["update-ca-trust"]),        // Fedora, RHEL
+        ("/etc/ca-certificates/trust-source/anchors", "bastion-ca.crt", &["trust", "extract-compat"]), // Arch
+    ];
+    ANCHORS
+        .iter()
+        .find(|(dir, _, _)| Path::new(dir).is_dir())
+        .map(|(dir, file, command)| (Path::new(dir).join(file), *command))
+}
+
+/// The current user's NSS database, where Chromium on Linux looks for extra CAs
+#[cfg(target_os = "linux")]
+fn nss_database() -> Option<String> {
+    std::env::var_os("HOME").map(|home| format!("sql:{}", Path::new(&home).join(".pki/nssdb").display()))
+}
+
+/// Run a trust store command, treating failure as missing admin rights (the usual cause).
+fn run_trust_command(program: &str, args: &[&str]) -> Result<(), BlockingError> {
+    let output = std::process::Command::new(program).args(args).output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(BlockingError {
+        message: format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim()),
+        permission_denied: true,
+    })
+}
+
+/// Add the CA certificate at `cert_path` to the system trust store and Firefox. Needs admin rights.
+pub fn install_ca(cert_path: &Path) -> Result<(), BlockingError> {
+    let path = cert_path.to_string_lossy();
+
+    #[cfg(target_os = "windows")]
+    run_trust_command("certutil", &["-addstore", "-f", "Root", &path])?;
+
+    #[cfg(target_os = "macos")]
+    run_trust_command(
+        "security",
+        &["add-trusted-cert", "-d", "-r", "trustRoot", "-k", "/Library/Keychains/System.keychain", &path],
+    )?;
+
+    #[cfg(target_os = "linux")]
+    {
+        let Some((anchor, command)) = linux_anchor() else {
+            return Err(BlockingError { message: "No system certificate store found".to_string(), permission_denied: false });
+        };
+        fs::copy(cert_path, &anchor)?;
+        run_trust_command(command[0], &command[1..])?;
+        // Chromium ignores the system store; best effort, as it needs `certutil` from the NSS tools
+        if let Some(db) = nss_database() {
+            let _ = run_trust_command("certutil", &["-d", &db, "-A", "-t", "C,,", "-n", CA_COMMON_NAME, "-i", &path]);
+        }
+    }
+
+    blocking::set_firefox_certificate(Some(cert_path))
+}
+
+/// Take the CA out of every store `install_ca` added it to.
+pub fn uninstall_ca() -> Result<(), BlockingError> {
+    #[cfg(target_os = "windows")]
+    run_trust_command("certutil", &["-delstore", "Root", CA_COMMON_NAME])?;
+
+    #[cfg(target_os = "macos")]
+    run_trust_command("security", &["delete-certificate", "-c", CA_COMMON_NAME, "/Library/Keychains/System.keychain"])?;
+
+    #[cfg(target_os = "linux")]
+    if let Some((anchor, command)) = linux_anchor().filter(|(anchor, _)| anchor.exists()) {
+        fs::remove_file(anchor)?;
+        run_trust_command(command[0], &command[1..])?;
+        if let Some(db) = nss_database() {
+            let _ = run_trust_command("certutil", &["-d", &db, "-D", "-n", CA_COMMON_NAME]);
+        }
+    }
+
+    blocking::set_firefox_certificate(None)
+}
+
+/// Delete the CA files, so a new CA (with a new key) is generated if the page is enabled again.
+pub fn remove_ca(data_dir: &Path) {
+    let _ = fs::remove_file(data_dir.join(CA_CERT_FILE));
+    let _ = fs::remove_file(data_dir.join(CA_KEY_FILE));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_page_ca() {
+        let dir = std::env::temp_dir().join(format!("bastion-tls-test-{}", std::process::id()));
+        let ca = BlockPageCa::load_or_create(&dir).unwrap();
+        let pem = fs::read_to_string(ca.cert_path()).unwrap();
+        assert!(pem.starts_with("-----BEGIN CERTIFICATE-----"));
+
+        let config = ca.server_config("reddit.com").unwrap();
+        assert!(Arc::ptr_eq(&config, &ca.server_config("reddit.com").unwrap()));
+        assert_eq!(config.alpn_protocols, vec![b"http/1.1".to_vec()]);
+
+        // Loading again reuses the stored CA instead of generating a new one
+        BlockPageCa::load_or_create(&dir).unwrap();
+        assert_eq!(fs::read_to_string(dir.join(CA_CERT_FILE)).unwrap(), pem);
+        remove_ca(&dir);
+        assert!(!dir.join(CA_KEY_FILE).exists());
+        let _ = fs::remove_dir(&dir);
+    }
+}
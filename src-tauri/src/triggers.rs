@@ -0,0 +1,140 @@
+//! Triggers module - Start a focus session when a process starts
+//!
+//! Triggers are stored as JSON in the `session_triggers` setting, e.g. "when `code` starts,
+//! begin a 50 minute session". The enforcement loop passes each process snapshot to
+//! `TriggerMonitor::poll`, which fires a trigger when a matching process appears that wasn't
+//! running at the previous check, at most once per trigger cooldown.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+pub const TRIGGERS_SETTING: &str = "session_triggers";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionTrigger {
+    pub id: String,
+    pub enabled: bool,
+    pub process_name: String, // case-insensitive, e.g. "code" or "winword.exe"
+    /// Only match when the command line contains this (e.g. a document path)
+    #[serde(default)]
+    pub argument: Option<String>,
+    pub session_name: String,
+    pub duration_minutes: i64,
+    #[serde(default)]
+    pub hardcore: bool,
+    #[serde(default)]
+    pub blocklist_id: Option<String>,
+    pub cooldown_minutes: i64, // minimum time between two sessions started by this trigger
+}
+
+impl SessionTrigger {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.process_name.trim().is_empty() {
+            return Err("Trigger process name can't be empty".to_string());
+        }
+        if !(1..=24 * 60).contains(&self.duration_minutes) {
+            return Err("Trigger session length must be between 1 minute and 24 hours".to_string());
+        }
+        if self.cooldown_minutes < 0 {
+            return Err("Trigger cooldown can't be negative".to_string());
+        }
+        Ok(())
+    }
+
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        process.name.eq_ignore_ascii_case(self.process_name.trim())
+            && self.argument.as_deref().is_none_or(|arg| {
+                process.command_line.to_lowercase().contains(&arg.to_lowercase())
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub name: String,
+    pub command_line: String,
+}
+
+#[derive(Default)]
+pub struct TriggerMonitor {
+    /// Ids of triggers whose process was running at the previous poll; `None` before the first poll
+    matched: Mutex<Option<HashSet<String>>>,
+    last_fired: Mutex<HashMap<String, i64>>,
+}
+
+impl TriggerMonitor {
+    /// Return the first trigger that should start a session now. `can_start` is false while a
+    /// session is already running; processes are still tracked so they don't fire later.
+    pub fn poll(&self, triggers: &[SessionTrigger], processes: &[ProcessInfo], now: i64, can_start: bool) -> Option<SessionTrigger> {
+        let running: HashSet<String> = triggers
+            .iter()
+            .filter(|t| t.enabled && processes.iter().any(|p| t.matches(p)))
+            .map(|t| t.id.clone())
+            .collect();
+        // Processes already running when Bastion starts don't count as starting
+        let previous = self.matched.lock().unwrap().replace(running.clone());
+        let previous = previous?;
+        if !can_start {
+            return None;
+        }
+
+        let mut last_fired = self.last_fired.lock().unwrap();
+        let fired = triggers.iter().find(|t| {
+            running.contains(&t.id)
+                && !previous.contains(&t.id)
+                && last_fired.get(&t.id).is_none_or(|at| now - at >= t.cooldown_minutes * 60)
+        })?;
+        last_fired.insert(fired.id.clone(), now);
+        Some(fired.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ide_trigger() -> SessionTrigger {
+        SessionTrigger {
+            id: "ide".to_string(),
+            enabled: true,
+            process_name: "code".to_string(),
+            argument: None,
+            session_name: "Coding".to_string(),
+            duration_minutes: 50,
+            hardcore: false,
+            blocklist_id: None,
+            cooldown_minutes: 60,
+        }
+    }
+
+    fn process(name: &str) -> ProcessInfo {
+        ProcessInfo { name: name.to_string(), command_line: String::new() }
+    }
+
+    #[test]
+    fn test_trigger_fires_on_start_with_cooldown() {
+        let monitor = TriggerMonitor::default();
+        let triggers = vec![ide_trigger()];
+
+        // First poll only records what is running
+        assert!(monitor.poll(&triggers, &[], 0, true).is_none());
+        assert!(monitor.poll(&triggers, &[process("Code")], 10, true).is_some());
+        // Still running: no new start
+        assert!(monitor.poll(&triggers, &[process("Code")], 20, true).is_none());
+        // Restarted within the cooldown
+        assert!(monitor.poll(&triggers, &[], 30, true).is_none());
+        assert!(monitor.poll(&triggers, &[process("Code")], 40, true).is_none());
+        // Restarted after the cooldown
+        assert!(monitor.poll(&triggers, &[], 4000, true).is_none());
+        assert!(monitor.poll(&triggers, &[process("Code")], 4010, true).is_some());
+    }
+
+    #[test]
+    fn test_trigger_validation() {
+        assert!(ide_trigger().validate().is_ok());
+        assert!(SessionTrigger { duration_minutes: 0, ..ide_trigger() }.validate().is_err());
+        assert!(SessionTrigger { process_name: " ".to_string(), ..ide_trigger() }.validate().is_err());
+        assert!(SessionTrigger { cooldown_minutes: -1, ..ide_trigger() }.validate().is_err());
+    }
+}
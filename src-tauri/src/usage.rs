@@ -0,0 +1,284 @@
+//! Usage module - Productivity classification of processes and websites for usage stats
+//!
+//! App time comes from the usage tracker (`run_usage_tracker`), which samples the focused
+//! process every few seconds, skips samples while the user is idle, and adds the time to
+//! `app_usage` about once a minute. Site time is reported by the browser companion.
+
+use crate::storage::{AppUsage, Database};
+use crate::{foreground, idle, AppState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const USAGE_TRACKING_SETTING: &str = "usage_tracking";
+
+/// Samples after this long without keyboard or mouse input aren't counted
+const IDLE_CUTOFF_SECS: u64 = 120;
+/// How often the time collected in memory is written to `app_usage`
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageTrackingConfig {
+    pub enabled: bool,
+    pub sample_seconds: u64,
+}
+
+impl Default for UsageTrackingConfig {
+    fn default() -> Self {
+        UsageTrackingConfig { enabled: true, sample_seconds: 5 }
+    }
+}
+
+impl UsageTrackingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=60).contains(&self.sample_seconds) {
+            return Err("The sampling interval must be between 1 and 60 seconds".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub fn load_config(db: &Database) -> UsageTrackingConfig {
+    db.get_setting(USAGE_TRACKING_SETTING)
+        .unwrap_or(None)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Seconds a sample stands for: the time since the previous one, capped at two intervals so
+/// sleep or a stalled sample isn't counted as use.
+fn credited_seconds(since_last_sample: Duration, sample_seconds: u64) -> i64 {
+    since_last_sample.as_secs().min(sample_seconds * 2) as i64
+}
+
+/// The focused process, unless the user is away from the keyboard and mouse.
+fn sample_foreground() -> Option<String> {
+    if idle::idle_seconds().is_some_and(|idle| idle >= IDLE_CUTOFF_SECS) {
+        return None;
+    }
+    foreground::foreground_process()
+}
+
+/// Sample the foreground app forever, adding its time to `app_usage`.
+pub async fn run_usage_tracker(state: Arc<AppState>) {
+    let mut pending: HashMap<String, i64> = HashMap::new();
+    let mut last_sample = Instant::now();
+    let mut last_flush = Instant::now();
+    loop {
+        let config = load_config(&state.db);
+        tokio::time::sleep(Duration::from_secs(config.sample_seconds)).await;
+        let seconds = credited_seconds(last_sample.elapsed(), config.sample_seconds);
+        last_sample = Instant::now();
+
+        if config.enabled {
+            // Shells out on macOS and Linux, so it stays off the async workers
+            let sample = tauri::async_runtime::spawn_blocking(sample_foreground).await.ok().flatten();
+            if let Some(process_name) = sample {
+                *pending.entry(process_name).or_default() += seconds;
+            }
+        }
+        if last_flush.elapsed() >= FLUSH_INTERVAL {
+            for (process_name, seconds) in pending.drain() {
+                if let Err(e) = state.db.add_app_usage(&process_name, seconds) {
+                    eprintln!("[Bastion] Could not record app usage: {}", e);
+                }
+            }
+            last_flush = Instant::now();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Productivity {
+    Productive,
+    Neutral,
+    Distracting,
+}
+
+impl Productivity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Productivity::Productive => "productive",
+            Productivity::Neutral => "neutral",
+            Productivity::Distracting => "distracting",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "productive" => Some(Productivity::Productive),
+            "neutral" => Some(Productivity::Neutral),
+            "distracting" => Some(Productivity::Distracting),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessClassification {
+    pub process_name: String,
+    pub classification: Productivity,
+    pub is_override: bool, // true if set by the user rather than the built-in map
+}
+
+/// Built-in classification for common processes, keyed by normalized name (see `normalize_process_name`).
+/// Anything not listed here (and not overridden by the user) is considered neutral.
+const DEFAULT_CLASSIFICATIONS: &[(&str, Productivity)] = &[
+    // Development
+    ("code", Productivity::Productive),
+    ("devenv", Productivity::Productive),
+    ("idea64", Productivity::Productive),
+    ("pycharm64", Productivity::Productive),
+    ("webstorm64", Productivity::Productive),
+    ("rustrover64", Productivity::Productive),
+    ("sublime_text", Productivity::Productive),
+    ("windowsterminal", Productivity::Productive),
+    ("alacritty", Productivity::Productive),
+    ("gnome-terminal-server", Productivity::Productive),
+    ("iterm2", Productivity::Productive),
+    // Office & writing
+    ("winword", Productivity::Productive),
+    ("excel", Productivity::Productive),
+    ("powerpnt", Productivity::Productive),
+    ("onenote", Productivity::Productive),
+    ("obsidian", Productivity::Productive),
+    ("notion", Productivity::Productive),
+    ("figma", Productivity::Productive),
+    // Communication
+    ("slack", Productivity::Neutral),
+    ("ms-teams", Productivity::Neutral),
+    ("outlook", Productivity::Neutral),
+    ("zoom", Productivity::Neutral),
+    // Entertainment & social
+    ("discord", Productivity::Distracting),
+    ("spotify", Productivity::Distracting),
+    ("steam", Productivity::Distracting),
+    ("steamwebhelper", Productivity::Distracting),
+    ("epicgameslauncher", Productivity::Distracting),
+    ("battle.net", Productivity::Distracting),
+    ("riotclientservices", Productivity::Distracting),
+    ("telegram", Productivity::Distracting),
+    ("whatsapp", Productivity::Distracting),
+    ("vlc", Productivity::Distracting),
+    ("netflix", Productivity::Distracting),
+    // Websites (reported by the browser extension, keyed by domain without "www.")
+    ("github.com", Productivity::Productive),
+    ("stackoverflow.com", Productivity::Productive),
+    ("docs.rs", Productivity::Productive),
+    ("youtube.com", Productivity::Distracting),
+    ("reddit.com", Productivity::Distracting),
+    ("twitter.com", Productivity::Distracting),
+    ("x.com", Productivity::Distracting),
+    ("instagram.com", Productivity::Distracting),
+    ("facebook.com", Productivity::Distracting),
+    ("tiktok.com", Productivity::Distracting),
+    ("netflix.com", Productivity::Distracting),
+    ("twitch.tv", Productivity::Distracting),
+];
+
+/// Normalize a process name for classification lookups ("Code.exe" -> "code").
+pub fn normalize_process_name(process_name: &str) -> String {
+    let lower = process_name.trim().to_lowercase();
+    lower.strip_suffix(".exe").unwrap_or(&lower).to_string()
+}
+
+/// Classify a process, preferring user overrides (keyed by normalized name) over the built-in map.
+pub fn classify(process_name: &str, overrides: &HashMap<String, String>) -> Productivity {
+    let name = normalize_process_name(process_name);
+
+    if let Some(classification) = overrides.get(&name).and_then(|c| Productivity::parse(c)) {
+        return classification;
+    }
+
+    DEFAULT_CLASSIFICATIONS
+        .iter()
+        .find(|(default_name, _)| *default_name == name)
+        .map(|(_, classification)| *classification)
+        .unwrap_or(Productivity::Neutral)
+}
+
+/// The built-in map merged with user overrides, for display in settings.
+pub fn list_classifications(overrides: &HashMap<String, String>) -> Vec<ProcessClassification> {
+    let mut list: Vec<ProcessClassification> = DEFAULT_CLASSIFICATIONS
+        .iter()
+        .filter(|(name, _)| !overrides.contains_key(*name))
+        .map(|(name, classification)| ProcessClassification {
+            process_name: name.to_string(),
+            classification: *classification,
+            is_override: false,
+        })
+        .collect();
+
+    for (name, classification) in overrides {
+        if let Some(classification) = Productivity::parse(classification) {
+            list.push(ProcessClassification {
+                process_name: name.clone(),
+                classification,
+                is_override: true,
+            });
+        }
+    }
+
+    list.sort_by(|a, b| a.process_name.cmp(&b.process_name));
+    list
+}
+
+/// Time per productivity category for one day or week
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageBucket {
+    pub period: String,
+    pub productive_secs: i64,
+    pub neutral_secs: i64,
+    pub distracting_secs: i64,
+}
+
+/// Fold per-process usage rows into per-period category totals, keeping the row order.
+pub fn breakdown(rows: &[AppUsage], overrides: &HashMap<String, String>) -> Vec<UsageBucket> {
+    let mut buckets: Vec<UsageBucket> = Vec::new();
+
+    for row in rows {
+        let index = match buckets.iter().position(|b| b.period == row.date) {
+            Some(index) => index,
+            None => {
+                buckets.push(UsageBucket { period: row.date.clone(), ..Default::default() });
+                buckets.len() - 1
+            }
+        };
+        let bucket = &mut buckets[index];
+
+        match classify(&row.process_name, overrides) {
+            Productivity::Productive => bucket.productive_secs += row.seconds,
+            Productivity::Neutral => bucket.neutral_secs += row.seconds,
+            Productivity::Distracting => bucket.distracting_secs += row.seconds,
+        }
+    }
+
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_sampling() {
+        assert_eq!(credited_seconds(Duration::from_millis(5200), 5), 5);
+        // Woke from sleep: only the last stretch counts
+        assert_eq!(credited_seconds(Duration::from_secs(3600), 5), 10);
+        assert!(UsageTrackingConfig::default().validate().is_ok());
+        assert!(UsageTrackingConfig { enabled: true, sample_seconds: 0 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_classify_with_overrides() {
+        let mut overrides = HashMap::new();
+        assert_eq!(classify("Code.exe", &overrides), Productivity::Productive);
+        assert_eq!(classify("Discord.exe", &overrides), Productivity::Distracting);
+        assert_eq!(classify("unknown-tool", &overrides), Productivity::Neutral);
+
+        overrides.insert("discord".to_string(), "productive".to_string());
+        assert_eq!(classify("discord", &overrides), Productivity::Productive);
+    }
+}
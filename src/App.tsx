@@ -3,13 +3,14 @@ import { useState, useEffect } from 'react';
 import TitleBar from './components/TitleBar';
 import Sidebar from './components/Sidebar';
 import WarningModal from './components/WarningModal';
+import PasswordModal from './components/PasswordModal';
 import Home from './pages/Home';
 import Blocks from './pages/Blocks';
 import Sessions from './pages/Sessions';
 import Pomodoro from './pages/Pomodoro';
 import Settings from './pages/Settings';
 import Onboarding from './pages/Onboarding';
-import { securityApi, settingsApi } from './lib/api';
+import { securityApi, settingsApi, systemApi } from './lib/api';
 import { Loader2 } from 'lucide-react';
 import { listen } from '@tauri-apps/api/event';
 
@@ -20,12 +21,16 @@ function App() {
     const [showWarning, setShowWarning] = useState(false);
     const [blockedItems, setBlockedItems] = useState<string[]>([]);
     const [customWarningText, setCustomWarningText] = useState<string>('');
+    const [showQuitModal, setShowQuitModal] = useState(false);
 
     useEffect(() => {
         // Check if user has completed onboarding via Tauri backend
         const checkOnboarding = async () => {
             try {
                 const onboarded = await securityApi.isOnboarded();
+                // Return to the page that was open before an elevated relaunch
+                const restored = await systemApi.takeRestoreUiState().catch(() => null);
+                if (restored?.path) window.history.replaceState(null, '', restored.path);
                 setIsOnboarded(onboarded);
             } catch (err) {
                 console.error('Failed to check onboarding status:', err);
@@ -59,8 +64,12 @@ function App() {
             }
         });
 
+        // The tray asks for the master password before quitting during a session
+        const unlistenQuit = listen('quit-requested', () => setShowQuitModal(true));
+
         return () => {
             unlisten.then(f => f());
+            unlistenQuit.then(f => f());
         };
     }, []);
 
@@ -129,6 +138,19 @@ function App() {
                         blockedItems={blockedItems}
                         customMessage={customWarningText}
                     />
+
+                    <PasswordModal
+                        isOpen={showQuitModal}
+                        onClose={() => setShowQuitModal(false)}
+                        onConfirm={async (password) => {
+                            const { token } = await settingsApi.authenticate(password);
+                            await systemApi.quit(token);
+                        }}
+                        title="Quit Bastion"
+                        message="A focus session is active. Enter your Master Password to quit Bastion, or close this dialog to keep it running in the tray."
+                        confirmLabel="Quit"
+                        type="verify"
+                    />
                 </div>
             </div>
         </BrowserRouter>
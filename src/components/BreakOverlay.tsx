@@ -0,0 +1,73 @@
+import { useState, useEffect } from 'react';
+import { motion } from 'framer-motion';
+import { Waves, X } from 'lucide-react';
+import { listen } from '@tauri-apps/api/event';
+import { pomodoroApi, quotesApi, settingsApi, PomodoroState, Quote } from '../lib/api';
+
+/**
+ * Full-screen break window opened by the backend when a pomodoro break starts.
+ * In strict mode there is no way to dismiss it; the backend closes it when the break ends.
+ */
+export default function BreakOverlay() {
+    const [timeLeft, setTimeLeft] = useState(0);
+    const [isStrict, setIsStrict] = useState(true);
+    const [error, setError] = useState('');
+    const [quote, setQuote] = useState<Quote | null>(null);
+
+    useEffect(() => {
+        pomodoroApi.getState().then(state => setTimeLeft(state.time_remaining)).catch(() => { });
+        settingsApi.get('break_overlay_strict').then(value => setIsStrict(value === 'true')).catch(() => { });
+        quotesApi.getRandom().then(setQuote).catch(() => { });
+
+        const unlisten = listen<PomodoroState>('pomodoro-tick', (event) => {
+            setTimeLeft(event.payload.time_remaining);
+        });
+
+        return () => {
+            unlisten.then(f => f());
+        };
+    }, []);
+
+    const dismiss = async () => {
+        try {
+            await pomodoroApi.closeBreakOverlay();
+        } catch (err) {
+            setError(String(err));
+        }
+    };
+
+    const minutes = Math.floor(timeLeft / 60).toString().padStart(2, '0');
+    const seconds = (timeLeft % 60).toString().padStart(2, '0');
+
+    return (
+        <div className="h-screen w-screen bg-black flex items-center justify-center select-none">
+            <motion.div
+                initial={{ opacity: 0, scale: 0.95 }}
+                animate={{ opacity: 1, scale: 1 }}
+                className="flex flex-col items-center gap-8 text-center"
+            >
+                <div className="p-5 rounded-3xl bg-white/5 border border-white/10">
+                    <Waves className="w-12 h-12 text-white" />
+                </div>
+                <h1 className="text-4xl font-black text-white uppercase tracking-tight">Time to rest</h1>
+                <div className="text-8xl font-black text-white tabular-nums">{minutes}:{seconds}</div>
+                <p className="text-bastion-muted text-lg font-medium max-w-md">
+                    {quote ? `“${quote.text}”` : 'Step away from the screen. Stretch, drink some water, look out of a window.'}
+                </p>
+                {quote?.author && (
+                    <p className="text-white/40 text-xs font-black uppercase tracking-widest">{quote.author}</p>
+                )}
+                {!isStrict && (
+                    <button
+                        onClick={dismiss}
+                        className="flex items-center gap-2 px-6 py-3 rounded-2xl bg-white/5 hover:bg-white/10 border border-white/10 text-white/60 text-xs font-black uppercase tracking-widest transition-colors"
+                    >
+                        <X className="w-4 h-4" />
+                        Dismiss
+                    </button>
+                )}
+                {error && <div className="text-red-400 text-sm">{error}</div>}
+            </motion.div>
+        </div>
+    );
+}
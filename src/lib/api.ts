@@ -1,17 +1,131 @@
 /**
  * Tauri API hooks for communicating with the Rust backend
  */
-import { invoke } from '@tauri-apps/api/core';
+import { invoke as tauriInvoke, type InvokeArgs } from '@tauri-apps/api/core';
+
+// ============= Errors =============
+
+/** Stable error codes returned by backend commands (see `src-tauri/src/error.rs`). */
+export type ErrorCode =
+    | 'HARDCORE_LOCKED'
+    | 'BEDTIME_LOCKED'
+    | 'BREAK_LOCKED'
+    | 'PASSWORD_REQUIRED'
+    | 'INVALID_PASSWORD'
+    | 'UNAUTHORIZED'
+    | 'HOSTS_PERMISSION_DENIED'
+    | 'DB_BUSY'
+    | 'DATABASE'
+    | 'UNLOCK_DELAY'
+    | 'INVALID_STATE'
+    | 'INVALID_INPUT'
+    | 'INVALID_DOMAIN'
+    | 'DEMO_MODE'
+    | 'INTERNAL';
+
+/** Thrown by every API call that fails. Branch on `code`; `String(err)` gives the English message. */
+export class CommandError extends Error {
+    constructor(public code: ErrorCode, message: string) {
+        super(message);
+        this.name = 'CommandError';
+    }
+
+    toString() {
+        return this.message;
+    }
+}
+
+async function invoke<T>(cmd: string, args?: InvokeArgs): Promise<T> {
+    try {
+        return await tauriInvoke<T>(cmd, args);
+    } catch (err) {
+        if (err && typeof err === 'object' && 'code' in err && 'message' in err) {
+            const { code, message } = err as { code: ErrorCode; message: string };
+            throw new CommandError(code, message);
+        }
+        throw err;
+    }
+}
 
 // ============= Types =============
 
-/** Represents a domain blocked by the hosts file. */
+/**
+ * 'hosts' blocks the whole domain through the hosts file. The soft modes leave the
+ * domain reachable and let the browser extension close or redirect matching tabs.
+ */
+export type BlockMode = 'hosts' | 'close_tab' | 'redirect';
+
+/** Represents a blocked domain (or, for soft modes, a domain plus path prefix). */
 export interface BlockedSite {
     id: number;
     domain: string;
     category: string;
     enabled: boolean;
     created_at: string;
+    mode: BlockMode;
+    include_subdomains: boolean; // Also blocks known subdomains (m., old., ...)
+}
+
+/**
+ * What a new entry covers: 'registrable' (twitter.com), the exact 'host' (mobile.twitter.com),
+ * or a 'rule' with the path prefix for soft modes (youtube.com/shorts).
+ */
+export type DomainScope = 'registrable' | 'host' | 'rule';
+
+/** A validated domain or pasted URL from the add-site flow. */
+export interface SiteInput {
+    host: string; // Without "www."
+    registrable: string; // Equals `host` when there is no parent domain to offer
+    path: string | null;
+}
+
+/** Result of adding a site; equivalent entries (case, "www.", punycode, URL form) are not duplicated. */
+export type AddSiteResult =
+    | { status: 'added'; id: number }
+    | { status: 'already_blocked'; site: BlockedSite }
+    | { status: 'reenabled'; site: BlockedSite };
+
+export interface AppEnforcement {
+    name: string;
+    pids: number[];
+    killed: number[];
+    method: 'quit' | 'terminate' | 'force' | null;
+    failures: { pid: number; error: string }[]; // e.g. elevated processes: "Access is denied"
+}
+
+/** Background app blocking: on by default, every 3 seconds. */
+export interface AppEnforcementConfig {
+    enabled: boolean; // outside sessions; during sessions, bedtime and category schedules it always runs
+    interval_seconds: number; // 1-60
+}
+
+export interface DevExclusions {
+    domains: string[];
+    ports: number[];
+}
+
+/** A built-in list of distracting sites, added under `category` (social, entertainment, news, games, adult). */
+export interface BlocklistPreset {
+    category: string;
+    name: string;
+    domains: string[];
+}
+
+export type BlocklistFormat = 'json' | 'csv';
+
+/** Outcome of importing a block list. */
+export interface ImportSummary {
+    sites_added: number;
+    apps_added: number;
+    duplicates: number; // already blocked, or repeated in the file
+    invalid: number;
+}
+
+/** A site (subdomains included) that stays reachable during allowlist-only sessions. */
+export interface AllowedSite {
+    id: number;
+    domain: string;
+    created_at: string;
 }
 
 /** Represents an application executable blocked by the process monitor. */
@@ -33,6 +147,64 @@ export interface Session {
     days: string;       // JSON string of days array (e.g. '["Mon", "Tue"]')
     hardcore: boolean;  // If true, cannot be cancelled easily
     enabled: boolean;
+    kind?: 'focus' | 'bedtime'; // Bedtime blocks 'entertainment' sites/apps overnight; defaults to 'focus'
+    delete_at?: number | null;  // Unix timestamp a pending deletion takes effect (hardcore only)
+}
+
+/** A soft-deleted site, app or schedule; restorable for 7 days. */
+export interface DeletedItem {
+    kind: 'site' | 'app' | 'schedule';
+    id: number;
+    name: string; // Domain for sites
+    deleted_at: number; // Unix timestamp
+}
+
+/** QR pairing code for the mobile companion. `payload` is JSON: name, addresses, port, token, expires_at. */
+export interface PairingOffer {
+    payload: string;
+    expires_at: number; // Unix timestamp
+}
+
+/** What the phone sends back after scanning the QR code. */
+export interface PairingConfirmation {
+    token: string;
+    device_id: string;
+    device_name: string;
+}
+
+export interface PairedDevice {
+    id: string;
+    name: string;
+    paired_at: string;
+    last_seen: string | null;
+}
+
+export interface PairingResult {
+    device: PairedDevice;
+    device_token: string; // Bearer token the phone uses from now on
+}
+
+export interface MorningRoutine {
+    enabled: boolean;
+    name: string;
+    duration_minutes: number;
+    hardcore: boolean;
+    window_start: string; // HH:MM, earliest time the routine may start
+    window_end: string;   // HH:MM, latest time the routine may start
+}
+
+export type HookEvent = 'session_start' | 'session_end' | 'hardcore_lock';
+
+/**
+ * A script run by the backend on a session event. It receives `BASTION_EVENT`,
+ * `BASTION_SESSION_NAME`, `BASTION_SESSION_START`, `BASTION_SESSION_END` and
+ * `BASTION_HARDCORE` env vars, and `{ event, session }` as JSON on stdin.
+ */
+export interface ScriptHook {
+    event: HookEvent;
+    command: string; // Path to a script or executable
+    args: string[];
+    enabled: boolean;
 }
 
 export interface ActiveSession {
@@ -42,6 +214,135 @@ export interface ActiveSession {
     end_time: number;
     hardcore: boolean;
     session_type: 'Manual' | 'Scheduled' | 'Pomodoro';
+    extreme: boolean; // System tools are blocked too (opt-in via the `extreme_hardcore` setting)
+    blocklist: BlocklistProfile | null;
+    schedule_id?: number; // the schedule that started it, for scheduled sessions
+    override_policy?: OverridePolicy; // absent means `never`
+    allowlist_only?: boolean; // everything but the allowed sites is blocked
+}
+
+/**
+ * How a hardcore session can still be ended early, chosen when it starts. The password
+ * policies need an unlock token from `authenticate`; the delay and typing challenge come
+ * from `requestUnlockChallenge`.
+ */
+export type OverridePolicy =
+    | { kind: 'never' }
+    | { kind: 'password' }
+    | { kind: 'delay_then_password'; delay_seconds: number }
+    | { kind: 'typing_challenge'; length: number };
+
+/** Starts a focus session when `process_name` starts (at most once per cooldown). */
+export interface SessionTrigger {
+    id: string;
+    enabled: boolean;
+    process_name: string;
+    argument?: string | null; // only match if the command line contains this, e.g. a document path
+    session_name: string;
+    duration_minutes: number;
+    hardcore: boolean;
+    blocklist_id?: string | null;
+    cooldown_minutes: number;
+}
+
+/** Parts of YouTube the extension blocks; direct video links and subscriptions keep working. */
+export interface YouTubeRules {
+    shorts: boolean; // Shorts open as regular videos
+    home_feed: boolean; // Home page goes to subscriptions; recommendations are hidden
+    comments: boolean;
+}
+
+/** Site/app categories a session blocks on top of the enabled block list. */
+export interface BlocklistProfile {
+    id: string;
+    name: string;
+    categories: string[];
+    youtube?: YouTubeRules; // Blocked during the session on top of the global rules
+}
+
+/**
+ * Blocks every site and app in `category` during a daily window, enabled or not.
+ * "Until 18:00" is 00:00-18:00; an end before the start runs past midnight.
+ */
+export interface CategorySchedule {
+    category: string;
+    days: string[]; // "Mon".."Sun", the days the window starts on
+    start_time: string; // HH:MM
+    end_time: string; // HH:MM
+    enabled: boolean;
+}
+
+export interface SessionStats {
+    session_id: string;
+    block_attempts: number;
+    app_kills: number;
+    tamper_attempts: number;
+    clean: boolean;
+}
+
+/** Escalating friction for bypasses: each one this week adds `delay_step_seconds` and `challenge_step_chars`. */
+export interface FrictionPolicy {
+    enabled: boolean;
+    delay_step_seconds: number;
+    max_delay_seconds: number;
+    challenge_step_chars: number;
+    max_challenge_chars: number;
+}
+
+export interface UnlockFriction {
+    bypasses_this_week: number;
+    delay_seconds: number;
+    challenge_length: number;
+}
+
+export interface UnlockChallenge {
+    text: string; // type this back
+    ready_at: number; // unix seconds
+    friction: UnlockFriction;
+}
+
+/** Ending sessions early or disabling sites waits out `delay_seconds` first (see `requestUnlock`). */
+export interface TemptationDelay {
+    enabled: boolean;
+    delay_seconds: number;
+}
+
+export type UnlockTarget =
+    | { kind: 'end_session'; session_id: string }
+    | { kind: 'disable_site'; id: number } // disabling or deleting the site, or moving it off the hosts file
+    | { kind: 'loosen_delay' }
+    | { kind: 'reset_blocks' }
+    | { kind: 'restore_hosts' };
+
+export interface UnlockStatus {
+    target: UnlockTarget;
+    remaining_seconds: number;
+    ready: boolean;
+}
+
+export interface SessionExtension {
+    id: number;
+    session_id: string;
+    session_name: string;
+    minutes: number;
+    hardcore: boolean;
+    extended_at: string;
+}
+
+/** Named timer setup; durations in seconds. */
+export interface PomodoroPreset {
+    name: string;
+    work_seconds: number;
+    short_break_seconds: number;
+    long_break_seconds: number;
+    sessions_until_long_break: number;
+}
+
+/** Shareable snippet produced by `pomodoroApi.exportConfig`. */
+export interface PomodoroExport {
+    bastion_pomodoro: number; // format version
+    config: PomodoroPreset | null;
+    presets: PomodoroPreset[];
 }
 
 export interface PomodoroState {
@@ -53,6 +354,64 @@ export interface PomodoroState {
     completed_sessions: number;
     time_remaining: number;
     is_running: boolean;
+    paused_for_idle: boolean; // paused automatically while the user is away
+}
+
+export interface IdlePauseConfig {
+    enabled: boolean;
+    idle_minutes: number;
+}
+
+export interface WeekTotals {
+    week_start: string; // Monday, YYYY-MM-DD
+    focus_minutes: number;
+    blocks: number;
+    pomodoros: number;
+}
+
+/** A quote from the user's library, shown on the block page and break overlay. */
+export interface Quote {
+    id: number;
+    text: string;
+    author: string | null;
+    enabled: boolean;
+    created_at: string;
+}
+
+/** Blocks and usage per category; events without a matching site/app are 'uncategorized'. */
+export interface CategoryBreakdown {
+    category: string;
+    blocks: number;
+    usage_seconds: number;
+}
+
+export interface TrendPoint {
+    date: string;
+    attempts: number;
+}
+
+/** Clean days (focus time, no early ends or emergency unlocks) in a row. */
+export interface StreakStatus {
+    current: number; // Includes today once it is clean
+    longest: number;
+    today_clean: boolean;
+    freezes_available: number; // One earned per 7 clean days, at most 3; spent automatically on a missed day
+    days_to_next_freeze: number;
+}
+
+export interface StreakFreeze {
+    id: number;
+    kind: 'earned' | 'used';
+    date: string; // Day it was earned on or protected
+    created_at: string;
+}
+
+export interface WeekComparison {
+    current: WeekTotals;
+    previous: WeekTotals;
+    focus_minutes_change: number | null;
+    blocks_change: number | null;
+    pomodoros_change: number | null;
 }
 
 export interface BlockEvent {
@@ -60,17 +419,270 @@ export interface BlockEvent {
     target: string;
     target_type: string;
     blocked_at: string;
+    session_id: string | null; // focus session active at the time
+    device_id: string | null; // paired phone that reported it; null for this computer
+    category: string | null; // of the blocked site/app when the event was logged
+}
+
+export interface SessionBlockSplit {
+    in_session: number;
+    outside_session: number;
 }
 
 export interface FocusStats {
     date: string;
     minutes_protected: number;
     blocks_count: number;
+    pomodoros_completed: number;
+}
+
+export interface DailySummary {
+    date: string;
+    focus_minutes: number;
+    blocks: number;
+    pomodoros: number;
+    goal_minutes: number;
+    goal_percent: number; // capped at 100
+}
+
+export interface DailySummaryConfig {
+    enabled: boolean;
+    time: string; // HH:MM
+    goal_minutes: number;
+}
+
+/**
+ * Appends the day's summary to a Markdown daily note (e.g. an Obsidian vault) at `time`.
+ * `template` and `file_name` take the placeholders {{date}}, {{focus_time}}, {{focus_minutes}},
+ * {{goal_percent}}, {{pomodoros}}, {{blocks}}, {{sessions}} and {{top_blocks}}.
+ */
+export interface DailyNoteConfig {
+    enabled: boolean;
+    folder: string; // vault folder, must exist
+    file_name: string; // relative to `folder`, e.g. "Daily/{{date}}.md"
+    time: string; // HH:MM
+    template: string;
+}
+
+export interface PomodoroDay {
+    date: string;
+    completed: number;
+}
+
+export interface Task {
+    id: number;
+    title: string;
+    completed: boolean;
+    created_at: string;
+    focus_minutes: number; // Total focus time attributed to this task
+}
+
+/** An outgoing integration request waiting to be delivered (e.g. a Toggl time entry). */
+export interface QueuedPush {
+    id: number;
+    provider: string;
+    payload: string;
+    attempts: number;
+    last_error: string | null;
+}
+
+/**
+ * Beeminder goal fed by finished sessions: focused minutes, or 1 per session that ran its
+ * full length. The auth token is kept in the OS keyring (see `setBeeminderToken`).
+ */
+export interface BeeminderConfig {
+    enabled: boolean;
+    username: string;
+    goal: string; // goal slug
+    metric: 'minutes' | 'sessions';
+}
+
+/**
+ * Habitica tasks scored "up": `session_task_id` per session run to its full length,
+ * `goal_task_id` once a day when the daily focus goal is met. The API token is kept in
+ * the OS keyring (see `setHabiticaApiToken`).
+ */
+export interface HabiticaConfig {
+    enabled: boolean;
+    user_id: string;
+    session_task_id: string | null;
+    goal_task_id: string | null;
+}
+
+/**
+ * Opt-in friend leaderboard. Only this week's focus minutes are published, under the random
+ * `user_id` (assigned when saving) and `display_name`; friends are followed by their ids.
+ */
+export interface LeaderboardConfig {
+    enabled: boolean;
+    endpoint: string;
+    display_name: string;
+    user_id: string;
+    friend_ids: string[];
+}
+
+export interface LeaderboardEntry {
+    id: string;
+    name: string;
+    focus_minutes: number;
+    is_me: boolean;
+}
+
+export interface Leaderboard {
+    week_start: string; // YYYY-MM-DD (Monday)
+    entries: LeaderboardEntry[]; // most focus first
+}
+
+export type Productivity = 'productive' | 'neutral' | 'distracting';
+
+export interface ProcessClassification {
+    process_name: string;
+    classification: Productivity;
+    is_override: boolean; // true if set by the user rather than the built-in map
+}
+
+/** Time per productivity category for one day or week. */
+export interface UsageBucket {
+    period: string;
+    productive_secs: number;
+    neutral_secs: number;
+    distracting_secs: number;
+}
+
+export interface SiteUsage {
+    domain: string;
+    seconds: number;
+}
+
+export interface AppUsageTotal {
+    process_name: string;
+    seconds: number;
+}
+
+/** Foreground app sampling; idle time isn't counted. */
+export interface UsageTrackingConfig {
+    enabled: boolean;
+    sample_seconds: number;
+}
+
+export interface MicroBreakConfig {
+    enabled: boolean;
+    interval_secs: number;
+    duration_secs: number;
+    snooze_secs: number;
+    max_snoozes: number; // Snoozes allowed per break
+}
+
+/** Emitted as the `micro-break` event when a break starts or ends. */
+export interface MicroBreakState {
+    config: MicroBreakConfig;
+    next_break_in: number;
+    break_remaining: number | null; // Set while a break is running
+    snoozes_used: number;
+}
+
+export interface MicroBreakStats {
+    date: string;
+    taken: number;
+    skipped: number;
+    snoozed: number;
+}
+
+export interface InstalledApp {
+    name: string;
+    id: string; // process/executable name (Windows: Start menu AppID)
+    running: boolean;
+}
+
+/** A `searchApps` result; `source` is where the entry came from (blocked wins over installed). */
+export interface AppMatch {
+    name: string;
+    id: string; // process name to block
+    source: 'blocked' | 'installed' | 'running';
+    running: boolean;
+    score: number;
 }
 
 export interface RunningProcess {
     pid: number;
     name: string;
+    exe: string | null; // full executable path, for getProcessIcon
+    parent_pid: number | null;
+    window_title: string | null; // main window title, where the platform exposes it
+}
+
+export interface ListenerStatus {
+    port: number; // 80 or 443
+    address: string;
+    bound_port: number | null; // null if binding failed
+    redirected: boolean;
+    holder: string | null; // program holding `port`
+    error: string | null;
+}
+
+export type PortConflictMode = 'skip' | 'alternate_port' | 'firewall_redirect';
+
+export interface BlockServerConfig {
+    on_conflict: PortConflictMode;
+    http_alternate_port: number;
+    https_alternate_port: number;
+}
+
+/**
+ * `dns` answers blocked domains from a local resolver instead of writing the hosts file;
+ * `pac` sets a proxy auto-config file as the system proxy, which needs no admin rights.
+ */
+export type BlockingBackend = 'hosts' | 'dns' | 'pac';
+
+export interface DnsSinkholeConfig {
+    port: number;
+    upstream: string; // "ip" or "ip:port"
+    answer: 'nxdomain' | 'localhost';
+}
+
+export interface DnsSinkholeStatus {
+    listening_port: number | null;
+    domains: number;
+    error: string | null;
+}
+
+export interface HttpsBlockPageStatus {
+    enabled: boolean;
+    certificate_path: string | null; // the local CA certificate, for trusting it by hand
+}
+
+export interface ProtectionStatus {
+    level: 'healthy' | 'degraded';
+    hosts_repaired: boolean; // the hosts section was modified elsewhere and rewritten by this check
+    is_admin: boolean;
+    hosts_writable: boolean;
+    hosts_section_present: boolean;
+    hosts_sync_pending: boolean; // queued until Bastion has admin rights
+    block_server: ListenerStatus[];
+    doh_policies: { browser: string; doh_disabled: boolean }[]; // empty where not managed
+    enforcement_loop_alive: boolean;
+    dns_domain: string | null;
+    dns_resolves_to_localhost: boolean | null;
+    issues: string[];
+}
+
+export type SelfTestLayer = 'block_list' | 'dns' | 'connect' | 'block_server';
+
+export interface SelfTestReport {
+    domain: string | null;
+    passed: boolean;
+    failed_layer: SelfTestLayer | null;
+    steps: { layer: SelfTestLayer; ok: boolean; detail: string }[];
+}
+
+export interface TelemetryConfig {
+    enabled: boolean;
+    endpoint: string; // OTLP/HTTP traces endpoint
+}
+
+export interface UnlockToken {
+    token: string;
+    expires_in: number; // seconds
 }
 
 // ============= Security API =============
@@ -82,17 +694,81 @@ export const securityApi = {
 
 // ============= System API =============
 
+/** What startup repaired after an unclean shutdown; also emitted as `recovery-completed`. */
+export interface RecoveryReport {
+    recovered_at: string; // RFC 3339
+    repaired: string[];
+    resumed_session: string | null; // Name of the hardcore session that was resumed
+}
+
+/** What a clean exit does with the hosts blocks. */
+export type ExitBehavior = 'keep_blocks' | 'remove_blocks';
+
 export const systemApi = {
     isAdmin: () => invoke<boolean>('is_app_admin'),
+    /** Started with `--demo`: sample data, and anything that changes state fails with DEMO_MODE. */
+    isDemoMode: () => invoke<boolean>('is_demo_mode'),
     fixBrowserPolicies: () => invoke<void>('fix_browser_policies'),
+    getSafeSearchEnforced: () => invoke<boolean>('get_safesearch_enforced'),
+    /** SafeSearch (Google/Bing/DuckDuckGo) and YouTube Restricted Mode; needs admin rights. */
+    setSafeSearchEnforced: (enabled: boolean) => invoke<void>('set_safesearch_enforced', { enabled }),
+    /** Checks every blocking layer; `issues` explains a degraded status. */
+    getProtectionStatus: () => invoke<ProtectionStatus>('get_protection_status'),
+    getBlockServerConfig: () => invoke<BlockServerConfig>('get_block_server_config'),
+    /** What to do when another program holds port 80/443. Takes effect after a restart. */
+    setBlockServerConfig: (config: BlockServerConfig) => invoke<void>('set_block_server_config', { config }),
+    getBlockingBackend: () => invoke<BlockingBackend>('get_blocking_backend'),
+    /** Switches backends and re-applies the block list; `pac` also changes the system proxy setting. */
+    setBlockingBackend: (backend: BlockingBackend) => invoke<void>('set_blocking_backend', { backend }),
+    getDnsSinkholeConfig: () => invoke<DnsSinkholeConfig>('get_dns_sinkhole_config'),
+    /** The system (or browser) DNS has to point at 127.0.0.1 on `port` for the sinkhole to see queries. */
+    setDnsSinkholeConfig: (config: DnsSinkholeConfig) => invoke<void>('set_dns_sinkhole_config', { config }),
+    getDnsSinkholeStatus: () => invoke<DnsSinkholeStatus>('get_dns_sinkhole_status'),
+    getHttpsBlockPageStatus: () => invoke<HttpsBlockPageStatus>('get_https_block_page_status'),
+    /** Generates and trusts a local CA so blocked HTTPS sites show the block page. Needs admin rights. */
+    enableHttpsBlockPage: () => invoke<HttpsBlockPageStatus>('enable_https_block_page'),
+    /** Removes the local CA from the trust stores and deletes it. */
+    disableHttpsBlockPage: () => invoke<void>('disable_https_block_page'),
+    /** Requests a blocked domain end to end; `failed_layer` says where blocking broke. */
+    runBlockingSelftest: () => invoke<SelfTestReport>('run_blocking_selftest'),
+    /** Writes a zip for bug reports (secrets redacted) and returns its path. */
+    createDiagnosticsBundle: () => invoke<string>('create_diagnostics_bundle'),
     killBrowsers: () => invoke<number>('kill_browsers'),
+    /** Restarts Bastion with admin rights; the current route is restored in the new instance. */
+    relaunchElevated: () =>
+        invoke<void>('relaunch_elevated', { uiState: JSON.stringify({ path: window.location.pathname }) }),
+    /**
+     * Emergency cleanup: removes everything Bastion wrote to the hosts file, keeping the original
+     * entries and the user's own edits. Blocks return with the next sync; fails during hardcore.
+     */
+    restoreHostsFile: () => invoke<void>('restore_hosts_file'),
+    /** UI state saved by `relaunchElevated`, returned once after the relaunch. */
+    takeRestoreUiState: async (): Promise<{ path?: string } | null> => {
+        const uiState = await invoke<string | null>('take_restore_ui_state');
+        return uiState ? JSON.parse(uiState) : null;
+    },
+    /** Quits the app. Requires an unlock token (see `settingsApi.authenticate`) while a session is active. */
+    quit: (token?: string) => invoke<void>('quit_app', { token }),
+    /** Whether a clean exit leaves the hosts blocks in place or removes them until the next start. */
+    getExitBehavior: () => invoke<ExitBehavior>('get_exit_behavior'),
+    setExitBehavior: (behavior: ExitBehavior) => invoke<void>('set_exit_behavior', { behavior }),
+    /** Report from the last crash recovery, or null if the app has always exited cleanly. */
+    getRecoveryReport: () => invoke<RecoveryReport | null>('get_recovery_report'),
 };
 
 // ============= Blocked Sites API =============
 
 export const blockedSitesApi = {
-    add: (domain: string, category: string = 'other') =>
-        invoke<number>('add_blocked_site', { domain, category }),
+    /**
+     * Accepts a domain or URL; fails with INVALID_DOMAIN. `scope` defaults to 'registrable'.
+     * Pass `reenable` to switch a disabled duplicate back on instead of reporting it.
+     */
+    add: (domain: string, category: string = 'other', scope?: DomainScope, reenable?: boolean) =>
+        invoke<AddSiteResult>('add_blocked_site', { domain, category, scope, reenable }),
+
+    /** Validates input without adding it, to offer the parent domain vs exact host choice. */
+    parseInput: (input: string) =>
+        invoke<SiteInput>('parse_site_input', { input }),
 
     getAll: () =>
         invoke<BlockedSite[]>('get_blocked_sites'),
@@ -102,6 +778,52 @@ export const blockedSitesApi = {
 
     delete: (id: number) =>
         invoke<void>('delete_blocked_site', { id }),
+
+    getPresets: () =>
+        invoke<BlocklistPreset[]>('get_blocklist_presets'),
+
+    /** Blocks every site of a preset, skipping ones already listed; resolves to how many were added. */
+    applyPreset: (category: string) =>
+        invoke<number>('apply_blocklist_preset', { category }),
+
+    exportBlocklist: (format: BlocklistFormat) =>
+        invoke<string>('export_blocklist', { format }),
+
+    /** Merges the file into the block list, or replaces the list when `replace` is set. */
+    importBlocklist: (contents: string, format: BlocklistFormat, replace = false) =>
+        invoke<ImportSummary>('import_blocklist', { contents, format, replace }),
+
+    setMode: (id: number, mode: BlockMode) =>
+        invoke<void>('set_blocked_site_mode', { id, mode }),
+
+    /** Blocks the site's known subdomains too (e.g. m.facebook.com, old.reddit.com), not just www. */
+    setIncludeSubdomains: (id: number, includeSubdomains: boolean) =>
+        invoke<void>('set_blocked_site_subdomains', { id, includeSubdomains }),
+
+    /** Checks a domain or URL against the active block rules (same logic as enforcement). */
+    isBlocked: (domain: string) =>
+        invoke<boolean>('is_domain_blocked', { domain }),
+
+    getDevExclusions: () =>
+        invoke<DevExclusions>('get_dev_exclusions'),
+
+    /** Domains never blocked and ports left free for local dev servers. Locked during hardcore. */
+    setDevExclusions: (exclusions: DevExclusions) =>
+        invoke<void>('set_dev_exclusions', { exclusions }),
+};
+
+// ============= Allowed Sites API =============
+
+export const allowedSitesApi = {
+    getAll: () =>
+        invoke<AllowedSite[]>('get_allowed_sites'),
+
+    /** Adds a domain or URL (its host is kept); rejected during a hardcore session. */
+    add: (domain: string) =>
+        invoke<number>('add_allowed_site', { domain }),
+
+    delete: (id: number) =>
+        invoke<void>('delete_allowed_site', { id }),
 };
 
 // ============= Blocked Apps API =============
@@ -122,13 +844,49 @@ export const blockedAppsApi = {
     getRunningProcesses: () =>
         invoke<RunningProcess[]>('get_running_processes'),
 
-    /** Returns a list of all installed applications (via PowerShell or common paths). */
-    getInstalledApplications: () =>
-        invoke<{ name: string; id: string }[]>('get_installed_applications'),
+    /** Returns the executable's icon as a PNG data URL, or null where unavailable (Linux). */
+    getProcessIcon: (exePath: string) =>
+        invoke<string | null>('get_process_icon', { exePath }),
 
-    /** Manually triggers an enforcement check for blocked apps. Returns killed app names. */
+    /** Fuzzy-searches running, installed and blocked apps ("disc" -> Discord), best match first. */
+    searchApps: (query: string, limit?: number) =>
+        invoke<AppMatch[]>('search_apps', { query, limit }),
+
+    /**
+     * Returns installed applications merged with running ones. Cached by the backend;
+     * pass `refresh` to rescan.
+     */
+    getInstalledApplications: (refresh?: boolean) =>
+        invoke<InstalledApp[]>('get_installed_applications', { refresh }),
+
+    /**
+     * Manually triggers an enforcement check for blocked apps. Returns one result per running
+     * blocked app; the background daemon emits failures as `app-block-failed`.
+     */
     enforceBlocks: () =>
-        invoke<string[]>('enforce_app_blocks'),
+        invoke<AppEnforcement[]>('enforce_app_blocks'),
+
+    getEnforcementConfig: () =>
+        invoke<AppEnforcementConfig>('get_app_enforcement_config'),
+
+    startEnforcement: () =>
+        invoke<void>('start_app_enforcement'),
+
+    /** Stops app blocking outside sessions; rejected while a session, bedtime or category schedule runs. */
+    stopEnforcement: () =>
+        invoke<void>('stop_app_enforcement'),
+
+    /** Seconds between checks (1-60); can't be lengthened during a hardcore session. */
+    setEnforcementInterval: (seconds: number) =>
+        invoke<void>('set_app_enforcement_interval', { seconds }),
+
+    /** App stores and package managers killed during hardcore when `block_installers` is on. */
+    getInstallerBlockList: () =>
+        invoke<string[]>('get_installer_block_list'),
+
+    /** Pass null to restore the default list. */
+    setInstallerBlockList: (processes: string[] | null) =>
+        invoke<void>('set_installer_block_list', { processes }),
 };
 
 // ============= Sessions API =============
@@ -140,14 +898,151 @@ export const sessionsApi = {
     getAll: () =>
         invoke<Session[]>('get_sessions'),
 
+    /**
+     * Edits or enables/disables a schedule. Hardcore schedules are locked while running and
+     * for `schedule_lock_minutes` (default 120) before they start.
+     */
+    update: (session: Session) =>
+        invoke<void>('update_session', { session }),
+
+    /**
+     * Deletes a schedule. Hardcore schedules are only marked for deletion and removed after
+     * `schedule_deletion_delay_hours` (default 24); returns that Unix timestamp, or null.
+     */
     delete: (id: number) =>
-        invoke<void>('delete_session', { id }),
+        invoke<number | null>('delete_session', { id }),
+
+    cancelDeletion: (id: number) =>
+        invoke<void>('cancel_session_deletion', { id }),
+
+    getDeletedItems: () =>
+        invoke<DeletedItem[]>('get_deleted_items'),
+
+    /** Restores the given item, or the most recently deleted one; resolves to null if nothing was restored. */
+    undoDelete: (kind?: DeletedItem['kind'], id?: number) =>
+        invoke<DeletedItem | null>('undo_delete', { kind, id }),
+
+    getCategorySchedules: () =>
+        invoke<CategorySchedule[]>('get_category_schedules'),
+
+    /**
+     * Replaces all category schedules; rejected during a hardcore session. The backend emits
+     * `scheduled-categories-changed` with the categories blocked whenever a window opens or closes.
+     */
+    setCategorySchedules: (schedules: CategorySchedule[]) =>
+        invoke<void>('set_category_schedules', { schedules }),
+
+    getHooks: () =>
+        invoke<ScriptHook[]>('get_script_hooks'),
+
+    /** Replaces all hooks. Rejected during a hardcore session. */
+    setHooks: (scriptHooks: ScriptHook[]) =>
+        invoke<void>('set_script_hooks', { scriptHooks }),
+
+    getMorningRoutine: () =>
+        invoke<MorningRoutine>('get_morning_routine'),
+
+    /** Saves the session started at the first login/unlock of the day. */
+    setMorningRoutine: (routine: MorningRoutine) =>
+        invoke<void>('set_morning_routine', { routine }),
+
+    getTriggers: () =>
+        invoke<SessionTrigger[]>('get_session_triggers'),
+
+    /** Replaces all triggers. */
+    setTriggers: (sessionTriggers: SessionTrigger[]) =>
+        invoke<void>('set_session_triggers', { sessionTriggers }),
+
+    /**
+     * `blocklistId` selects a blocklist profile whose categories are blocked for this session only.
+     * `overridePolicy` decides how a hardcore session can be ended early (default: never).
+     * `allowlistOnly` blocks every page but the allowed sites; browsers enforce it through the
+     * companion extension, and it needs at least one allowed site.
+     */
+    startFocus: (name: string, durationMinutes: number, hardcore: boolean, blocklistId: string | null = null, overridePolicy: OverridePolicy | null = null, allowlistOnly = false) =>
+        invoke<ActiveSession>('start_focus_session', { name, durationMinutes, hardcore, blocklistId, overridePolicy, allowlistOnly }),
+
+    getBlocklistProfiles: () =>
+        invoke<BlocklistProfile[]>('get_blocklist_profiles'),
+
+    setBlocklistProfiles: (profiles: BlocklistProfile[]) =>
+        invoke<void>('set_blocklist_profiles', { profiles }),
+
+    /**
+     * Ends a session: `sessionId`, or the primary one (highest precedence). Before its time is
+     * up this is a bypass: pass the text of `requestUnlockChallenge` once its wait is over
+     * (unless friction is zero). Hardcore sessions also need what their `override_policy`
+     * asks for, including `token` for the password policies.
+     */
+    endFocus: (challengeResponse: string | null = null, sessionId: string | null = null, token: string | null = null) =>
+        invoke<void>('end_focus_session', { sessionId, challengeResponse, token }),
+
+    /**
+     * Every running session, highest precedence first (hardcore > scheduled > manual).
+     * A session that runs out is ended by the backend, which emits `session-completed` with it.
+     * Schedules start their session in the backend too (`scheduled-session-started`); a
+     * scheduled session ended early isn't restarted for the rest of that window.
+     */
+    getActive: () =>
+        invoke<ActiveSession[]>('get_active_sessions'),
+
+    /** Friction the next bypass (early end, emergency unlock) will face this week. */
+    getUnlockFriction: () =>
+        invoke<UnlockFriction>('get_unlock_friction'),
+
+    /**
+     * Starts the wait and returns the text to type back. Asking again restarts the wait.
+     * For a hardcore session (`sessionId` or the primary one) it covers its override policy too.
+     */
+    requestUnlockChallenge: (sessionId: string | null = null) =>
+        invoke<UnlockChallenge>('request_unlock_challenge', { sessionId }),
+
+    getTemptationDelay: () =>
+        invoke<TemptationDelay>('get_temptation_delay'),
+
+    /** Turning it off or shortening it fails with `UNLOCK_DELAY` unless a `loosen_delay` unlock is ready. */
+    setTemptationDelay: (config: TemptationDelay) =>
+        invoke<void>('set_temptation_delay', { config }),
+
+    /**
+     * Starts the temptation-delay countdown for `target` (after an `UNLOCK_DELAY` error); retry
+     * the action once `pollUnlockStatus` reports it ready. Asking again keeps the time waited.
+     */
+    requestUnlock: (target: UnlockTarget) =>
+        invoke<UnlockStatus>('request_unlock', { target }),
+
+    /** The pending unlock; the countdown runs in the backend and survives restarts. */
+    pollUnlockStatus: () =>
+        invoke<UnlockStatus | null>('poll_unlock_status'),
 
-    startFocus: (name: string, durationMinutes: number, hardcore: boolean) =>
-        invoke<ActiveSession>('start_focus_session', { name, durationMinutes, hardcore }),
+    cancelUnlock: () =>
+        invoke<void>('cancel_unlock'),
 
-    endFocus: () =>
-        invoke<void>('end_focus_session'),
+    getFrictionPolicy: () =>
+        invoke<FrictionPolicy>('get_friction_policy'),
+
+    /** Loosening or disabling the policy fails unless the last 7 days had no bypasses. */
+    setFrictionPolicy: (policy: FrictionPolicy) =>
+        invoke<void>('set_friction_policy', { policy }),
+
+    /** Restores the default (disabled) policy; only after a clean week. */
+    resetFrictionPolicy: () =>
+        invoke<void>('reset_friction_policy'),
+
+    /** Adds time to the running session. Emits `session-extended`; irreversible in hardcore mode. At most 24 hours per call. */
+    extend: (minutes: number) =>
+        invoke<ActiveSession>('extend_session', { minutes }),
+
+    /** Makes the running session hardcore. One-way: it can't be undone or ended early. */
+    harden: () =>
+        invoke<ActiveSession>('harden_session'),
+
+    /** Events attributed to a session (`ActiveSession.id`); `clean` if nothing was blocked. */
+    getStats: (sessionId: string) =>
+        invoke<SessionStats>('get_session_stats', { sessionId }),
+
+    getExtensions: (limit: number = 50) =>
+        invoke<SessionExtension[]>('get_session_extensions', { limit }),
 
     /** Returns the remaining time in seconds for the current session, or null if inactive. */
     getTimeRemaining: () =>
@@ -157,8 +1052,9 @@ export const sessionsApi = {
     isHardcoreLocked: () =>
         invoke<boolean>('is_hardcore_locked'),
 
-    emergencyUnlock: (password: string) =>
-        invoke<void>('emergency_unlock', { password }),
+    /** Ends the session even in hardcore mode. The token is used up; friction applies as for `endFocus`. */
+    emergencyUnlock: (token: string, challengeResponse: string | null = null) =>
+        invoke<void>('emergency_unlock', { token, challengeResponse }),
 };
 
 // ============= Pomodoro API =============
@@ -173,27 +1069,393 @@ export const pomodoroApi = {
     reset: () =>
         invoke<void>('pomodoro_reset'),
 
+    getIdleConfig: () =>
+        invoke<IdlePauseConfig>('get_pomodoro_idle_config'),
+
+    /** Pauses the work timer after `idle_minutes` without input and resumes it on activity. */
+    setIdleConfig: (config: IdlePauseConfig) =>
+        invoke<void>('set_pomodoro_idle_config', { config }),
+
+    /** Advances to the next phase. Work phases cannot be skipped during hardcore sessions. */
+    skipPhase: () =>
+        invoke<PomodoroState['phase']>('pomodoro_skip_phase'),
+
     getState: () =>
         invoke<PomodoroState>('pomodoro_get_state'),
 
+    /** Dismisses the full-screen break overlay (rejected in strict mode). */
+    closeBreakOverlay: () =>
+        invoke<void>('close_break_overlay'),
+
     configure: (work: number, shortBreak: number, longBreak: number, sessions: number) =>
         invoke<void>('pomodoro_configure', { work, shortBreak, longBreak, sessions }),
+
+    getPresets: () =>
+        invoke<PomodoroPreset[]>('get_pomodoro_presets'),
+
+    setPresets: (presets: PomodoroPreset[]) =>
+        invoke<void>('set_pomodoro_presets', { presets }),
+
+    /** Returns a JSON snippet of the current timer setup (and custom presets) to share. */
+    exportConfig: (includePresets: boolean = true) =>
+        invoke<string>('export_pomodoro_config', { includePresets }),
+
+    /**
+     * Imports a snippet from `exportConfig`: presets are added, replacing ones with the same
+     * name; `apply` also switches the timer to the snippet's setup.
+     */
+    importConfig: (json: string, apply: boolean) =>
+        invoke<PomodoroExport>('import_pomodoro_config', { json, apply }),
+};
+
+// ============= Micro-break API =============
+
+export const microBreakApi = {
+    getState: () =>
+        invoke<MicroBreakState>('micro_break_get_state'),
+
+    configure: (config: MicroBreakConfig) =>
+        invoke<void>('micro_break_configure', { config }),
+
+    snooze: () =>
+        invoke<void>('micro_break_snooze'),
+
+    skip: () =>
+        invoke<void>('micro_break_skip'),
+
+    /** Taken/skipped/snoozed micro-breaks per day. */
+    getStats: (days: number = 7) =>
+        invoke<MicroBreakStats[]>('get_micro_break_stats', { days }),
+};
+
+// ============= Tasks API =============
+
+export const tasksApi = {
+    add: (title: string) =>
+        invoke<number>('add_task', { title }),
+
+    getAll: () =>
+        invoke<Task[]>('get_tasks'),
+
+    update: (id: number, title: string, completed: boolean) =>
+        invoke<void>('update_task', { id, title, completed }),
+
+    delete: (id: number) =>
+        invoke<void>('delete_task', { id }),
+
+    /** The task that finished sessions and pomodoros are attributed to. */
+    getActive: () =>
+        invoke<number | null>('get_active_task'),
+
+    setActive: (id: number | null) =>
+        invoke<void>('set_active_task', { id }),
+};
+
+// ============= Usage API =============
+
+export const usageApi = {
+    /** Built-in productive/neutral/distracting map merged with user overrides. */
+    getClassifications: () =>
+        invoke<ProcessClassification[]>('get_process_classifications'),
+
+    setClassification: (processName: string, classification: Productivity) =>
+        invoke<void>('set_process_classification', { processName, classification }),
+
+    resetClassification: (processName: string) =>
+        invoke<void>('reset_process_classification', { processName }),
+
+    getBreakdown: (days: number = 7, groupBy: 'day' | 'week' = 'day') =>
+        invoke<UsageBucket[]>('get_usage_breakdown', { days, groupBy }),
+
+    /** Time spent per domain over the last `days` days, most visited first. */
+    getSiteUsage: (days: number = 7) =>
+        invoke<SiteUsage[]>('get_site_usage', { days }),
+
+    /** Time each app spent in the foreground over the last `days` days, most used first. */
+    getAppUsage: (days: number = 7) =>
+        invoke<AppUsageTotal[]>('get_app_usage', { days }),
+
+    getTrackingConfig: () =>
+        invoke<UsageTrackingConfig>('get_usage_tracking_config'),
+
+    setTrackingConfig: (config: UsageTrackingConfig) =>
+        invoke<void>('set_usage_tracking_config', { config }),
+};
+
+// ============= Integrations API =============
+
+/**
+ * Time tracker export is configured through settings: `time_tracker_provider`
+ * ('toggl' | 'clockify') and `time_tracker_workspace`. The API token is kept in the OS keyring
+ * (see `setTimeTrackerToken`).
+ */
+export const integrationsApi = {
+    getQueue: () =>
+        invoke<QueuedPush[]>('get_integration_queue'),
+
+    /** Retries queued exports immediately. Returns the number delivered. */
+    flushQueue: () =>
+        invoke<number>('flush_integration_queue'),
+
+    /** Saves the Toggl or Clockify token to the OS keyring; pass null to remove it. */
+    setTimeTrackerToken: (token: string | null) =>
+        invoke<void>('set_time_tracker_token', { token }),
+
+    hasTimeTrackerToken: () =>
+        invoke<boolean>('has_time_tracker_token'),
+
+    getBeeminderConfig: () =>
+        invoke<BeeminderConfig>('get_beeminder_config'),
+
+    setBeeminderConfig: (config: BeeminderConfig) =>
+        invoke<void>('set_beeminder_config', { config }),
+
+    /** Saves the token to the OS keyring; pass null to remove it. */
+    setBeeminderToken: (token: string | null) =>
+        invoke<void>('set_beeminder_token', { token }),
+
+    hasBeeminderToken: () =>
+        invoke<boolean>('has_beeminder_token'),
+
+    getHabiticaConfig: () =>
+        invoke<HabiticaConfig>('get_habitica_config'),
+
+    setHabiticaConfig: (config: HabiticaConfig) =>
+        invoke<void>('set_habitica_config', { config }),
+
+    /** Saves the token to the OS keyring; pass null to remove it. */
+    setHabiticaApiToken: (token: string | null) =>
+        invoke<void>('set_habitica_api_token', { token }),
+
+    hasHabiticaApiToken: () =>
+        invoke<boolean>('has_habitica_api_token'),
+};
+
+// ============= Leaderboard API =============
+
+export const leaderboardApi = {
+    getConfig: () =>
+        invoke<LeaderboardConfig>('get_leaderboard_config'),
+
+    /** Returns the saved config, including the generated `user_id`. */
+    setConfig: (config: LeaderboardConfig) =>
+        invoke<LeaderboardConfig>('set_leaderboard_config', { config }),
+
+    /** Publishes this week's total and fetches friends' totals. */
+    get: () =>
+        invoke<Leaderboard>('get_leaderboard'),
+};
+
+// ============= Browser Extension API =============
+
+/** Whether the force-install policy for the companion extension is in place in a browser. */
+export interface ExtensionPolicyStatus {
+    browser: string;
+    force_installed: boolean;
+}
+
+/**
+ * The companion extension posts visits to `POST http://127.0.0.1:47615/visit`
+ * with `Authorization: Bearer <token>`.
+ */
+export const extensionApi = {
+    getToken: () =>
+        invoke<string>('get_extension_token'),
+
+    /** Invalidates the current token. Returns the new one. */
+    regenerateToken: () =>
+        invoke<string>('regenerate_extension_token'),
+
+    /**
+     * Registers Bastion as the extension's native messaging host (no token needed).
+     * Returns the browsers it was registered with.
+     */
+    installNativeHost: () =>
+        invoke<string[]>('install_native_messaging_host'),
+
+    /** Daily limits in minutes, keyed by domain. */
+    getSiteLimits: () =>
+        invoke<Record<string, number>>('get_site_time_limits'),
+
+    /** Pass null to remove the limit. */
+    setSiteLimit: (domain: string, minutes: number | null) =>
+        invoke<void>('set_site_time_limit', { domain, minutes }),
+
+    getStrictMode: () =>
+        invoke<boolean>('get_extension_strict_mode'),
+
+    /**
+     * Strict mode force-installs and pins the extension via browser Enterprise Policies.
+     * Requires admin rights; can't be disabled during a hardcore session.
+     */
+    setStrictMode: (enabled: boolean) =>
+        invoke<void>('set_extension_strict_mode', { enabled }),
+
+    getPolicyStatus: () =>
+        invoke<ExtensionPolicyStatus[]>('get_extension_policy_status'),
+
+    /** YouTube sections blocked at all times. */
+    getYouTubeRules: () =>
+        invoke<YouTubeRules>('get_youtube_rules'),
+
+    setYouTubeRules: (rules: YouTubeRules) =>
+        invoke<void>('set_youtube_rules', { rules }),
+};
+
+export const quotesApi = {
+    getAll: () =>
+        invoke<Quote[]>('get_quotes'),
+
+    add: (text: string, author?: string) =>
+        invoke<number>('add_quote', { text, author }),
+
+    update: (id: number, text: string, author: string | null, enabled: boolean) =>
+        invoke<void>('update_quote', { id, text, author, enabled }),
+
+    delete: (id: number) =>
+        invoke<void>('delete_quote', { id }),
+
+    /** Null if the library is empty or every quote is disabled. */
+    getRandom: () =>
+        invoke<Quote | null>('get_random_quote'),
+
+    /**
+     * Add a pack: a JSON array of { text, author? }, or one quote per line with an optional
+     * " — Author" suffix. Quotes already in the library are skipped; returns how many were added.
+     */
+    importPack: (pack: string) =>
+        invoke<number>('import_quote_pack', { pack }),
+};
+
+/** Pairing with the mobile companion app. */
+export const companionApi = {
+    /** The companion port only opens while this is on and a phone is pairing or paired. */
+    isEnabled: async () =>
+        (await invoke<string | null>('get_setting', { key: 'companion_enabled' })) === 'true',
+
+    setEnabled: (enabled: boolean) =>
+        invoke<void>('set_companion_enabled', { enabled }),
+
+    /**
+     * New single-use pairing code, valid for 5 minutes; render `payload` as a QR code.
+     * Fails with INVALID_STATE while the companion is turned off.
+     */
+    startPairing: () =>
+        invoke<PairingOffer>('start_companion_pairing'),
+
+    cancelPairing: () =>
+        invoke<void>('cancel_companion_pairing'),
+
+    /** Fails with UNAUTHORIZED if the pairing code is wrong or expired. Emits 'companion-paired'. */
+    confirmPairing: (confirmation: PairingConfirmation) =>
+        invoke<PairingResult>('confirm_companion_pairing', { confirmation }),
+
+    getDevices: () =>
+        invoke<PairedDevice[]>('get_companion_devices'),
+
+    unpairDevice: (deviceId: string) =>
+        invoke<boolean>('unpair_companion_device', { deviceId }),
+};
+
+// ============= Plugins API =============
+
+/** A `.wasm` file from the `plugins` data directory. */
+export interface PluginInfo {
+    name: string;
+    loaded: boolean;
+    error: string | null; // Why the plugin failed to load
+}
+
+export const pluginsApi = {
+    getAll: () =>
+        invoke<PluginInfo[]>('get_plugins'),
 };
 
 // ============= Stats API =============
 
+/** Everything the dashboard shows on load, read in one pass. */
+export interface DashboardSnapshot {
+    blocked_sites: BlockedSite[];
+    blocked_apps: BlockedApp[];
+    sessions: Session[];
+    recent_blocks: BlockEvent[];
+    stats: FocusStats[];
+    settings: Record<string, string>; // Never includes the master password hash
+    has_master_password: boolean;
+    active_session: ActiveSession | null; // The primary session, see `active_sessions`
+    active_sessions: ActiveSession[]; // All running sessions, highest precedence first
+    time_remaining: number | null; // Seconds, of the primary session
+    is_hardcore_locked: boolean;
+    pomodoro: PomodoroState;
+}
+
 export const statsApi = {
+    /** Replaces the separate load-time calls; defaults to 8 recent blocks and 7 days of stats. */
+    getDashboardSnapshot: (recentBlocks?: number, statsDays?: number) =>
+        invoke<DashboardSnapshot>('get_dashboard_snapshot', { recentBlocks, statsDays }),
+
     getRecentBlocks: (limit: number = 10) =>
         invoke<BlockEvent[]>('get_recent_blocks', { limit }),
 
     getFocusStats: (days: number = 7) =>
         invoke<FocusStats[]>('get_focus_stats', { days }),
 
+    /** Pomodoros completed per day over the last `days` days (days with none are omitted). */
+    getPomodoroHistory: (days: number = 7) =>
+        invoke<PomodoroDay[]>('get_pomodoro_history', { days }),
+
+    /** This week so far vs. the same days last week; changes are percentages (null if last week was 0). */
+    getWeekComparison: () =>
+        invoke<WeekComparison>('get_week_comparison'),
+
+    /** Daily block attempts for a site (with subdomains) or app process, oldest first, zero-filled. */
+    getTargetTrend: (target: string, days: number) =>
+        invoke<TrendPoint[]>('get_target_trend', { target, days }),
+
+    /** Most blocked category first. */
+    getCategoryBreakdown: (days: number) =>
+        invoke<CategoryBreakdown[]>('get_category_breakdown', { days }),
+
+    getStreak: () =>
+        invoke<StreakStatus>('get_streak'),
+
+    /** Freezes earned and spent, most recent first. */
+    getStreakFreezeHistory: (limit?: number) =>
+        invoke<StreakFreeze[]>('get_streak_freeze_history', { limit }),
+
     logProtectedTime: (minutes: number) =>
         invoke<void>('log_protected_time', { minutes }),
 
+    /** Website/app blocks over the last `days` days, during vs. outside focus sessions. */
+    getSessionBlockSplit: (days: number = 7) =>
+        invoke<SessionBlockSplit>('get_session_block_split', { days }),
+
     getBlockCounts: () =>
         invoke<Record<string, number>>('get_block_counts'),
+
+    /** Today's focus summary. The backend also pushes it as a `daily-summary` event each evening. */
+    getDailySummary: () =>
+        invoke<DailySummary>('get_daily_summary'),
+
+    getDailySummaryConfig: () =>
+        invoke<DailySummaryConfig>('get_daily_summary_config'),
+
+    setDailySummaryConfig: (config: DailySummaryConfig) =>
+        invoke<void>('set_daily_summary_config', { config }),
+
+    getDailyNoteConfig: () =>
+        invoke<DailyNoteConfig>('get_daily_note_config'),
+
+    /** Fails if the folder doesn't exist or the file name leaves it. */
+    setDailyNoteConfig: (config: DailyNoteConfig) =>
+        invoke<void>('set_daily_note_config', { config }),
+
+    /**
+     * Appends today's summary to today's note now; returns the note's path. The automatic
+     * export emits `daily-note-failed` with the reason when it can't write the note.
+     */
+    exportDailyNote: () =>
+        invoke<string>('export_daily_note'),
 };
 
 // ============= Settings API =============
@@ -208,11 +1470,30 @@ export const settingsApi = {
     factoryReset: () =>
         invoke<void>('factory_reset'),
 
-    setMasterPassword: (password: string) =>
-        invoke<void>('set_master_password', { password }),
+    getTelemetryConfig: () =>
+        invoke<TelemetryConfig>('get_telemetry_config'),
+
+    /** OpenTelemetry trace export. Takes effect after a restart. */
+    setTelemetryConfig: (config: TelemetryConfig) =>
+        invoke<void>('set_telemetry_config', { config }),
+
+    /** Changing an existing password requires an unlock token. */
+    setMasterPassword: (password: string, token?: string) =>
+        invoke<void>('set_master_password', { password, token }),
+
+    clearMasterPassword: (token: string) =>
+        invoke<void>('clear_master_password', { token }),
 
     verifyMasterPassword: (password: string) =>
         invoke<boolean>('verify_master_password', { password }),
+
+    /** Exchanges the master password for a short-lived unlock token. */
+    authenticate: (password: string) =>
+        invoke<UnlockToken>('authenticate', { password }),
+
+    /** Revokes the current unlock token. */
+    lock: () =>
+        invoke<void>('lock'),
 };
 
 // ============= Combined API =============
@@ -223,6 +1504,14 @@ export const api = {
     blockedApps: blockedAppsApi,
     sessions: sessionsApi,
     pomodoro: pomodoroApi,
+    microBreaks: microBreakApi,
+    tasks: tasksApi,
+    usage: usageApi,
+    integrations: integrationsApi,
+    extension: extensionApi,
+    companion: companionApi,
+    quotes: quotesApi,
+    plugins: pluginsApi,
     stats: statsApi,
     settings: settingsApi,
     system: systemApi,
@@ -1,10 +1,14 @@
 import React from 'react';
 import ReactDOM from 'react-dom/client';
 import App from './App';
+import BreakOverlay from './components/BreakOverlay';
 import './styles/globals.css';
 
+// The backend opens a separate full-screen window for pomodoro breaks
+const isBreakOverlay = new URLSearchParams(window.location.search).get('overlay') === 'break';
+
 ReactDOM.createRoot(document.getElementById('root')!).render(
     <React.StrictMode>
-        <App />
+        {isBreakOverlay ? <BreakOverlay /> : <App />}
     </React.StrictMode>,
 );
@@ -222,7 +222,15 @@ export default function Blocks() {
 
         try {
             if (activeTab === 'websites') {
-                await blockedSitesApi.add(cleanItem);
+                const result = await blockedSitesApi.add(cleanItem);
+                if (result.status === 'already_blocked') {
+                    if (result.site.enabled || !confirm(`${result.site.domain} is already in your list but disabled. Re-enable it?`)) {
+                        showNotification(`${result.site.domain} is already blocked.`, 'error');
+                        setIsSaving(false);
+                        return;
+                    }
+                    await blockedSitesApi.add(cleanItem, 'other', undefined, true);
+                }
 
                 // Smart Aliasing for Meta
                 if (cleanItem === 'facebook.com') {
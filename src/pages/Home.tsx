@@ -12,7 +12,7 @@ import {
     Rocket,
     Radar
 } from 'lucide-react';
-import { statsApi, sessionsApi, blockedSitesApi, BlockEvent, settingsApi } from '../lib/api';
+import { statsApi, sessionsApi, BlockEvent, settingsApi } from '../lib/api';
 import PasswordModal from '../components/PasswordModal';
 
 export default function Home() {
@@ -63,16 +63,12 @@ export default function Home() {
     const loadDashboardData = async () => {
         setIsLoading(true);
         try {
-            const [blocks, sites, stats, masterHash] = await Promise.all([
-                statsApi.getRecentBlocks(8),
-                blockedSitesApi.getAll(),
-                statsApi.getFocusStats(1),
-                settingsApi.get('master_password_hash')
-            ]);
+            const snapshot = await statsApi.getDashboardSnapshot(8, 1);
+            const stats = snapshot.stats;
 
-            setRecentBlocks(blocks);
-            setBlockedCount(sites.filter(s => s.enabled).length);
-            setCanOverride(!!masterHash && masterHash.length > 0);
+            setRecentBlocks(snapshot.recent_blocks);
+            setBlockedCount(snapshot.blocked_sites.filter(s => s.enabled).length);
+            setCanOverride(snapshot.has_master_password);
 
             if (stats.length > 0) {
                 const today = stats[0];
@@ -454,7 +450,8 @@ export default function Home() {
                         isOpen={showUnlockModal}
                         onClose={() => setShowUnlockModal(false)}
                         onConfirm={async (password) => {
-                            await sessionsApi.emergencyUnlock(password);
+                            const { token } = await settingsApi.authenticate(password);
+                            await sessionsApi.emergencyUnlock(token);
                             setIsSessionActive(false);
                             setHardcoreMode(false);
                         }}
@@ -1,7 +1,8 @@
 import { useState, useEffect, useCallback } from 'react';
 import { motion, AnimatePresence } from 'framer-motion';
 import { Play, Pause, RotateCcw, Waves, Brain, Settings2, Volume2, VolumeX, Maximize2, SkipForward } from 'lucide-react';
-import { pomodoroApi, sessionsApi } from '../lib/api';
+import { pomodoroApi, sessionsApi, PomodoroState } from '../lib/api';
+import { listen } from '@tauri-apps/api/event';
 
 type Phase = 'work' | 'break' | 'longBreak';
 
@@ -29,29 +30,32 @@ export default function Pomodoro() {
     const [showSettings, setShowSettings] = useState(false);
     const [isLocked, setIsLocked] = useState(false);
 
-    // Initial load and Polling
+    // Initial load, then follow the backend's per-second tick events
     useEffect(() => {
+        const applyState = (state: PomodoroState) => {
+            setPhase(state.phase.toLowerCase() as Phase);
+            setTimeLeft(state.time_remaining);
+            setIsRunning(state.is_running);
+            setCompletedSessions(state.completed_sessions);
+            setSettings(s => ({
+                ...s,
+                workDuration: Math.floor(state.work_duration / 60),
+                breakDuration: Math.floor(state.break_duration / 60),
+                longBreakDuration: Math.floor(state.long_break_duration / 60),
+                sessionsUntilLongBreak: state.sessions_until_long_break
+            }));
+        };
+
         const syncState = async () => {
             try {
-                const state = await pomodoroApi.getState();
-                setPhase(state.phase.toLowerCase() as Phase);
-                setTimeLeft(state.time_remaining);
-                setIsRunning(state.is_running);
-                setCompletedSessions(state.completed_sessions);
-                setSettings(s => ({
-                    ...s,
-                    workDuration: Math.floor(state.work_duration / 60),
-                    breakDuration: Math.floor(state.break_duration / 60),
-                    longBreakDuration: Math.floor(state.long_break_duration / 60),
-                    sessionsUntilLongBreak: state.sessions_until_long_break
-                }));
+                applyState(await pomodoroApi.getState());
             } catch (err) {
                 console.error('Failed to sync pomodoro state:', err);
             }
         };
 
         syncState();
-        const interval = setInterval(syncState, 1000);
+        const unlisten = listen<PomodoroState>('pomodoro-tick', (event) => applyState(event.payload));
 
         const checkLock = async () => {
             try {
@@ -63,7 +67,7 @@ export default function Pomodoro() {
         const lockInterval = setInterval(checkLock, 5000);
 
         return () => {
-            clearInterval(interval);
+            unlisten.then(f => f());
             clearInterval(lockInterval);
         };
     }, []);
@@ -125,11 +129,7 @@ export default function Pomodoro() {
 
     const skipBreak = async () => {
         try {
-            // Configure with current settings to reset to work phase if needed
-            // Or add a 'skip' command to Rust. 
-            // For now, let's just trigger a reset and start if it was a break.
-            await pomodoroApi.reset();
-            await pomodoroApi.start();
+            await pomodoroApi.skipPhase();
         } catch (err) {
             console.error('Failed to skip break:', err);
         }
@@ -24,6 +24,7 @@ const presetTemplates = [
         endTime: '17:00',
         days: ['Mon', 'Tue', 'Wed', 'Thu', 'Fri'],
         hardcore: false,
+        bedtime: false,
         icon: Briefcase,
         color: 'text-black dark:text-white'
     },
@@ -33,6 +34,7 @@ const presetTemplates = [
         endTime: '12:00',
         days: ['Mon', 'Tue', 'Wed', 'Thu', 'Fri'],
         hardcore: true,
+        bedtime: false,
         icon: Flame,
         color: 'text-black dark:text-white'
     },
@@ -42,6 +44,17 @@ const presetTemplates = [
         endTime: '22:00',
         days: ['Mon', 'Tue', 'Wed', 'Thu', 'Fri'],
         hardcore: false,
+        bedtime: false,
+        icon: Moon,
+        color: 'text-black dark:text-white'
+    },
+    {
+        name: 'Bedtime',
+        startTime: '22:30',
+        endTime: '07:00',
+        days: ['Mon', 'Tue', 'Wed', 'Thu', 'Fri', 'Sat', 'Sun'],
+        hardcore: false,
+        bedtime: true,
         icon: Moon,
         color: 'text-black dark:text-white'
     },
@@ -60,6 +73,7 @@ export default function Sessions() {
         endTime: '17:00',
         days: ['Mon', 'Tue', 'Wed', 'Thu', 'Fri'],
         hardcore: false,
+        bedtime: false,
     });
 
     const loadSessions = async () => {
@@ -86,6 +100,7 @@ export default function Sessions() {
             endTime: '17:00',
             days: ['Mon', 'Tue', 'Wed', 'Thu', 'Fri'],
             hardcore: false,
+            bedtime: false,
         });
         setShowModal(true);
     };
@@ -97,6 +112,7 @@ export default function Sessions() {
             endTime: template.endTime,
             days: template.days,
             hardcore: template.hardcore,
+            bedtime: template.bedtime,
         });
         setShowModal(true);
     };
@@ -122,6 +138,7 @@ export default function Sessions() {
                 days: JSON.stringify(formData.days),
                 hardcore: formData.hardcore,
                 enabled: true,
+                kind: formData.bedtime ? 'bedtime' : 'focus',
             });
 
             setShowModal(false);
@@ -416,6 +433,28 @@ export default function Sessions() {
                                         </button>
                                     </div>
 
+                                    <div className="flex items-center justify-between p-4 rounded-xl bg-black/5 dark:bg-white/5 border border-black/5 dark:border-white/5">
+                                        <div className="flex items-center gap-3">
+                                            <div className={`p-2 rounded-lg ${formData.bedtime ? 'bg-black dark:bg-white text-white dark:text-black' : 'bg-black/5 dark:bg-white/10 text-gray-400 dark:text-bastion-muted'}`}>
+                                                <Moon className="w-5 h-5" />
+                                            </div>
+                                            <div>
+                                                <p className={`font-black uppercase tracking-widest text-xs ${formData.bedtime ? 'text-black dark:text-white' : 'text-gray-400 dark:text-bastion-secondary'}`}>Bedtime</p>
+                                                <p className="text-[10px] text-gray-400 dark:text-bastion-muted font-bold uppercase tracking-tight">Blocks entertainment until morning</p>
+                                            </div>
+                                        </div>
+                                        <button
+                                            onClick={() => setFormData(prev => ({ ...prev, bedtime: !prev.bedtime }))}
+                                            className={`w-14 h-8 rounded-full transition-all relative ${formData.bedtime ? 'bg-black dark:bg-white' : 'bg-black/10 dark:bg-white/10'}`}
+                                        >
+                                            <motion.div
+                                                layout
+                                                className="w-6 h-6 bg-white dark:bg-black rounded-full absolute top-1"
+                                                animate={{ left: formData.bedtime ? 28 : 4 }}
+                                            />
+                                        </button>
+                                    </div>
+
                                     <div className="pt-4 flex justify-end gap-3">
                                         <button
                                             onClick={() => setShowModal(false)}
@@ -65,6 +65,7 @@ export default function Settings() {
     const [startOnBoot, setStartOnBoot] = useState(false);
     const [minimizeToTray, setMinimizeToTray] = useState(true);
     const [showNotifications, setShowNotifications] = useState(true);
+    const [osEventLog, setOsEventLog] = useState(false);
     const [hardcoreEnabled, setHardcoreEnabled] = useState(false);
     const [emergencyOverride, setEmergencyOverride] = useState(false);
 
@@ -83,12 +84,13 @@ export default function Settings() {
         // Load settings
         const loadSettings = async () => {
             try {
-                const [boot, tray, notify, autostart, warningText] = await Promise.all([
+                const [boot, tray, notify, autostart, warningText, eventLog] = await Promise.all([
                     settingsApi.get('start_on_boot'),
                     settingsApi.get('minimize_to_tray'),
                     settingsApi.get('show_notifications'),
                     isEnabled(),
-                    settingsApi.get('custom_warning_text')
+                    settingsApi.get('custom_warning_text'),
+                    settingsApi.get('os_event_log')
                 ]);
 
                 if (boot !== null) setStartOnBoot(boot === 'true');
@@ -96,6 +98,7 @@ export default function Settings() {
 
                 if (tray !== null) setMinimizeToTray(tray === 'true');
                 if (notify !== null) setShowNotifications(notify === 'true');
+                setOsEventLog(eventLog === 'true');
                 if (warningText !== null) setCustomWarningText(warningText);
                 const masterPasswordHash = await settingsApi.get('master_password_hash');
                 setEmergencyOverride(!!masterPasswordHash && masterPasswordHash.length > 0);
@@ -149,6 +152,16 @@ export default function Settings() {
         }
     };
 
+    const toggleOsEventLog = async () => {
+        const newValue = !osEventLog;
+        setOsEventLog(newValue);
+        try {
+            await settingsApi.set('os_event_log', String(newValue));
+        } catch (err) {
+            console.error('Failed to update event log export:', err);
+        }
+    };
+
     const updateWarningText = async (text: string) => {
         setCustomWarningText(text);
         try {
@@ -179,16 +192,8 @@ export default function Settings() {
             await settingsApi.setMasterPassword(password);
             setEmergencyOverride(true);
         } else {
-            const isValid = await settingsApi.verifyMasterPassword(password);
-            if (!isValid) throw new Error('Incorrect password');
-            // To disable, we clear the password hash (or just consider it disabled if we had a flag, but clearing hash is safer)
-            // Ideally we'd have a removeSetting, but setting it to empty string works if we check for Some() and non-empty in backend.
-            // Actually, let's just use empty string for now as "disabled".
-            // Backend `verify_master_password` handles Option, so if we can delete it that's best.
-            // Our set_setting overwrites. So we can overwrite with empty string?
-            // Let's assume empty string means disabled for now, or add a delete_setting command?
-            // Simpler: Just set it to empty string.
-            await settingsApi.set('master_password_hash', '');
+            const { token } = await settingsApi.authenticate(password);
+            await settingsApi.clearMasterPassword(token);
             setEmergencyOverride(false);
         }
     };
@@ -272,6 +277,10 @@ export default function Settings() {
                                         <Toggle enabled={showNotifications} onChange={toggleNotifications} />
                                     </SettingRow>
 
+                                    <SettingRow label="System Event Log" description="Mirror block and tamper events to syslog or the Windows Event Log">
+                                        <Toggle enabled={osEventLog} onChange={toggleOsEventLog} />
+                                    </SettingRow>
+
                                     <div className="h-px bg-black/10 dark:bg-white/10 my-4" />
 
                                     {/* Custom Warning Text */}